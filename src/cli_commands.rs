@@ -1,3 +1,4 @@
+use crate::gui::terminal::Height;
 use clap::{Parser, Subcommand, ValueEnum};
 
 #[derive(Parser)]
@@ -27,6 +28,94 @@ pub enum Mode {
     Revert,
 }
 
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+pub enum ImportFormat {
+    /// A hoard trove file
+    Hoard,
+    /// navi `.cheat` cheatsheets. Namespaces are named after the files
+    Navi,
+    /// pet `snippet.toml` files. Namespaces are named after the files
+    Pet,
+    /// Local tldr markdown pages. Namespaces are named after the pages
+    Tldr,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+pub enum ExportFormat {
+    /// A hoard trove file
+    Yaml,
+    /// A markdown cheat sheet grouped by namespace
+    Md,
+    /// The trove in json format
+    Json,
+    /// The trove in toml format
+    Toml,
+    /// One row per command
+    Csv,
+    /// A navi cheatsheet
+    Navi,
+    /// A sourceable shell file with one function per command. Parameters become positional arguments
+    ShellAliases,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+pub enum StorageFormat {
+    /// The yaml trove file
+    Yaml,
+    /// A SQLite database with full text search
+    Sqlite,
+}
+
+#[derive(Subcommand)]
+pub enum SecretAction {
+    /// Store a secret. Its value is asked for without echoing it
+    Set {
+        /// Name of the secret, as used in `#secret:<name>!`
+        name: String,
+    },
+    /// Print the value of a secret
+    Get {
+        /// Name of the secret
+        name: String,
+    },
+    /// List the names of all secrets
+    List,
+    /// Remove a secret
+    Rm {
+        /// Name of the secret
+        name: String,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum WorkflowAction {
+    /// Save a workflow running hoarded commands one after another
+    New {
+        /// Name of the workflow
+        #[arg(short = 'n', long)]
+        name: String,
+
+        /// [Optional] Namespace of the workflow. Defaults to the namespace of its first step
+        #[arg(long)]
+        namespace: Option<String>,
+
+        /// [Optional] Description of what the workflow does
+        #[arg(short = 'd', long)]
+        description: Option<String>,
+
+        /// Id, namespace/name or name of a command to run. Repeat it for every step, in order
+        #[arg(short = 's', long = "step", required = true)]
+        steps: Vec<String>,
+    },
+    /// List all workflows with their steps
+    List,
+    /// Remove a workflow. Its commands are kept
+    Rm {
+        /// Id, namespace/name or name of the workflow
+        name: String,
+    },
+}
+
 #[derive(Subcommand)]
 pub enum Commands {
     /// Shows setting file paths
@@ -57,22 +146,56 @@ pub enum Commands {
         #[arg(short = 'f', long)]
         filter: Option<String>,
 
-        /// Return hoarded commands in structured format
-        #[arg(short = 'j', long)]
+        /// Return hoarded commands as json. The schema is versioned by its `schema_version` field
+        #[arg(short = 'j', long, conflicts_with_all = ["yaml", "fields", "format"])]
         json: bool,
 
+        /// Return hoarded commands in the yaml format of the trove file
+        #[arg(short = 'y', long, conflicts_with_all = ["fields", "format"])]
+        yaml: bool,
+
+        /// Return one line per command with the given comma separated fields, separated by tabs
+        #[arg(long, value_name = "FIELDS", conflicts_with = "format")]
+        fields: Option<String>,
+
+        /// Return one line per command rendered from a template like '{name}\t{command}'.
+        /// Available fields: id, name, namespace, command, description, tags, parameters, usage_count
+        #[arg(long, value_name = "TEMPLATE", conflicts_with = "preview_format")]
+        format: Option<String>,
+
+        /// Return one line per command for external pickers like fzf: '{id}\t{namespace}/{name}\t{command}'.
+        /// Pass the id to `hoard preview` and `hoard pick`
+        #[arg(long)]
+        preview_format: bool,
+
+        /// Terminate every line of --fields, --format or --preview-format with a null character instead of a newline.
+        /// Implies --preview-format if no other format is given
+        #[arg(short = '0', long, conflicts_with_all = ["json", "yaml"])]
+        print0: bool,
+
         /// Return hoarded commands in a simplified table view
         #[arg(short = 's', long)]
         simple: bool,
+
+        /// Show the list below the prompt instead of taking over the whole terminal,
+        /// HEIGHT lines or percent of the terminal high like '20' or '40%'. Overrides `inline_height` of the config
+        #[arg(long, value_name = "HEIGHT")]
+        height: Option<Height>,
     },
 
     /// Pick a command of the trove and print it
     Pick {
-        /// Name of the command to print
+        /// Id, namespace/name or name of the command to print
         #[arg(short = 'n', long)]
         name: String,
     },
 
+    /// Show a preview of a command with its description, tags, parameters and usage
+    Preview {
+        /// Id, namespace/name or name of the command to preview
+        reference: String,
+    },
+
     /// Set a custom parameter token
     SetParameterToken {
         /// Parameter token to replace
@@ -80,9 +203,9 @@ pub enum Commands {
         name: String,
     },
 
-    /// Removes a command in the trove by name
+    /// Removes a command in the trove by id, namespace/name or name
     Remove {
-        /// command to remove
+        /// Id, namespace/name or name of the command to remove
         #[arg(short = 'n', long)]
         name: String,
     },
@@ -94,18 +217,26 @@ pub enum Commands {
         namespace: String,
     },
 
-    /// Import a trove file from a local file or URL
+    /// Import a trove file or commands of other command organizers
     Import {
-        /// URL or path to .trove file to import
+        /// Path to the file or directory to import
         #[arg(long)]
         uri: String,
+
+        /// Format of the file(s) to import
+        #[arg(short = 'f', long, value_enum, default_value_t = ImportFormat::Hoard)]
+        from: ImportFormat,
     },
 
-    /// Export a trove file
+    /// Export a trove file or render the commands in another format
     Export {
-        /// Path where the trove file should be saved to
+        /// Path where the exported file should be saved to
         #[arg(long)]
         path: String,
+
+        /// Format of the exported file
+        #[arg(short = 'f', long, value_enum, default_value_t = ExportFormat::Yaml)]
+        format: ExportFormat,
     },
 
     /// Edit a saved command
@@ -120,6 +251,91 @@ pub enum Commands {
         /// shell type to print the config for
         #[arg(short = 's', long)]
         shell: String,
+
+        /// Use fzf to pick a command instead of the hoard TUI
+        #[arg(long)]
+        fzf: bool,
+    },
+
+    /// Manage the secrets of the encrypted vault, which fill `#secret:<name>!` parameters
+    Secret {
+        #[command(subcommand)]
+        action: SecretAction,
+    },
+
+    /// Manage workflows, which run several hoarded commands one after another
+    Workflow {
+        #[command(subcommand)]
+        action: WorkflowAction,
+    },
+
+    /// Run a command or the steps of a workflow one by one, stopping at the first failing step
+    Run {
+        /// Id, namespace/name or name of the workflow or command to run
+        name: String,
+    },
+
+    /// Move the trove to another storage backend and use it from now on
+    MigrateStorage {
+        /// Storage backend to move the trove to
+        #[arg(long, value_enum)]
+        to: StorageFormat,
     },
 
+    /// Undo the last change of the trove, made on the command line or in `hoard list`
+    Undo {},
+
+    /// Redo the last undone change of the trove
+    Redo {},
+}
+
+impl Commands {
+    /// Check if running the command may change the trove file
+    pub const fn mutates_trove(&self) -> bool {
+        match self {
+            Self::New { .. }
+            | Self::Remove { .. }
+            | Self::RemoveNamespace { .. }
+            | Self::Import { .. }
+            | Self::Edit { .. }
+            | Self::Undo {}
+            | Self::Redo {}
+            | Self::MigrateStorage { .. } => true,
+            Self::Workflow { action } => !matches!(action, WorkflowAction::List),
+            // The interactive list saves usage counts and edits made in the TUI
+            Self::List {
+                json,
+                yaml,
+                fields,
+                format,
+                preview_format,
+                print0,
+                simple,
+                ..
+            } => {
+                !(*json
+                    || *yaml
+                    || fields.is_some()
+                    || format.is_some()
+                    || *preview_format
+                    || *print0
+                    || *simple)
+            }
+            Self::Info {}
+            | Self::Pick { .. }
+            | Self::Preview { .. }
+            | Self::SetParameterToken { .. }
+            | Self::Export { .. }
+            | Self::ShellConfig { .. }
+            | Self::Secret { .. }
+            | Self::Run { .. } => false,
+        }
+    }
+
+    /// Check if the trove stays locked from loading it until the command saved it
+    ///
+    /// The interactive list merges its changes on exit instead, it would keep every other hoard process waiting
+    pub const fn locks_trove(&self) -> bool {
+        self.mutates_trove() && !matches!(self, Self::List { .. })
+    }
 }
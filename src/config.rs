@@ -1,8 +1,10 @@
-use crate::gui::prompts::prompt_input;
+use crate::core::store::StorageBackend;
+use crate::core::workflow::WorkflowMode;
 use anyhow::{anyhow, Error, Result};
 use log::info;
 use serde::{Deserialize, Serialize};
 use std::{
+    collections::HashMap,
     fs,
     path::{Path, PathBuf},
 };
@@ -10,8 +12,26 @@ use std::{
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 const HOARD_HOMEDIR: &str = ".config/hoard";
 const HOARD_FILE: &str = "trove.yml";
+const HOARD_DATABASE: &str = "trove.db";
+const HOARD_VAULT: &str = "vault.yml";
+const HOARD_JOURNAL: &str = "journal.yml";
 pub const HOARD_CONFIG: &str = "config.yml";
 
+/// Colors the terminal can show. Colors of themes are turned into the closest ones it has
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ColorDepth {
+    /// Truecolor, unless `TERM` names a terminal with 256 colors like `xterm-256color` or less like `linux` or `vt100`
+    #[default]
+    #[serde(rename = "auto")]
+    Auto,
+    #[serde(rename = "truecolor")]
+    TrueColor,
+    #[serde(rename = "256")]
+    Ansi256,
+    #[serde(rename = "16")]
+    Ansi16,
+}
+
 #[allow(clippy::module_name_repetitions)]
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct HoardConfig {
@@ -25,6 +45,10 @@ pub struct HoardConfig {
     pub secondary_color: Option<(u8, u8, u8)>,
     pub tertiary_color: Option<(u8, u8, u8)>,
     pub command_color: Option<(u8, u8, u8)>,
+    // Color theme of `hoard list`, one of `default` ( the colors above ), `gruvbox`, `solarized`, `high-contrast` or `no-color`
+    pub theme: Option<String>,
+    // Colors the terminal can show, one of `auto`, `truecolor`, `256` or `16`
+    pub color_depth: Option<ColorDepth>,
     // Parameter settings
     pub parameter_token: Option<String>,
     // Token to indicate the end of a named parameter
@@ -32,6 +56,28 @@ pub struct HoardConfig {
     pub read_from_current_directory: Option<bool>,
     // URL to trove sync server
     pub sync_server_url: Option<String>,
+    // Number of timestamped trove backups kept in the `backups` folder of the config directory
+    pub backup_count: Option<usize>,
+    // Where the trove is stored, either `yaml` ( the trove file ) or `sqlite` ( the trove database )
+    pub storage_backend: Option<StorageBackend>,
+    // Path of the trove database, used by the `sqlite` storage backend
+    pub database_path: Option<PathBuf>,
+    // Path of the encrypted vault with the values of `#secret:<name>!` parameters
+    pub vault_path: Option<PathBuf>,
+    // Path of the journal of the last changes of the trove, which `hoard undo` and `hoard redo` step through
+    pub journal_path: Option<PathBuf>,
+    // Number of changes of the trove which can be undone
+    pub journal_size: Option<usize>,
+    // `.env` file per namespace, to expand `${VAR}` in its commands with
+    pub env_files: Option<HashMap<String, PathBuf>>,
+    // What picking a workflow in `hoard list` returns, either `join` ( its steps joined with `&&` ) or `run` ( `hoard run <workflow>` )
+    pub workflow_mode: Option<WorkflowMode>,
+    // Keys of the actions of `hoard list` by screen, e.g. `list: { quit: [q, <Esc>] }`. Replaces the default keys of the listed actions
+    pub keymap: Option<HashMap<String, HashMap<String, Vec<String>>>>,
+    // Lines of `hoard list` below the prompt like `20`, or percent of the terminal like `40%`. Takes the whole terminal if unset
+    pub inline_height: Option<String>,
+    // Clicks and the scroll wheel in `hoard list`. Turn it off to select text with the mouse
+    pub mouse: Option<bool>,
 }
 
 impl Default for HoardConfig {
@@ -46,10 +92,23 @@ impl Default for HoardConfig {
             secondary_color: Some(Self::default_colors(1)),
             tertiary_color: Some(Self::default_colors(2)),
             command_color: Some(Self::default_colors(3)),
+            theme: Some(Self::default_theme()),
+            color_depth: Some(ColorDepth::default()),
             parameter_token: Some(Self::default_parameter_token()),
             parameter_ending_token: Some(Self::default_ending_parameter_token()),
             read_from_current_directory: Some(Self::default_read_from_current_directory()),
             sync_server_url: Some(Self::default_sync_server_url()),
+            backup_count: Some(Self::default_backup_count()),
+            storage_backend: Some(StorageBackend::default()),
+            database_path: None,
+            vault_path: None,
+            journal_path: None,
+            journal_size: Some(Self::default_journal_size()),
+            env_files: Some(HashMap::new()),
+            workflow_mode: Some(WorkflowMode::default()),
+            keymap: Some(HashMap::new()),
+            inline_height: None,
+            mouse: Some(true),
         }
     }
 }
@@ -66,19 +125,27 @@ impl HoardConfig {
             secondary_color: Some(Self::default_colors(1)),
             tertiary_color: Some(Self::default_colors(2)),
             command_color: Some(Self::default_colors(3)),
+            theme: Some(Self::default_theme()),
+            color_depth: Some(ColorDepth::default()),
             parameter_token: Some(Self::default_parameter_token()),
             parameter_ending_token: Some(Self::default_ending_parameter_token()),
             read_from_current_directory: Some(Self::default_read_from_current_directory()),
             sync_server_url: Some(Self::default_sync_server_url()),
+            backup_count: Some(Self::default_backup_count()),
+            storage_backend: Some(StorageBackend::default()),
+            database_path: Some(hoard_home_path.join(HOARD_DATABASE)),
+            vault_path: Some(hoard_home_path.join(HOARD_VAULT)),
+            journal_path: Some(hoard_home_path.join(HOARD_JOURNAL)),
+            journal_size: Some(Self::default_journal_size()),
+            env_files: Some(HashMap::new()),
+            workflow_mode: Some(WorkflowMode::default()),
+            keymap: Some(HashMap::new()),
+            inline_height: None,
+            mouse: Some(true),
         }
     }
 
-    pub fn with_default_namespace(self) -> Self {
-        let default_namespace = prompt_input(
-            "This is the first time running hoard.\nChoose a default namespace where you want to hoard your commands.",
-            false,
-            Some("default".to_string())
-        );
+    pub fn with_default_namespace(self, default_namespace: String) -> Self {
         Self {
             version: self.version,
             default_namespace,
@@ -89,13 +156,30 @@ impl HoardConfig {
             secondary_color: self.secondary_color,
             tertiary_color: self.tertiary_color,
             command_color: self.command_color,
+            theme: self.theme,
+            color_depth: self.color_depth,
             parameter_token: self.parameter_token,
             parameter_ending_token: self.parameter_ending_token,
             read_from_current_directory: self.read_from_current_directory,
             sync_server_url: self.sync_server_url,
+            backup_count: self.backup_count,
+            storage_backend: self.storage_backend,
+            database_path: self.database_path,
+            vault_path: self.vault_path,
+            journal_path: self.journal_path,
+            journal_size: self.journal_size,
+            env_files: self.env_files,
+            workflow_mode: self.workflow_mode,
+            keymap: self.keymap,
+            inline_height: self.inline_height,
+            mouse: self.mouse,
         }
     }
 
+    fn default_theme() -> String {
+        "default".to_string()
+    }
+
     fn default_parameter_token() -> String {
         "#".to_string()
     }
@@ -108,6 +192,14 @@ impl HoardConfig {
         "https://troveserver.herokuapp.com/".to_string()
     }
 
+    const fn default_backup_count() -> usize {
+        10
+    }
+
+    const fn default_journal_size() -> usize {
+        50
+    }
+
     const fn default_read_from_current_directory() -> bool {
         true
     }
@@ -126,25 +218,41 @@ impl HoardConfig {
 // if `hoard_home_path` is set, try to read it from that custom path
 //
 // If no `hoard_home_path` is found, a new config.yml will be created at the specified path
+// with the namespace returned by `default_namespace` as the default namespace
 #[allow(clippy::module_name_repetitions)]
-pub fn load_or_build_config(hoard_home_path: Option<String>) -> Result<HoardConfig> {
+pub fn load_or_build_config<F>(
+    hoard_home_path: Option<String>,
+    default_namespace: F,
+) -> Result<HoardConfig>
+where
+    F: FnOnce() -> String,
+{
     // First check if custom path should be used
-    hoard_home_path.map_or_else(load_or_build_default_path, |custom_path| {
-        info!("Found custom_path {:?}", custom_path);
-        let path = PathBuf::from(custom_path);
-        load_or_build(&path)
-    })
+    match hoard_home_path {
+        Some(custom_path) => {
+            info!("Found custom_path {:?}", custom_path);
+            let path = PathBuf::from(custom_path);
+            load_or_build(&path, default_namespace)
+        }
+        None => load_or_build_default_path(default_namespace),
+    }
 }
 
-fn load_or_build_default_path() -> Result<HoardConfig, Error> {
+fn load_or_build_default_path<F>(default_namespace: F) -> Result<HoardConfig, Error>
+where
+    F: FnOnce() -> String,
+{
     dirs::home_dir().map_or_else(
         || Err(anyhow!("No $HOME directory found for hoard config")),
-        |home| load_or_build(&home),
+        |home| load_or_build(&home, default_namespace),
     )
 }
 
 #[allow(clippy::useless_let_if_seq)]
-fn load_or_build(path: &Path) -> Result<HoardConfig, Error> {
+fn load_or_build<F>(path: &Path, default_namespace: F) -> Result<HoardConfig, Error>
+where
+    F: FnOnce() -> String,
+{
     info!("Loading or building in {:?}", path);
     let home_path = Path::new(&path);
 
@@ -176,12 +284,14 @@ fn load_or_build(path: &Path) -> Result<HoardConfig, Error> {
         }
 
         // Sanity check. If the config makes sense
-        assert!(loaded_config.parameter_token != loaded_config.parameter_ending_token, "Your parameter token {} is equal to your ending token {}. Please set one of them to another character!", loaded_config.parameter_token.as_ref().unwrap(), loaded_config.parameter_ending_token.as_ref().unwrap());
+        if loaded_config.parameter_token == loaded_config.parameter_ending_token {
+            return Err(anyhow!("Your parameter token {} is equal to your ending token {}. Please set one of them to another character!", loaded_config.parameter_token.as_ref().unwrap(), loaded_config.parameter_ending_token.as_ref().unwrap()));
+        }
 
         Ok(loaded_config)
     } else {
         info!("Config file does not exist. Creating new one");
-        let new_config = HoardConfig::new(&hoard_dir).with_default_namespace();
+        let new_config = HoardConfig::new(&hoard_dir).with_default_namespace(default_namespace());
         save_config(&new_config, &hoard_config_path)?;
         Ok(new_config)
     };
@@ -209,6 +319,12 @@ fn append_missing_default_values_to_config(
     } else if loaded_config.command_color.is_none() {
         loaded_config.command_color = Some(HoardConfig::default_colors(3));
         true
+    } else if loaded_config.theme.is_none() {
+        loaded_config.theme = Some(HoardConfig::default_theme());
+        true
+    } else if loaded_config.color_depth.is_none() {
+        loaded_config.color_depth = Some(ColorDepth::default());
+        true
     } else if loaded_config.trove_path.is_none() {
         loaded_config.trove_path = Some(hoard_dir.join(HOARD_FILE));
         true
@@ -224,6 +340,36 @@ fn append_missing_default_values_to_config(
     } else if loaded_config.sync_server_url.is_none() {
         loaded_config.sync_server_url = Some(HoardConfig::default_sync_server_url());
         true
+    } else if loaded_config.backup_count.is_none() {
+        loaded_config.backup_count = Some(HoardConfig::default_backup_count());
+        true
+    } else if loaded_config.storage_backend.is_none() {
+        loaded_config.storage_backend = Some(StorageBackend::default());
+        true
+    } else if loaded_config.database_path.is_none() {
+        loaded_config.database_path = Some(hoard_dir.join(HOARD_DATABASE));
+        true
+    } else if loaded_config.vault_path.is_none() {
+        loaded_config.vault_path = Some(hoard_dir.join(HOARD_VAULT));
+        true
+    } else if loaded_config.journal_path.is_none() {
+        loaded_config.journal_path = Some(hoard_dir.join(HOARD_JOURNAL));
+        true
+    } else if loaded_config.journal_size.is_none() {
+        loaded_config.journal_size = Some(HoardConfig::default_journal_size());
+        true
+    } else if loaded_config.env_files.is_none() {
+        loaded_config.env_files = Some(HashMap::new());
+        true
+    } else if loaded_config.workflow_mode.is_none() {
+        loaded_config.workflow_mode = Some(WorkflowMode::default());
+        true
+    } else if loaded_config.keymap.is_none() {
+        loaded_config.keymap = Some(HashMap::new());
+        true
+    } else if loaded_config.mouse.is_none() {
+        loaded_config.mouse = Some(true);
+        true
     } else {
         false
     };
@@ -238,20 +384,22 @@ pub fn save_parameter_token(
     config: &HoardConfig,
     config_path: &Path,
     parameter_token: &str,
-) -> bool {
+) -> Result<(), Error> {
     let mut new_config = config.clone();
     let path_buf = config_path.join(HOARD_CONFIG);
     new_config.parameter_token = Some(String::from(parameter_token));
-    match save_config(&new_config, path_buf.as_path()) {
-        Ok(()) => true,
-        Err(err) => {
-            eprintln!("ERROR: {err}");
-            err.chain()
-                .skip(1)
-                .for_each(|cause| eprintln!("because: {cause}"));
-            false
-        }
-    }
+    save_config(&new_config, path_buf.as_path())
+}
+
+pub fn save_storage_backend(
+    config: &HoardConfig,
+    config_path: &Path,
+    storage_backend: StorageBackend,
+) -> Result<(), Error> {
+    let mut new_config = config.clone();
+    let path_buf = config_path.join(HOARD_CONFIG);
+    new_config.storage_backend = Some(storage_backend);
+    save_config(&new_config, path_buf.as_path())
 }
 
 #[derive(Deserialize, Debug)]
@@ -281,14 +429,14 @@ pub struct ClientResponse {
 
 fn save_config(config_to_save: &HoardConfig, config_path: &Path) -> Result<(), Error> {
     let s = serde_yaml::to_string(&config_to_save)?;
-    fs::write(config_path, s).expect("Unable to write config file");
+    fs::write(config_path, s)?;
     Ok(())
 }
 
-
 #[cfg(test)]
 mod test_config {
-    use super::{save_parameter_token, HoardConfig, HOARD_CONFIG};
+    use super::{save_parameter_token, save_storage_backend, HoardConfig, HOARD_CONFIG};
+    use crate::core::store::StorageBackend;
     use std::fs::File;
     use tempfile::tempdir;
 
@@ -299,7 +447,7 @@ mod test_config {
         // write config file.
         let tmp_path = tmp_dir.path();
         let config = HoardConfig::new(tmp_path);
-        assert!(save_parameter_token(&config, tmp_path, "@"));
+        assert!(save_parameter_token(&config, tmp_path, "@").is_ok());
 
         // read config file, and check parameter token.
         let tmp_file = tmp_dir.path().join(HOARD_CONFIG);
@@ -307,4 +455,18 @@ mod test_config {
         let parsed_config = serde_yaml::from_reader::<_, HoardConfig>(f).ok().unwrap();
         assert_eq!(parsed_config.parameter_token, Some(String::from("@")));
     }
+
+    #[test]
+    fn test_save_storage_backend() {
+        let tmp_dir = tempdir().ok().unwrap();
+        let tmp_path = tmp_dir.path();
+        let config = HoardConfig::new(tmp_path);
+        assert_eq!(config.storage_backend, Some(StorageBackend::Yaml));
+        assert!(save_storage_backend(&config, tmp_path, StorageBackend::Sqlite).is_ok());
+
+        let f = File::open(tmp_path.join(HOARD_CONFIG)).ok().unwrap();
+        let parsed_config = serde_yaml::from_reader::<_, HoardConfig>(f).ok().unwrap();
+        assert_eq!(parsed_config.storage_backend, Some(StorageBackend::Sqlite));
+        assert_eq!(parsed_config.database_path, Some(tmp_path.join("trove.db")));
+    }
 }
@@ -0,0 +1,170 @@
+use anyhow::{anyhow, Result};
+use regex::{Captures, Regex};
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use crate::core::quoting::{quoting_at, QuotingMode};
+
+/// Variables to expand `${VAR}` and `${VAR:-default}` in commands with
+///
+/// Variables of the process environment take precedence over the `.env` file of the namespace of a command,
+/// the same way `dotenv` files never override variables which are already set
+#[derive(Debug, Clone, Default)]
+pub struct Environment {
+    namespaces: HashMap<String, HashMap<String, String>>,
+}
+
+impl Environment {
+    /// Reads the `.env` file of every namespace in `env_files`. Missing files are skipped
+    pub fn load(env_files: &HashMap<String, PathBuf>) -> Result<Self> {
+        let mut namespaces = HashMap::new();
+        for (namespace, path) in env_files {
+            if !path.exists() {
+                log::info!(
+                    "No .env file of namespace {namespace} at {}",
+                    path.display()
+                );
+                continue;
+            }
+            let variables = dotenvy::from_path_iter(path)
+                .and_then(Iterator::collect::<Result<HashMap<_, _>, _>>)
+                .map_err(|e| anyhow!("Invalid .env file {}: {e}", path.display()))?;
+            namespaces.insert(namespace.clone(), variables);
+        }
+        Ok(Self { namespaces })
+    }
+
+    /// The value of the variable `name` for commands of `namespace`
+    pub fn get(&self, namespace: &str, name: &str) -> Option<String> {
+        std::env::var(name).ok().or_else(|| {
+            self.namespaces
+                .get(namespace)
+                .and_then(|variables| variables.get(name))
+                .cloned()
+        })
+    }
+
+    /// Expands the variables in `command`, a command of `namespace`. See [`expand_variables`]
+    pub fn expand(
+        &self,
+        command: &str,
+        namespace: &str,
+        token: &str,
+        ending_token: &str,
+    ) -> String {
+        expand_variables(command, token, ending_token, |name| {
+            self.get(namespace, name)
+        })
+    }
+}
+
+/// Replaces `${VAR}` and `${VAR:-default}` in `command` with the value `lookup` returns for `VAR`
+///
+/// - Values of variables are quoted for POSIX shells, the same way values of parameters are. Defaults are kept as they are typed
+/// - `${VAR:-default}` is replaced with `default` if `VAR` is not set or empty
+/// - `${VAR}` of a variable which is not set becomes the parameter `<token>VAR<ending_token>`, so it is asked for
+/// - `$${VAR}` is kept as `${VAR}`, to leave it to the shell
+/// - Other shell expansions like `$VAR` or `${VAR%.*}` are kept as they are
+pub fn expand_variables<F>(command: &str, token: &str, ending_token: &str, lookup: F) -> String
+where
+    F: Fn(&str) -> Option<String>,
+{
+    let pattern = Regex::new(r"(\$?)\$\{([A-Za-z_][A-Za-z0-9_]*)(:-([^}]*))?\}").unwrap();
+    pattern
+        .replace_all(command, |captures: &Captures| {
+            let expression = &captures[0];
+            if !captures[1].is_empty() {
+                return expression[1..].to_string();
+            }
+            let name = &captures[2];
+            let value = lookup(name);
+            match (value, captures.get(4)) {
+                (Some(value), Some(default)) if value.is_empty() => default.as_str().to_string(),
+                // An empty variable leaves nothing behind, as it does in the shell
+                (Some(value), _) if value.is_empty() => value,
+                (Some(value), _) => {
+                    let quoting = quoting_at(command, captures.get(0).unwrap().start());
+                    QuotingMode::Sh.quote(&value, quoting)
+                }
+                (None, Some(default)) => default.as_str().to_string(),
+                (None, None) => format!("{token}{name}{ending_token}"),
+            }
+        })
+        .into_owned()
+}
+
+#[cfg(test)]
+mod test_environment {
+    use super::*;
+
+    fn lookup(name: &str) -> Option<String> {
+        match name {
+            "HOST" => Some("example.com".to_string()),
+            "EMPTY" => Some(String::new()),
+            "TITLE" => Some(r#"it's "done""#.to_string()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_expand_variables() {
+        let expand = |command| expand_variables(command, "#", "!", lookup);
+        assert_eq!("ssh example.com", expand("ssh ${HOST}"));
+        assert_eq!("ssh example.com", expand("ssh ${HOST:-localhost}"));
+        assert_eq!("ssh localhost", expand("ssh ${EMPTY:-localhost}"));
+        assert_eq!("ssh ", expand("ssh ${EMPTY}"));
+        assert_eq!(
+            "ssh localhost:22",
+            expand("ssh ${MISSING:-localhost}:${PORT:-22}")
+        );
+        assert_eq!(
+            "ssh #USER!@example.com",
+            expand("ssh ${USER_NAME_UNSET:-#USER!}@${HOST}")
+        );
+        assert_eq!("curl #TOKEN!", expand("curl ${TOKEN}"));
+    }
+
+    #[test]
+    fn test_values_are_quoted() {
+        let expand = |command| expand_variables(command, "#", "!", lookup);
+        assert_eq!(r#"echo 'it'\''s "done"'"#, expand("echo ${TITLE}"));
+        assert_eq!(r#"echo "it's \"done\"""#, expand(r#"echo "${TITLE}""#));
+        assert_eq!(r#"echo 'it'\''s "done"'"#, expand("echo '${TITLE}'"));
+        assert_eq!("echo a b", expand("echo ${MISSING:-a b}"));
+    }
+
+    #[test]
+    fn test_shell_expansions_are_kept() {
+        let expand = |command| expand_variables(command, "#", "!", lookup);
+        assert_eq!("echo ${HOST}", expand("echo $${HOST}"));
+        assert_eq!("echo $HOST $$", expand("echo $HOST $$"));
+        assert_eq!(
+            "echo ${HOST%.*} ${#HOST}",
+            expand("echo ${HOST%.*} ${#HOST}")
+        );
+    }
+
+    #[test]
+    fn test_namespace_env_files() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let env_file = tmp_dir.path().join("k8s.env");
+        std::fs::write(&env_file, "HOARD_TEST_CLUSTER=staging\nPATH=/nowhere\n").unwrap();
+        let env_files = HashMap::from([
+            ("k8s".to_string(), env_file),
+            ("missing".to_string(), tmp_dir.path().join("missing.env")),
+        ]);
+        let environment = Environment::load(&env_files).unwrap();
+
+        assert_eq!(
+            Some("staging".to_string()),
+            environment.get("k8s", "HOARD_TEST_CLUSTER")
+        );
+        assert_eq!(None, environment.get("git", "HOARD_TEST_CLUSTER"));
+        // The process environment wins
+        assert_ne!(Some("/nowhere".to_string()), environment.get("k8s", "PATH"));
+        assert_eq!(
+            "kubectl --context staging",
+            environment.expand("kubectl --context ${HOARD_TEST_CLUSTER}", "k8s", "#", "!")
+        );
+    }
+}
@@ -1,27 +1,157 @@
-use std::error::Error;
-use std::fmt;
+use std::path::{Path, PathBuf};
 
-#[derive(Debug)]
-pub struct HoardErr {
-    details: String,
+/// Everything that can go wrong in hoard
+///
+/// Every variant has its own exit code, so scripts can tell the failures apart.
+/// See [`HoardError::exit_code`]
+#[derive(Debug, thiserror::Error)]
+pub enum HoardError {
+    /// The config file could not be loaded or saved
+    #[error("Invalid config: {0}")]
+    Config(String),
+    /// The trove file could not be read or parsed
+    #[error(transparent)]
+    Trove(#[from] TroveError),
+    /// The trove file could not be written
+    #[error("Could not save the trove file: {0}")]
+    TroveWrite(String),
+    /// A command, its name or its tags are invalid
+    #[error("{0}")]
+    Validation(String),
+    /// No command matches the given reference
+    #[error("No matching command found for: {0}")]
+    NotFound(String),
+    /// A name matches commands in several namespaces
+    #[error("{reference} matches several commands, use its id or namespace/name instead: {}", .candidates.join(", "))]
+    Ambiguous {
+        reference: String,
+        candidates: Vec<String>,
+    },
+    /// The sync server could not be reached or rejected the request
+    #[error("Sync failed: {0}")]
+    Sync(String),
+    /// The vault could not be read, unlocked or written
+    #[error("{0}")]
+    Secret(String),
+    /// A step run by `hoard run` failed
+    #[error("{0}")]
+    StepFailed(String),
+    /// Invalid command line arguments
+    #[error("{0}")]
+    Usage(String),
+    /// Any other failure, e.g. of the terminal
+    #[error("{0}")]
+    Other(String),
 }
 
-impl HoardErr {
-    pub fn new(msg: &str) -> Self {
-        Self {
-            details: msg.to_string(),
+impl HoardError {
+    /// The exit code of the hoard process failing with this error
+    ///
+    /// | Code | Error |
+    /// |------|-------|
+    /// | 1 | Any other failure |
+    /// | 2 | Invalid command line arguments |
+    /// | 3 | Invalid config |
+    /// | 4 | Unreadable or invalid trove file |
+    /// | 5 | The trove file could not be saved |
+    /// | 6 | Invalid command, name or tags |
+    /// | 7 | Command not found |
+    /// | 8 | Ambiguous command name |
+    /// | 9 | Sync failed |
+    /// | 10 | The vault could not be read, unlocked or written |
+    /// | 11 | A step run by `hoard run` failed |
+    pub const fn exit_code(&self) -> i32 {
+        match self {
+            Self::Other(_) => 1,
+            Self::Usage(_) => 2,
+            Self::Config(_) => 3,
+            Self::Trove(_) => 4,
+            Self::TroveWrite(_) => 5,
+            Self::Validation(_) => 6,
+            Self::NotFound(_) => 7,
+            Self::Ambiguous { .. } => 8,
+            Self::Sync(_) => 9,
+            Self::Secret(_) => 10,
+            Self::StepFailed(_) => 11,
         }
     }
 }
 
-impl fmt::Display for HoardErr {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}", self.details)
+/// Reasons why a trove file could not be loaded
+#[derive(Debug, thiserror::Error)]
+pub enum TroveError {
+    #[error("Could not read trove file {}: {source}", path.display())]
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("Invalid trove file {}:{line}:{column}: {message}", path.display())]
+    Syntax {
+        path: PathBuf,
+        line: usize,
+        column: usize,
+        message: String,
+    },
+    #[error("Invalid trove file {}: {message}", path.display())]
+    Invalid { path: PathBuf, message: String },
+}
+
+impl TroveError {
+    /// Build an error from a yaml error, keeping the location of the error if there is one
+    pub fn from_yaml(path: &Path, error: &serde_yaml::Error) -> Self {
+        let message = error.to_string();
+        match error.location() {
+            Some(location) => Self::Syntax {
+                path: path.to_path_buf(),
+                line: location.line(),
+                column: location.column(),
+                // The location is part of the error itself
+                message: message
+                    .split(" at line ")
+                    .next()
+                    .unwrap_or_default()
+                    .to_string(),
+            },
+            None => Self::Invalid {
+                path: path.to_path_buf(),
+                message,
+            },
+        }
     }
 }
 
-impl Error for HoardErr {
-    fn description(&self) -> &str {
-        &self.details
+#[cfg(test)]
+mod test_error {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_exit_codes_are_distinct() {
+        let errors = [
+            HoardError::Other(String::new()),
+            HoardError::Usage(String::new()),
+            HoardError::Config(String::new()),
+            HoardError::Trove(TroveError::Invalid {
+                path: PathBuf::new(),
+                message: String::new(),
+            }),
+            HoardError::TroveWrite(String::new()),
+            HoardError::Validation(String::new()),
+            HoardError::NotFound(String::new()),
+            HoardError::Ambiguous {
+                reference: String::new(),
+                candidates: Vec::new(),
+            },
+            HoardError::Sync(String::new()),
+            HoardError::Secret(String::new()),
+            HoardError::StepFailed(String::new()),
+        ];
+        let exit_codes = errors
+            .iter()
+            .map(HoardError::exit_code)
+            .collect::<HashSet<_>>();
+        assert_eq!(errors.len(), exit_codes.len());
+        // 0 is success
+        assert!(!exit_codes.contains(&0));
     }
 }
@@ -0,0 +1,651 @@
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::time::SystemTime;
+
+use crate::core::parameters::Parameterized;
+use crate::core::quoting::{quoting_at, Quoting};
+use crate::core::trove::Trove;
+use crate::core::HoardCmd;
+
+/// Returns the commands of the trove ordered by namespace and name
+fn sorted_commands(trove: &Trove) -> Vec<&HoardCmd> {
+    let mut commands: Vec<&HoardCmd> = trove.commands.iter().collect();
+    commands.sort_by(|a, b| (&a.namespace, &a.name).cmp(&(&b.namespace, &b.name)));
+    commands
+}
+
+/// Rebuilds the command string with every parameter replaced by the result of `replacement`
+///
+/// `replacement` is called with the parameter name and the position in the command string the parameter started at
+fn replace_parameters<F>(
+    command: &HoardCmd,
+    token: &str,
+    ending_token: &str,
+    mut replacement: F,
+) -> String
+where
+    F: FnMut(&str, usize) -> String,
+{
+    let mut replaced = String::new();
+    let mut last_end = 0;
+    for parameter in command.get_parameters(token, ending_token) {
+        replaced.push_str(&command.command[last_end..parameter.range.start]);
+        replaced.push_str(&replacement(&parameter.name, parameter.range.start));
+        last_end = parameter.range.end;
+    }
+    replaced.push_str(&command.command[last_end..]);
+    replaced
+}
+
+/// Renders the trove as a markdown cheat sheet with one section per namespace
+pub fn to_markdown(trove: &Trove) -> String {
+    let mut markdown = String::from("# Hoard cheat sheet\n");
+    let mut namespace = "";
+    for command in sorted_commands(trove) {
+        if command.namespace != namespace {
+            namespace = &command.namespace;
+            markdown.push_str(&format!("\n## {namespace}\n"));
+        }
+        markdown.push_str(&format!("\n### {}\n\n", command.name));
+        if !command.description.is_empty() {
+            markdown.push_str(&format!("{}\n\n", command.description));
+        }
+        if !command.tags.is_empty() {
+            let tags: Vec<String> = command.tags.iter().map(|t| format!("`{t}`")).collect();
+            markdown.push_str(&format!("Tags: {}\n\n", tags.join(", ")));
+        }
+        markdown.push_str(&format!("```sh\n{}\n```\n", command.command));
+    }
+    markdown
+}
+
+/// Machine readable formats `hoard list` can print the commands in
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ListFormat {
+    Json,
+    Yaml,
+    /// One line per command, see `render_template`
+    Template(String),
+    /// Like `Template`, but every record is terminated by a null character instead of separated by newlines
+    Records(String),
+}
+
+/// Version of the json schema of `ListedTrove`.
+/// Has to be increased whenever a field is removed or changes its meaning
+pub const LIST_SCHEMA_VERSION: u32 = 1;
+
+/// Machine readable representation of a trove, as printed by `hoard list --json`
+#[derive(Debug, Serialize)]
+pub struct ListedTrove {
+    pub schema_version: u32,
+    pub hoard_version: String,
+    pub commands: Vec<ListedCommand>,
+}
+
+/// Machine readable representation of a single command
+#[derive(Debug, Serialize)]
+pub struct ListedCommand {
+    pub id: String,
+    pub name: String,
+    pub namespace: String,
+    pub command: String,
+    pub description: String,
+    pub tags: Vec<String>,
+    pub parameters: Vec<ListedParameter>,
+    pub usage_count: usize,
+    pub flags: ListedFlags,
+    pub created: String,
+    pub modified: String,
+    pub last_used: String,
+}
+
+/// A parameter of a command. Named parameters are listed once, unnamed parameters once per occurrence
+#[derive(Debug, Serialize, PartialEq, Eq)]
+pub struct ListedParameter {
+    pub name: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ListedFlags {
+    pub favorite: bool,
+    pub hidden: bool,
+    pub deleted: bool,
+}
+
+fn to_rfc3339(time: SystemTime) -> String {
+    DateTime::<Utc>::from(time).to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+/// Lists the parameters of a command, every named parameter only once
+fn listed_parameters(command: &HoardCmd, token: &str, ending_token: &str) -> Vec<ListedParameter> {
+    let mut parameters: Vec<ListedParameter> = Vec::new();
+    for parameter in command.get_parameters(token, ending_token) {
+        let listed = ListedParameter {
+            name: Some(parameter.name).filter(|name| !name.is_empty()),
+        };
+        if listed.name.is_none() || !parameters.contains(&listed) {
+            parameters.push(listed);
+        }
+    }
+    parameters
+}
+
+impl ListedCommand {
+    pub fn new(command: &HoardCmd, token: &str, ending_token: &str) -> Self {
+        Self {
+            id: command.id.clone(),
+            name: command.name.clone(),
+            namespace: command.namespace.clone(),
+            command: command.command.clone(),
+            description: command.description.clone(),
+            tags: command.tags.clone(),
+            parameters: listed_parameters(command, token, ending_token),
+            usage_count: command.usage_count,
+            flags: ListedFlags {
+                favorite: command.is_favorite,
+                hidden: command.is_hidden,
+                deleted: command.is_deleted,
+            },
+            created: to_rfc3339(command.created),
+            modified: to_rfc3339(command.modified),
+            last_used: to_rfc3339(command.last_used),
+        }
+    }
+}
+
+impl ListedTrove {
+    pub fn new(trove: &Trove, token: &str, ending_token: &str) -> Self {
+        Self {
+            schema_version: LIST_SCHEMA_VERSION,
+            hoard_version: trove.version.clone(),
+            commands: trove
+                .commands
+                .iter()
+                .map(|command| ListedCommand::new(command, token, ending_token))
+                .collect(),
+        }
+    }
+}
+
+/// Renders the trove as pretty printed json
+pub fn to_json(trove: &Trove) -> Result<String> {
+    Ok(serde_json::to_string_pretty(trove)?)
+}
+
+/// Renders the trove as pretty printed json following the schema of `ListedTrove`
+pub fn to_list_json(trove: &Trove, token: &str, ending_token: &str) -> Result<String> {
+    Ok(serde_json::to_string_pretty(&ListedTrove::new(
+        trove,
+        token,
+        ending_token,
+    ))?)
+}
+
+/// Fields that can be used as `{field}` placeholders in templates of `hoard list --format`
+pub const TEMPLATE_FIELDS: &[&str] = &[
+    "id",
+    "name",
+    "namespace",
+    "command",
+    "description",
+    "tags",
+    "parameters",
+    "usage_count",
+];
+
+fn template_field(command: &HoardCmd, field: &str, token: &str, ending_token: &str) -> String {
+    match field {
+        "id" => command.id.clone(),
+        "name" => command.name.clone(),
+        "namespace" => command.namespace.clone(),
+        "command" => command.command.clone(),
+        "description" => command.description.clone(),
+        "tags" => command.get_tags_as_string(),
+        "parameters" => listed_parameters(command, token, ending_token)
+            .into_iter()
+            .map(|p| p.name.unwrap_or_default())
+            .collect::<Vec<_>>()
+            .join(","),
+        "usage_count" => command.usage_count.to_string(),
+        _ => String::new(),
+    }
+}
+
+/// Builds a template printing the given comma separated `fields` separated by tabs
+pub fn template_from_fields(fields: &str) -> String {
+    fields
+        .split(',')
+        .map(|field| format!("{{{}}}", field.trim()))
+        .collect::<Vec<_>>()
+        .join("\\t")
+}
+
+/// Template used by `hoard list --preview-format` for external pickers like fzf.
+/// The first tab separated field is the id of the command, which can be passed to `hoard preview` and `hoard pick`
+pub const PICKER_TEMPLATE: &str = "{id}\\t{namespace}/{name}\\t{command}";
+
+/// Renders every command of the trove into one record using `template`
+///
+/// `{field}` placeholders are replaced by the field of the command, see `TEMPLATE_FIELDS`.
+/// `\t`, `\n` and `\0` are replaced by a tab, newline or null character, `\\` by a single backslash.
+pub fn render_template(
+    trove: &Trove,
+    template: &str,
+    token: &str,
+    ending_token: &str,
+) -> Result<Vec<String>> {
+    let placeholder = regex::Regex::new(r"\{([a-z_]*)\}").unwrap();
+    if let Some(unknown) = placeholder
+        .captures_iter(template)
+        .map(|capture| capture[1].to_string())
+        .find(|field| !TEMPLATE_FIELDS.contains(&field.as_str()))
+    {
+        return Err(anyhow!(
+            "Unknown field {{{unknown}}}. Available fields: {}",
+            TEMPLATE_FIELDS.join(", ")
+        ));
+    }
+    let template = unescape(template);
+    let records = trove
+        .commands
+        .iter()
+        .map(|command| {
+            placeholder
+                .replace_all(&template, |capture: &regex::Captures| {
+                    template_field(command, &capture[1], token, ending_token)
+                })
+                .to_string()
+        })
+        .collect();
+    Ok(records)
+}
+
+/// Renders a human readable preview of a command, as shown by `hoard preview`
+pub fn render_preview(command: &HoardCmd, token: &str, ending_token: &str) -> String {
+    let mut preview = format!(
+        "{}/{}  (id: {})\n",
+        command.namespace, command.name, command.id
+    );
+    if !command.description.is_empty() {
+        preview.push_str(&format!("\n{}\n", command.description));
+    }
+    preview.push('\n');
+    if !command.tags.is_empty() {
+        preview.push_str(&format!("Tags:        {}\n", command.tags.join(", ")));
+    }
+    let parameters: Vec<String> = listed_parameters(command, token, ending_token)
+        .into_iter()
+        .map(|p| p.name.unwrap_or_else(|| String::from("<unnamed>")))
+        .collect();
+    if !parameters.is_empty() {
+        preview.push_str(&format!("Parameters:  {}\n", parameters.join(", ")));
+    }
+    let last_used = DateTime::<chrono::Local>::from(command.last_used).format("%Y-%m-%d %H:%M");
+    preview.push_str(&format!(
+        "Used:        {} times, last {last_used}\n",
+        command.usage_count
+    ));
+    if command.is_favorite {
+        preview.push_str("Favorite:    yes\n");
+    }
+    preview.push_str(&format!("\n{}\n", command.command));
+    preview
+}
+
+fn unescape(template: &str) -> String {
+    let mut unescaped = String::new();
+    let mut chars = template.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('t') => unescaped.push('\t'),
+            Some('n') => unescaped.push('\n'),
+            Some('0') => unescaped.push('\0'),
+            Some('\\') => unescaped.push('\\'),
+            Some(other) => {
+                unescaped.push('\\');
+                unescaped.push(other);
+            }
+            None => unescaped.push('\\'),
+        }
+    }
+    unescaped
+}
+
+/// Renders the trove as toml
+pub fn to_toml(trove: &Trove) -> Result<String> {
+    Ok(toml::to_string(trove)?)
+}
+
+fn escape_csv(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Renders the commands of the trove as csv with a header row
+pub fn to_csv(trove: &Trove) -> String {
+    let mut csv = String::from("namespace,name,command,description,tags,usage_count\n");
+    for command in sorted_commands(trove) {
+        let row = [
+            escape_csv(&command.namespace),
+            escape_csv(&command.name),
+            escape_csv(&command.command),
+            escape_csv(&command.description),
+            escape_csv(&command.get_tags_as_string()),
+            command.usage_count.to_string(),
+        ];
+        csv.push_str(&row.join(","));
+        csv.push('\n');
+    }
+    csv
+}
+
+/// Renders the trove as a navi cheatsheet
+///
+/// Parameters are converted to `<name>` placeholders, unnamed parameters are numbered.
+/// A new `%` section is started whenever the namespace or the tags of the commands change.
+pub fn to_navi(trove: &Trove, token: &str, ending_token: &str) -> String {
+    let mut cheat = String::new();
+    let mut section = String::new();
+    for command in sorted_commands(trove) {
+        let mut tags = vec![command.namespace.clone()];
+        tags.extend(
+            command
+                .tags
+                .iter()
+                .filter(|t| **t != command.namespace)
+                .cloned(),
+        );
+        let command_section = format!("% {}\n", tags.join(", "));
+        if command_section != section {
+            if !section.is_empty() {
+                cheat.push('\n');
+            }
+            cheat.push_str(&command_section);
+            section = command_section;
+        }
+        let description = command.description.lines().next().unwrap_or_default();
+        let description = if description.is_empty() {
+            &command.name
+        } else {
+            description
+        };
+        let mut unnamed = 0;
+        let command_string = replace_parameters(command, token, ending_token, |name, _| {
+            if name.is_empty() {
+                unnamed += 1;
+                format!("<param{unnamed}>")
+            } else {
+                format!("<{}>", name.replace(char::is_whitespace, "_"))
+            }
+        });
+        cheat.push_str(&format!("\n# {description}\n{command_string}\n"));
+    }
+    cheat
+}
+
+/// Name of the shell function of `command`, a valid shell identifier which none of the `taken` names is
+fn function_name(command: &HoardCmd, taken: &HashSet<String>) -> String {
+    let mut name: String = format!("{}_{}", command.namespace, command.name)
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    // Shell identifiers can't start with a digit
+    if name.starts_with(|c: char| c.is_ascii_digit()) {
+        name.insert(0, '_');
+    }
+    if !taken.contains(&name) {
+        return name;
+    }
+    (2..)
+        .map(|n| format!("{name}_{n}"))
+        .find(|numbered| !taken.contains(numbered))
+        .unwrap()
+}
+
+/// Renders the trove as a sourceable shell file with one function per command
+///
+/// Functions are named `<namespace>_<name>`, with `_` for every character a shell function name can't have.
+/// Names which come out alike are numbered. Parameters become positional arguments of the function,
+/// every occurrence of a named parameter refers to the same argument.
+pub fn to_shell_functions(trove: &Trove, token: &str, ending_token: &str) -> String {
+    let mut script = String::from("# Generated by hoard. Source this file in your shell config\n");
+    let mut names = HashSet::new();
+    for command in sorted_commands(trove) {
+        let mut positions: HashMap<String, usize> = HashMap::new();
+        let mut argument_count = 0;
+        let body = replace_parameters(command, token, ending_token, |name, start| {
+            let argument = if name.is_empty() {
+                argument_count += 1;
+                argument_count
+            } else {
+                *positions.entry(name.to_string()).or_insert_with(|| {
+                    argument_count += 1;
+                    argument_count
+                })
+            };
+            match quoting_at(&command.command, start) {
+                Quoting::None => format!("\"${{{argument}}}\""),
+                Quoting::Single => format!("'\"${{{argument}}}\"'"),
+                Quoting::Double => format!("${{{argument}}}"),
+            }
+        });
+        script.push('\n');
+        for line in command.description.lines() {
+            script.push_str(&format!("# {line}\n"));
+        }
+        let name = function_name(command, &names);
+        script.push_str(&format!("{name}() {{\n    {body}\n}}\n"));
+        names.insert(name);
+    }
+    script
+}
+
+#[cfg(test)]
+mod test_export {
+    use super::*;
+
+    fn create_trove() -> Trove {
+        let mut trove = Trove::default();
+        let commands = [
+            HoardCmd::default()
+                .with_name("checkout")
+                .with_namespace("git")
+                .with_command("git checkout #branch")
+                .with_description("Change branch")
+                .with_tags(vec!["vcs".to_string()]),
+            HoardCmd::default()
+                .with_name("greet")
+                .with_namespace("echo")
+                .with_command("echo \"Hi #name!, bye #name!\" '#x!' #")
+                .with_description("Greet, then leave"),
+        ];
+        for command in commands {
+            trove.add_command(command, true).unwrap();
+        }
+        trove
+    }
+
+    #[test]
+    fn export_markdown() {
+        let expected = "# Hoard cheat sheet
+
+## echo
+
+### greet
+
+Greet, then leave
+
+```sh
+echo \"Hi #name!, bye #name!\" '#x!' #
+```
+
+## git
+
+### checkout
+
+Change branch
+
+Tags: `vcs`
+
+```sh
+git checkout #branch
+```
+";
+        assert_eq!(expected, to_markdown(&create_trove()));
+    }
+
+    #[test]
+    fn export_csv() {
+        let expected = "namespace,name,command,description,tags,usage_count
+echo,greet,\"echo \"\"Hi #name!, bye #name!\"\" '#x!' #\",\"Greet, then leave\",,0
+git,checkout,git checkout #branch,Change branch,vcs,0
+";
+        assert_eq!(expected, to_csv(&create_trove()));
+    }
+
+    #[test]
+    fn export_navi() {
+        let expected = "% echo
+
+# Greet, then leave
+echo \"Hi <name>, bye <name>\" '<x>' <param1>
+
+% git, vcs
+
+# Change branch
+git checkout <branch>
+";
+        assert_eq!(expected, to_navi(&create_trove(), "#", "!"));
+    }
+
+    #[test]
+    fn export_shell_functions() {
+        let expected = "# Generated by hoard. Source this file in your shell config
+
+# Greet, then leave
+echo_greet() {
+    echo \"Hi ${1}, bye ${1}\" ''\"${2}\"'' \"${3}\"
+}
+
+# Change branch
+git_checkout() {
+    git checkout \"${1}\"
+}
+";
+        assert_eq!(expected, to_shell_functions(&create_trove(), "#", "!"));
+    }
+
+    #[test]
+    fn shell_function_names_are_identifiers() {
+        let mut trove = Trove::default();
+        for (namespace, name) in [
+            ("1password", "read"),
+            ("k8s", "get-pods"),
+            ("k8s", "get_pods"),
+            ("k8s_get", "pods"),
+        ] {
+            let command = HoardCmd::default()
+                .with_namespace(namespace)
+                .with_name(name)
+                .with_command("true");
+            trove.add_command(command, true).unwrap();
+        }
+        let script = to_shell_functions(&trove, "#", "!");
+        let names: Vec<&str> = script
+            .lines()
+            .filter_map(|line| line.strip_suffix("() {"))
+            .collect();
+        assert_eq!(
+            vec![
+                "_1password_read",
+                "k8s_get_pods",
+                "k8s_get_pods_2",
+                "k8s_get_pods_3"
+            ],
+            names
+        );
+    }
+
+    #[test]
+    fn export_json_and_toml() {
+        let trove = create_trove();
+        let json: Trove = serde_json::from_str(&to_json(&trove).unwrap()).unwrap();
+        assert_eq!(trove.commands, json.commands);
+        let toml: Trove = toml::from_str(&to_toml(&trove).unwrap()).unwrap();
+        assert_eq!(trove.commands, toml.commands);
+    }
+
+    #[test]
+    fn list_json() {
+        let mut trove = create_trove();
+        trove.commands[0].created = std::time::UNIX_EPOCH;
+        let json: serde_json::Value =
+            serde_json::from_str(&to_list_json(&trove, "#", "!").unwrap()).unwrap();
+        assert_eq!(LIST_SCHEMA_VERSION, json["schema_version"]);
+        let command = &json["commands"][0];
+        assert_eq!(trove.commands[0].id, command["id"]);
+        assert_eq!("1970-01-01T00:00:00Z", command["created"]);
+        assert_eq!(false, command["flags"]["favorite"]);
+        let parameters = &json["commands"][1]["parameters"];
+        assert_eq!(
+            serde_json::json!([{"name": "name"}, {"name": "x"}, {"name": null}]),
+            *parameters
+        );
+    }
+
+    #[test]
+    fn render_fields_template() {
+        let template = template_from_fields("namespace, name");
+        assert_eq!("{namespace}\\t{name}", template);
+        let rendered = render_template(&create_trove(), &template, "#", "!").unwrap();
+        assert_eq!(vec!["git\tcheckout", "echo\tgreet"], rendered);
+    }
+
+    #[test]
+    fn render_template_with_escapes() {
+        let rendered =
+            render_template(&create_trove(), "{name}\\0{parameters}\\\\", "#", "!").unwrap();
+        assert_eq!(vec!["checkout\0branch\\", "greet\0name,x,\\"], rendered);
+    }
+
+    #[test]
+    fn render_picker_template() {
+        let trove = create_trove();
+        let rendered = render_template(&trove, PICKER_TEMPLATE, "#", "!").unwrap();
+        assert_eq!(
+            format!(
+                "{}\tgit/checkout\tgit checkout #branch",
+                trove.commands[0].id
+            ),
+            rendered[0]
+        );
+    }
+
+    #[test]
+    fn render_command_preview() {
+        let trove = create_trove();
+        let preview = render_preview(&trove.commands[1], "#", "!");
+        assert!(preview.starts_with(&format!(
+            "echo/greet  (id: {})\n\nGreet, then leave\n\n",
+            trove.commands[1].id
+        )));
+        assert!(preview.contains("\nParameters:  name, x, <unnamed>\n"));
+        assert!(preview.contains("\nUsed:        0 times, last "));
+        assert!(!preview.contains("Tags:"));
+        assert!(preview.ends_with("\n\necho \"Hi #name!, bye #name!\" '#x!' #\n"));
+    }
+
+    #[test]
+    fn render_template_with_unknown_field() {
+        assert!(render_template(&create_trove(), "{nope}", "#", "!").is_err());
+    }
+}
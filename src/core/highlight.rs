@@ -0,0 +1,362 @@
+use std::ops::Range;
+
+use crate::core::parameters::{parse_parameters, SECRET_PREFIX};
+use crate::core::quoting::{Quoting, QuotingMode};
+
+/// What a part of a command is, to color it by
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    /// Arguments, whitespace and everything else without a color of its own
+    Plain,
+    /// The program a command runs
+    Command,
+    Flag,
+    /// Quoted text, including its quotes
+    String,
+    /// A shell variable like `$HOME` or `${HOME}`
+    Variable,
+    /// Pipes, redirections and what chains commands, like `&&` and `;`
+    Operator,
+    Comment,
+    /// The tokens a parameter starts and ends with, like `#` and `!`
+    ParameterToken,
+    /// The prefix of a parameter name telling how its value is quoted, or that it is a secret
+    ParameterType,
+    ParameterName,
+    /// A value filled into a parameter
+    Filled,
+}
+
+/// Splits `command` into the parts the shell and hoard see in it, so it can be colored by them
+///
+/// Parameters starting with `token` and ending with `ending_token` are split into their tokens, type and name.
+/// `filled` are the byte ranges of values filled into parameters so far, which are shown as they are.
+/// The parts cover all of `command`, and parts of the same kind next to each other are merged
+///
+/// # Example
+///
+/// ```
+/// # use hoardlib::core::highlight::{highlight, Kind};
+/// let parts = highlight("ls -l #path:dir! | less", "#", "!", &[]);
+/// let kinds: Vec<Kind> = parts.iter().map(|(kind, _)| *kind).collect();
+/// assert_eq!(kinds[..4], [Kind::Command, Kind::Plain, Kind::Flag, Kind::Plain]);
+/// assert_eq!((Kind::ParameterType, 7..12), parts[5]);
+/// ```
+pub fn highlight(
+    command: &str,
+    token: &str,
+    ending_token: &str,
+    filled: &[Range<usize>],
+) -> Vec<(Kind, Range<usize>)> {
+    // Parameters and filled values aren't lexed, they are one word each
+    let mut opaque: Vec<Vec<(Kind, Range<usize>)>> = filled
+        .iter()
+        .filter(|range| range.end <= command.len())
+        .map(|range| vec![(Kind::Filled, range.clone())])
+        .collect();
+    for parameter in parse_parameters(command, token, ending_token) {
+        let range = parameter.range;
+        if !filled
+            .iter()
+            .any(|f| f.start < range.end && range.start < f.end)
+        {
+            opaque.push(parameter_parts(command, range, token, ending_token));
+        }
+    }
+    opaque.sort_by_key(|parts| parts[0].1.start);
+
+    let mut lexer = Lexer::default();
+    let mut position = 0;
+    for parts in opaque {
+        let (start, end) = (parts[0].1.start, parts[parts.len() - 1].1.end);
+        if start < position {
+            continue;
+        }
+        lexer.lex(command, position..start);
+        lexer.word();
+        lexer.parts.extend(parts);
+        position = end;
+    }
+    lexer.lex(command, position..command.len());
+    merge(lexer.parts)
+}
+
+/// The tokens, type and name of the parameter at `range` of `command`
+fn parameter_parts(
+    command: &str,
+    range: Range<usize>,
+    token: &str,
+    ending_token: &str,
+) -> Vec<(Kind, Range<usize>)> {
+    let name_start = range.start + token.len();
+    let ended = !ending_token.is_empty()
+        && range.end >= name_start + ending_token.len()
+        && command[..range.end].ends_with(ending_token);
+    let name_end = if ended {
+        range.end - ending_token.len()
+    } else {
+        range.end
+    };
+    let name = &command[name_start..name_end];
+    let (_, unquoted) = QuotingMode::from_name(name);
+    let typed = unquoted.strip_prefix(SECRET_PREFIX).unwrap_or(unquoted);
+    let type_end = name_end - typed.len();
+    let mut parts = vec![
+        (Kind::ParameterToken, range.start..name_start),
+        (Kind::ParameterType, name_start..type_end),
+        (Kind::ParameterName, type_end..name_end),
+    ];
+    if ended {
+        parts.push((Kind::ParameterToken, name_end..range.end));
+    }
+    parts.retain(|(_, range)| !range.is_empty());
+    parts
+}
+
+/// Parts of the same kind next to each other as one
+fn merge(parts: Vec<(Kind, Range<usize>)>) -> Vec<(Kind, Range<usize>)> {
+    let mut merged: Vec<(Kind, Range<usize>)> = Vec::with_capacity(parts.len());
+    for (kind, range) in parts.into_iter().filter(|(_, range)| !range.is_empty()) {
+        match merged.last_mut() {
+            Some((last, last_range)) if *last == kind && last_range.end == range.start => {
+                last_range.end = range.end;
+            }
+            _ => merged.push((kind, range)),
+        }
+    }
+    merged
+}
+
+/// Splits text into the parts the shell sees in it. Its state is kept between calls,
+/// so a string or word can go on after a parameter
+#[derive(Debug)]
+struct Lexer {
+    parts: Vec<(Kind, Range<usize>)>,
+    quoting: Quoting,
+    /// Whether the next word is a command
+    command_expected: bool,
+    /// The kind of the unquoted word being lexed, if any
+    word: Option<Kind>,
+}
+
+impl Default for Lexer {
+    fn default() -> Self {
+        Self {
+            parts: Vec::new(),
+            quoting: Quoting::None,
+            command_expected: true,
+            word: None,
+        }
+    }
+}
+
+impl Lexer {
+    /// Starts a word if there is none, and returns its kind
+    fn word(&mut self) -> Kind {
+        if self.quoting != Quoting::None {
+            return Kind::String;
+        }
+        *self.word.get_or_insert_with(|| {
+            if std::mem::take(&mut self.command_expected) {
+                Kind::Command
+            } else {
+                Kind::Plain
+            }
+        })
+    }
+
+    fn push(&mut self, kind: Kind, start: usize, len: usize) {
+        self.parts.push((kind, start..start + len));
+    }
+
+    /// Lexes the part `range` of `command`
+    fn lex(&mut self, command: &str, range: Range<usize>) {
+        let text = &command[range.clone()];
+        let mut chars = text.char_indices();
+        while let Some((i, c)) = chars.next() {
+            let at = range.start + i;
+            let rest = &text[i..];
+            match (self.quoting, c) {
+                (Quoting::Single, '\'') | (Quoting::Double, '"') => {
+                    self.push(Kind::String, at, 1);
+                    self.quoting = Quoting::None;
+                }
+                (Quoting::Double, '\\') | (Quoting::None, '\\') => {
+                    let kind = self.word();
+                    let escaped = chars.next().map_or(0, |(_, c)| c.len_utf8());
+                    self.push(kind, at, 1 + escaped);
+                }
+                (Quoting::Double, '$') | (Quoting::None, '$') => {
+                    if self.quoting == Quoting::None {
+                        self.word();
+                    }
+                    let len = variable_len(rest);
+                    if rest.starts_with("$(") {
+                        self.command_expected = true;
+                        self.word = None;
+                    }
+                    self.push(Kind::Variable, at, len);
+                    for _ in 1..rest[..len].chars().count() {
+                        chars.next();
+                    }
+                }
+                (Quoting::Single | Quoting::Double, _) => self.push(Kind::String, at, c.len_utf8()),
+                (Quoting::None, '\'' | '"') => {
+                    self.word();
+                    self.quoting = if c == '"' {
+                        Quoting::Double
+                    } else {
+                        Quoting::Single
+                    };
+                    self.push(Kind::String, at, 1);
+                }
+                (Quoting::None, '#') if self.word.is_none() => {
+                    let len = rest.find('\n').unwrap_or(rest.len());
+                    self.push(Kind::Comment, at, len);
+                    for _ in 1..rest[..len].chars().count() {
+                        chars.next();
+                    }
+                }
+                (Quoting::None, '|' | '&' | ';' | '(' | ')' | '<' | '>' | '`' | '\n') => {
+                    self.word = None;
+                    if c != '<' && c != '>' {
+                        self.command_expected = true;
+                    }
+                    self.push(Kind::Operator, at, c.len_utf8());
+                }
+                // Braces group commands, like the steps of a workflow
+                (Quoting::None, '{' | '}') if self.word.is_none() => {
+                    self.command_expected = c == '{';
+                    self.push(Kind::Operator, at, 1);
+                }
+                (Quoting::None, c) if c.is_whitespace() => {
+                    self.word = None;
+                    self.push(Kind::Plain, at, c.len_utf8());
+                }
+                (Quoting::None, '-') if self.word.is_none() && !self.command_expected => {
+                    self.word = Some(Kind::Flag);
+                    self.push(Kind::Flag, at, 1);
+                }
+                (Quoting::None, c) => {
+                    let kind = self.word();
+                    self.push(kind, at, c.len_utf8());
+                }
+            }
+        }
+    }
+}
+
+/// Length in bytes of the variable `text` starts with, which is at least the `$`
+fn variable_len(text: &str) -> usize {
+    let rest = &text[1..];
+    if rest.starts_with('(') {
+        return 2;
+    }
+    if rest.starts_with('{') {
+        return rest.find('}').map_or(text.len(), |end| end + 2);
+    }
+    match rest.chars().next() {
+        Some(c) if c.is_ascii_digit() || "?!#$@*-".contains(c) => 2,
+        _ => {
+            1 + rest
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(rest.len())
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_highlight {
+    use super::*;
+
+    /// The parts of `command` as the text they cover, without whitespace
+    fn parts<'a>(command: &'a str, filled: &[Range<usize>]) -> Vec<(Kind, &'a str)> {
+        highlight(command, "#", "!", filled)
+            .into_iter()
+            .map(|(kind, range)| (kind, command[range].trim()))
+            .filter(|(_, text)| !text.is_empty())
+            .collect()
+    }
+
+    #[test]
+    fn highlight_shell_syntax() {
+        assert_eq!(
+            vec![
+                (Kind::Command, "git"),
+                (Kind::Plain, "commit"),
+                (Kind::Flag, "-m"),
+                (Kind::String, "\"fix for"),
+                (Kind::Variable, "$USER"),
+                (Kind::String, "\""),
+                (Kind::Operator, "|"),
+                (Kind::Command, "tee"),
+                (Kind::Variable, "${HOME}"),
+                (Kind::Plain, "/log"),
+                (Kind::Operator, "&&"),
+                (Kind::Command, "echo"),
+                (Kind::String, "'$done'"),
+            ],
+            parts(
+                "git commit -m \"fix for $USER\" | tee ${HOME}/log && echo '$done'",
+                &[]
+            )
+        );
+    }
+
+    #[test]
+    fn highlight_parameters() {
+        assert_eq!(
+            vec![
+                (Kind::Command, "cp"),
+                (Kind::ParameterToken, "#"),
+                (Kind::ParameterType, "path:"),
+                (Kind::ParameterName, "from"),
+                (Kind::ParameterToken, "!"),
+                (Kind::ParameterToken, "#"),
+                (Kind::Operator, ">"),
+                (Kind::ParameterToken, "#"),
+                (Kind::ParameterType, "secret:"),
+                (Kind::ParameterName, "log"),
+            ],
+            parts("cp #path:from! # > #secret:log", &[])
+        );
+    }
+
+    #[test]
+    fn strings_go_on_after_parameters() {
+        assert_eq!(
+            vec![
+                (Kind::Command, "echo"),
+                (Kind::String, "\"hi"),
+                (Kind::ParameterToken, "#"),
+                (Kind::ParameterName, "name"),
+                (Kind::ParameterToken, "!"),
+                (Kind::String, "| there\""),
+                (Kind::Operator, ";"),
+                (Kind::Operator, "{"),
+                (Kind::Command, "ls"),
+                (Kind::Operator, ";"),
+                (Kind::Operator, "}"),
+            ],
+            parts("echo \"hi #name!| there\"; { ls; }", &[])
+        );
+    }
+
+    #[test]
+    fn filled_values_are_kept_as_they_are() {
+        let command = "ssh 'my host' #cmd! | grep #!";
+        assert_eq!(
+            vec![
+                (Kind::Command, "ssh"),
+                (Kind::Filled, "'my host'"),
+                (Kind::ParameterToken, "#"),
+                (Kind::ParameterName, "cmd"),
+                (Kind::ParameterToken, "!"),
+                (Kind::Operator, "|"),
+                (Kind::Command, "grep"),
+                (Kind::Filled, "#!"),
+            ],
+            parts(command, &[4..13, 27..29])
+        );
+    }
+}
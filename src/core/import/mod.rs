@@ -0,0 +1,185 @@
+pub mod navi;
+pub mod pet;
+pub mod tldr;
+
+use anyhow::{anyhow, Result};
+use log::info;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::core::trove::Trove;
+use crate::core::HoardCmd;
+
+/// Builds a hoard named parameter out of a parameter name used by another command organizer
+///
+/// Occurrences of the parameter tokens inside of `name` are replaced, so the resulting parameter can always be
+/// replaced as a whole when the command is picked.
+pub fn to_named_parameter(name: &str, token: &str, ending_token: &str) -> String {
+    let name = name.trim().replace(token, "_").replace(ending_token, "_");
+    format!("{token}{name}{ending_token}")
+}
+
+/// Derives a command name from a free text description
+///
+/// Takes the first few words of the description, lowercases them and joins them with `-`
+/// # Example
+/// ```
+/// # use hoardlib::core::import::name_from_description;
+/// assert_eq!(name_from_description("Create an archive from files:"), "create-an-archive-from-files");
+/// ```
+pub fn name_from_description(description: &str) -> String {
+    let name = description
+        .split_whitespace()
+        .map(|word| {
+            word.chars()
+                .filter(|c| c.is_alphanumeric() || *c == '-' || *c == '_')
+                .collect::<String>()
+                .to_lowercase()
+        })
+        .filter(|word| !word.is_empty())
+        .take(5)
+        .collect::<Vec<_>>()
+        .join("-");
+    if name.is_empty() {
+        String::from("command")
+    } else {
+        name
+    }
+}
+
+/// Derives a namespace from the name of the file the commands have been read from
+pub fn namespace_from_path(path: &Path) -> String {
+    path.file_stem()
+        .and_then(|stem| stem.to_str())
+        .map_or_else(|| String::from("imported"), ToString::to_string)
+}
+
+/// Reads all files ending with `extension` at `path` and parses them into a single trove
+///
+/// `path` can either point to a single file or to a directory, which is searched recursively.
+/// `parser` is called with the content of each file and the namespace derived from its file name.
+/// Commands ending up with the same name in a namespace get a numbered suffix.
+pub fn import_path<F>(path: &Path, extension: &str, parser: F) -> Result<Trove>
+where
+    F: Fn(&str, &str) -> Result<Vec<HoardCmd>>,
+{
+    if !path.exists() {
+        return Err(anyhow!("Nothing to import at {}", path.display()));
+    }
+    let mut trove = Trove::default();
+    for file in collect_files(path, extension)? {
+        let content = fs::read_to_string(&file)?;
+        let commands = parser(&content, &namespace_from_path(&file))
+            .map_err(|e| anyhow!("Could not import {}: {e}", file.display()))?;
+        for command in commands {
+            let command = with_unique_name(&trove, command);
+            if trove.add_command(command, true).is_err() {
+                info!("[DEBUG] Skipping invalid command in {:?}", file);
+            }
+        }
+    }
+    Ok(trove)
+}
+
+fn with_unique_name(trove: &Trove, command: HoardCmd) -> HoardCmd {
+    let base_name = command.name.clone();
+    let mut command = command;
+    let mut suffix = 2;
+    while trove.get_command_collision(&command).is_some() {
+        command.name = format!("{base_name}-{suffix}");
+        suffix += 1;
+    }
+    command
+}
+
+fn collect_files(path: &Path, extension: &str) -> Result<Vec<PathBuf>> {
+    if path.is_file() {
+        return Ok(vec![path.to_path_buf()]);
+    }
+    let mut entries = fs::read_dir(path)?
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+    entries.sort();
+
+    let mut files = Vec::new();
+    for entry in entries {
+        if entry.is_dir() {
+            files.append(&mut collect_files(&entry, extension)?);
+        } else if entry.extension().and_then(|e| e.to_str()) == Some(extension) {
+            files.push(entry);
+        }
+    }
+    Ok(files)
+}
+
+#[cfg(test)]
+mod test_import {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn named_parameter() {
+        assert_eq!("#branch!", to_named_parameter("branch", "#", "!"));
+    }
+
+    #[test]
+    fn named_parameter_with_tokens_in_name() {
+        assert_eq!(
+            "#very_important_!",
+            to_named_parameter("very#important!", "#", "!")
+        );
+    }
+
+    #[test]
+    fn name_from_long_description() {
+        assert_eq!(
+            "create-an-archive-from-files",
+            name_from_description("Create an archive from files:")
+        );
+    }
+
+    #[test]
+    fn name_from_empty_description() {
+        assert_eq!("command", name_from_description(" :: "));
+    }
+
+    #[test]
+    fn namespace_from_file_name() {
+        assert_eq!("git", namespace_from_path(Path::new("/cheats/git.cheat")));
+    }
+
+    #[test]
+    fn import_directory_with_colliding_names() {
+        let tmp_dir = tempdir().ok().unwrap();
+        fs::create_dir(tmp_dir.path().join("nested")).unwrap();
+        fs::write(
+            tmp_dir.path().join("nested").join("git.txt"),
+            "status\nstatus",
+        )
+        .unwrap();
+        fs::write(tmp_dir.path().join("ignored.md"), "status").unwrap();
+
+        let trove = import_path(tmp_dir.path(), "txt", |content, namespace| {
+            Ok(content
+                .lines()
+                .map(|line| {
+                    HoardCmd::default()
+                        .with_name(line)
+                        .with_namespace(namespace)
+                        .with_command(&format!("git {line}"))
+                })
+                .collect())
+        })
+        .unwrap();
+
+        let names: Vec<&str> = trove.commands.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(vec!["status", "status-2"], names);
+        assert_eq!(vec!["git"], trove.namespaces());
+    }
+
+    #[test]
+    fn import_missing_path() {
+        let result = import_path(Path::new("/does/not/exist"), "cheat", |_, _| Ok(Vec::new()));
+        assert!(result.is_err());
+    }
+}
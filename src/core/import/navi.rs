@@ -0,0 +1,183 @@
+use regex::Regex;
+use std::collections::HashMap;
+
+use crate::core::import::{name_from_description, to_named_parameter};
+use crate::core::HoardCmd;
+
+/// File extension of navi cheatsheets
+pub const EXTENSION: &str = "cheat";
+
+/// A single cheat of a navi cheatsheet before it got converted into a `HoardCmd`
+struct Cheat {
+    description: String,
+    lines: Vec<String>,
+}
+
+/// Parses the content of a navi `.cheat` file into hoard commands
+///
+/// - `% tag1, tag2` lines set the tags of all following cheats
+/// - `# description` lines start a new cheat, every following line is part of its command
+/// - `<var>` placeholders are converted into named hoard parameters
+/// - `$ var: command` generators apply to every cheat of the current `%` section.
+///   As hoard can't generate parameter values, they are kept in the description of the commands that use `<var>`
+pub fn parse(content: &str, namespace: &str, token: &str, ending_token: &str) -> Vec<HoardCmd> {
+    let mut commands = Vec::new();
+    let mut tags: Vec<String> = Vec::new();
+    let mut cheats: Vec<Cheat> = Vec::new();
+    let mut generators: HashMap<String, String> = HashMap::new();
+
+    for line in content.lines() {
+        let trimmed = line.trim();
+        if let Some(section_tags) = trimmed.strip_prefix('%') {
+            commands.append(&mut convert_section(
+                &mut cheats,
+                &mut generators,
+                &tags,
+                namespace,
+                token,
+                ending_token,
+            ));
+            tags = section_tags
+                .split(',')
+                .map(|tag| tag.split_whitespace().collect::<String>())
+                .filter(|tag| !tag.is_empty())
+                .collect();
+        } else if let Some(description) = trimmed.strip_prefix('#') {
+            cheats.push(Cheat {
+                description: description.trim().to_string(),
+                lines: Vec::new(),
+            });
+        } else if let Some(generator) = trimmed.strip_prefix('$') {
+            if let Some((variable, command)) = generator.split_once(':') {
+                // Options for navi's finder are separated by `---` and of no use for hoard
+                let command = command.split("---").next().unwrap_or_default();
+                generators.insert(variable.trim().to_string(), command.trim().to_string());
+            }
+        } else if trimmed.is_empty() || trimmed.starts_with(';') || trimmed.starts_with('@') {
+            // Empty lines, comments and extended cheats end the current command
+            if let Some(cheat) = cheats.last_mut() {
+                if !cheat.lines.is_empty() {
+                    cheats.push(Cheat {
+                        description: String::new(),
+                        lines: Vec::new(),
+                    });
+                }
+            }
+        } else if let Some(cheat) = cheats.last_mut() {
+            cheat.lines.push(line.trim_end().to_string());
+        }
+    }
+    commands.append(&mut convert_section(
+        &mut cheats,
+        &mut generators,
+        &tags,
+        namespace,
+        token,
+        ending_token,
+    ));
+    commands
+}
+
+fn convert_section(
+    cheats: &mut Vec<Cheat>,
+    generators: &mut HashMap<String, String>,
+    tags: &[String],
+    namespace: &str,
+    token: &str,
+    ending_token: &str,
+) -> Vec<HoardCmd> {
+    let variable = Regex::new(r"<([A-Za-z0-9_\-]+)>").unwrap();
+    let commands = cheats
+        .drain(..)
+        .filter(|cheat| !cheat.lines.is_empty())
+        .map(|cheat| {
+            let command = cheat.lines.join("\n");
+            let mut description = cheat.description.clone();
+            let mut seen: Vec<&str> = Vec::new();
+            for capture in variable.captures_iter(&command) {
+                let name = capture.get(1).unwrap().as_str();
+                if seen.contains(&name) {
+                    continue;
+                }
+                seen.push(name);
+                if let Some(generator) = generators.get(name) {
+                    description.push_str(&format!("\n<{name}> values: {generator}"));
+                }
+            }
+            let command = variable.replace_all(&command, |capture: &regex::Captures| {
+                to_named_parameter(&capture[1], token, ending_token)
+            });
+            HoardCmd::default()
+                .with_name(&name_from_description(&cheat.description))
+                .with_namespace(namespace)
+                .with_command(&command)
+                .with_description(description.trim())
+                .with_tags(tags.to_vec())
+        })
+        .collect();
+    generators.clear();
+    commands
+}
+
+#[cfg(test)]
+mod test_navi {
+    use super::*;
+
+    const CHEAT: &str = "% git, code
+
+# Change branch
+git checkout <branch>
+
+# Push a branch to a remote
+git push <remote> <branch>
+
+$ branch: git branch | awk '{print $NF}' --- --multi
+$ remote: git remote
+
+% docker
+
+; not part of any command
+# Remove a container
+docker rm <container>
+";
+
+    #[test]
+    fn parse_commands() {
+        let commands = parse(CHEAT, "git", "#", "!");
+        assert_eq!(3, commands.len());
+        assert_eq!("change-branch", commands[0].name);
+        assert_eq!("git", commands[0].namespace);
+        assert_eq!("git push #remote! #branch!", commands[1].command);
+        assert_eq!("docker rm #container!", commands[2].command);
+    }
+
+    #[test]
+    fn parse_tags_per_section() {
+        let commands = parse(CHEAT, "git", "#", "!");
+        assert_eq!(vec!["git", "code"], commands[0].tags);
+        assert_eq!(vec!["docker"], commands[2].tags);
+    }
+
+    #[test]
+    fn keep_generators_in_description() {
+        let commands = parse(CHEAT, "git", "#", "!");
+        assert_eq!(
+            "Change branch\n<branch> values: git branch | awk '{print $NF}'",
+            commands[0].description
+        );
+        assert_eq!("Remove a container", commands[2].description);
+    }
+
+    #[test]
+    fn parse_multiline_command() {
+        let commands = parse("# Multiline\necho foo \\\n  <bar>\n", "ns", "#", "!");
+        assert_eq!(1, commands.len());
+        assert_eq!("echo foo \\\n  #bar!", commands[0].command);
+    }
+
+    #[test]
+    fn parse_custom_tokens() {
+        let commands = parse("# Greet\necho <name>", "ns", "@", "$");
+        assert_eq!("echo @name$", commands[0].command);
+    }
+}
@@ -0,0 +1,119 @@
+use anyhow::Result;
+use regex::Regex;
+use serde::Deserialize;
+
+use crate::core::import::{name_from_description, to_named_parameter};
+use crate::core::HoardCmd;
+
+/// File extension of pet snippet files
+pub const EXTENSION: &str = "toml";
+
+#[derive(Debug, Deserialize)]
+struct PetSnippets {
+    #[serde(default)]
+    snippets: Vec<PetSnippet>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PetSnippet {
+    #[serde(default)]
+    description: String,
+    command: String,
+    #[serde(default)]
+    tag: Vec<String>,
+}
+
+/// Parses the content of a pet `snippet.toml` file into hoard commands
+///
+/// `<param>` and `<param=default>` placeholders are converted into named hoard parameters.
+/// Default values are kept in the description of the command.
+pub fn parse(
+    content: &str,
+    namespace: &str,
+    token: &str,
+    ending_token: &str,
+) -> Result<Vec<HoardCmd>> {
+    let parameter = Regex::new(r"<([^<>=\s]+)(?:=([^<>]*))?>").unwrap();
+    let snippets: PetSnippets = toml::from_str(content)?;
+    let commands = snippets
+        .snippets
+        .into_iter()
+        .map(|snippet| {
+            let defaults: Vec<String> = parameter
+                .captures_iter(&snippet.command)
+                .filter_map(|capture| {
+                    capture
+                        .get(2)
+                        .map(|default| format!("{}={}", &capture[1], default.as_str()))
+                })
+                .collect();
+            let command = parameter.replace_all(&snippet.command, |capture: &regex::Captures| {
+                to_named_parameter(&capture[1], token, ending_token)
+            });
+            let mut description = snippet.description.clone();
+            if !defaults.is_empty() {
+                description.push_str(&format!("\nDefaults: {}", defaults.join(", ")));
+            }
+            let tags = snippet
+                .tag
+                .iter()
+                .map(|tag| tag.split_whitespace().collect::<String>())
+                .filter(|tag| !tag.is_empty())
+                .collect();
+            HoardCmd::default()
+                .with_name(&name_from_description(&snippet.description))
+                .with_namespace(namespace)
+                .with_command(&command)
+                .with_description(description.trim())
+                .with_tags(tags)
+        })
+        .collect();
+    Ok(commands)
+}
+
+#[cfg(test)]
+mod test_pet {
+    use super::*;
+
+    const SNIPPETS: &str = r#"
+[[snippets]]
+  description = "Show expiration date of SSL certificate"
+  command = "echo | openssl s_client -connect <host=example.com>:443 2>/dev/null | openssl x509 -dates -noout"
+  tag = ["ssl", "network"]
+  output = ""
+
+[[snippets]]
+  description = "Remove a container"
+  command = "docker rm <container>"
+"#;
+
+    #[test]
+    fn parse_snippets() {
+        let commands = parse(SNIPPETS, "snippet", "#", "!").unwrap();
+        assert_eq!(2, commands.len());
+        assert_eq!("show-expiration-date-of-ssl", commands[0].name);
+        assert_eq!("snippet", commands[0].namespace);
+        assert_eq!(vec!["ssl", "network"], commands[0].tags);
+        assert_eq!("docker rm #container!", commands[1].command);
+        assert!(commands[1].tags.is_empty());
+    }
+
+    #[test]
+    fn keep_defaults_in_description() {
+        let commands = parse(SNIPPETS, "snippet", "#", "!").unwrap();
+        assert_eq!(
+            "echo | openssl s_client -connect #host!:443 2>/dev/null | openssl x509 -dates -noout",
+            commands[0].command
+        );
+        assert_eq!(
+            "Show expiration date of SSL certificate\nDefaults: host=example.com",
+            commands[0].description
+        );
+        assert_eq!("Remove a container", commands[1].description);
+    }
+
+    #[test]
+    fn parse_invalid_toml() {
+        assert!(parse("[[snippets]\ncommand = ", "snippet", "#", "!").is_err());
+    }
+}
@@ -0,0 +1,88 @@
+use regex::Regex;
+
+use crate::core::import::{name_from_description, to_named_parameter};
+use crate::core::HoardCmd;
+
+/// File extension of tldr pages
+pub const EXTENSION: &str = "md";
+
+/// Parses a local tldr markdown page into hoard commands
+///
+/// Every `- description:` line followed by a `` `command` `` line becomes one command.
+/// `{{placeholder}}` arguments are converted into named hoard parameters.
+pub fn parse(content: &str, namespace: &str, token: &str, ending_token: &str) -> Vec<HoardCmd> {
+    let placeholder = Regex::new(r"\{\{(.*?)\}\}").unwrap();
+    let mut commands = Vec::new();
+    let mut description: Option<String> = None;
+
+    for line in content.lines().map(str::trim) {
+        if let Some(example) = line.strip_prefix("- ") {
+            description = Some(example.trim_end_matches(':').trim().to_string());
+        } else if line.len() > 1 && line.starts_with('`') && line.ends_with('`') {
+            let Some(description) = description.take() else {
+                continue;
+            };
+            let command = placeholder
+                .replace_all(&line[1..line.len() - 1], |capture: &regex::Captures| {
+                    to_named_parameter(&capture[1], token, ending_token)
+                });
+            commands.push(
+                HoardCmd::default()
+                    .with_name(&name_from_description(&description))
+                    .with_namespace(namespace)
+                    .with_command(&command)
+                    .with_description(&description),
+            );
+        }
+    }
+    commands
+}
+
+#[cfg(test)]
+mod test_tldr {
+    use super::*;
+
+    const PAGE: &str = "# tar
+
+> Archiving utility.
+> More information: <https://www.gnu.org/software/tar>.
+
+- [c]reate an archive and write it to a [f]ile:
+
+`tar cf {{path/to/target.tar}} {{path/to/file1 path/to/file2 ...}}`
+
+- E[x]tract a (compressed) archive [f]ile into the current directory [v]erbosely:
+
+`tar xvf {{path/to/source.tar[.gz|.bz2|.xz]}}`
+";
+
+    #[test]
+    fn parse_page() {
+        let commands = parse(PAGE, "tar", "#", "!");
+        assert_eq!(2, commands.len());
+        assert_eq!("create-an-archive-and-write", commands[0].name);
+        assert_eq!("tar", commands[0].namespace);
+        assert_eq!(
+            "[c]reate an archive and write it to a [f]ile",
+            commands[0].description
+        );
+    }
+
+    #[test]
+    fn parse_placeholders() {
+        let commands = parse(PAGE, "tar", "#", "!");
+        assert_eq!(
+            "tar cf #path/to/target.tar! #path/to/file1 path/to/file2 ...!",
+            commands[0].command
+        );
+        assert_eq!(
+            "tar xvf #path/to/source.tar[.gz|.bz2|.xz]!",
+            commands[1].command
+        );
+    }
+
+    #[test]
+    fn ignore_code_without_description() {
+        assert!(parse("`tar cf {{file}}`", "tar", "#", "!").is_empty());
+    }
+}
@@ -0,0 +1,494 @@
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::core::error::HoardError;
+use crate::core::storage;
+use crate::core::trove::Trove;
+use crate::core::workflow::Workflow;
+use crate::core::HoardCmd;
+
+const JOURNAL_VERSION: u32 = 1;
+
+/// A command or a workflow before and after a change. `None` if it didn't exist
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Change<T> {
+    pub before: Option<T>,
+    pub after: Option<T>,
+}
+
+/// One change of the trove, undone and redone as a whole
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Entry {
+    /// What has been done, like `Edited git/status`
+    pub description: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub commands: Vec<Change<HoardCmd>>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub workflows: Vec<Change<Workflow>>,
+}
+
+/// Content of the journal file
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct JournalFile {
+    version: u32,
+    #[serde(default)]
+    undo: Vec<Entry>,
+    #[serde(default)]
+    redo: Vec<Entry>,
+}
+
+/// What has been done to the journal since it has been read, to do it again to the journal file when saving
+#[derive(Debug, Clone)]
+enum Step {
+    Record(Entry),
+    Undo(Entry),
+    Redo(Entry),
+}
+
+/// The last changes of the trove, to undo and redo them, stored in a file next to the trove
+///
+/// Every entry keeps the commands and workflows it changed as they were before and after the change.
+/// Undoing an entry puts them back as they were before, redoing it as they were after
+#[derive(Debug, Default)]
+pub struct Journal {
+    // `None` keeps the journal in memory only
+    path: Option<PathBuf>,
+    // Number of entries which can be undone, older ones are dropped
+    size: usize,
+    file: JournalFile,
+    // Steps since the journal file has been read, other hoard processes may have changed it in the meantime
+    steps: Vec<Step>,
+}
+
+impl Journal {
+    /// Reads the journal at `path`, which keeps the last `size` changes. A missing journal is empty
+    pub fn open(path: &Path, size: usize) -> Result<Self> {
+        Ok(Self {
+            path: Some(path.to_path_buf()),
+            size,
+            file: JournalFile::read(path)?,
+            steps: Vec::new(),
+        })
+    }
+
+    /// A journal of the last `size` changes which is never written to a file
+    pub fn in_memory(size: usize) -> Self {
+        Self {
+            path: None,
+            size,
+            file: JournalFile::default(),
+            steps: Vec::new(),
+        }
+    }
+
+    /// Records the changes from `before` to `after` as one entry called `description`
+    ///
+    /// Usage counts and times of use aren't changes worth undoing. Returns `false` if nothing else changed.
+    /// A new entry can't be followed by the entries undone before it, so they can't be redone anymore
+    pub fn record(&mut self, description: &str, before: &Trove, after: &Trove) -> bool {
+        let entry = Entry {
+            description: description.to_string(),
+            commands: diff(&before.commands, &after.commands),
+            workflows: diff(&before.workflows, &after.workflows),
+        };
+        if self.size == 0 || (entry.commands.is_empty() && entry.workflows.is_empty()) {
+            return false;
+        }
+        self.steps.push(Step::Record(entry.clone()));
+        self.file.take(Step::Record(entry), self.size);
+        true
+    }
+
+    /// Undoes the last entry in `trove` and returns its description, or `None` if there is nothing to undo
+    ///
+    /// An entry whose commands changed since can't be undone without losing those changes.
+    /// It stays in the journal and the conflict is returned as an error
+    pub fn undo(&mut self, trove: &mut Trove) -> Result<Option<String>, HoardError> {
+        let Some(entry) = self.file.undo.last() else {
+            return Ok(None);
+        };
+        entry.apply(trove, false)?;
+        let description = entry.description.clone();
+        let step = Step::Undo(entry.clone());
+        self.steps.push(step.clone());
+        self.file.take(step, self.size);
+        Ok(Some(description))
+    }
+
+    /// Redoes the last undone entry in `trove` and returns its description, or `None` if there is nothing to redo
+    pub fn redo(&mut self, trove: &mut Trove) -> Result<Option<String>, HoardError> {
+        let Some(entry) = self.file.redo.last() else {
+            return Ok(None);
+        };
+        entry.apply(trove, true)?;
+        let description = entry.description.clone();
+        let step = Step::Redo(entry.clone());
+        self.steps.push(step.clone());
+        self.file.take(step, self.size);
+        Ok(Some(description))
+    }
+
+    /// Whether entries have been recorded, undone or redone since the journal has been saved
+    pub fn has_changes(&self) -> bool {
+        !self.steps.is_empty()
+    }
+
+    /// Writes the journal file, unless the journal is kept in memory
+    ///
+    /// The file is read again and the steps since it has been read are taken onto it, so the entries other hoard
+    /// processes wrote in the meantime are kept. Call it while holding the lock of the trove, like when saving the trove
+    pub fn save(&mut self) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let mut file = JournalFile::read(path)?;
+        for step in self.steps.drain(..) {
+            file.take(step, self.size);
+        }
+        file.version = JOURNAL_VERSION;
+        storage::write_atomic(path, &serde_yaml::to_string(&file)?)?;
+        self.file = file;
+        Ok(())
+    }
+}
+
+impl JournalFile {
+    /// Reads the journal file at `path`. A missing file is an empty journal
+    fn read(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = fs::read_to_string(path)
+            .map_err(|e| anyhow!("Could not read journal {}: {e}", path.display()))?;
+        let file: Self = serde_yaml::from_str(&content)
+            .map_err(|e| anyhow!("Invalid journal {}: {e}", path.display()))?;
+        if file.version > JOURNAL_VERSION {
+            return Err(anyhow!(
+                "The journal {} has been written by a newer version of hoard",
+                path.display()
+            ));
+        }
+        Ok(file)
+    }
+
+    /// Takes `step` onto the journal, which keeps `size` entries to undo
+    ///
+    /// Undoing or redoing an entry which isn't in the journal anymore, because another process undid it already, does nothing
+    fn take(&mut self, step: Step, size: usize) {
+        match step {
+            Step::Record(entry) => {
+                self.redo.clear();
+                self.undo.push(entry);
+                let outdated = self.undo.len().saturating_sub(size);
+                self.undo.drain(..outdated);
+            }
+            Step::Undo(entry) => move_entry(&mut self.undo, &mut self.redo, &entry),
+            Step::Redo(entry) => move_entry(&mut self.redo, &mut self.undo, &entry),
+        }
+    }
+}
+
+/// Moves the last `entry` of `from` onto `to`
+fn move_entry(from: &mut Vec<Entry>, to: &mut Vec<Entry>, entry: &Entry) {
+    if let Some(position) = from.iter().rposition(|e| e == entry) {
+        to.push(from.remove(position));
+    }
+}
+
+impl Entry {
+    /// Puts the changed commands and workflows into their state after the entry if `forward`, else before it
+    fn apply(&self, trove: &mut Trove, forward: bool) -> Result<(), HoardError> {
+        let (done, verb) = if forward {
+            ("undone", "redone")
+        } else {
+            ("done", "undone")
+        };
+        let conflict = first_conflict(&trove.commands, &self.commands, forward)
+            .or_else(|| first_conflict(&trove.workflows, &self.workflows, forward));
+        if let Some(label) = conflict {
+            return Err(HoardError::Validation(format!(
+                "{label} changed since \"{}\" has been {done}, so it can't be {verb} anymore",
+                self.description
+            )));
+        }
+        for change in &self.commands {
+            set(&mut trove.commands, id_of(change), change.state(forward));
+        }
+        for change in &self.workflows {
+            set(&mut trove.workflows, id_of(change), change.state(forward));
+        }
+        // Namespaces come and go with their commands
+        trove.namespaces = trove
+            .namespaces()
+            .into_iter()
+            .map(ToString::to_string)
+            .collect();
+        Ok(())
+    }
+}
+
+impl<T> Change<T> {
+    /// The state after the change if `after`, else the one before it
+    const fn state(&self, after: bool) -> Option<&T> {
+        if after {
+            self.after.as_ref()
+        } else {
+            self.before.as_ref()
+        }
+    }
+}
+
+/// Commands and workflows, which the journal tells apart by their id
+trait Journaled: Clone {
+    fn id(&self) -> &str;
+
+    /// `namespace/name`, to tell the user about it
+    fn label(&self) -> String;
+
+    /// Compares what is worth undoing. How often and when a command has been used isn't
+    fn same(&self, other: &Self) -> bool;
+
+    /// `self` with what isn't worth undoing taken from `current`
+    #[must_use]
+    fn with_usage_of(self, _current: &Self) -> Self {
+        self
+    }
+}
+
+impl Journaled for HoardCmd {
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn label(&self) -> String {
+        format!("{}/{}", self.namespace, self.name)
+    }
+
+    fn same(&self, other: &Self) -> bool {
+        self == other && self.is_favorite == other.is_favorite && self.is_hidden == other.is_hidden
+    }
+
+    fn with_usage_of(self, current: &Self) -> Self {
+        Self {
+            usage_count: current.usage_count,
+            last_used: current.last_used,
+            ..self
+        }
+    }
+}
+
+impl Journaled for Workflow {
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn label(&self) -> String {
+        format!("workflow {}/{}", self.namespace, self.name)
+    }
+
+    fn same(&self, other: &Self) -> bool {
+        self == other
+    }
+}
+
+/// Id of the item `change` changed
+fn id_of<T: Journaled>(change: &Change<T>) -> &str {
+    change
+        .before
+        .as_ref()
+        .or(change.after.as_ref())
+        .map_or("", Journaled::id)
+}
+
+/// The items which are new, changed or gone in `after`
+fn diff<T: Journaled>(before: &[T], after: &[T]) -> Vec<Change<T>> {
+    let old: HashMap<&str, &T> = before.iter().map(|item| (item.id(), item)).collect();
+    let new: HashMap<&str, &T> = after.iter().map(|item| (item.id(), item)).collect();
+    let changed = after.iter().filter_map(|item| match old.get(item.id()) {
+        Some(previous) if previous.same(item) => None,
+        previous => Some(Change {
+            before: previous.map(|previous| (*previous).clone()),
+            after: Some(item.clone()),
+        }),
+    });
+    let removed = before
+        .iter()
+        .filter(|item| !new.contains_key(item.id()))
+        .map(|item| Change {
+            before: Some(item.clone()),
+            after: None,
+        });
+    changed.chain(removed).collect()
+}
+
+/// The label of the first item of `changes` which isn't in the state `apply` expects anymore
+fn first_conflict<T: Journaled>(
+    items: &[T],
+    changes: &[Change<T>],
+    forward: bool,
+) -> Option<String> {
+    changes.iter().find_map(|change| {
+        let current = items.iter().find(|item| item.id() == id_of(change));
+        let expected = change.state(!forward);
+        let unchanged = match (current, expected) {
+            (Some(current), Some(expected)) => current.same(expected),
+            (None, None) => true,
+            _ => false,
+        };
+        (!unchanged).then(|| {
+            current
+                .or(expected)
+                .map_or_else(String::new, Journaled::label)
+        })
+    })
+}
+
+/// Puts the item with the id `id` into the state `state`, adding or removing it if needed
+fn set<T: Journaled>(items: &mut Vec<T>, id: &str, state: Option<&T>) {
+    let position = items.iter().position(|item| item.id() == id);
+    match (position, state) {
+        (Some(i), Some(state)) => items[i] = state.clone().with_usage_of(&items[i]),
+        (Some(i), None) => {
+            items.remove(i);
+        }
+        (None, Some(state)) => items.push(state.clone()),
+        (None, None) => (),
+    }
+}
+
+#[cfg(test)]
+mod test_journal {
+    use super::*;
+
+    fn trove() -> Trove {
+        let commands = ["status", "log"].map(|name| {
+            HoardCmd::default()
+                .with_name(name)
+                .with_namespace("git")
+                .with_command(&format!("git {name}"))
+        });
+        Trove::from_commands(&commands)
+    }
+
+    #[test]
+    fn undo_and_redo_changes() {
+        let mut journal = Journal::in_memory(10);
+        let mut trove = trove();
+
+        let before = trove.clone();
+        trove.commands[0].description = "Show the working tree".to_string();
+        trove.commands.remove(1);
+        assert!(journal.record("Edited git/status", &before, &trove));
+
+        let before = trove.clone();
+        trove.commands[0].usage_count += 1;
+        assert!(!journal.record("Picked git/status", &before, &trove));
+
+        assert_eq!(
+            Some("Edited git/status".to_string()),
+            journal.undo(&mut trove).unwrap()
+        );
+        assert_eq!(2, trove.commands.len());
+        assert!(trove.commands[0].description.is_empty());
+        assert_eq!(1, trove.commands[0].usage_count);
+        assert_eq!(None, journal.undo(&mut trove).unwrap());
+
+        assert_eq!(
+            Some("Edited git/status".to_string()),
+            journal.redo(&mut trove).unwrap()
+        );
+        assert_eq!(1, trove.commands.len());
+        assert_eq!("Show the working tree", trove.commands[0].description);
+        assert_eq!(None, journal.redo(&mut trove).unwrap());
+    }
+
+    #[test]
+    fn changes_made_since_are_not_overwritten() {
+        let mut journal = Journal::in_memory(10);
+        let mut trove = trove();
+        let before = trove.clone();
+        trove.commands[0].tags = vec!["vcs".to_string()];
+        journal.record("Tagged git/status", &before, &trove);
+
+        trove.commands[0].command = "git status --short".to_string();
+        let error = journal.undo(&mut trove).unwrap_err();
+        assert!(error.to_string().contains("git/status changed since"));
+        assert_eq!("git status --short", trove.commands[0].command);
+        // The entry is kept, it can be undone once the change is reverted
+        assert!(journal.undo(&mut trove).is_err());
+        trove.commands[0].command = "git status".to_string();
+        assert_eq!(
+            Some("Tagged git/status".to_string()),
+            journal.undo(&mut trove).unwrap()
+        );
+        assert!(trove.commands[0].tags.is_empty());
+    }
+
+    #[test]
+    fn journal_survives_reopening_and_keeps_its_size() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let path = tmp_dir.path().join("journal.yml");
+        let mut journal = Journal::open(&path, 2).unwrap();
+        let mut trove = trove();
+        for name in ["first", "second", "third"] {
+            let before = trove.clone();
+            trove
+                .commands
+                .push(HoardCmd::default().with_name(name).with_namespace("misc"));
+            journal.record(&format!("Created misc/{name}"), &before, &trove);
+        }
+        journal.save().unwrap();
+
+        let mut journal = Journal::open(&path, 2).unwrap();
+        assert_eq!(
+            Some("Created misc/third".to_string()),
+            journal.undo(&mut trove).unwrap()
+        );
+        assert_eq!(
+            Some("Created misc/second".to_string()),
+            journal.undo(&mut trove).unwrap()
+        );
+        assert_eq!(None, journal.undo(&mut trove).unwrap());
+        assert_eq!(vec!["git", "misc"], trove.namespaces());
+    }
+
+    #[test]
+    fn entries_of_other_processes_are_kept() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let path = tmp_dir.path().join("journal.yml");
+        let mut ours = Journal::open(&path, 10).unwrap();
+        let mut theirs = Journal::open(&path, 10).unwrap();
+        let mut trove = trove();
+
+        let before = trove.clone();
+        trove.commands[0].description = "Ours".to_string();
+        ours.record("Edited git/status", &before, &trove);
+        let before = trove.clone();
+        trove.commands[1].description = "Theirs".to_string();
+        theirs.record("Edited git/log", &before, &trove);
+        theirs.save().unwrap();
+        assert_eq!(
+            Some("Edited git/status".to_string()),
+            ours.undo(&mut trove).unwrap()
+        );
+        ours.save().unwrap();
+
+        let mut journal = Journal::open(&path, 10).unwrap();
+        assert_eq!(
+            Some("Edited git/log".to_string()),
+            journal.undo(&mut trove).unwrap()
+        );
+        assert_eq!(None, journal.undo(&mut trove).unwrap());
+        assert_eq!(
+            Some("Edited git/log".to_string()),
+            journal.redo(&mut trove).unwrap()
+        );
+        assert_eq!(
+            Some("Edited git/status".to_string()),
+            journal.redo(&mut trove).unwrap()
+        );
+    }
+}
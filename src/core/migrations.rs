@@ -0,0 +1,150 @@
+use anyhow::{anyhow, Result};
+use serde_yaml::{Mapping, Value};
+
+use crate::core::new_id;
+
+/// Schema version of troves written by this version of hoard.
+///
+/// Has to be increased together with a new migration in `MIGRATIONS` whenever the structure of the trove file changes
+/// in a way serde defaults can't cover
+pub const SCHEMA_VERSION: u32 = 2;
+
+/// Troves written before the schema version has been introduced
+const UNVERSIONED_SCHEMA_VERSION: u32 = 1;
+
+const SCHEMA_VERSION_KEY: &str = "schema_version";
+
+/// A single migration step upgrading a trove from the schema version before it to its own schema version
+type Migration = fn(&mut Mapping) -> Result<()>;
+
+/// All migration steps. `MIGRATIONS[i]` migrates from schema version `i + 1` to `i + 2`
+const MIGRATIONS: &[Migration] = &[add_command_ids];
+
+/// Reads the schema version of a parsed trove file
+pub fn schema_version(trove: &Value) -> Result<u32> {
+    match trove.get(SCHEMA_VERSION_KEY) {
+        None => Ok(UNVERSIONED_SCHEMA_VERSION),
+        Some(version) => version
+            .as_u64()
+            .and_then(|v| u32::try_from(v).ok())
+            .ok_or_else(|| anyhow!("Invalid schema version {:?}", version)),
+    }
+}
+
+/// Upgrades a parsed trove file step by step to `SCHEMA_VERSION`
+///
+/// Returns the schema version the trove had before, if it has been migrated.
+/// Troves of a newer schema version are left untouched.
+pub fn migrate(trove: &mut Value) -> Result<Option<u32>> {
+    let original_version = schema_version(trove)?;
+    if original_version >= SCHEMA_VERSION {
+        return Ok(None);
+    }
+    let mapping = trove
+        .as_mapping_mut()
+        .ok_or_else(|| anyhow!("A trove file has to be a mapping"))?;
+    for version in original_version..SCHEMA_VERSION {
+        let migration = MIGRATIONS[(version - UNVERSIONED_SCHEMA_VERSION) as usize];
+        migration(mapping).map_err(|e| {
+            anyhow!(
+                "Could not migrate trove from schema version {version} to {}: {e}",
+                version + 1
+            )
+        })?;
+        mapping.insert(
+            Value::from(SCHEMA_VERSION_KEY),
+            Value::from(u64::from(version + 1)),
+        );
+    }
+    Ok(Some(original_version))
+}
+
+/// 1 -> 2: Every command gets a unique `id`
+fn add_command_ids(trove: &mut Mapping) -> Result<()> {
+    let Some(commands) = trove.get_mut(&Value::from("commands")) else {
+        return Ok(());
+    };
+    let commands = commands
+        .as_sequence_mut()
+        .ok_or_else(|| anyhow!("`commands` has to be a list"))?;
+    for command in commands {
+        let command = command
+            .as_mapping_mut()
+            .ok_or_else(|| anyhow!("Every command has to be a mapping"))?;
+        let id = Value::from("id");
+        if command
+            .get(&id)
+            .and_then(Value::as_str)
+            .is_none_or(str::is_empty)
+        {
+            command.insert(id, Value::from(new_id()));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test_migrations {
+    use super::*;
+
+    const UNVERSIONED_TROVE: &str = "---
+version: 1.4.2
+commands:
+  - name: status
+    namespace: git
+    command: git status
+    description: ''
+    tags: []
+  - id: keepmyid
+    name: log
+    namespace: git
+    command: git log
+    description: ''
+    tags: []
+";
+
+    #[test]
+    fn migrations_cover_every_version() {
+        assert_eq!(
+            (SCHEMA_VERSION - UNVERSIONED_SCHEMA_VERSION) as usize,
+            MIGRATIONS.len()
+        );
+    }
+
+    #[test]
+    fn unversioned_trove() {
+        let trove: Value = serde_yaml::from_str(UNVERSIONED_TROVE).unwrap();
+        assert_eq!(UNVERSIONED_SCHEMA_VERSION, schema_version(&trove).unwrap());
+    }
+
+    #[test]
+    fn migrate_unversioned_trove() {
+        let mut trove: Value = serde_yaml::from_str(UNVERSIONED_TROVE).unwrap();
+        assert_eq!(Some(1), migrate(&mut trove).unwrap());
+        assert_eq!(SCHEMA_VERSION, schema_version(&trove).unwrap());
+        let commands = trove["commands"].as_sequence().unwrap();
+        assert!(!commands[0]["id"].as_str().unwrap().is_empty());
+        assert_eq!("keepmyid", commands[1]["id"].as_str().unwrap());
+    }
+
+    #[test]
+    fn leave_current_trove_untouched() {
+        let mut trove: Value = serde_yaml::from_str("schema_version: 2\ncommands: []").unwrap();
+        let original = trove.clone();
+        assert_eq!(None, migrate(&mut trove).unwrap());
+        assert_eq!(original, trove);
+    }
+
+    #[test]
+    fn leave_newer_trove_untouched() {
+        let mut trove: Value = serde_yaml::from_str("schema_version: 99\ncommands: 5").unwrap();
+        assert_eq!(None, migrate(&mut trove).unwrap());
+        assert_eq!(99, schema_version(&trove).unwrap());
+    }
+
+    #[test]
+    fn fail_on_invalid_commands() {
+        let mut trove: Value = serde_yaml::from_str("commands: 5").unwrap();
+        assert!(migrate(&mut trove).is_err());
+    }
+}
@@ -1,11 +1,20 @@
+pub mod environment;
 pub mod error;
+pub mod export;
+pub mod highlight;
+pub mod import;
+pub mod journal;
+pub mod migrations;
 pub mod parameters;
+pub mod quoting;
+pub mod references;
+pub mod storage;
+pub mod store;
 pub mod trove;
+pub mod vault;
+pub mod workflow;
 
-use crate::core::error::HoardErr;
-use crate::core::trove::Trove;
-use crate::gui::merge::{with_conflict_resolve_prompt, ConflictResolve};
-use crate::gui::prompts::{prompt_input, prompt_input_validate, prompt_select_with_options};
+use crate::core::error::HoardError;
 use rand::distributions::Alphanumeric;
 use rand::Rng;
 use serde::{Deserialize, Serialize};
@@ -15,9 +24,20 @@ fn default_time() -> time::SystemTime {
     time::SystemTime::now()
 }
 
+/// Generates a new random identifier for a command
+pub fn new_id() -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(10)
+        .map(char::from)
+        .collect::<String>()
+        .to_lowercase()
+}
+
 /// Storage for the saved command structure
 ///
 /// A `HoardCmd` can store the following parameters
+/// - `id`: A unique identifier of the command, which does not change when the command is renamed
 /// - `name`: The name of the command by which it is referenced
 /// - `command`: The terminal command to be stored and executed
 /// - `description`: A description of the command for the user
@@ -33,6 +53,11 @@ fn default_time() -> time::SystemTime {
 /// - `namespace_id`: The id of the namespace the command belongs to
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct HoardCmd {
+    /// A unique identifier of the command, which does not change when the command is renamed
+    /// Commands of older troves get an id assigned when the trove is loaded
+    #[serde(default)]
+    pub id: String,
+
     /// The name of the command by which it is referenced
     pub name: String,
 
@@ -87,10 +112,11 @@ impl PartialEq for HoardCmd {
     }
 }
 
-impl HoardCmd {
+impl Default for HoardCmd {
     /// Create a new `HoardCmd` with default values
-    pub fn default() -> Self {
+    fn default() -> Self {
         Self {
+            id: new_id(),
             name: String::new(),
             command: String::new(),
             description: String::new(),
@@ -105,7 +131,9 @@ impl HoardCmd {
             namespace: String::new(),
         }
     }
+}
 
+impl HoardCmd {
     /// set the name of the command
     pub fn with_name(self, name: &str) -> Self {
         Self {
@@ -138,9 +166,9 @@ impl HoardCmd {
     /// Check if a command is valid for saving
     /// A valid command cant be an empty string
     /// Returns a Result with the error if the command is invalid
-    pub fn is_command_valid(c: &str) -> Result<(), HoardErr> {
+    pub fn is_command_valid(c: &str) -> Result<(), HoardError> {
         if c.is_empty() {
-            return Err(HoardErr::new("Command can't be empty"));
+            return Err(HoardError::Validation("Command can't be empty".to_string()));
         }
         Ok(())
     }
@@ -163,12 +191,31 @@ impl HoardCmd {
     /// Check if a name is valid for saving
     /// A valid name cant be an empty string and can't contain whitespaces
     /// Returns a Result with the error if the name is invalid
-    pub fn is_name_valid(c: &str) -> Result<(), HoardErr> {
+    pub fn is_name_valid(c: &str) -> Result<(), HoardError> {
         if c.is_empty() {
-            return Err(HoardErr::new("Name can't be empty"));
+            return Err(HoardError::Validation("Name can't be empty".to_string()));
         }
         if c.contains(' ') {
-            return Err(HoardErr::new("Name can't contain whitespaces"));
+            return Err(HoardError::Validation(
+                "Name can't contain whitespaces".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Check if a namespace is valid for saving
+    /// A valid namespace cant be an empty string and can't contain whitespaces or `/`, which separates it from the name in references
+    /// Returns a Result with the error if the namespace is invalid
+    pub fn is_namespace_valid(c: &str) -> Result<(), HoardError> {
+        if c.is_empty() {
+            return Err(HoardError::Validation(
+                "Namespace can't be empty".to_string(),
+            ));
+        }
+        if c.contains(char::is_whitespace) || c.contains('/') {
+            return Err(HoardError::Validation(
+                "Namespace can't contain whitespaces or /".to_string(),
+            ));
         }
         Ok(())
     }
@@ -176,9 +223,9 @@ impl HoardCmd {
     /// Check if the tags are valid for saving
     /// A valid tag vector cant be empty
     /// Returns a Result with the error if the tags are invalid
-    pub fn are_tags_valid(c: &str) -> Result<(), HoardErr> {
+    pub fn are_tags_valid(c: &str) -> Result<(), HoardError> {
         if c.is_empty() {
-            return Err(HoardErr::new("Tags can't be empty"));
+            return Err(HoardError::Validation("Tags can't be empty".to_string()));
         }
         Ok(())
     }
@@ -187,7 +234,7 @@ impl HoardCmd {
     /// Tags are separated by a comma
     /// # Example  
     /// ```
-    /// use hoardlib::command::HoardCmd;
+    /// use hoardlib::core::HoardCmd;
     ///
     /// let mut cmd = HoardCmd::default();
     /// cmd.tags.push("tag1".to_string());
@@ -212,45 +259,6 @@ impl HoardCmd {
             ..self
         }
     }
-    /// Prompts the user for a command string, with optional default value and parameter tokens.
-    ///
-    /// This function prompts the user for a command string. The user can mark unknown parameters with a specified token
-    /// and name the parameter with any string, ending it with a specified ending token. An optional default value can be provided.
-    ///
-    /// # Arguments
-    ///
-    /// * `default_value` - An Option that holds a default value for the command string.
-    /// * `parameter_token` - A string slice that holds the token to mark unknown parameters.
-    /// * `parameter_ending_token` - A string slice that holds the token to end the parameter name.
-    ///
-    /// # Returns
-    ///
-    /// This function returns a new instance of the command with the user-inputted command string.
-    ///
-    /// # Example
-    ///
-    /// ```
-    /// let command = HoardCmd::default();
-    /// let command_with_input = command.with_command_string_input(None, "#", "$");
-    /// // The user is prompted for a command string.
-    /// // The command string is updated with the user's input.
-    /// ```
-    pub fn with_command_string_input(
-        self,
-        default_value: Option<String>,
-        parameter_token: &str,
-        parameter_ending_token: &str,
-    ) -> Self {
-        let base_prompt = format!(
-            "Command to hoard ( Mark unknown parameters with '{parameter_token}'. Name the parameter with any string and end it with '{parameter_ending_token}' )\n"
-        );
-        let command_string: String = prompt_input(&base_prompt, false, default_value);
-        Self {
-            command: command_string,
-            ..self
-        }
-    }
-
     /// set the namespace of the command
     pub fn with_namespace(self, namespace: &str) -> Self {
         Self {
@@ -285,98 +293,6 @@ impl HoardCmd {
         }
     }
 
-    /// Prompts the user for tags, with an optional default value, and validates the input.
-    ///
-    /// This function prompts the user for tags, which are comma-separated. The input is validated to ensure that
-    /// it does not contain any whitespaces. An optional default value can be provided.
-    ///
-    /// # Arguments
-    ///
-    /// * `default_value` - An Option that holds a default value for the tags.
-    ///
-    /// # Returns
-    ///
-    /// This function returns a new instance of the command with the user-inputted tags.
-    ///
-    /// # Example
-    ///
-    /// ```
-    /// let command = HoardCmd::default();
-    /// let command_with_tags = command.with_tags_input(Some("default-tag"));
-    /// // The user is prompted for tags.
-    /// // The tags are updated with the user's input.
-    /// ```
-    pub fn with_tags_input(self, default_value: Option<String>) -> Self {
-        let tag_validator = move |input: &String| -> Result<(), String> {
-            if input.contains(' ') {
-                Err("Tags can't contain whitespaces".to_string())
-            } else {
-                Ok(())
-            }
-        };
-        let tags: String = prompt_input_validate(
-            "Give your command some optional tags ( comma separated )",
-            true,
-            default_value,
-            Some(tag_validator),
-        );
-        self.with_tags_raw(&tags)
-    }
-
-    pub fn with_namespace_input(self, selection: &[&str]) -> Self {
-        // Add "New namespace" option to selction
-        let mut selection = selection.to_vec();
-        selection.push("New namespace");
-
-        let selected: usize = prompt_select_with_options("Namespace of the command", &selection);
-
-        let mut selected_namespace: String = (*selection.get(selected).unwrap()).to_string();
-        if selected_namespace == "New namespace" {
-            selected_namespace = prompt_input(
-                "Namespace of the command",
-                false,
-                Some(String::from("default")),
-            );
-        }
-
-        Self {
-            namespace: selected_namespace,
-            ..self
-        }
-    }
-
-    fn with_name_input_prompt(
-        self,
-        default_value: Option<String>,
-        trove: &Trove,
-        prompt_string: &str,
-    ) -> Self {
-        let namespace = self.namespace.clone();
-        let command_names = trove.commands.clone();
-        let validator = move |input: &String| -> Result<(), String> {
-            if input.contains(' ') {
-                Err("The name can't contain whitespaces".to_string())
-            } else if command_names
-                .iter()
-                .filter(|x| x.namespace == namespace)
-                .any(|x| x.name == *input)
-            {
-                Err(
-                    "A command with same name exists in the this namespace. Input a different name"
-                        .to_string(),
-                )
-            } else {
-                Ok(())
-            }
-        };
-        let name = prompt_input_validate(prompt_string, false, default_value, Some(validator));
-        Self { name, ..self }
-    }
-
-    pub fn with_name_input(self, default_value: Option<String>, trove: &Trove) -> Self {
-        self.with_name_input_prompt(default_value, trove, "Name your command")
-    }
-
     pub fn resolve_name_conflict_random(self) -> Self {
         let rng = rand::thread_rng();
         let random_string: String = rng
@@ -390,78 +306,6 @@ impl HoardCmd {
         }
     }
 
-    /// Resolves a name conflict when a command should be added to a trove file.
-    ///
-    /// This function takes a command with a conflicting name and a reference to a trove. It prompts the user to resolve the conflict
-    /// by either replacing the existing command, keeping the existing command, or providing a new name for the new command.
-    ///
-    /// # Arguments
-    ///
-    /// * `collision` - A command that has a name conflict with the current command.
-    /// * `trove` - A reference to a trove where the command should be added.
-    ///
-    /// # Returns
-    ///
-    /// This function returns a tuple of options. If the first option is set, the new command should be added. If the second option is set,
-    /// the existing command should be removed.
-    ///
-    /// # Example
-    ///
-    /// ```
-    /// let command = HoardCmd::default().with_command("echo Hello, world!");
-    /// let colliding_command = HoardCmd::default().with_command("echo Hello, world!");
-    /// let trove = Trove::new();
-    /// let (add_command, remove_command) = command.resolve_name_conflict(colliding_command, &trove);
-    /// // The user is prompted to resolve the conflict.
-    /// // The commands to add and remove are determined based on the user's input.
-    /// ```
-    pub fn resolve_name_conflict(
-        self,
-        collision: Self,
-        trove: &Trove,
-    ) -> (Option<Self>, Option<Self>) {
-        // Behaviour if a command should be added to a trove file
-        // Returns a tuple of options
-        // If the first is set, add this as a new command
-        // If the second is set, remove this exact command
-        let name = self.name.clone();
-        let command = self.command.clone();
-        let namespace = self.namespace.clone();
-        let colliding_command = collision.command.clone();
-        // Ask user how to resolve conflict
-        let mode: ConflictResolve =
-            with_conflict_resolve_prompt(&name, &namespace, &command, &colliding_command);
-
-        match mode {
-            ConflictResolve::Replace => {
-                // Add new command, remove colliding command in the local trove
-                (Some(self), Some(collision))
-            }
-            ConflictResolve::Keep => {
-                // Do nothing
-                (None, None)
-            }
-            ConflictResolve::New => {
-                (Some(self.with_name_input_prompt(
-                    None,
-                    trove,
-                    &format!(
-                        "Enter a new name for command: '{command}'\nOld name: {name} in namespace: {namespace}\nEnter new name: "
-                    ),
-                )) , None)
-            }
-        }
-    }
-
-    pub fn with_description_input(self, default_value: String) -> Self {
-        let description_string: String =
-            prompt_input("Describe what the command does", false, Some(default_value));
-        Self {
-            description: description_string,
-            ..self
-        }
-    }
-
     pub fn mut_update_last_used(&mut self) {
         self.last_used = time::SystemTime::now();
     }
@@ -579,4 +423,12 @@ mod test_commands {
         let expected: Vec<String> = Vec::new();
         assert_eq!(expected, command.tags);
     }
+
+    #[test]
+    fn validate_namespaces() {
+        assert!(HoardCmd::is_namespace_valid("k8s-prod").is_ok());
+        assert!(HoardCmd::is_namespace_valid("").is_err());
+        assert!(HoardCmd::is_namespace_valid("k8s prod").is_err());
+        assert!(HoardCmd::is_namespace_valid("k8s/prod").is_err());
+    }
 }
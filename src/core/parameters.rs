@@ -1,7 +1,36 @@
 use regex::Regex;
+use std::collections::BTreeMap;
+use std::ops::Range;
 
+use crate::core::quoting::{quoting_at, QuotingMode};
 use crate::core::HoardCmd;
-use crate::gui::prompts::prompt_input;
+
+/// A parameter found in a command string
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Parameter {
+    /// The name of the parameter, without its quoting mode. Empty if the parameter is not named
+    pub name: String,
+    /// How the value of the parameter is quoted, chosen with a prefix of its name like `#raw:args!`
+    pub quoting: QuotingMode,
+    /// Byte range of the parameter in the command string, including its tokens
+    pub range: Range<usize>,
+}
+
+/// Prefix of the name of parameters filled with a secret, e.g. `#secret:api_token!`
+pub const SECRET_PREFIX: &str = "secret:";
+
+impl Parameter {
+    /// The name of the secret to fill this parameter with, if it is a secret parameter
+    pub fn secret_name(&self) -> Option<&str> {
+        self.name.strip_prefix(SECRET_PREFIX)
+    }
+
+    /// `value` quoted to replace this parameter in `command` with
+    pub fn quote(&self, command: &str, value: &str) -> String {
+        self.quoting
+            .quote(value, quoting_at(command, self.range.start))
+    }
+}
 
 pub trait Parameterized {
     /// Checks if the command string contains a specific token.
@@ -20,11 +49,12 @@ pub trait Parameterized {
     /// # Example
     ///
     /// ```
-    /// let command = HoardCmd::default()::with_command("echo $");
+    /// # use hoardlib::core::{parameters::Parameterized, HoardCmd};
+    /// let command = HoardCmd::default().with_command("echo $");
     /// assert!(command.is_parameterized("$"));
     /// ```
-
     fn is_parameterized(&self, token: &str) -> bool;
+
     /// Counts the number of occurrences of a specific token in the command string.
     ///
     /// This function takes a token and counts how many times this token appears in the command string.
@@ -40,11 +70,12 @@ pub trait Parameterized {
     /// # Example
     ///
     /// ```
-    /// let command = HoardCmd::default()::with_command("echo $ $");
+    /// # use hoardlib::core::{parameters::Parameterized, HoardCmd};
+    /// let command = HoardCmd::default().with_command("echo $ $");
     /// assert_eq!(command.get_parameter_count("$"), 2);
     /// ```
-
     fn get_parameter_count(&self, token: &str) -> usize;
+
     /// Splits the command string into a vector of substrings at each occurrence of a specific token.
     ///
     /// This function takes a token and splits the command string into a vector of substrings
@@ -61,7 +92,8 @@ pub trait Parameterized {
     /// # Example
     ///
     /// ```
-    /// let command = HoardCmd::default()::with_command("This is a #1 with one parameter token");
+    /// # use hoardlib::core::{parameters::Parameterized, HoardCmd};
+    /// let command = HoardCmd::default().with_command("This is a # with one parameter token");
     /// assert_eq!(command.split("#"), vec!["This is a ", " with one parameter token"]);
     /// ```
     fn split(&self, token: &str) -> Vec<String>;
@@ -82,8 +114,9 @@ pub trait Parameterized {
     /// # Example
     ///
     /// ```
-    /// let command = HoardCmd::default()::with_command("This is a #1 with one parameter token");
-    /// assert_eq!(command.split("#"), vec!["This is a ", "#1", " with one parameter token"]);
+    /// # use hoardlib::core::{parameters::Parameterized, HoardCmd};
+    /// let command = HoardCmd::default().with_command("This is a # with one parameter token");
+    /// assert_eq!(command.split_inclusive_token("#"), vec!["This is a ", "#", " with one parameter token"]);
     /// ```
     fn split_inclusive_token(&self, token: &str) -> Vec<String>;
 
@@ -105,16 +138,17 @@ pub trait Parameterized {
     /// # Example
     ///
     /// ```
-    /// let command = HoardCmd::default()::with_command("echo #param1$");
+    /// # use hoardlib::core::{parameters::Parameterized, HoardCmd};
+    /// let command = HoardCmd::default().with_command("echo #param1$");
     /// let replaced_command = command.replace_parameter("#", "$", "Hello, world!");
-    /// assert_eq!(replaced_command.get_command(), "echo Hello, world!");
+    /// assert_eq!(replaced_command.command, "echo Hello, world!");
     /// ```
     fn replace_parameter(&self, token: &str, ending_token: &str, parameter: &str) -> HoardCmd;
 
-    /// Replaces all occurrences of a parameter, identified by a token and an ending token, in the command string with user input.
+    /// Finds all parameters in the command string in order of their occurrence.
     ///
-    /// This function takes a token and an ending token. It prompts the user for input for each occurrence of the parameter
-    /// in the command string and replaces the parameter with the user's input.
+    /// A parameter starts with `token`. Its name ends with `ending_token` if it occurs before the next parameter,
+    /// otherwise with the first whitespace.
     ///
     /// # Arguments
     ///
@@ -123,17 +157,209 @@ pub trait Parameterized {
     ///
     /// # Returns
     ///
-    /// This function returns a new instance of the command with the replaced parameters.
+    /// This function returns a Vec<Parameter> with the name and position of every parameter occurrence.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use hoardlib::core::{parameters::Parameterized, HoardCmd};
+    /// let command = HoardCmd::default().with_command("echo #first name! # #city");
+    /// let names: Vec<String> = command.get_parameters("#", "!").into_iter().map(|p| p.name).collect();
+    /// assert_eq!(names, vec!["first name", "", "city"]);
+    /// ```
+    fn get_parameters(&self, token: &str, ending_token: &str) -> Vec<Parameter>;
+
+    /// Checks if the command string contains parameters the user has to provide a value for.
+    ///
+    /// Secret parameters are not counted, they are filled from the vault or asked for separately.
     ///
     /// # Example
     ///
     /// ```
-    /// let mut command = HoardCmd::default()::with_command("echo #param1$");
-    /// command = command.with_input_parameters("#", "$");
-    /// // The user is prompted for input for each occurrence of the parameter.
-    /// // The command string is updated with the user's input.
+    /// # use hoardlib::core::{parameters::Parameterized, HoardCmd};
+    /// let command = HoardCmd::default().with_command("curl -H #secret:token! #url!");
+    /// assert!(command.has_input_parameters("#", "!"));
+    /// let command = HoardCmd::default().with_command("curl -H #secret:token! example.com");
+    /// assert!(!command.has_input_parameters("#", "!"));
     /// ```
-    fn with_input_parameters(&mut self, token: &str, ending_token: &str) -> HoardCmd;
+    fn has_input_parameters(&self, token: &str, ending_token: &str) -> bool;
+}
+
+/// Finds all parameters in `command`. See `Parameterized::get_parameters`
+pub fn parse_parameters(command: &str, token: &str, ending_token: &str) -> Vec<Parameter> {
+    if token.is_empty() {
+        return Vec::new();
+    }
+    let starts: Vec<usize> = command.match_indices(token).map(|(i, _)| i).collect();
+    let mut parameters = Vec::new();
+    let mut consumed = 0;
+    for (i, start) in starts.iter().enumerate() {
+        if *start < consumed {
+            continue;
+        }
+        let name_start = start + token.len();
+        let rest = &command[name_start..];
+        let next_parameter = starts
+            .get(i + 1)
+            .map_or(command.len(), |next| *next)
+            .max(name_start);
+        let ending = if ending_token.is_empty() {
+            None
+        } else {
+            rest.find(ending_token)
+                .filter(|end| name_start + end <= next_parameter)
+        };
+        let (name, end) = ending.map_or_else(
+            || {
+                let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+                (&rest[..end], name_start + end)
+            },
+            |end| (&rest[..end], name_start + end + ending_token.len()),
+        );
+        let (quoting, name) = QuotingMode::from_name(name);
+        parameters.push(Parameter {
+            name: name.to_string(),
+            quoting,
+            range: *start..end,
+        });
+        consumed = end;
+    }
+    parameters
+}
+
+/// Names of the secrets used by the secret parameters in `command`, without duplicates
+pub fn secret_names(command: &str, token: &str, ending_token: &str) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    for parameter in parse_parameters(command, token, ending_token) {
+        if let Some(name) = parameter.secret_name() {
+            if !names.iter().any(|n| n == name) {
+                names.push(name.to_string());
+            }
+        }
+    }
+    names
+}
+
+/// Replaces the secret parameters in `command` with the values in `secrets`, quoted like other parameters
+///
+/// Secret parameters without a value are kept
+pub fn replace_secrets(
+    command: &str,
+    token: &str,
+    ending_token: &str,
+    secrets: &BTreeMap<String, String>,
+) -> String {
+    let mut replaced = command.to_string();
+    for parameter in parse_parameters(command, token, ending_token).iter().rev() {
+        if let Some(value) = parameter.secret_name().and_then(|name| secrets.get(name)) {
+            replaced.replace_range(parameter.range.clone(), &parameter.quote(command, value));
+        }
+    }
+    replaced
+}
+
+/// Replaces every parameter named `name` in `command` with `value`, quoted for each of them
+pub fn replace_named_parameter(
+    command: &str,
+    token: &str,
+    ending_token: &str,
+    name: &str,
+    value: &str,
+) -> String {
+    fill_named_parameter(command, token, ending_token, name, value, &mut Vec::new())
+}
+
+/// Like `replace_named_parameter`, keeping track of the values filled into `command` in `filled`. See `fill_next_parameter`
+pub fn fill_named_parameter(
+    command: &str,
+    token: &str,
+    ending_token: &str,
+    name: &str,
+    value: &str,
+    filled: &mut Vec<Range<usize>>,
+) -> String {
+    let parameters = parse_parameters(command, token, ending_token);
+    let replaced: Vec<&Parameter> = parameters.iter().filter(|p| p.name == name).collect();
+    fill(command, &replaced, value, filled)
+}
+
+/// Replaces the next parameter of `command` which isn't a secret, and the ones with the same name, with `value`.
+///
+/// The value is quoted for the shell according to the quoting mode of each occurrence, see [`QuotingMode`].
+///
+/// `filled` are the byte ranges of the values filled into `command` before. They are moved along with the text
+/// around them, and the ranges of `value` are added to them, so the values can be told apart from the rest of the command
+///
+/// # Example
+///
+/// ```
+/// # use hoardlib::core::parameters::fill_next_parameter;
+/// let mut filled = Vec::new();
+/// let command = fill_next_parameter("scp #file! #host!:#file!", "#", "!", "a b", &mut filled);
+/// assert_eq!(command, "scp 'a b' #host!:'a b'");
+/// let command = fill_next_parameter(&command, "#", "!", "pi", &mut filled);
+/// assert_eq!(command, "scp 'a b' pi:'a b'");
+/// assert_eq!(filled, vec![4..9, 13..18, 10..12]);
+///
+/// let command = fill_next_parameter("echo \"Hi #name!\" #raw:args!", "#", "!", "$USER", &mut Vec::new());
+/// let command = fill_next_parameter(&command, "#", "!", "-n *", &mut Vec::new());
+/// assert_eq!(command, "echo \"Hi \\$USER\" -n *");
+/// ```
+pub fn fill_next_parameter(
+    command: &str,
+    token: &str,
+    ending_token: &str,
+    value: &str,
+    filled: &mut Vec<Range<usize>>,
+) -> String {
+    let parameters = parse_parameters(command, token, ending_token);
+    let Some(next) = parameters.iter().find(|p| p.secret_name().is_none()) else {
+        return command.to_string();
+    };
+    let replaced: Vec<&Parameter> = parameters
+        .iter()
+        .filter(|p| p.range == next.range || (!next.name.is_empty() && p.name == next.name))
+        .collect();
+    fill(command, &replaced, value, filled)
+}
+
+/// Replaces the `replaced` parameters of `command`, in the order they are in, with `value` quoted for each of them,
+/// and moves the ranges of `filled` along
+fn fill(
+    command: &str,
+    replaced: &[&Parameter],
+    value: &str,
+    filled: &mut Vec<Range<usize>>,
+) -> String {
+    let mut result = String::with_capacity(command.len());
+    let mut position = 0;
+    // Ranges of the quoted values in the result, and how far the text after each parameter moved
+    let mut values = Vec::with_capacity(replaced.len());
+    let mut shifts = Vec::with_capacity(replaced.len());
+    for parameter in replaced {
+        result.push_str(&command[position..parameter.range.start]);
+        let start = result.len();
+        result.push_str(&parameter.quote(command, value));
+        values.push(start..result.len());
+        shifts.push((
+            parameter.range.end,
+            result.len() as isize - parameter.range.end as isize,
+        ));
+        position = parameter.range.end;
+    }
+    result.push_str(&command[position..]);
+    let shift = |at: usize| {
+        shifts
+            .iter()
+            .rev()
+            .find(|(end, _)| *end <= at)
+            .map_or(at, |(_, shift)| at.saturating_add_signed(*shift))
+    };
+    for range in filled.iter_mut() {
+        *range = shift(range.start)..shift(range.end);
+    }
+    filled.extend(values);
+    result
 }
 
 impl Parameterized for HoardCmd {
@@ -172,22 +398,14 @@ impl Parameterized for HoardCmd {
         Self::default().with_command(&replaced)
     }
 
-    fn with_input_parameters(&mut self, token: &str, ending_token: &str) -> Self {
-        let mut param_count = 0;
-        while self.get_parameter_count(token) != 0 {
-            let prompt_dialog = format!(
-                "Enter parameter({}) nr {} \n~> {}\n",
-                token,
-                (param_count + 1),
-                self.command
-            );
-            let parameter = prompt_input(&prompt_dialog, false, None);
-            self.command = self
-                .replace_parameter(token, ending_token, &parameter)
-                .command;
-            param_count += 1;
-        }
-        self.clone()
+    fn get_parameters(&self, token: &str, ending_token: &str) -> Vec<Parameter> {
+        parse_parameters(&self.command, token, ending_token)
+    }
+
+    fn has_input_parameters(&self, token: &str, ending_token: &str) -> bool {
+        self.get_parameters(token, ending_token)
+            .iter()
+            .any(|p| p.secret_name().is_none())
     }
 }
 
@@ -262,10 +480,102 @@ mod test_commands {
         assert_eq!(expected, command.replace_parameter("#", "!", "replacement"));
     }
 
+    #[test]
+    fn test_get_parameters() {
+        let command = HoardCmd::default().with_command("echo #first name! # #city. done");
+        let parameters = command.get_parameters("#", "!");
+        let names: Vec<&str> = parameters.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(vec!["first name", "", "city."], names);
+        assert_eq!(
+            "#first name!",
+            &command.command[parameters[0].range.clone()]
+        );
+        assert_eq!("#", &command.command[parameters[1].range.clone()]);
+        assert_eq!("#city.", &command.command[parameters[2].range.clone()]);
+    }
+
+    #[test]
+    fn test_get_parameters_ending_token_of_later_parameter() {
+        let command = HoardCmd::default().with_command("echo #a #b!");
+        let parameters = command.get_parameters("#", "!");
+        let names: Vec<&str> = parameters.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(vec!["a", "b"], names);
+    }
+
+    #[test]
+    fn test_get_parameters_no_spaces() {
+        let command = HoardCmd::default().with_command("test1#name!test3");
+        let parameters = command.get_parameters("#", "!");
+        assert_eq!(1, parameters.len());
+        assert_eq!("name", parameters[0].name);
+        assert_eq!(5..11, parameters[0].range);
+    }
+
     #[test]
     fn test_replace_parameter_with_endtoken_no_spaces() {
         let command = HoardCmd::default().with_command("test1#thisisacommand!test3");
         let expected = HoardCmd::default().with_command("test1replacementtest3");
         assert_eq!(expected, command.replace_parameter("#", "!", "replacement"));
     }
+
+    fn fill(command: &str, value: &str) -> String {
+        fill_next_parameter(command, "#", "!", value, &mut Vec::new())
+    }
+
+    #[test]
+    fn test_fill_next_parameter_named_and_unnamed() {
+        let replaced = fill("cp #file! # #file! #", "a.txt");
+        assert_eq!("cp a.txt # a.txt #", replaced);
+        let replaced = fill(&replaced, "b");
+        assert_eq!("cp a.txt b a.txt #", replaced);
+    }
+
+    #[test]
+    fn test_fill_next_parameter_skips_secrets() {
+        let replaced = fill("curl -H #secret:token! #url!", "example.com");
+        assert_eq!("curl -H #secret:token! example.com", replaced);
+        // Nothing left to replace but the secret
+        assert_eq!(replaced, fill(&replaced, "oops"));
+    }
+
+    #[test]
+    fn test_fill_next_parameter_quotes_values() {
+        let command =
+            "grep #pattern! #path:file! && echo \"found #pattern!\" '#pattern!' #raw:flags!";
+        let replaced = fill(
+            &fill(&fill(command, "it's $(rm -rf ~)"), "~/my notes.txt"),
+            "-i -n",
+        );
+        assert_eq!(
+            r#"grep 'it'\''s $(rm -rf ~)' ~/'my notes.txt' && echo "found it's \$(rm -rf ~)" 'it'\''s $(rm -rf ~)' -i -n"#,
+            replaced
+        );
+        let command = HoardCmd::default().with_command(command);
+        let names: Vec<String> = command
+            .get_parameters("#", "!")
+            .into_iter()
+            .map(|p| p.name)
+            .collect();
+        assert_eq!(
+            vec!["pattern", "file", "pattern", "pattern", "flags"],
+            names
+        );
+    }
+
+    #[test]
+    fn test_replace_secrets() {
+        let command = "login #secret:user! #secret:password! #secret:user! #secret:other!";
+        assert_eq!(
+            vec!["user", "password", "other"],
+            secret_names(command, "#", "!")
+        );
+        let secrets = BTreeMap::from([
+            ("user".to_string(), "me".to_string()),
+            ("password".to_string(), "hunter2".to_string()),
+        ]);
+        assert_eq!(
+            "login me hunter2 me #secret:other!",
+            replace_secrets(command, "#", "!", &secrets)
+        );
+    }
 }
@@ -0,0 +1,228 @@
+/// The kind of shell quotes a position of a command is in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Quoting {
+    None,
+    Single,
+    Double,
+}
+
+/// How the value of a parameter is quoted when it is put into a command
+///
+/// It is chosen with a prefix of the parameter name, e.g. `#path:file!`. Parameters without one are quoted for POSIX shells.
+/// Values are only quoted where they need to be, and escaped to fit into quotes the parameter is in already
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum QuotingMode {
+    /// The value is inserted as it is typed, e.g. to provide several arguments at once
+    Raw,
+    /// Quoted for POSIX shells like sh, bash and zsh
+    #[default]
+    Sh,
+    /// Quoted for fish
+    Fish,
+    /// Quoted for POSIX shells, but a leading `~/` is kept out of the quotes, so the shell still expands it
+    Path,
+}
+
+impl QuotingMode {
+    const PREFIXES: [(&'static str, Self); 4] = [
+        ("raw:", Self::Raw),
+        ("sh:", Self::Sh),
+        ("fish:", Self::Fish),
+        ("path:", Self::Path),
+    ];
+
+    /// Splits the quoting mode off the name of a parameter
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use hoardlib::core::quoting::QuotingMode;
+    /// assert_eq!(QuotingMode::from_name("path:file"), (QuotingMode::Path, "file"));
+    /// assert_eq!(QuotingMode::from_name("file"), (QuotingMode::Sh, "file"));
+    /// ```
+    pub fn from_name(name: &str) -> (Self, &str) {
+        Self::PREFIXES
+            .iter()
+            .find_map(|(prefix, mode)| name.strip_prefix(prefix).map(|name| (*mode, name)))
+            .unwrap_or((Self::default(), name))
+    }
+
+    /// Quotes `value` for a parameter at a position of a command which is in `quoting`
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use hoardlib::core::quoting::{Quoting, QuotingMode};
+    /// assert_eq!(QuotingMode::Sh.quote("it's $HOME", Quoting::None), r"'it'\''s $HOME'");
+    /// assert_eq!(QuotingMode::Sh.quote("$HOME", Quoting::Double), r"\$HOME");
+    /// assert_eq!(QuotingMode::Path.quote("~/my notes", Quoting::None), "~/'my notes'");
+    /// ```
+    pub fn quote(self, value: &str, quoting: Quoting) -> String {
+        match (self, quoting) {
+            (Self::Raw, _) => value.to_string(),
+            (Self::Sh | Self::Path, Quoting::Single) => value.replace('\'', r"'\''"),
+            (Self::Sh | Self::Path, Quoting::Double) => escape(value, &['\\', '"', '$', '`']),
+            (Self::Fish, Quoting::Single) => escape(value, &['\\', '\'']),
+            (Self::Fish, Quoting::Double) => escape(value, &['\\', '"', '$']),
+            (Self::Path, Quoting::None) => match value.strip_prefix("~/") {
+                Some("") => value.to_string(),
+                Some(rest) => format!("~/{}", Self::Sh.quote(rest, quoting)),
+                None => Self::Sh.quote(value, quoting),
+            },
+            (_, Quoting::None) if !value.is_empty() && value.chars().all(is_safe) => {
+                value.to_string()
+            }
+            (Self::Sh, Quoting::None) => format!("'{}'", value.replace('\'', r"'\''")),
+            (Self::Fish, Quoting::None) => format!("'{}'", escape(value, &['\\', '\''])),
+        }
+    }
+}
+
+/// Characters no shell gives a meaning to
+const fn is_safe(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.' | '/' | ',' | ':' | '+' | '@' | '%')
+}
+
+/// Puts a backslash in front of every character of `value` in `special`
+fn escape(value: &str, special: &[char]) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if special.contains(&c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Determines in which kind of shell quotes the position `at` of `command` is
+pub fn quoting_at(command: &str, at: usize) -> Quoting {
+    let mut quoting = Quoting::None;
+    let mut escaped = false;
+    for c in command[..at].chars() {
+        quoting = match (quoting, c) {
+            (Quoting::Single, '\'') => Quoting::None,
+            (Quoting::Single, _) => Quoting::Single,
+            (q, _) if escaped => {
+                escaped = false;
+                q
+            }
+            (q, '\\') => {
+                escaped = true;
+                q
+            }
+            (Quoting::None, '\'') => Quoting::Single,
+            (Quoting::None, '"') => Quoting::Double,
+            (Quoting::Double, '"') => Quoting::None,
+            (q, _) => q,
+        };
+    }
+    quoting
+}
+
+#[cfg(test)]
+mod test_quoting {
+    use super::*;
+
+    const TRICKY_VALUES: [&str; 9] = [
+        "hello world",
+        "it's",
+        "say \"hi\"",
+        "$HOME `id` $(id)",
+        "a; rm -rf ~",
+        "back\\slash",
+        "*.txt",
+        "",
+        "line\nbreak",
+    ];
+
+    /// What `sh` makes of `command`, one argument per line
+    fn sh_arguments(command: &str) -> String {
+        let output = std::process::Command::new("sh")
+            .arg("-c")
+            .arg(format!("printf '%s\\n' {command}"))
+            .output()
+            .unwrap();
+        String::from_utf8(output.stdout).unwrap()
+    }
+
+    #[test]
+    fn test_sh_quoting_keeps_values_intact() {
+        for value in TRICKY_VALUES {
+            let expected = format!("{value}\n");
+            let unquoted = QuotingMode::Sh.quote(value, Quoting::None);
+            assert_eq!(expected, sh_arguments(&unquoted), "{unquoted}");
+            let in_single = format!("'{}'", QuotingMode::Sh.quote(value, Quoting::Single));
+            assert_eq!(expected, sh_arguments(&in_single), "{in_single}");
+            let in_double = format!("\"{}\"", QuotingMode::Sh.quote(value, Quoting::Double));
+            assert_eq!(expected, sh_arguments(&in_double), "{in_double}");
+        }
+    }
+
+    #[test]
+    fn test_fish_quoting() {
+        assert_eq!(
+            r"'it\'s \\n'",
+            QuotingMode::Fish.quote(r"it's \n", Quoting::None)
+        );
+        assert_eq!(r"it\'s", QuotingMode::Fish.quote("it's", Quoting::Single));
+        assert_eq!(
+            r#"\$HOME \"x\""#,
+            QuotingMode::Fish.quote("$HOME \"x\"", Quoting::Double)
+        );
+        assert_eq!("main.rs", QuotingMode::Fish.quote("main.rs", Quoting::None));
+    }
+
+    #[test]
+    fn test_safe_values_and_raw_mode() {
+        assert_eq!("worker-1", QuotingMode::Sh.quote("worker-1", Quoting::None));
+        assert_eq!(
+            "user@example.com:22",
+            QuotingMode::Sh.quote("user@example.com:22", Quoting::None)
+        );
+        assert_eq!("''", QuotingMode::Sh.quote("", Quoting::None));
+        assert_eq!(
+            "-la *.rs",
+            QuotingMode::Raw.quote("-la *.rs", Quoting::None)
+        );
+    }
+
+    #[test]
+    fn test_path_quoting() {
+        assert_eq!("~/", QuotingMode::Path.quote("~/", Quoting::None));
+        assert_eq!(
+            "~/'my notes/todo.md'",
+            QuotingMode::Path.quote("~/my notes/todo.md", Quoting::None)
+        );
+        assert_eq!(
+            "'~user/x'",
+            QuotingMode::Path.quote("~user/x", Quoting::None)
+        );
+        assert_eq!("~/x", QuotingMode::Path.quote("~/x", Quoting::Double));
+        assert_eq!(
+            "'/tmp/a b'",
+            QuotingMode::Path.quote("/tmp/a b", Quoting::None)
+        );
+    }
+
+    #[test]
+    fn test_quoting_at() {
+        let command = r#"ls A 'B "C' "D \" 'E" F"#;
+        assert_eq!(
+            Quoting::None,
+            quoting_at(command, command.find('A').unwrap())
+        );
+        assert_eq!(
+            Quoting::Single,
+            quoting_at(command, command.find('C').unwrap())
+        );
+        assert_eq!(
+            Quoting::Double,
+            quoting_at(command, command.find('E').unwrap())
+        );
+        assert_eq!(
+            Quoting::None,
+            quoting_at(command, command.find('F').unwrap())
+        );
+    }
+}
@@ -0,0 +1,171 @@
+use regex::{Captures, Regex};
+use std::sync::OnceLock;
+
+use crate::core::error::HoardError;
+use crate::core::trove::find_by_reference;
+use crate::core::HoardCmd;
+
+/// Matches `{{reference}}`, where the reference is an id, `namespace/name` or a name
+fn reference_pattern() -> &'static Regex {
+    // Compiled once, `hoard list` looks for references in every command whenever it reloads them
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| Regex::new(r"\{\{\s*([^{}\s]+)\s*\}\}").unwrap())
+}
+
+/// The commands `command` embeds with `{{reference}}`, in order
+///
+/// References which don't point to a command of `commands` are skipped
+pub fn referenced_commands<'a>(command: &HoardCmd, commands: &'a [HoardCmd]) -> Vec<&'a HoardCmd> {
+    reference_pattern()
+        .captures_iter(&command.command)
+        .filter_map(|captures| find_command(commands, &captures[1]).ok().flatten())
+        .collect()
+}
+
+/// Replaces the `{{reference}}`s to other commands in `command` with their command strings, recursively
+///
+/// The parameters of the referenced commands end up in the resolved command, so a parameter with the
+/// same name in several of them is asked for once.
+/// References which don't point to a command are kept, so templates like `docker ps --format '{{.Names}}'`
+/// keep working. A command which embeds itself, directly or through other commands, is an error
+///
+/// # Example
+///
+/// ```
+/// # use hoardlib::core::{references::resolve_references, HoardCmd};
+/// let context = HoardCmd::default()
+///     .with_name("ctx")
+///     .with_namespace("k8s")
+///     .with_command("kubectl --context #cluster!");
+/// let pods = HoardCmd::default().with_command("{{k8s/ctx}} get pods");
+/// let commands = [context, pods.clone()];
+/// assert_eq!(
+///     resolve_references(&pods, &commands).unwrap(),
+///     "kubectl --context #cluster! get pods"
+/// );
+/// ```
+pub fn resolve_references(command: &HoardCmd, commands: &[HoardCmd]) -> Result<String, HoardError> {
+    resolve(command, commands, &mut Vec::new())
+}
+
+/// Commands of `commands` which embed the command with the id `id` directly
+pub fn dependents<'a>(id: &str, commands: &'a [HoardCmd]) -> Vec<&'a HoardCmd> {
+    commands
+        .iter()
+        .filter(|command| {
+            command.id != id
+                && referenced_commands(command, commands)
+                    .iter()
+                    .any(|referenced| referenced.id == id)
+        })
+        .collect()
+}
+
+fn resolve<'a>(
+    command: &'a HoardCmd,
+    commands: &'a [HoardCmd],
+    stack: &mut Vec<&'a HoardCmd>,
+) -> Result<String, HoardError> {
+    if stack.iter().any(|c| c.id == command.id) {
+        let cycle = stack
+            .iter()
+            .skip_while(|c| c.id != command.id)
+            .chain(std::iter::once(&command))
+            .map(|c| format!("{}/{}", c.namespace, c.name))
+            .collect::<Vec<_>>()
+            .join(" -> ");
+        return Err(HoardError::Validation(format!(
+            "Commands embed each other: {cycle}"
+        )));
+    }
+    stack.push(command);
+    let mut error = None;
+    let resolved = reference_pattern().replace_all(&command.command, |captures: &Captures| {
+        let resolved = find_command(commands, &captures[1]).and_then(|referenced| {
+            referenced.map_or_else(
+                || Ok(captures[0].to_string()),
+                |referenced| resolve(referenced, commands, stack),
+            )
+        });
+        resolved.unwrap_or_else(|e| {
+            error.get_or_insert(e);
+            String::new()
+        })
+    });
+    stack.pop();
+    match error {
+        Some(e) => Err(e),
+        None => Ok(resolved.into_owned()),
+    }
+}
+
+/// The command `reference` points to, or `None` if there is no such command
+fn find_command<'a>(
+    commands: &'a [HoardCmd],
+    reference: &str,
+) -> Result<Option<&'a HoardCmd>, HoardError> {
+    match find_by_reference(commands, reference, |c| (&c.id, &c.namespace, &c.name)) {
+        Ok(command) => Ok(Some(command)),
+        Err(HoardError::NotFound(_)) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+#[cfg(test)]
+mod test_references {
+    use super::*;
+
+    fn command(namespace: &str, name: &str, command: &str) -> HoardCmd {
+        HoardCmd::default()
+            .with_namespace(namespace)
+            .with_name(name)
+            .with_command(command)
+    }
+
+    #[test]
+    fn test_nested_references() {
+        let commands = vec![
+            command("k8s", "ctx", "kubectl --context #cluster!"),
+            command("k8s", "pods", "{{k8s/ctx}} get pods -n #namespace!"),
+            command("k8s", "logs", "{{ pods }} | grep #cluster!"),
+        ];
+        assert_eq!(
+            "kubectl --context #cluster! get pods -n #namespace! | grep #cluster!",
+            resolve_references(&commands[2], &commands).unwrap()
+        );
+        assert_eq!(
+            vec!["pods"],
+            dependents(&commands[0].id, &commands)
+                .iter()
+                .map(|c| c.name.as_str())
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_unknown_references_are_kept() {
+        let commands = vec![command(
+            "docker",
+            "ps",
+            "docker ps --format '{{.Names}} {{ json . }}'",
+        )];
+        assert_eq!(
+            "docker ps --format '{{.Names}} {{ json . }}'",
+            resolve_references(&commands[0], &commands).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_cycles_are_detected() {
+        let commands = vec![
+            command("a", "one", "echo {{a/two}}"),
+            command("a", "two", "echo {{a/three}}"),
+            command("a", "three", "echo {{one}}"),
+        ];
+        let error = resolve_references(&commands[0], &commands).unwrap_err();
+        assert!(matches!(error, HoardError::Validation(_)));
+        assert!(error
+            .to_string()
+            .contains("a/one -> a/two -> a/three -> a/one"));
+    }
+}
@@ -0,0 +1,309 @@
+use anyhow::{anyhow, Result};
+use chrono::Local;
+use std::collections::hash_map::DefaultHasher;
+use std::fs::{self, File, OpenOptions};
+use std::hash::{Hash, Hasher};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+const BACKUP_PREFIX: &str = "trove-";
+const BACKUP_EXTENSION: &str = ".yml";
+
+/// Write `contents` to `path` without ever leaving a partially written file behind
+///
+/// The contents are written to a temporary file next to `path`, flushed to disk and then renamed over `path`.
+/// A crash or a full disk either keeps the old file or leaves the new one, never something in between.
+/// A symlink at `path` is followed, so the file it points to is replaced, and the permissions of the old file are kept
+pub fn write_atomic(path: &Path, contents: &str) -> Result<()> {
+    write_atomic_with(path, contents, false)
+}
+
+/// Write `contents` to `path` like [`write_atomic`], readable and writable only by the current user
+///
+/// The temporary file is created that way before anything is written to it, and `path` keeps no other permissions
+pub fn write_private(path: &Path, contents: &str) -> Result<()> {
+    write_atomic_with(path, contents, true)
+}
+
+fn write_atomic_with(path: &Path, contents: &str, private: bool) -> Result<()> {
+    let resolved = fs::canonicalize(path).ok();
+    let path = resolved.as_deref().unwrap_or(path);
+    let permissions = if private {
+        private_permissions()
+    } else {
+        fs::metadata(path)
+            .ok()
+            .map(|metadata| metadata.permissions())
+    };
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| anyhow!("{} is not a file path", path.display()))?;
+    let directory = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let mut tmp_name = file_name.to_owned();
+    tmp_name.push(format!(".{}.tmp", std::process::id()));
+    let tmp_path = directory.join(tmp_name);
+
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    if private {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let written = options
+        .open(&tmp_path)
+        .and_then(|mut file| {
+            // A temporary file left behind by a crash keeps its permissions, they are set before writing
+            if let Some(permissions) = permissions {
+                file.set_permissions(permissions)?;
+            }
+            file.write_all(contents.as_bytes())?;
+            file.sync_all()
+        })
+        .and_then(|()| fs::rename(&tmp_path, path));
+    if let Err(e) = written {
+        let _ = fs::remove_file(&tmp_path);
+        return Err(anyhow!("Could not write {}: {e}", path.display()));
+    }
+    // Persist the rename itself. Not every platform allows to open directories, so this is best effort
+    if let Ok(dir) = File::open(directory) {
+        let _ = dir.sync_all();
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn private_permissions() -> Option<fs::Permissions> {
+    use std::os::unix::fs::PermissionsExt;
+    Some(fs::Permissions::from_mode(0o600))
+}
+
+#[cfg(not(unix))]
+fn private_permissions() -> Option<fs::Permissions> {
+    None
+}
+
+/// Copy the trove file at `path` into `backup_dir` as `trove-<timestamp>.yml`
+///
+/// Only the `keep` most recent backups are kept, older ones are removed.
+/// Returns the path of the new backup, or `None` if there was nothing to back up
+pub fn backup_trove_file(path: &Path, backup_dir: &Path, keep: usize) -> Result<Option<PathBuf>> {
+    if keep == 0 || !path.exists() {
+        return Ok(None);
+    }
+    fs::create_dir_all(backup_dir)?;
+    let timestamp = Local::now().format("%Y%m%d-%H%M%S%.3f");
+    let backup_path = backup_dir.join(format!("{BACKUP_PREFIX}{timestamp}{BACKUP_EXTENSION}"));
+    write_atomic(&backup_path, &fs::read_to_string(path)?)?;
+
+    let backups = list_backups(backup_dir)?;
+    for outdated in backups.iter().take(backups.len().saturating_sub(keep)) {
+        fs::remove_file(outdated)?;
+    }
+    Ok(Some(backup_path))
+}
+
+/// All backups in `backup_dir`, oldest first
+pub fn list_backups(backup_dir: &Path) -> Result<Vec<PathBuf>> {
+    if !backup_dir.exists() {
+        return Ok(Vec::new());
+    }
+    let mut backups = fs::read_dir(backup_dir)?
+        .filter_map(std::result::Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| {
+                    name.starts_with(BACKUP_PREFIX) && name.ends_with(BACKUP_EXTENSION)
+                })
+        })
+        .collect::<Vec<_>>();
+    // Timestamps are zero padded, so sorting by name sorts by age
+    backups.sort();
+    Ok(backups)
+}
+
+/// Advisory lock of a trove file, released when dropped
+///
+/// Every hoard process takes the lock before reading a trove file it is going to write to, and holds it until it is written.
+/// The lock is taken on a separate `<trove file>.lock` file, because saving replaces the trove file itself
+#[derive(Debug)]
+pub struct TroveLock {
+    _file: File,
+}
+
+impl TroveLock {
+    /// Blocks until no other hoard process holds the lock of the trove file at `path`
+    pub fn acquire(path: &Path) -> Result<Self> {
+        let mut lock_path = path.as_os_str().to_owned();
+        lock_path.push(".lock");
+        let lock_path = PathBuf::from(lock_path);
+        let file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&lock_path)
+            .map_err(|e| anyhow!("Could not open lock file {}: {e}", lock_path.display()))?;
+        file.lock()
+            .map_err(|e| anyhow!("Could not lock {}: {e}", lock_path.display()))?;
+        Ok(Self { _file: file })
+    }
+}
+
+/// State of a file on disk, to notice if it has been changed by someone else
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Fingerprint {
+    len: usize,
+    hash: u64,
+}
+
+impl Fingerprint {
+    /// Fingerprint of the file at `path`, or `None` if there is no such file
+    pub fn of(path: &Path) -> Result<Option<Self>> {
+        if !path.exists() {
+            return Ok(None);
+        }
+        // Modification times are too coarse on some file systems to notice quick successive writes, so compare the content
+        let content = fs::read(path)?;
+        let mut hasher = DefaultHasher::new();
+        content.hash(&mut hasher);
+        Ok(Some(Self {
+            len: content.len(),
+            hash: hasher.finish(),
+        }))
+    }
+
+    /// Check if the file changed since `fingerprint` has been taken
+    ///
+    /// Touching the file without changing its content is not considered a change
+    pub fn has_changed(fingerprint: Option<Self>, current: Option<Self>) -> bool {
+        fingerprint != current
+    }
+}
+
+#[cfg(test)]
+mod test_storage {
+    use super::*;
+
+    #[test]
+    fn test_write_atomic() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let path = tmp_dir.path().join("trove.yml");
+        write_atomic(&path, "first").unwrap();
+        write_atomic(&path, "second").unwrap();
+
+        assert_eq!("second", fs::read_to_string(&path).unwrap());
+        // No temporary files are left behind
+        assert_eq!(1, fs::read_dir(tmp_dir.path()).unwrap().count());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_write_atomic_keeps_symlinks_and_permissions() {
+        use std::os::unix::fs::{symlink, PermissionsExt};
+
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let target = tmp_dir.path().join("dotfiles-trove.yml");
+        let link = tmp_dir.path().join("trove.yml");
+        fs::write(&target, "first").unwrap();
+        fs::set_permissions(&target, fs::Permissions::from_mode(0o600)).unwrap();
+        symlink(&target, &link).unwrap();
+
+        write_atomic(&link, "second").unwrap();
+
+        assert!(fs::symlink_metadata(&link)
+            .unwrap()
+            .file_type()
+            .is_symlink());
+        assert_eq!("second", fs::read_to_string(&target).unwrap());
+        assert_eq!(
+            0o600,
+            fs::metadata(&target).unwrap().permissions().mode() & 0o777
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_write_private() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let path = tmp_dir.path().join("vault.yml");
+        write_private(&path, "first").unwrap();
+        assert_eq!(
+            0o600,
+            fs::metadata(&path).unwrap().permissions().mode() & 0o777
+        );
+
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o644)).unwrap();
+        // A temporary file left behind readable by others
+        let tmp_path = tmp_dir
+            .path()
+            .join(format!("vault.yml.{}.tmp", std::process::id()));
+        fs::write(&tmp_path, "").unwrap();
+        fs::set_permissions(&tmp_path, fs::Permissions::from_mode(0o644)).unwrap();
+        write_private(&path, "second").unwrap();
+        assert_eq!("second", fs::read_to_string(&path).unwrap());
+        assert_eq!(
+            0o600,
+            fs::metadata(&path).unwrap().permissions().mode() & 0o777
+        );
+    }
+
+    #[test]
+    fn test_backups_are_rotated() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let path = tmp_dir.path().join("trove.yml");
+        let backup_dir = tmp_dir.path().join("backups");
+        assert!(backup_trove_file(&path, &backup_dir, 2).unwrap().is_none());
+
+        for content in ["first", "second", "third"] {
+            fs::write(&path, content).unwrap();
+            backup_trove_file(&path, &backup_dir, 2).unwrap().unwrap();
+            std::thread::sleep(std::time::Duration::from_millis(2));
+        }
+
+        let backups = list_backups(&backup_dir).unwrap();
+        let contents = backups
+            .iter()
+            .map(|backup| fs::read_to_string(backup).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(vec!["second", "third"], contents);
+    }
+
+    #[test]
+    fn test_fingerprint_detects_changes() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let path = tmp_dir.path().join("trove.yml");
+        let missing = Fingerprint::of(&path).unwrap();
+        assert!(missing.is_none());
+
+        fs::write(&path, "first").unwrap();
+        let first = Fingerprint::of(&path).unwrap();
+        assert!(Fingerprint::has_changed(missing, first));
+        assert!(!Fingerprint::has_changed(
+            first,
+            Fingerprint::of(&path).unwrap()
+        ));
+
+        fs::write(&path, "second").unwrap();
+        assert!(Fingerprint::has_changed(
+            first,
+            Fingerprint::of(&path).unwrap()
+        ));
+    }
+
+    #[test]
+    fn test_lock_is_released_on_drop() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let path = tmp_dir.path().join("trove.yml");
+        let lock = TroveLock::acquire(&path).unwrap();
+        drop(lock);
+        // Would block forever if the first lock was still held
+        TroveLock::acquire(&path).unwrap();
+    }
+}
@@ -0,0 +1,356 @@
+pub mod sqlite;
+pub mod yaml;
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::io;
+use std::path::Path;
+
+use crate::config::HoardConfig;
+use crate::core::error::TroveError;
+use crate::core::storage::TroveLock;
+use crate::core::trove::Trove;
+use crate::core::HoardCmd;
+
+pub use sqlite::SqliteStore;
+pub use yaml::YamlStore;
+
+/// The ways a trove can be stored
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum StorageBackend {
+    /// A single yaml file, easy to read, edit and put into version control
+    #[default]
+    Yaml,
+    /// A SQLite database with a full text search index, for large troves
+    Sqlite,
+}
+
+impl fmt::Display for StorageBackend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Yaml => write!(f, "yaml"),
+            Self::Sqlite => write!(f, "sqlite"),
+        }
+    }
+}
+
+/// Storage of the commands of a trove
+///
+/// Commands are identified by their id. Every store is safe to be used by several hoard processes at the same time
+pub trait TroveStore: fmt::Debug {
+    /// Keeps other hoard processes from changing the store until [`TroveStore::unlock`]
+    ///
+    /// Held from loading the trove until it is saved, no change of another process gets lost in between
+    fn lock(&mut self) -> Result<()>;
+
+    /// Lets other hoard processes change the store again
+    fn unlock(&mut self);
+
+    /// Loads all commands of the store
+    fn load(&mut self) -> Result<Trove, TroveError>;
+
+    /// The command with the id `id`, if there is one
+    fn get(&self, id: &str) -> Result<Option<HoardCmd>>;
+
+    /// Adds `command`, or replaces the command with the same id
+    fn upsert(&mut self, command: &HoardCmd) -> Result<()>;
+
+    /// Removes the command with the id `id`. Returns `false` if there was no such command
+    fn delete(&mut self, id: &str) -> Result<bool>;
+
+    /// All commands matching every word of `query` in their name, namespace, command, description or tags
+    fn query(&self, query: &str) -> Result<Vec<HoardCmd>>;
+
+    /// Counts a usage of the command with the id `id`
+    fn record_usage(&mut self, id: &str) -> Result<()>;
+
+    /// Saves the changes made from `base`, the trove as it has been loaded, to `trove`
+    ///
+    /// Changes saved by other hoard processes since `base` has been loaded are kept, see [`Trove::merge_changes`].
+    /// Returns the trove as it is stored now
+    fn save(&mut self, base: &Trove, trove: &Trove) -> Result<Trove>;
+}
+
+/// Opens the store of the trove configured in `config`, using `backend` to store it
+pub fn open_store(config: &HoardConfig, backend: StorageBackend) -> Result<Box<dyn TroveStore>> {
+    match backend {
+        StorageBackend::Yaml => {
+            let trove_path = config
+                .trove_path
+                .clone()
+                .ok_or_else(|| anyhow!("No trove file configured"))?;
+            let backup_dir = config
+                .config_home_path
+                .as_ref()
+                .map(|config_home_path| config_home_path.join("backups"));
+            Ok(Box::new(YamlStore::new(
+                trove_path,
+                backup_dir,
+                config.backup_count.unwrap_or_default(),
+            )))
+        }
+        StorageBackend::Sqlite => {
+            let database_path = config
+                .database_path
+                .as_ref()
+                .ok_or_else(|| anyhow!("No trove database configured"))?;
+            Ok(Box::new(SqliteStore::open(database_path)?))
+        }
+    }
+}
+
+/// The lock of the store at `path` for a single operation, unless the store is `locked` already
+fn lock_once(locked: Option<&TroveLock>, path: &Path) -> Result<Option<TroveLock>> {
+    if locked.is_some() {
+        return Ok(None);
+    }
+    TroveLock::acquire(path).map(Some)
+}
+
+/// Reports that the store at `path` could not be locked while loading it
+fn lock_error(path: &Path, error: &anyhow::Error) -> TroveError {
+    TroveError::Io {
+        path: path.to_path_buf(),
+        source: io::Error::other(format!("{error:#}")),
+    }
+}
+
+/// Whether `command` contains every whitespace separated word of `query`
+///
+/// An empty query matches every command
+fn matches_query(command: &HoardCmd, query: &str) -> bool {
+    let tags = command.get_tags_as_string();
+    query.split_whitespace().all(|word| {
+        [
+            command.name.as_str(),
+            command.namespace.as_str(),
+            command.command.as_str(),
+            command.description.as_str(),
+            tags.as_str(),
+        ]
+        .iter()
+        .any(|field| field.to_lowercase().contains(&word.to_lowercase()))
+    })
+}
+
+#[cfg(test)]
+mod test_store {
+    use super::*;
+    use crate::core::workflow::Workflow;
+
+    fn command(name: &str, command: &str) -> HoardCmd {
+        HoardCmd::default()
+            .with_name(name)
+            .with_namespace("default")
+            .with_command(command)
+    }
+
+    fn stores(dir: &std::path::Path) -> Vec<Box<dyn TroveStore>> {
+        vec![
+            Box::new(YamlStore::new(dir.join("trove.yml"), None, 0)),
+            Box::new(SqliteStore::open(&dir.join("trove.db")).unwrap()),
+        ]
+    }
+
+    #[test]
+    fn test_upsert_get_delete() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        for mut store in stores(tmp_dir.path()) {
+            assert!(store.load().unwrap().is_empty());
+            let mut cmd = command("status", "git status");
+            store.upsert(&cmd).unwrap();
+            cmd.description = "Show the working tree status".to_string();
+            store.upsert(&cmd).unwrap();
+
+            let stored = store.get(&cmd.id).unwrap().unwrap();
+            assert_eq!(cmd, stored);
+            assert_eq!(1, store.load().unwrap().commands.len());
+
+            assert!(store.delete(&cmd.id).unwrap());
+            assert!(!store.delete(&cmd.id).unwrap());
+            assert!(store.get(&cmd.id).unwrap().is_none());
+        }
+    }
+
+    #[test]
+    fn test_query_and_record_usage() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        for mut store in stores(tmp_dir.path()) {
+            let status = command("status", "git status");
+            let checkout = command("checkout", "git checkout #branch!");
+            let list = command("list", "ls -la");
+            for cmd in [&status, &checkout, &list] {
+                store.upsert(cmd).unwrap();
+            }
+
+            assert_eq!(3, store.query("").unwrap().len());
+            assert_eq!(2, store.query("git").unwrap().len());
+            let found = store.query("git check").unwrap();
+            assert_eq!(vec![checkout.clone()], found, "{store:?}");
+
+            store.record_usage(&list.id).unwrap();
+            store.record_usage(&list.id).unwrap();
+            assert_eq!(2, store.get(&list.id).unwrap().unwrap().usage_count);
+            assert!(store.record_usage("missing").is_err());
+        }
+    }
+
+    #[test]
+    fn test_stores_agree_on_queries() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let commands = [
+            command("status", "git status").with_tags(vec!["vcs".to_string(), "Daily".to_string()]),
+            command("checkout", "git checkout #branch!").with_description("Switch branches"),
+            command("list", "ls -la"),
+            command("pods", "kubectl get pods -n #namespace!").with_namespace("k8s"),
+            command("greet", "echo \"say hi\""),
+        ];
+        let queries = [
+            "",
+            "git",
+            "GIT",
+            "atus",
+            "it st",
+            "ls",
+            "-la",
+            "-n",
+            "vcs,daily",
+            "ch",
+            "k8s pods",
+            "say\"hi",
+            "\"say",
+            "nothing",
+        ];
+        let mut results = stores(tmp_dir.path()).into_iter().map(|mut store| {
+            for cmd in &commands {
+                store.upsert(cmd).unwrap();
+            }
+            queries.map(|query| {
+                let mut names = store
+                    .query(query)
+                    .unwrap()
+                    .into_iter()
+                    .map(|c| c.name)
+                    .collect::<Vec<_>>();
+                names.sort();
+                names
+            })
+        });
+        let yaml = results.next().unwrap();
+        let sqlite = results.next().unwrap();
+        for ((query, yaml), sqlite) in queries.iter().zip(&yaml).zip(&sqlite) {
+            assert_eq!(yaml, sqlite, "{query}");
+        }
+        assert_eq!(vec!["status"], yaml[3]);
+        assert_eq!(vec!["list"], yaml[5]);
+        assert_eq!(vec!["status"], yaml[8]);
+        assert_eq!(vec!["checkout", "greet"], yaml[9]);
+    }
+
+    #[test]
+    fn test_save_merges_concurrent_changes() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        for mut store in stores(tmp_dir.path()) {
+            let status = command("status", "git status");
+            store.upsert(&status).unwrap();
+            let base = store.load().unwrap();
+
+            // Another process adds a command in the meantime
+            let list = command("list", "ls -la");
+            store.upsert(&list).unwrap();
+
+            let mut ours = base.clone();
+            ours.commands[0].usage_count += 1;
+            ours.add_command(command("log", "git log"), true).unwrap();
+            let saved = store.save(&base, &ours).unwrap();
+
+            assert_eq!(3, saved.commands.len());
+            assert_eq!(3, store.load().unwrap().commands.len());
+            assert_eq!(1, store.get(&status.id).unwrap().unwrap().usage_count);
+        }
+    }
+
+    #[test]
+    fn test_lock_is_held_until_unlocked() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        for mut store in stores(tmp_dir.path()) {
+            store.lock().unwrap();
+            // Locking again, loading and saving while the lock is held don't wait for the lock itself
+            store.lock().unwrap();
+            let base = store.load().unwrap();
+            let mut ours = base.clone();
+            ours.add_command(command("status", "git status"), true)
+                .unwrap();
+            store.save(&base, &ours).unwrap();
+            store.unlock();
+        }
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let path = tmp_dir.path().join("trove.yml");
+        let mut store = YamlStore::new(path.clone(), None, 0);
+        store.lock().unwrap();
+        let waiting = std::thread::spawn(move || {
+            let _lock = TroveLock::acquire(&path).unwrap();
+            sender.send(()).unwrap();
+        });
+        assert!(receiver
+            .recv_timeout(std::time::Duration::from_millis(100))
+            .is_err());
+        store.unlock();
+        receiver
+            .recv_timeout(std::time::Duration::from_secs(5))
+            .unwrap();
+        waiting.join().unwrap();
+    }
+
+    #[test]
+    fn test_unchanged_trove_is_not_backed_up() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let backup_dir = tmp_dir.path().join("backups");
+        let mut store = YamlStore::new(
+            tmp_dir.path().join("trove.yml"),
+            Some(backup_dir.clone()),
+            10,
+        );
+        let base = store.load().unwrap();
+        let mut ours = base.clone();
+        let status = command("status", "git status");
+        ours.add_command(status.clone(), true).unwrap();
+        let saved = store.save(&base, &ours).unwrap();
+        // There was no trove file to back up yet
+        store.save(&saved, &saved).unwrap();
+        store.save(&saved, &saved).unwrap();
+        assert!(crate::core::storage::list_backups(&backup_dir)
+            .unwrap()
+            .is_empty());
+
+        store.record_usage(&status.id).unwrap();
+        assert_eq!(
+            1,
+            crate::core::storage::list_backups(&backup_dir)
+                .unwrap()
+                .len()
+        );
+    }
+
+    #[test]
+    fn test_workflows_are_saved() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        for mut store in stores(tmp_dir.path()) {
+            let base = store.load().unwrap();
+            let mut ours = base.clone();
+            let drain = command("drain", "kubectl drain #node!");
+            let workflow = Workflow::new("upgrade", "default", "", vec![drain.id.clone()]);
+            ours.add_command(drain, true).unwrap();
+            ours.add_workflow(workflow.clone()).unwrap();
+            let saved = store.save(&base, &ours).unwrap();
+
+            assert_eq!(vec![workflow.clone()], store.load().unwrap().workflows);
+            let mut removed = saved.clone();
+            removed.remove_workflow(&workflow.id).unwrap();
+            store.save(&saved, &removed).unwrap();
+            assert!(store.load().unwrap().workflows.is_empty());
+        }
+    }
+}
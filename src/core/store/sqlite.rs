@@ -0,0 +1,516 @@
+use anyhow::{anyhow, Result};
+use rusqlite::{params, Connection, OptionalExtension, Row, Transaction, TransactionBehavior};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use super::{lock_error, lock_once, matches_query, TroveStore};
+use crate::core::error::TroveError;
+use crate::core::storage::TroveLock;
+use crate::core::trove::Trove;
+use crate::core::workflow::Workflow;
+use crate::core::HoardCmd;
+
+/// Statements which upgrade the database schema by one version each, the first one creates a new database
+///
+/// The version of the schema is stored as `user_version` of the database. Released migrations are never changed,
+/// changes of the schema are added as another migration
+const MIGRATIONS: [&str; 3] = [
+    // 1: Commands. Their index of whole words is replaced by the one of version 3
+    "
+CREATE TABLE IF NOT EXISTS commands (
+    id TEXT PRIMARY KEY NOT NULL,
+    name TEXT NOT NULL,
+    namespace TEXT NOT NULL,
+    command TEXT NOT NULL,
+    description TEXT NOT NULL,
+    tags TEXT NOT NULL,
+    created INTEGER NOT NULL,
+    modified INTEGER NOT NULL,
+    last_used INTEGER NOT NULL,
+    usage_count INTEGER NOT NULL,
+    is_favorite INTEGER NOT NULL,
+    is_hidden INTEGER NOT NULL,
+    is_deleted INTEGER NOT NULL
+);
+",
+    // 2: Workflows
+    "
+CREATE TABLE IF NOT EXISTS workflows (
+    id TEXT PRIMARY KEY NOT NULL,
+    name TEXT NOT NULL,
+    namespace TEXT NOT NULL,
+    description TEXT NOT NULL,
+    steps TEXT NOT NULL
+);
+",
+    // 3: Index of every substring of three characters, to find parts of words like the yaml store does.
+    // Tags are indexed separated by commas, like `HoardCmd::get_tags_as_string`
+    "
+DROP TRIGGER IF EXISTS commands_after_insert;
+DROP TRIGGER IF EXISTS commands_after_delete;
+DROP TRIGGER IF EXISTS commands_after_update;
+DROP TABLE IF EXISTS commands_fts;
+CREATE VIRTUAL TABLE commands_fts USING fts5(
+    name, namespace, command, description, tags,
+    content = '', tokenize = 'trigram'
+);
+INSERT INTO commands_fts (rowid, name, namespace, command, description, tags)
+SELECT rowid, name, namespace, command, description, (SELECT ifnull(group_concat(value, ','), '') FROM json_each(tags))
+FROM commands;
+CREATE TRIGGER commands_after_insert AFTER INSERT ON commands BEGIN
+    INSERT INTO commands_fts (rowid, name, namespace, command, description, tags)
+    VALUES (new.rowid, new.name, new.namespace, new.command, new.description,
+        (SELECT ifnull(group_concat(value, ','), '') FROM json_each(new.tags)));
+END;
+CREATE TRIGGER commands_after_delete AFTER DELETE ON commands BEGIN
+    INSERT INTO commands_fts (commands_fts, rowid, name, namespace, command, description, tags)
+    VALUES ('delete', old.rowid, old.name, old.namespace, old.command, old.description,
+        (SELECT ifnull(group_concat(value, ','), '') FROM json_each(old.tags)));
+END;
+CREATE TRIGGER commands_after_update AFTER UPDATE ON commands BEGIN
+    INSERT INTO commands_fts (commands_fts, rowid, name, namespace, command, description, tags)
+    VALUES ('delete', old.rowid, old.name, old.namespace, old.command, old.description,
+        (SELECT ifnull(group_concat(value, ','), '') FROM json_each(old.tags)));
+    INSERT INTO commands_fts (rowid, name, namespace, command, description, tags)
+    VALUES (new.rowid, new.name, new.namespace, new.command, new.description,
+        (SELECT ifnull(group_concat(value, ','), '') FROM json_each(new.tags)));
+END;
+",
+];
+
+/// Version of the database schema after all migrations
+const DATABASE_VERSION: usize = MIGRATIONS.len();
+
+const COLUMNS: &str = "id, name, namespace, command, description, tags, created, modified, last_used, usage_count, is_favorite, is_hidden, is_deleted";
+
+const UPSERT: &str = "
+INSERT INTO commands (id, name, namespace, command, description, tags, created, modified, last_used, usage_count, is_favorite, is_hidden, is_deleted)
+VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)
+ON CONFLICT (id) DO UPDATE SET
+    name = excluded.name,
+    namespace = excluded.namespace,
+    command = excluded.command,
+    description = excluded.description,
+    tags = excluded.tags,
+    created = excluded.created,
+    modified = excluded.modified,
+    last_used = excluded.last_used,
+    usage_count = excluded.usage_count,
+    is_favorite = excluded.is_favorite,
+    is_hidden = excluded.is_hidden,
+    is_deleted = excluded.is_deleted
+";
+
+const UPSERT_WORKFLOW: &str = "
+INSERT INTO workflows (id, name, namespace, description, steps)
+VALUES (?1, ?2, ?3, ?4, ?5)
+ON CONFLICT (id) DO UPDATE SET
+    name = excluded.name,
+    namespace = excluded.namespace,
+    description = excluded.description,
+    steps = excluded.steps
+";
+
+/// A trove stored in a SQLite database
+///
+/// Every change is written on its own, without rewriting the whole trove.
+/// Searches use a full text index of the name, namespace, command, description and tags of the commands
+#[derive(Debug)]
+pub struct SqliteStore {
+    path: PathBuf,
+    connection: Connection,
+    // Held between `lock` and `unlock`. Saving takes the lock on its own otherwise, single changes are atomic anyway
+    lock: Option<TroveLock>,
+}
+
+impl SqliteStore {
+    /// Opens the database at `path`, creating it if it does not exist yet
+    pub fn open(path: &Path) -> Result<Self> {
+        if let Some(parent) = path
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+        {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut connection = Connection::open(path)
+            .map_err(|e| anyhow!("Could not open trove database {}: {e}", path.display()))?;
+        // Wait for other hoard processes instead of failing right away
+        connection.busy_timeout(Duration::from_secs(5))?;
+        migrate(&mut connection).map_err(|e| {
+            anyhow!(
+                "Could not upgrade the trove database {}: {e}",
+                path.display()
+            )
+        })?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            connection,
+            lock: None,
+        })
+    }
+
+    fn commands(connection: &Connection) -> rusqlite::Result<Vec<HoardCmd>> {
+        connection
+            .prepare(&format!(
+                "SELECT {COLUMNS} FROM commands ORDER BY namespace, name"
+            ))?
+            .query_map([], command_from_row)?
+            .collect()
+    }
+
+    /// The whole trove, with its commands and workflows
+    fn trove(connection: &Connection) -> rusqlite::Result<Trove> {
+        let mut trove = Trove::from_commands(&Self::commands(connection)?);
+        trove.workflows = connection
+            .prepare("SELECT id, name, namespace, description, steps FROM workflows ORDER BY namespace, name")?
+            .query_map([], workflow_from_row)?
+            .collect::<rusqlite::Result<_>>()?;
+        trove.namespaces = trove
+            .namespaces()
+            .into_iter()
+            .map(ToString::to_string)
+            .collect();
+        Ok(trove)
+    }
+
+    fn upsert_with(connection: &Connection, command: &HoardCmd) -> rusqlite::Result<()> {
+        connection.execute(
+            UPSERT,
+            params![
+                command.id,
+                command.name,
+                command.namespace,
+                command.command,
+                command.description,
+                to_json(&command.tags)?,
+                to_nanos(command.created),
+                to_nanos(command.modified),
+                to_nanos(command.last_used),
+                i64::try_from(command.usage_count).unwrap_or(i64::MAX),
+                command.is_favorite,
+                command.is_hidden,
+                command.is_deleted,
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn upsert_workflow_with(connection: &Connection, workflow: &Workflow) -> rusqlite::Result<()> {
+        connection.execute(
+            UPSERT_WORKFLOW,
+            params![
+                workflow.id,
+                workflow.name,
+                workflow.namespace,
+                workflow.description,
+                to_json(&workflow.steps)?,
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn transaction(&mut self) -> rusqlite::Result<Transaction<'_>> {
+        // Take the write lock right away, so no other process writes between reading and writing the trove
+        self.connection
+            .transaction_with_behavior(TransactionBehavior::Immediate)
+    }
+}
+
+impl TroveStore for SqliteStore {
+    fn lock(&mut self) -> Result<()> {
+        if self.lock.is_none() {
+            self.lock = Some(TroveLock::acquire(&self.path)?);
+        }
+        Ok(())
+    }
+
+    fn unlock(&mut self) {
+        self.lock = None;
+    }
+
+    fn load(&mut self) -> Result<Trove, TroveError> {
+        let _lock =
+            lock_once(self.lock.as_ref(), &self.path).map_err(|e| lock_error(&self.path, &e))?;
+        Self::trove(&self.connection).map_err(|e| TroveError::Invalid {
+            path: self.path.clone(),
+            message: e.to_string(),
+        })
+    }
+
+    fn get(&self, id: &str) -> Result<Option<HoardCmd>> {
+        Ok(self
+            .connection
+            .query_row(
+                &format!("SELECT {COLUMNS} FROM commands WHERE id = ?1"),
+                [id],
+                command_from_row,
+            )
+            .optional()?)
+    }
+
+    fn upsert(&mut self, command: &HoardCmd) -> Result<()> {
+        Ok(Self::upsert_with(&self.connection, command)?)
+    }
+
+    fn delete(&mut self, id: &str) -> Result<bool> {
+        Ok(self
+            .connection
+            .execute("DELETE FROM commands WHERE id = ?1", [id])?
+            > 0)
+    }
+
+    fn query(&self, query: &str) -> Result<Vec<HoardCmd>> {
+        let candidates = match fts_query(query) {
+            None => Self::commands(&self.connection)?,
+            Some(fts_query) => {
+                let columns = COLUMNS
+                    .split(", ")
+                    .map(|column| format!("commands.{column}"))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                self.connection
+                    .prepare(&format!(
+                        "SELECT {columns} FROM commands_fts JOIN commands ON commands.rowid = commands_fts.rowid WHERE commands_fts MATCH ?1 ORDER BY rank"
+                    ))?
+                    .query_map([fts_query], command_from_row)?
+                    .collect::<rusqlite::Result<Vec<_>>>()?
+            }
+        };
+        // The index only narrows down the commands, they match the same queries as in the yaml store
+        Ok(candidates
+            .into_iter()
+            .filter(|c| matches_query(c, query))
+            .collect())
+    }
+
+    fn record_usage(&mut self, id: &str) -> Result<()> {
+        let updated = self.connection.execute(
+            "UPDATE commands SET usage_count = usage_count + 1, last_used = ?2 WHERE id = ?1",
+            params![id, to_nanos(SystemTime::now())],
+        )?;
+        if updated == 0 {
+            return Err(anyhow!("No command with id {id}"));
+        }
+        Ok(())
+    }
+
+    fn save(&mut self, base: &Trove, trove: &Trove) -> Result<Trove> {
+        let _lock = lock_once(self.lock.as_ref(), &self.path)?;
+        let transaction = self.transaction()?;
+        let theirs = Self::trove(&transaction)?;
+        let merged = Trove::merge_changes(base, trove, &theirs);
+        for command in &theirs.commands {
+            if !merged.commands.iter().any(|c| c.id == command.id) {
+                transaction.execute("DELETE FROM commands WHERE id = ?1", [&command.id])?;
+            }
+        }
+        for command in &merged.commands {
+            let stored = theirs.commands.iter().find(|c| c.id == command.id);
+            if !stored.is_some_and(|stored| Trove::is_unchanged(stored, command)) {
+                Self::upsert_with(&transaction, command)?;
+            }
+        }
+        for workflow in &theirs.workflows {
+            if !merged.workflows.iter().any(|w| w.id == workflow.id) {
+                transaction.execute("DELETE FROM workflows WHERE id = ?1", [&workflow.id])?;
+            }
+        }
+        for workflow in &merged.workflows {
+            if !theirs.workflows.contains(workflow) {
+                Self::upsert_workflow_with(&transaction, workflow)?;
+            }
+        }
+        transaction.commit()?;
+        Ok(merged)
+    }
+}
+
+/// Upgrades the schema of the database to [`DATABASE_VERSION`], refusing databases of newer versions of hoard
+fn migrate(connection: &mut Connection) -> Result<()> {
+    // Another hoard process may be upgrading the database at the same time, the version is read in the transaction
+    let transaction = connection.transaction_with_behavior(TransactionBehavior::Immediate)?;
+    let version: usize = transaction.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+    if version > DATABASE_VERSION {
+        return Err(anyhow!(
+            "it has been written by a newer version of hoard ( version {version}, this hoard knows up to {DATABASE_VERSION} )"
+        ));
+    }
+    if version == DATABASE_VERSION {
+        return Ok(());
+    }
+    for migration in &MIGRATIONS[version..] {
+        transaction.execute_batch(migration)?;
+    }
+    transaction.pragma_update(None, "user_version", DATABASE_VERSION)?;
+    transaction.commit()?;
+    Ok(())
+}
+
+fn command_from_row(row: &Row) -> rusqlite::Result<HoardCmd> {
+    let tags: String = row.get(5)?;
+    let usage_count: i64 = row.get(9)?;
+    Ok(HoardCmd {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        namespace: row.get(2)?,
+        command: row.get(3)?,
+        description: row.get(4)?,
+        tags: from_json(5, &tags)?,
+        created: from_nanos(row.get(6)?),
+        modified: from_nanos(row.get(7)?),
+        last_used: from_nanos(row.get(8)?),
+        usage_count: usize::try_from(usage_count).unwrap_or_default(),
+        is_favorite: row.get(10)?,
+        is_hidden: row.get(11)?,
+        is_deleted: row.get(12)?,
+    })
+}
+
+fn workflow_from_row(row: &Row) -> rusqlite::Result<Workflow> {
+    let steps: String = row.get(4)?;
+    Ok(Workflow {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        namespace: row.get(2)?,
+        description: row.get(3)?,
+        steps: from_json(4, &steps)?,
+    })
+}
+
+/// `value` as a json column
+fn to_json<T: serde::Serialize>(value: &T) -> rusqlite::Result<String> {
+    serde_json::to_string(value).map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))
+}
+
+/// The value of the json column `column`, an unreadable value is an error instead of an empty list
+fn from_json<T: serde::de::DeserializeOwned>(column: usize, json: &str) -> rusqlite::Result<T> {
+    serde_json::from_str(json).map_err(|e| {
+        rusqlite::Error::FromSqlConversionFailure(column, rusqlite::types::Type::Text, Box::new(e))
+    })
+}
+
+/// Nanoseconds since the unix epoch, which keeps timestamps exact when they are read again
+fn to_nanos(time: SystemTime) -> i64 {
+    time.duration_since(UNIX_EPOCH).map_or(0, |duration| {
+        i64::try_from(duration.as_nanos()).unwrap_or(i64::MAX)
+    })
+}
+
+fn from_nanos(nanos: i64) -> SystemTime {
+    UNIX_EPOCH + Duration::from_nanos(u64::try_from(nanos).unwrap_or_default())
+}
+
+/// Full text query which matches every word of `query` with three or more characters anywhere, or `None` if there
+/// are no such words
+///
+/// Shorter words have no substrings of three characters in the index. Words are quoted, so characters like `-` or
+/// `*` in them are searched for instead of being interpreted as query syntax
+fn fts_query(query: &str) -> Option<String> {
+    let words = query
+        .split_whitespace()
+        .filter(|word| word.chars().count() >= 3)
+        .map(|word| format!("\"{}\"", word.replace('"', "\"\"")))
+        .collect::<Vec<_>>();
+    (!words.is_empty()).then(|| words.join(" "))
+}
+
+#[cfg(test)]
+mod test_sqlite {
+    use super::*;
+
+    #[test]
+    fn test_fts_query() {
+        assert_eq!(None, fts_query("  "));
+        assert_eq!(None, fts_query("ls -l"));
+        assert_eq!(
+            Some("\"git\" \"say\"\"hi\"".to_string()),
+            fts_query("git say\"hi ls")
+        );
+    }
+
+    #[test]
+    fn test_older_databases_are_upgraded() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let path = tmp_dir.path().join("trove.db");
+        let connection = Connection::open(&path).unwrap();
+        // A database of the first version, with its index of whole words
+        connection
+            .execute_batch(&format!(
+                "{}
+                CREATE VIRTUAL TABLE commands_fts USING fts5(
+                    name, namespace, command, description, tags, content = 'commands', content_rowid = 'rowid'
+                );
+                PRAGMA user_version = 1;",
+                MIGRATIONS[0]
+            ))
+            .unwrap();
+        drop(connection);
+        let mut store = SqliteStore::open(&path).unwrap();
+        let status = HoardCmd::default()
+            .with_name("status")
+            .with_namespace("git")
+            .with_command("git status")
+            .with_tags(vec!["vcs".to_string()]);
+        store.upsert(&status).unwrap();
+        drop(store);
+
+        let mut store = SqliteStore::open(&path).unwrap();
+        let version: usize = store
+            .connection
+            .query_row("PRAGMA user_version", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(DATABASE_VERSION, version);
+        assert_eq!(vec![status.clone()], store.query("tatu").unwrap());
+        let mut trove = store.load().unwrap();
+        let base = trove.clone();
+        trove
+            .add_workflow(Workflow::new("check", "git", "", vec![status.id.clone()]))
+            .unwrap();
+        store.save(&base, &trove).unwrap();
+        assert_eq!(1, store.load().unwrap().workflows.len());
+    }
+
+    #[test]
+    fn test_newer_databases_are_refused() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let path = tmp_dir.path().join("trove.db");
+        Connection::open(&path)
+            .unwrap()
+            .pragma_update(None, "user_version", DATABASE_VERSION + 1)
+            .unwrap();
+        let error = SqliteStore::open(&path).unwrap_err().to_string();
+        assert!(error.contains("newer version of hoard"), "{error}");
+    }
+
+    #[test]
+    fn test_commands_survive_reopening() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let path = tmp_dir.path().join("trove.db");
+        let command = HoardCmd::default()
+            .with_name("status")
+            .with_namespace("git")
+            .with_command("git status")
+            .with_tags(vec!["git".to_string(), "status".to_string()]);
+        SqliteStore::open(&path).unwrap().upsert(&command).unwrap();
+
+        let mut store = SqliteStore::open(&path).unwrap();
+        let trove = store.load().unwrap();
+        assert_eq!(vec!["git"], trove.namespaces());
+        // Compares all fields, including the timestamps
+        assert!(Trove::is_unchanged(&command, &trove.commands[0]));
+    }
+
+    #[test]
+    fn test_broken_steps_are_an_error() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let mut store = SqliteStore::open(&tmp_dir.path().join("trove.db")).unwrap();
+        store
+            .connection
+            .execute(
+                "INSERT INTO workflows (id, name, namespace, description, steps) VALUES ('1', 'upgrade', 'k8s', '', 'not json')",
+                [],
+            )
+            .unwrap();
+        assert!(matches!(store.load(), Err(TroveError::Invalid { .. })));
+    }
+}
@@ -0,0 +1,153 @@
+use anyhow::Result;
+use log::info;
+use std::path::PathBuf;
+
+use super::{lock_error, lock_once, matches_query, TroveStore};
+use crate::core::error::TroveError;
+use crate::core::storage::{self, Fingerprint, TroveLock};
+use crate::core::trove::Trove;
+use crate::core::HoardCmd;
+
+/// A trove stored in a single yaml file
+///
+/// Every change rewrites the whole file while holding its [`TroveLock`]
+#[derive(Debug)]
+pub struct YamlStore {
+    path: PathBuf,
+    // Timestamped copies of the trove file are written here before it is overwritten
+    backup_dir: Option<PathBuf>,
+    backup_count: usize,
+    // State of the trove file when it has been loaded, to notice changes of other hoard processes
+    loaded_fingerprint: Option<Fingerprint>,
+    // Held between `lock` and `unlock`, every other operation takes the lock on its own
+    lock: Option<TroveLock>,
+}
+
+impl YamlStore {
+    pub const fn new(path: PathBuf, backup_dir: Option<PathBuf>, backup_count: usize) -> Self {
+        Self {
+            path,
+            backup_dir,
+            backup_count,
+            loaded_fingerprint: None,
+            lock: None,
+        }
+    }
+
+    fn read(&self) -> Result<Trove, TroveError> {
+        Trove::load_trove_file(&Some(self.path.clone()))
+    }
+
+    fn write(&mut self, trove: &Trove) -> Result<()> {
+        // Backups of an unchanged trove would only rotate out the older versions
+        if std::fs::read_to_string(&self.path).is_ok_and(|content| content == trove.to_yaml()) {
+            return Ok(());
+        }
+        if let Some(backup_dir) = &self.backup_dir {
+            if let Err(e) = storage::backup_trove_file(&self.path, backup_dir, self.backup_count) {
+                eprintln!("Could not back up the trove file: {e}");
+            }
+        }
+        trove.save_trove_file(&self.path)
+    }
+
+    /// Reads the trove, applies `change` to it and writes it back, while holding the lock
+    fn update<T>(&mut self, change: impl FnOnce(&mut Trove) -> Result<T>) -> Result<T> {
+        let _lock = lock_once(self.lock.as_ref(), &self.path)?;
+        let mut trove = self.read()?;
+        let result = change(&mut trove)?;
+        self.write(&trove)?;
+        Ok(result)
+    }
+}
+
+impl TroveStore for YamlStore {
+    fn lock(&mut self) -> Result<()> {
+        if self.lock.is_none() {
+            self.lock = Some(TroveLock::acquire(&self.path)?);
+        }
+        Ok(())
+    }
+
+    fn unlock(&mut self) {
+        self.lock = None;
+    }
+
+    fn load(&mut self) -> Result<Trove, TroveError> {
+        // Hold the lock while migrating and reading, so no other hoard process writes a half merged trove in the meantime
+        let _lock =
+            lock_once(self.lock.as_ref(), &self.path).map_err(|e| lock_error(&self.path, &e))?;
+        match Trove::migrate_trove_file(&self.path) {
+            Ok(Some(backup_path)) => eprintln!(
+                "Migrated your trove file to the current format. The original file has been kept at {}",
+                backup_path.display()
+            ),
+            Ok(None) => (),
+            // Unreadable trove files are reported when reading them
+            Err(e) if e.is::<TroveError>() => (),
+            Err(e) => eprintln!("Could not migrate your trove file: {e}"),
+        }
+        let trove = self.read()?;
+        self.loaded_fingerprint = Fingerprint::of(&self.path).ok().flatten();
+        Ok(trove)
+    }
+
+    fn get(&self, id: &str) -> Result<Option<HoardCmd>> {
+        Ok(self.read()?.commands.into_iter().find(|c| c.id == id))
+    }
+
+    fn upsert(&mut self, command: &HoardCmd) -> Result<()> {
+        self.update(|trove| {
+            match trove.commands.iter_mut().find(|c| c.id == command.id) {
+                Some(existing) => *existing = command.clone(),
+                None => trove.commands.push(command.clone()),
+            }
+            Ok(())
+        })
+    }
+
+    fn delete(&mut self, id: &str) -> Result<bool> {
+        self.update(|trove| {
+            let count = trove.commands.len();
+            trove.commands.retain(|c| c.id != id);
+            Ok(trove.commands.len() != count)
+        })
+    }
+
+    fn query(&self, query: &str) -> Result<Vec<HoardCmd>> {
+        Ok(self
+            .read()?
+            .commands
+            .into_iter()
+            .filter(|c| matches_query(c, query))
+            .collect())
+    }
+
+    fn record_usage(&mut self, id: &str) -> Result<()> {
+        self.update(|trove| {
+            let command = trove
+                .commands
+                .iter_mut()
+                .find(|c| c.id == id)
+                .ok_or_else(|| anyhow::anyhow!("No command with id {id}"))?;
+            command.mut_increase_usage_count().mut_update_last_used();
+            Ok(())
+        })
+    }
+
+    fn save(&mut self, base: &Trove, trove: &Trove) -> Result<Trove> {
+        let _lock = lock_once(self.lock.as_ref(), &self.path)?;
+        let current = Fingerprint::of(&self.path)?;
+        let trove = if Fingerprint::has_changed(self.loaded_fingerprint, current) {
+            info!("Trove file changed since it has been loaded, merging changes");
+            // A trove file someone else broke in the meantime is never overwritten
+            let theirs = self.read()?;
+            Trove::merge_changes(base, trove, &theirs)
+        } else {
+            trove.clone()
+        };
+        self.write(&trove)?;
+        self.loaded_fingerprint = Fingerprint::of(&self.path).ok().flatten();
+        Ok(trove)
+    }
+}
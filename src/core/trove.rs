@@ -1,15 +1,16 @@
 use anyhow::{anyhow, Result};
 use log::info;
-use prettytable::{color, Attr, Cell, Row, Table};
 use serde::{Deserialize, Serialize};
 
 use std::collections::HashSet;
 use std::{fs, path::Path, path::PathBuf};
 
-use crate::config::HoardConfig;
-use crate::core::error::HoardErr;
-use crate::core::parameters::Parameterized;
-use crate::core::HoardCmd;
+use crate::core::error::{HoardError, TroveError};
+use crate::core::migrations::{self, SCHEMA_VERSION};
+use crate::core::references::{self, resolve_references};
+use crate::core::storage;
+use crate::core::workflow::Workflow;
+use crate::core::{new_id, HoardCmd};
 
 const CARGO_VERSION: &str = env!("CARGO_PKG_VERSION");
 
@@ -19,22 +20,36 @@ const CARGO_VERSION: &str = env!("CARGO_PKG_VERSION");
 /// A Trove can store the following parameters
 /// - `version`: The hoard version with which the commands are being stored
 ///              To potentially support migrating older collections to new ones when breaking changes happen
+/// - `schema_version`: The version of the structure of the trove file.
+///   Older troves are migrated when they are loaded, newer ones are never overwritten
 /// - `commands`: Vector of `HoardCmd`s, the stored commands
 /// - `namespaces`: Set of all namespaces used in the collection
+/// - `workflows`: Vector of `Workflow`s, commands of the trove which are run one after another
+#[allow(clippy::doc_overindented_list_items)]
 #[derive(Debug, Serialize, Clone, Deserialize)]
 pub struct Trove {
     pub version: String,
+    #[serde(default = "current_schema_version")]
+    pub schema_version: u32,
     pub commands: Vec<HoardCmd>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub workflows: Vec<Workflow>,
     #[serde(default)]
     pub namespaces: HashSet<String>,
 }
 
+const fn current_schema_version() -> u32 {
+    SCHEMA_VERSION
+}
+
 impl Default for Trove {
     /// Create a new trove collection with the currently running hoard version
     fn default() -> Self {
         Self {
             version: CARGO_VERSION.to_string(),
+            schema_version: SCHEMA_VERSION,
             commands: Vec::new(),
+            workflows: Vec::new(),
             namespaces: HashSet::new(),
         }
     }
@@ -52,40 +67,94 @@ impl Trove {
 
         Self {
             version: CARGO_VERSION.to_string(),
+            schema_version: SCHEMA_VERSION,
             commands: commands.to_vec(),
+            workflows: Vec::new(),
             namespaces,
         }
     }
 
     /// Loads a local trove file and tries to parse it to load it into memory
-    pub fn load_trove_file(path: &Option<PathBuf>) -> Self {
-        let mut trove = path.clone().map_or_else(
-            || {
+    ///
+    /// A missing trove file results in an empty trove. A trove file which can't be read or parsed is an error,
+    /// so it never gets replaced by an empty trove
+    pub fn load_trove_file(path: &Option<PathBuf>) -> Result<Self, TroveError> {
+        let mut trove = match path {
+            Some(p) if p.exists() => {
+                let content = fs::read_to_string(p).map_err(|source| TroveError::Io {
+                    path: p.clone(),
+                    source,
+                })?;
+                Self::parse_trove(p, &content)?.0
+            }
+            Some(p) => {
+                info!("[DEBUG] No trove file found at {:?}", p);
+                Self::default()
+            }
+            None => {
                 info!("[DEBUG] No trove path available. Creating new trove file");
                 Self::default()
-            },
-            |p| {
-                if p.exists() {
-                    let f = std::fs::File::open(p).ok().unwrap();
-                    let parsed_trove = serde_yaml::from_reader::<_, Self>(f);
-                    match parsed_trove {
-                        Ok(trove) => trove,
-                        Err(e) => {
-                            eprintln!("The supplied trove file is invalid!");
-                            eprintln!("{e}");
-                            Self::default()
-                        }
-                    }
-                } else {
-                    info!("[DEBUG] No trove file found at {:?}", p);
-                    Self::default()
-                }
-            },
-        );
-        trove.namespaces = trove.namespaces().into_iter().map(std::string::ToString::to_string).collect();
-        trove
+            }
+        };
+        if trove.is_newer_schema() {
+            eprintln!(
+                "The trove file has been written by a newer version of hoard (schema version {}). Changes to it will not be saved!",
+                trove.schema_version
+            );
+        }
+        trove.namespaces = trove
+            .namespaces()
+            .into_iter()
+            .map(std::string::ToString::to_string)
+            .collect();
+        Ok(trove)
+    }
+
+    /// Parses the `content` of the trove file at `path` and migrates it to the current schema version
+    ///
+    /// Returns the trove and the schema version it has been migrated from, if it had to be migrated
+    fn parse_trove(path: &Path, content: &str) -> Result<(Self, Option<u32>), TroveError> {
+        let mut value: serde_yaml::Value =
+            serde_yaml::from_str(content).map_err(|e| TroveError::from_yaml(path, &e))?;
+        let migrated_from = migrations::migrate(&mut value).map_err(|e| TroveError::Invalid {
+            path: path.to_path_buf(),
+            message: e.to_string(),
+        })?;
+        let trove = if migrated_from.is_some() {
+            serde_yaml::from_value(value)
+        } else {
+            // Parse the text again to report the location of invalid fields
+            serde_yaml::from_str(content)
+        }
+        .map_err(|e| TroveError::from_yaml(path, &e))?;
+        Ok((trove, migrated_from))
+    }
+
+    /// Migrates the trove file at `path` to the current schema version, if it has been written by an older version of hoard
+    ///
+    /// The original file is kept next to it as `<trove file>.v<old schema version>.bak`.
+    /// Returns the path of that copy if the trove file has been migrated
+    pub fn migrate_trove_file(path: &Path) -> Result<Option<PathBuf>> {
+        if !path.exists() {
+            return Ok(None);
+        }
+        let original = fs::read_to_string(path)?;
+        let (trove, Some(original_version)) = Self::parse_trove(path, &original)? else {
+            return Ok(None);
+        };
+
+        let mut backup_path = path.as_os_str().to_owned();
+        backup_path.push(format!(".v{original_version}.bak"));
+        let backup_path = PathBuf::from(backup_path);
+        storage::write_atomic(&backup_path, &original)?;
+        trove.save_trove_file(path)?;
+        Ok(Some(backup_path))
     }
 
+    /// Check if the trove has been written by a newer version of hoard, which this version can't safely write
+    pub const fn is_newer_schema(&self) -> bool {
+        self.schema_version > SCHEMA_VERSION
+    }
 
     /// Serialize trove collection to yaml format and returns it as a string
     pub fn to_yaml(&self) -> String {
@@ -93,9 +162,16 @@ impl Trove {
     }
 
     /// Save the trove collection to `path` as a yaml file
-    pub fn save_trove_file(&self, path: &Path) {
-        let s = self.to_yaml();
-        fs::write(path, s).expect("Unable to write config file");
+    ///
+    /// The file is replaced atomically, it is never left half written
+    pub fn save_trove_file(&self, path: &Path) -> Result<()> {
+        if self.is_newer_schema() {
+            return Err(anyhow!(
+                "Refusing to overwrite {} with an older trove schema version",
+                path.display()
+            ));
+        }
+        storage::write_atomic(path, &self.to_yaml())
     }
 
     /// Given a `HoardCmd`, check if there is a command with the same name and namespace already in the collection
@@ -112,6 +188,7 @@ impl Trove {
     }
 
     /// Get all commands in the trove collection sorted by usage count
+    #[allow(clippy::unnecessary_sort_by)]
     pub fn get_commands_sorted_by_usage(&self) -> Vec<HoardCmd> {
         let mut commands = self.commands.clone();
         commands.sort_by(|a, b| b.usage_count.cmp(&a.usage_count));
@@ -135,21 +212,27 @@ impl Trove {
     }
 
     /// Adds a command to trove file
-    /// 
+    ///
     /// Returns `true` if the command has been added
-    /// 
+    ///
     /// Returns `false` if the command has not been added due to a name collision that has been resolved where the trove did not change
-    /// 
+    ///
     /// if `overwrite_colliding` is set to true, the name of the command will get a random string suffix to resolve the name collision before adding it to the trove
-    /// 
+    ///
     /// if `overwrite_colliding` is set to false, the name collision will not be resolved and the command will not be added to the trove
     pub fn add_command(
         &mut self,
         new_command: HoardCmd,
         overwrite_colliding: bool,
-    ) -> Result<bool, HoardErr> {
+    ) -> Result<bool, HoardError> {
         if !new_command.is_valid() {
-            return Err(HoardErr::new("cannot save invalid command"));
+            return Err(HoardError::Validation(
+                "cannot save invalid command".to_string(),
+            ));
+        }
+        let mut new_command = new_command;
+        if new_command.id.is_empty() {
+            new_command.id = new_id();
         }
         let dirty = match self.get_command_collision(&new_command) {
             // Collision is present, but its the same command, do nothing
@@ -185,23 +268,36 @@ impl Trove {
     }
 
     /// Remove a command from the trove collection
-    /// 
+    ///
     /// Returns `Ok(())` if the command has been removed
-    /// 
-    /// Returns `Err(anyhow::Error)` if the command to remove is not in the trove
-    pub fn remove_command(&mut self, name: &str) -> Result<(), anyhow::Error> {
+    ///
+    /// Returns `Err(HoardError::NotFound)` if the command to remove is not in the trove
+    ///
+    /// Returns `Err(HoardError::Validation)` if the command is a step of a workflow, which has to be removed first
+    pub fn remove_command(&mut self, name: &str) -> Result<(), HoardError> {
         let command_position = self.commands.iter().position(|x| x.name == name);
         if command_position.is_none() {
-            return Err(anyhow!("Command not found [{}]", name));
+            return Err(HoardError::NotFound(name.to_string()));
         }
+        self.check_removable(|x| x.name == name)?;
         self.commands.retain(|x| &*x.name != name);
         Ok(())
     }
 
-    pub fn update_command_meta(&mut self, command: &HoardCmd) -> Result<(), anyhow::Error> {
+    /// Removes the command with the id `id`, unlike [`Trove::remove_command`] which removes every command named alike
+    pub fn remove_command_by_id(&mut self, id: &str) -> Result<(), HoardError> {
+        if !self.commands.iter().any(|x| x.id == id) {
+            return Err(HoardError::NotFound(id.to_string()));
+        }
+        self.check_removable(|x| x.id == id)?;
+        self.commands.retain(|x| x.id != id);
+        Ok(())
+    }
+
+    pub fn update_command_meta(&mut self, command: &HoardCmd) -> Result<(), HoardError> {
         let command_position = self.commands.iter().position(|x| x.name == command.name);
         if command_position.is_none() {
-            return Err(anyhow!("Command not found [{}]", command.name));
+            return Err(HoardError::NotFound(command.name.clone()));
         }
         let mut updated_command = command.clone();
         updated_command.mut_increase_usage_count();
@@ -210,11 +306,12 @@ impl Trove {
         Ok(())
     }
 
-    pub fn remove_namespace_commands(&mut self, namespace: &str) -> Result<(), anyhow::Error> {
+    pub fn remove_namespace_commands(&mut self, namespace: &str) -> Result<(), HoardError> {
         let command_position = self.commands.iter().position(|x| x.namespace == namespace);
         if command_position.is_none() {
-            return Err(anyhow!("No Commands found in namespace [{}]", namespace));
+            return Err(HoardError::NotFound(format!("namespace {namespace}")));
         }
+        self.check_removable(|x| x.namespace == namespace)?;
         self.commands.retain(|x| &*x.namespace != namespace);
         Ok(())
     }
@@ -225,6 +322,11 @@ impl Trove {
             .commands
             .iter()
             .map(|command| command.namespace.as_str())
+            .chain(
+                self.workflows
+                    .iter()
+                    .map(|workflow| workflow.namespace.as_str()),
+            )
             .collect::<HashSet<_>>()
             .into_iter()
             .collect();
@@ -233,18 +335,112 @@ impl Trove {
         namespaces
     }
 
-    pub fn pick_command(&self, config: &HoardConfig, name: &str) -> Result<HoardCmd> {
-        let filtered_command: Option<&HoardCmd> = self.commands.iter().find(|c| c.name == name);
-        filtered_command.map_or_else(
-            || Err(anyhow!("No matching command found with name: {}", name)),
-            |command| {
-                let command = command.clone().with_input_parameters(
-                    &config.parameter_token.clone().unwrap(),
-                    &config.parameter_ending_token.clone().unwrap(),
-                );
-                Ok(command)
-            },
-        )
+    /// Finds a command by its id, by `namespace/name` or by its name
+    ///
+    /// The id takes precedence over `namespace/name`, which takes precedence over the name.
+    /// A name used in several namespaces is ambiguous
+    pub fn get_command(&self, reference: &str) -> Result<&HoardCmd, HoardError> {
+        find_by_reference(&self.commands, reference, |c| {
+            (&c.id, &c.namespace, &c.name)
+        })
+    }
+
+    /// Finds a command like [`Trove::get_command`] and replaces the `{{reference}}`s to other commands in it
+    ///
+    /// See [`resolve_references`] on how they are resolved
+    pub fn pick_command(&self, reference: &str) -> Result<HoardCmd, HoardError> {
+        let command = self.get_command(reference)?;
+        let resolved = resolve_references(command, &self.commands)?;
+        Ok(command.clone().with_command(&resolved))
+    }
+
+    /// Commands and workflows which use the command with the id `id`, as `namespace/name`
+    ///
+    /// Removing the command breaks them
+    pub fn dependents(&self, id: &str) -> Vec<String> {
+        let commands = references::dependents(id, &self.commands)
+            .into_iter()
+            .map(|c| format!("{}/{}", c.namespace, c.name));
+        let workflows = self
+            .workflows_using(id)
+            .map(|w| format!("workflow {}/{}", w.namespace, w.name));
+        commands.chain(workflows).collect()
+    }
+
+    /// Workflows which have the command with the id `id` as one of their steps
+    fn workflows_using<'a>(&'a self, id: &'a str) -> impl Iterator<Item = &'a Workflow> {
+        self.workflows
+            .iter()
+            .filter(move |w| w.steps.iter().any(|step| step == id))
+    }
+
+    /// Refuses to remove the commands `removed` matches if they are steps of workflows
+    ///
+    /// Their workflows could not be run anymore, so they have to be removed first
+    pub fn check_removable(&self, removed: impl Fn(&HoardCmd) -> bool) -> Result<(), HoardError> {
+        let mut workflows: Vec<String> = self
+            .commands
+            .iter()
+            .filter(|c| removed(c))
+            .flat_map(|c| self.workflows_using(&c.id))
+            .map(|w| format!("{}/{}", w.namespace, w.name))
+            .collect();
+        if workflows.is_empty() {
+            return Ok(());
+        }
+        workflows.sort_unstable();
+        workflows.dedup();
+        Err(HoardError::Validation(format!(
+            "Used as a step by the workflow {}, remove it first",
+            workflows.join(", ")
+        )))
+    }
+
+    /// Finds a workflow by its id, by `namespace/name` or by its name, like [`Trove::get_command`]
+    pub fn get_workflow(&self, reference: &str) -> Result<&Workflow, HoardError> {
+        find_by_reference(&self.workflows, reference, |w| {
+            (&w.id, &w.namespace, &w.name)
+        })
+    }
+
+    /// Adds a workflow to the trove
+    ///
+    /// Every step has to be a command of the trove and the name has to be free in the namespace of the workflow
+    pub fn add_workflow(&mut self, workflow: Workflow) -> Result<(), HoardError> {
+        HoardCmd::is_name_valid(&workflow.name)?;
+        if workflow.namespace.is_empty() {
+            return Err(HoardError::Validation(
+                "Namespace can't be empty".to_string(),
+            ));
+        }
+        if workflow.steps.is_empty() {
+            return Err(HoardError::Validation(
+                "A workflow needs at least one step".to_string(),
+            ));
+        }
+        if let Some(step) = workflow.steps.iter().find(|id| !self.contains_id(id)) {
+            return Err(HoardError::NotFound(format!("step {step}")));
+        }
+        if self
+            .workflows
+            .iter()
+            .any(|w| w.namespace == workflow.namespace && w.name == workflow.name)
+        {
+            return Err(HoardError::Validation(format!(
+                "There already is a workflow {}/{}",
+                workflow.namespace, workflow.name
+            )));
+        }
+        self.add_namespace(&workflow.namespace);
+        self.workflows.push(workflow);
+        Ok(())
+    }
+
+    /// Removes the workflow `reference` points to, see [`Trove::get_workflow`]
+    pub fn remove_workflow(&mut self, reference: &str) -> Result<Workflow, HoardError> {
+        let id = self.get_workflow(reference)?.id.clone();
+        let position = self.workflows.iter().position(|w| w.id == id).unwrap();
+        Ok(self.workflows.remove(position))
     }
 
     pub fn update_command_by_name(&mut self, command: &HoardCmd) -> &mut Self {
@@ -257,6 +453,35 @@ impl Trove {
         self
     }
 
+    /// Replaces the command with the id of `command`, which may have a new name or namespace
+    pub fn update_command(&mut self, command: &HoardCmd) -> &mut Self {
+        if let Some(c) = self.commands.iter_mut().find(|c| c.id == command.id) {
+            *c = command.clone();
+            c.mut_update_last_used();
+        }
+        // The old namespace may have no commands left
+        self.namespaces = self
+            .namespaces()
+            .into_iter()
+            .map(std::string::ToString::to_string)
+            .collect();
+        self
+    }
+
+    /// Puts `command` back as it was, replacing the command with its id or adding it again if it was removed
+    pub fn restore_command(&mut self, command: HoardCmd) {
+        self.add_namespace(&command.namespace);
+        match self.commands.iter_mut().find(|c| c.id == command.id) {
+            Some(c) => *c = command,
+            None => self.commands.push(command),
+        }
+        self.namespaces = self
+            .namespaces()
+            .into_iter()
+            .map(std::string::ToString::to_string)
+            .collect();
+    }
+
     /// check if the trove collection is empty
     pub fn is_empty(&self) -> bool {
         self.commands.is_empty()
@@ -270,34 +495,121 @@ impl Trove {
             .any(|x| x.is_ok())
     }
 
-    pub fn print_trove(&self) {
-        // Create the table
-        let mut table = Table::new();
-        // Add header
-        table.add_row(row!["Name", "namespace", "command", "description", "tags"]);
-        // Iterate through trove and populate table
-        self.commands.iter().for_each(|c| {
-            table.add_row(Row::new(vec![
-                // Name
-                Cell::new(&c.name[..])
-                    .with_style(Attr::Bold)
-                    .with_style(Attr::ForegroundColor(color::GREEN)),
-                // namespace
-                Cell::new(&c.namespace[..]),
-                // command
-                Cell::new(&c.command[..]),
-                // description
-                Cell::new(&c.description[..]),
-                // tags
-                Cell::new(&c.get_tags_as_string()),
-            ]));
+    /// Apply the changes made from `base` to `ours` onto `theirs`, a newer version of `base` written by another hoard process
+    ///
+    /// - Commands added in `ours` are added, with a random name suffix if `theirs` added a command with the same name
+    /// - Commands removed in `ours` are removed
+    /// - Commands changed in `ours` replace the ones in `theirs`, usages of both sides are added up
+    /// - Everything else is kept the way it is in `theirs`
+    pub fn merge_changes(base: &Self, ours: &Self, theirs: &Self) -> Self {
+        let mut merged = theirs.clone();
+        merged
+            .commands
+            .retain(|c| !base.contains_id(&c.id) || ours.contains_id(&c.id));
+
+        for command in &ours.commands {
+            let base_command = base.commands.iter().find(|c| c.id == command.id);
+            let position = merged.commands.iter().position(|c| c.id == command.id);
+            match (base_command, position) {
+                // Added by us and already written by someone else, e.g. the same import twice
+                (None, Some(_)) => (),
+                (None, None) => {
+                    let _ = merged.add_command(command.clone(), false);
+                }
+                (Some(base_command), _) if Self::is_unchanged(base_command, command) => (),
+                (Some(base_command), Some(position)) => {
+                    let their_command = &merged.commands[position];
+                    let mut merged_command = command.clone();
+                    merged_command.usage_count = their_command.usage_count
+                        + command.usage_count.saturating_sub(base_command.usage_count);
+                    merged_command.last_used = command.last_used.max(their_command.last_used);
+                    merged.commands[position] = merged_command;
+                }
+                // Removed by someone else. Only bring it back if we edited more than its usage
+                (Some(base_command), None) => {
+                    if command != base_command {
+                        let _ = merged.add_command(command.clone(), false);
+                    }
+                }
+            }
+        }
+        merge_workflows(&base.workflows, &ours.workflows, &mut merged.workflows);
+        merged.namespaces = merged
+            .namespaces()
+            .into_iter()
+            .map(std::string::ToString::to_string)
+            .collect();
+        merged
+    }
+
+    fn contains_id(&self, id: &str) -> bool {
+        self.commands.iter().any(|c| c.id == id)
+    }
+
+    /// Compares all fields of two commands, including their usage
+    pub(crate) fn is_unchanged(a: &HoardCmd, b: &HoardCmd) -> bool {
+        serde_yaml::to_value(a).ok() == serde_yaml::to_value(b).ok()
+    }
+}
+
+/// Finds the item with the id `reference`, or else with its `namespace/name`, or else with its name. See [`Trove::get_command`]
+///
+/// `keys` returns the id, namespace and name of an item
+pub(crate) fn find_by_reference<'a, T>(
+    items: &'a [T],
+    reference: &str,
+    keys: impl Fn(&T) -> (&str, &str, &str),
+) -> Result<&'a T, HoardError> {
+    let by_reference = items
+        .iter()
+        .find(|item| keys(item).0 == reference)
+        .or_else(|| {
+            reference.split_once('/').and_then(|(namespace, name)| {
+                items.iter().find(|item| {
+                    let (_, item_namespace, item_name) = keys(item);
+                    item_namespace == namespace && item_name == name
+                })
+            })
         });
-        // Print the table to stdout
-        table.printstd();
+    if let Some(item) = by_reference {
+        return Ok(item);
+    }
+    let by_name = items
+        .iter()
+        .filter(|item| keys(item).2 == reference)
+        .collect::<Vec<_>>();
+    match by_name.as_slice() {
+        [] => Err(HoardError::NotFound(reference.to_string())),
+        [item] => Ok(item),
+        items => Err(HoardError::Ambiguous {
+            reference: reference.to_string(),
+            candidates: items
+                .iter()
+                .map(|item| {
+                    let (_, namespace, name) = keys(item);
+                    format!("{namespace}/{name}")
+                })
+                .collect(),
+        }),
+    }
+}
+
+/// Applies the workflows added, changed or removed from `base` to `ours` onto `merged`, see [`Trove::merge_changes`]
+fn merge_workflows(base: &[Workflow], ours: &[Workflow], merged: &mut Vec<Workflow>) {
+    merged.retain(|w| !base.iter().any(|b| b.id == w.id) || ours.iter().any(|o| o.id == w.id));
+    for workflow in ours {
+        if base.contains(workflow) {
+            continue;
+        }
+        match merged.iter_mut().find(|w| w.id == workflow.id) {
+            Some(theirs) => *theirs = workflow.clone(),
+            None => merged.push(workflow.clone()),
+        }
     }
 }
 
 #[cfg(test)]
+#[allow(clippy::field_reassign_with_default)]
 mod test_commands {
     use super::*;
 
@@ -356,6 +668,56 @@ mod test_commands {
         assert_eq!(vec![namespace1, namespace2], trove.namespaces());
     }
 
+    #[test]
+    fn update_renamed_command() {
+        let command = HoardCmd::default()
+            .with_name("pods")
+            .with_namespace("k8s")
+            .with_command("kubectl get pods");
+        let other = HoardCmd::default()
+            .with_name("pods")
+            .with_namespace("docker")
+            .with_command("docker ps");
+        let mut trove = Trove::from_commands(&[command.clone(), other]);
+
+        trove.update_command(&command.clone().with_name("po").with_namespace("kube"));
+
+        let names: Vec<_> = trove
+            .commands
+            .iter()
+            .map(|c| format!("{}/{}", c.namespace, c.name))
+            .collect();
+        assert_eq!(vec!["kube/po", "docker/pods"], names);
+        assert_eq!(vec!["docker", "kube"], trove.namespaces());
+        assert!(!trove.namespaces.contains("k8s"));
+    }
+
+    #[test]
+    fn restore_changed_and_removed_commands() {
+        let pods = HoardCmd::default()
+            .with_name("pods")
+            .with_namespace("k8s")
+            .with_command("kubectl get pods");
+        let nodes = HoardCmd::default()
+            .with_name("nodes")
+            .with_namespace("k8s")
+            .with_command("kubectl get nodes");
+        let mut trove = Trove::from_commands(&[pods.clone(), nodes.clone()]);
+        trove.update_command(&pods.clone().with_namespace("kube"));
+        trove.commands.retain(|c| c.id != nodes.id);
+
+        trove.restore_command(pods.clone());
+        trove.restore_command(nodes);
+
+        let names: Vec<_> = trove
+            .commands
+            .iter()
+            .map(|c| format!("{}/{}", c.namespace, c.name))
+            .collect();
+        assert_eq!(vec!["k8s/pods", "k8s/nodes"], names);
+        assert_eq!(vec!["k8s"], trove.namespaces());
+    }
+
     #[test]
     fn add_valid_command() {
         // test adding a valid command
@@ -510,6 +872,261 @@ mod test_commands {
         assert!(trove.is_empty());
     }
 
+    #[test]
+    fn test_remove_command_by_id() {
+        let mut trove = Trove::from_commands(&[
+            HoardCmd::default()
+                .with_name("status")
+                .with_namespace("git"),
+            HoardCmd::default()
+                .with_name("status")
+                .with_namespace("svn"),
+        ]);
+        let git_id = trove.commands[0].id.clone();
+        trove.remove_command_by_id(&git_id).unwrap();
+        assert_eq!(1, trove.commands.len());
+        assert_eq!("svn", trove.commands[0].namespace);
+        assert!(matches!(
+            trove.remove_command_by_id(&git_id),
+            Err(HoardError::NotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_get_command_by_reference() {
+        let trove = Trove::from_commands(&[
+            HoardCmd::default()
+                .with_name("status")
+                .with_namespace("git"),
+            HoardCmd::default()
+                .with_name("status")
+                .with_namespace("svn"),
+        ]);
+        let svn_id = trove.commands[1].id.clone();
+        assert_eq!("svn", trove.get_command(&svn_id).unwrap().namespace);
+        assert_eq!("svn", trove.get_command("svn/status").unwrap().namespace);
+        assert!(matches!(
+            trove.get_command("status"),
+            Err(HoardError::Ambiguous { candidates, .. }) if candidates == ["git/status", "svn/status"]
+        ));
+        assert!(matches!(
+            trove.get_command("git/nope"),
+            Err(HoardError::NotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_migrate_trove_file() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let path = tmp_dir.path().join("trove.yml");
+        let original = "version: 1.4.2\ncommands:\n  - name: status\n    namespace: git\n    command: git status\n    description: ''\n    tags: []\n";
+        fs::write(&path, original).unwrap();
+
+        let backup_path = Trove::migrate_trove_file(&path).unwrap().unwrap();
+        assert_eq!(tmp_dir.path().join("trove.yml.v1.bak"), backup_path);
+        assert_eq!(original, fs::read_to_string(&backup_path).unwrap());
+
+        let trove = Trove::load_trove_file(&Some(path.clone())).unwrap();
+        assert_eq!(SCHEMA_VERSION, trove.schema_version);
+        let id = trove.commands[0].id.clone();
+        assert!(!id.is_empty());
+
+        // Already migrated troves are left untouched
+        assert!(Trove::migrate_trove_file(&path).unwrap().is_none());
+        assert_eq!(
+            id,
+            Trove::load_trove_file(&Some(path)).unwrap().commands[0].id
+        );
+    }
+
+    #[test]
+    fn test_refuse_to_save_newer_trove() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let path = tmp_dir.path().join("trove.yml");
+        let newer = format!(
+            "version: 99.0.0\nschema_version: {}\ncommands: []\n",
+            SCHEMA_VERSION + 1
+        );
+        fs::write(&path, &newer).unwrap();
+
+        let trove = Trove::load_trove_file(&Some(path.clone())).unwrap();
+        assert!(trove.is_newer_schema());
+        assert!(trove.save_trove_file(&path).is_err());
+        assert_eq!(newer, fs::read_to_string(&path).unwrap());
+    }
+
+    #[test]
+    fn test_load_invalid_trove_file() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let path = tmp_dir.path().join("trove.yml");
+        let invalid = "version: 2.0.0\ncommands:\n  - name: status\n   command: git status\n";
+        fs::write(&path, invalid).unwrap();
+
+        match Trove::load_trove_file(&Some(path.clone())) {
+            Err(TroveError::Syntax { line, column, .. }) => {
+                assert_eq!(4, line);
+                assert_eq!(11, column);
+            }
+            other => panic!("Expected a syntax error, got {other:?}"),
+        }
+        // The broken file is left untouched
+        assert_eq!(invalid, fs::read_to_string(&path).unwrap());
+
+        fs::remove_file(&path).unwrap();
+        assert!(Trove::load_trove_file(&Some(path)).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_merge_changes() {
+        let mut base = Trove::default();
+        for name in ["kept", "removed", "used", "edited"] {
+            base.add_command(
+                HoardCmd::default()
+                    .with_name(name)
+                    .with_namespace("default")
+                    .with_command("echo"),
+                false,
+            )
+            .unwrap();
+        }
+
+        let mut ours = base.clone();
+        ours.remove_command("removed").unwrap();
+        ours.commands
+            .iter_mut()
+            .find(|c| c.name == "used")
+            .unwrap()
+            .usage_count = 2;
+        ours.commands
+            .iter_mut()
+            .find(|c| c.name == "edited")
+            .unwrap()
+            .command = "echo ours".to_string();
+        let ours_new = HoardCmd::default()
+            .with_name("new")
+            .with_namespace("default")
+            .with_command("ls");
+        ours.add_command(ours_new, false).unwrap();
+
+        let mut theirs = base.clone();
+        theirs
+            .commands
+            .iter_mut()
+            .find(|c| c.name == "used")
+            .unwrap()
+            .usage_count = 3;
+        theirs.remove_command("edited").unwrap();
+        let theirs_new = HoardCmd::default()
+            .with_name("new")
+            .with_namespace("default")
+            .with_command("pwd");
+        theirs.add_command(theirs_new, false).unwrap();
+
+        let merged = Trove::merge_changes(&base, &ours, &theirs);
+        let find = |name: &str| merged.commands.iter().find(|c| c.name == name);
+        assert!(find("kept").is_some());
+        assert!(find("removed").is_none());
+        assert_eq!(5, find("used").unwrap().usage_count);
+        assert_eq!("echo ours", find("edited").unwrap().command);
+        assert_eq!("pwd", find("new").unwrap().command);
+        // Our new command got a suffix, because theirs took the name
+        assert_eq!(5, merged.commands.len());
+    }
+
+    #[test]
+    fn test_add_and_get_workflow() {
+        let mut trove = Trove::default();
+        let drain = HoardCmd::default()
+            .with_name("drain")
+            .with_namespace("k8s")
+            .with_command("kubectl drain #node!");
+        let steps = vec![drain.id.clone()];
+        trove.add_command(drain, true).unwrap();
+
+        let workflow = Workflow::new("upgrade", "k8s", "Upgrade a node", steps.clone());
+        trove.add_workflow(workflow.clone()).unwrap();
+        assert_eq!(&workflow, trove.get_workflow("k8s/upgrade").unwrap());
+        assert_eq!(&workflow, trove.get_workflow(&workflow.id).unwrap());
+        // Names are unique per namespace
+        let duplicate = Workflow::new("upgrade", "k8s", "", steps);
+        assert!(matches!(
+            trove.add_workflow(duplicate),
+            Err(HoardError::Validation(_))
+        ));
+        let missing_step = Workflow::new("broken", "k8s", "", vec!["gone".to_string()]);
+        assert!(matches!(
+            trove.add_workflow(missing_step),
+            Err(HoardError::NotFound(_))
+        ));
+        // Steps of a workflow are kept until the workflow is removed
+        assert!(matches!(
+            trove.remove_command("drain"),
+            Err(HoardError::Validation(_))
+        ));
+        assert!(matches!(
+            trove.remove_namespace_commands("k8s"),
+            Err(HoardError::Validation(_))
+        ));
+
+        assert_eq!(workflow, trove.remove_workflow("upgrade").unwrap());
+        assert!(trove.get_workflow("upgrade").is_err());
+        trove.remove_command("drain").unwrap();
+    }
+
+    #[test]
+    fn test_pick_command_with_references() {
+        let mut trove = Trove::default();
+        let context = HoardCmd::default()
+            .with_name("ctx")
+            .with_namespace("k8s")
+            .with_command("kubectl --context #cluster!");
+        let id = context.id.clone();
+        trove.add_command(context, true).unwrap();
+        let pods = HoardCmd::default()
+            .with_name("pods")
+            .with_namespace("k8s")
+            .with_command("{{k8s/ctx}} get pods");
+        let pods_id = pods.id.clone();
+        trove.add_command(pods, true).unwrap();
+        trove
+            .add_workflow(Workflow::new("check", "ops", "", vec![id.clone()]))
+            .unwrap();
+
+        let picked = trove.pick_command("pods").unwrap();
+        assert_eq!("kubectl --context #cluster! get pods", picked.command);
+        assert_eq!(pods_id, picked.id);
+        assert_eq!(
+            "{{k8s/ctx}} get pods",
+            trove.get_command("pods").unwrap().command
+        );
+        assert_eq!(
+            vec!["k8s/pods", "workflow ops/check"],
+            trove.dependents(&id)
+        );
+        assert!(trove.dependents(&pods_id).is_empty());
+    }
+
+    #[test]
+    fn test_merge_workflows() {
+        let mut base = Trove::default();
+        base.workflows = vec![
+            Workflow::new("kept", "k8s", "", vec!["a".to_string()]),
+            Workflow::new("removed", "k8s", "", vec!["a".to_string()]),
+        ];
+        let mut ours = base.clone();
+        ours.workflows.retain(|w| w.name != "removed");
+        ours.workflows[0].steps.push("b".to_string());
+        let mut theirs = base.clone();
+        theirs
+            .workflows
+            .push(Workflow::new("theirs", "k8s", "", vec!["c".to_string()]));
+
+        let merged = Trove::merge_changes(&base, &ours, &theirs);
+        let names: Vec<_> = merged.workflows.iter().map(|w| w.name.as_str()).collect();
+        assert_eq!(vec!["kept", "theirs"], names);
+        assert_eq!(vec!["a", "b"], merged.workflows[0].steps);
+    }
+
     #[test]
     fn test_is_empty_new_trove() {
         // create a new trove and check if it is empty
@@ -0,0 +1,239 @@
+use anyhow::{anyhow, Result};
+use argon2::Argon2;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use chacha20poly1305::{Key, XChaCha20Poly1305, XNonce};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::core::storage;
+
+/// Environment variable with the passphrase of the vault, so scripts don't have to enter it
+pub const PASSPHRASE_ENV: &str = "HOARD_VAULT_PASSPHRASE";
+
+const VAULT_VERSION: u32 = 1;
+const SALT_LENGTH: usize = 16;
+const NONCE_LENGTH: usize = 24;
+// Encrypted with the key derived from the passphrase, to tell a wrong passphrase apart from a broken vault
+const CHECK_VALUE: &str = "hoard";
+
+/// Content of the vault file
+///
+/// The names of the secrets are stored in clear, so they can be listed without the passphrase. Only their values are encrypted
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct VaultFile {
+    version: u32,
+    salt: String,
+    check: String,
+    secrets: BTreeMap<String, String>,
+}
+
+/// Key to read and write the secrets of a vault, derived from its passphrase
+pub struct VaultKey {
+    cipher: XChaCha20Poly1305,
+}
+
+/// Secrets used to fill `#secret:<name>!` parameters, stored encrypted in a file next to the trove
+///
+/// Every value is encrypted with XChaCha20-Poly1305. The key is derived from a passphrase with Argon2
+#[derive(Debug)]
+pub struct Vault {
+    path: PathBuf,
+    file: VaultFile,
+}
+
+impl Vault {
+    /// Reads the vault at `path`. A missing vault is empty and gets its passphrase when it is unlocked for the first time
+    pub fn open(path: &Path) -> Result<Self> {
+        let file = if path.exists() {
+            let content = fs::read_to_string(path)
+                .map_err(|e| anyhow!("Could not read vault {}: {e}", path.display()))?;
+            let file: VaultFile = serde_yaml::from_str(&content)
+                .map_err(|e| anyhow!("Invalid vault {}: {e}", path.display()))?;
+            if file.version > VAULT_VERSION {
+                return Err(anyhow!(
+                    "The vault {} has been written by a newer version of hoard",
+                    path.display()
+                ));
+            }
+            file
+        } else {
+            VaultFile::default()
+        };
+        Ok(Self {
+            path: path.to_path_buf(),
+            file,
+        })
+    }
+
+    /// Check if the vault has no passphrase yet
+    pub fn is_new(&self) -> bool {
+        self.file.salt.is_empty()
+    }
+
+    /// Names of all secrets, sorted
+    pub fn names(&self) -> Vec<&str> {
+        self.file.secrets.keys().map(String::as_str).collect()
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.file.secrets.contains_key(name)
+    }
+
+    /// Derives the key of the vault from `passphrase`
+    ///
+    /// The first passphrase given to a new vault becomes its passphrase
+    pub fn unlock(&mut self, passphrase: &str) -> Result<VaultKey> {
+        if self.is_new() {
+            let mut salt = [0u8; SALT_LENGTH];
+            rand::thread_rng().fill_bytes(&mut salt);
+            let key = derive_key(passphrase, &salt)?;
+            self.file = VaultFile {
+                version: VAULT_VERSION,
+                salt: STANDARD.encode(salt),
+                check: encrypt(&key, CHECK_VALUE, "")?,
+                secrets: BTreeMap::new(),
+            };
+            return Ok(key);
+        }
+        let salt = STANDARD
+            .decode(&self.file.salt)
+            .map_err(|_| anyhow!("Invalid vault {}", self.path.display()))?;
+        let key = derive_key(passphrase, &salt)?;
+        if decrypt(&key, &self.file.check, "").ok().as_deref() != Some(CHECK_VALUE) {
+            return Err(anyhow!("Wrong passphrase for the vault"));
+        }
+        Ok(key)
+    }
+
+    /// The value of the secret `name`, if there is one
+    pub fn get(&self, key: &VaultKey, name: &str) -> Result<Option<String>> {
+        self.file
+            .secrets
+            .get(name)
+            .map(|encrypted| decrypt(key, encrypted, name))
+            .transpose()
+    }
+
+    /// Sets the secret `name` to `value`. Call [`Vault::save`] to write it to the vault file
+    pub fn set(&mut self, key: &VaultKey, name: &str, value: &str) -> Result<()> {
+        let encrypted = encrypt(key, value, name)?;
+        self.file.secrets.insert(name.to_string(), encrypted);
+        Ok(())
+    }
+
+    /// Removes the secret `name`. Returns `false` if there was no such secret
+    pub fn remove(&mut self, name: &str) -> bool {
+        self.file.secrets.remove(name).is_some()
+    }
+
+    /// Writes the vault file, readable only by the current user
+    pub fn save(&self) -> Result<()> {
+        let content = serde_yaml::to_string(&self.file)?;
+        storage::write_private(&self.path, &content)
+    }
+}
+
+fn derive_key(passphrase: &str, salt: &[u8]) -> Result<VaultKey> {
+    let mut key = [0u8; 32];
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| anyhow!("Could not derive the vault key: {e}"))?;
+    Ok(VaultKey {
+        cipher: XChaCha20Poly1305::new(Key::from_slice(&key)),
+    })
+}
+
+/// Encrypts `value` and returns the nonce followed by the cipher text, base64 encoded
+///
+/// The name of the secret is authenticated with it, so encrypted values can't be swapped between secrets
+fn encrypt(key: &VaultKey, value: &str, name: &str) -> Result<String> {
+    let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let payload = Payload {
+        msg: value.as_bytes(),
+        aad: name.as_bytes(),
+    };
+    let mut encrypted = nonce.to_vec();
+    encrypted.extend(
+        key.cipher
+            .encrypt(&nonce, payload)
+            .map_err(|_| anyhow!("Could not encrypt secret {name}"))?,
+    );
+    Ok(STANDARD.encode(encrypted))
+}
+
+fn decrypt(key: &VaultKey, encrypted: &str, name: &str) -> Result<String> {
+    let encrypted = STANDARD
+        .decode(encrypted)
+        .map_err(|_| anyhow!("Invalid secret {name}"))?;
+    if encrypted.len() < NONCE_LENGTH {
+        return Err(anyhow!("Invalid secret {name}"));
+    }
+    let (nonce, cipher_text) = encrypted.split_at(NONCE_LENGTH);
+    let payload = Payload {
+        msg: cipher_text,
+        aad: name.as_bytes(),
+    };
+    let value = key
+        .cipher
+        .decrypt(XNonce::from_slice(nonce), payload)
+        .map_err(|_| anyhow!("Could not decrypt secret {name}"))?;
+    String::from_utf8(value).map_err(|_| anyhow!("Invalid secret {name}"))
+}
+
+#[cfg(test)]
+mod test_vault {
+    use super::*;
+
+    #[test]
+    fn test_secrets_survive_reopening() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let path = tmp_dir.path().join("vault.yml");
+        let mut vault = Vault::open(&path).unwrap();
+        assert!(vault.is_new());
+        let key = vault.unlock("correct horse").unwrap();
+        vault.set(&key, "api_token", "s3cr3t").unwrap();
+        vault.save().unwrap();
+
+        let content = fs::read_to_string(&path).unwrap();
+        assert!(content.contains("api_token"));
+        assert!(!content.contains("s3cr3t"));
+
+        let mut vault = Vault::open(&path).unwrap();
+        assert_eq!(vec!["api_token"], vault.names());
+        let key = vault.unlock("correct horse").unwrap();
+        assert_eq!(
+            Some("s3cr3t".to_string()),
+            vault.get(&key, "api_token").unwrap()
+        );
+        assert_eq!(None, vault.get(&key, "missing").unwrap());
+        assert!(vault.remove("api_token"));
+        assert!(!vault.contains("api_token"));
+    }
+
+    #[test]
+    fn test_wrong_passphrase() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let path = tmp_dir.path().join("vault.yml");
+        let mut vault = Vault::open(&path).unwrap();
+        vault.unlock("correct horse").unwrap();
+        vault.save().unwrap();
+
+        let mut vault = Vault::open(&path).unwrap();
+        assert!(vault.unlock("battery staple").is_err());
+    }
+
+    #[test]
+    fn test_secrets_can_not_be_swapped() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let mut vault = Vault::open(&tmp_dir.path().join("vault.yml")).unwrap();
+        let key = vault.unlock("correct horse").unwrap();
+        vault.set(&key, "a", "first").unwrap();
+        let encrypted = vault.file.secrets["a"].clone();
+        vault.file.secrets.insert("b".to_string(), encrypted);
+        assert!(vault.get(&key, "b").is_err());
+    }
+}
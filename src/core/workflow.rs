@@ -0,0 +1,244 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::ops::Range;
+
+use crate::core::error::HoardError;
+use crate::core::parameters::{fill_named_parameter, fill_next_parameter, Parameterized};
+use crate::core::trove::Trove;
+use crate::core::{new_id, HoardCmd};
+
+/// Tag of the entries which stand for a workflow in lists of commands
+pub const WORKFLOW_TAG: &str = "workflow";
+
+/// An ordered list of hoarded commands which are run one after another, e.g. a runbook
+///
+/// Parameters with the same name are shared by all steps, so their value is asked for once
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Workflow {
+    /// A unique identifier of the workflow
+    pub id: String,
+
+    /// The name of the workflow by which it is referenced
+    pub name: String,
+
+    /// The namespace the workflow belongs to
+    pub namespace: String,
+
+    /// A description of the workflow for the user
+    #[serde(default)]
+    pub description: String,
+
+    /// Ids of the commands of the steps, in the order they are run in
+    pub steps: Vec<String>,
+}
+
+/// What picking a workflow in `hoard list` puts into the shell
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WorkflowMode {
+    /// The steps joined with `&&`, with their parameters filled in
+    #[default]
+    Join,
+    /// `hoard run <workflow>`, which runs the steps one by one and stops at the first failing step
+    Run,
+}
+
+impl fmt::Display for WorkflowMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Join => write!(f, "join"),
+            Self::Run => write!(f, "run"),
+        }
+    }
+}
+
+impl Workflow {
+    /// Create a new workflow running the commands with the ids in `steps`
+    pub fn new(name: &str, namespace: &str, description: &str, steps: Vec<String>) -> Self {
+        Self {
+            id: new_id(),
+            name: name.to_string(),
+            namespace: namespace.to_string(),
+            description: description.to_string(),
+            steps,
+        }
+    }
+
+    /// The commands of the steps, in order
+    ///
+    /// A command which has been removed from the trove since the workflow has been created is an error
+    pub fn step_commands(&self, trove: &Trove) -> Result<Vec<HoardCmd>, HoardError> {
+        self.steps
+            .iter()
+            .map(|id| {
+                trove
+                    .commands
+                    .iter()
+                    .find(|c| &c.id == id)
+                    .cloned()
+                    .ok_or_else(|| {
+                        HoardError::NotFound(format!(
+                            "step {id} of workflow {}/{}",
+                            self.namespace, self.name
+                        ))
+                    })
+            })
+            .collect()
+    }
+
+    /// The workflow as a single command, with `steps` joined by `&&`
+    ///
+    /// It keeps the id, name, namespace and description of the workflow and is tagged with [`WORKFLOW_TAG`]
+    pub fn to_command(&self, steps: &[HoardCmd]) -> HoardCmd {
+        HoardCmd {
+            id: self.id.clone(),
+            name: self.name.clone(),
+            namespace: self.namespace.clone(),
+            description: self.description.clone(),
+            tags: vec![WORKFLOW_TAG.to_string()],
+            ..HoardCmd::default()
+        }
+        .with_command(&join_steps(steps))
+    }
+}
+
+/// Joins the commands of `steps` with `&&`, so the first failing step stops the others
+///
+/// Steps which are lists of commands themselves are grouped with `{ ...; }`, so `&&` applies to the whole step.
+/// A single step is returned as it is
+///
+/// # Example
+///
+/// ```
+/// # use hoardlib::core::{workflow::join_steps, HoardCmd};
+/// let steps = [
+///     HoardCmd::default().with_command("kubectl drain #node!"),
+///     HoardCmd::default().with_command("apt update; apt upgrade"),
+/// ];
+/// assert_eq!(join_steps(&steps), "kubectl drain #node! && { apt update; apt upgrade; }");
+/// ```
+pub fn join_steps(steps: &[HoardCmd]) -> String {
+    if let [step] = steps {
+        return step.command.clone();
+    }
+    steps
+        .iter()
+        .map(|step| {
+            let command = step.command.trim().trim_end_matches(';');
+            if command.contains(';') || command.contains("||") || command.contains('\n') {
+                format!("{{ {command}; }}")
+            } else {
+                command.to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" && ")
+}
+
+/// Index of the first step with a parameter the user has to provide a value for
+pub fn current_step(steps: &[HoardCmd], token: &str, ending_token: &str) -> Option<usize> {
+    steps
+        .iter()
+        .position(|step| step.has_input_parameters(token, ending_token))
+}
+
+/// Replaces the next parameter of the current step with `value`
+///
+/// A named parameter is shared by all steps, so it is replaced in the steps after the current one as well
+pub fn replace_parameter(steps: &mut [HoardCmd], token: &str, ending_token: &str, value: &str) {
+    fill_parameter(
+        steps,
+        token,
+        ending_token,
+        value,
+        &mut vec![Vec::new(); steps.len()],
+    );
+}
+
+/// Like `replace_parameter`, keeping track of the values filled into each step in `filled`,
+/// which has the byte ranges of the values of a step at its index. See `parameters::fill_next_parameter`
+pub fn fill_parameter(
+    steps: &mut [HoardCmd],
+    token: &str,
+    ending_token: &str,
+    value: &str,
+    filled: &mut [Vec<Range<usize>>],
+) {
+    let Some(current) = current_step(steps, token, ending_token) else {
+        return;
+    };
+    let name = steps[current]
+        .get_parameters(token, ending_token)
+        .into_iter()
+        .find(|p| p.secret_name().is_none())
+        .map(|p| p.name)
+        .unwrap_or_default();
+    steps[current].command = fill_next_parameter(
+        &steps[current].command,
+        token,
+        ending_token,
+        value,
+        &mut filled[current],
+    );
+    if name.is_empty() {
+        return;
+    }
+    for (step, filled) in steps.iter_mut().zip(filled).skip(current + 1) {
+        step.command =
+            fill_named_parameter(&step.command, token, ending_token, &name, value, filled);
+    }
+}
+
+#[cfg(test)]
+mod test_workflow {
+    use super::*;
+
+    fn steps(commands: &[&str]) -> Vec<HoardCmd> {
+        commands
+            .iter()
+            .map(|command| HoardCmd::default().with_command(command))
+            .collect()
+    }
+
+    #[test]
+    fn test_join_steps() {
+        assert_eq!(
+            "cd /tmp && { make || make clean; } && ls | wc -l",
+            join_steps(&steps(&["cd /tmp;", "make || make clean", "ls | wc -l"]))
+        );
+    }
+
+    #[test]
+    fn test_shared_parameters_are_asked_for_once() {
+        let mut steps = steps(&[
+            "kubectl drain #node!",
+            "ssh #node! apt upgrade #",
+            "kubectl uncordon #node!",
+        ]);
+        assert_eq!(Some(0), current_step(&steps, "#", "!"));
+        replace_parameter(&mut steps, "#", "!", "worker-1");
+        assert_eq!(Some(1), current_step(&steps, "#", "!"));
+        replace_parameter(&mut steps, "#", "!", "-y");
+        assert_eq!(None, current_step(&steps, "#", "!"));
+        assert_eq!(
+            "kubectl drain worker-1 && ssh worker-1 apt upgrade -y && kubectl uncordon worker-1",
+            join_steps(&steps)
+        );
+    }
+
+    #[test]
+    fn test_missing_step() {
+        let mut trove = Trove::default();
+        let command = HoardCmd::default()
+            .with_name("drain")
+            .with_namespace("k8s")
+            .with_command("kubectl drain #node!");
+        let id = command.id.clone();
+        trove.add_command(command, true).unwrap();
+        let workflow = Workflow::new("upgrade", "k8s", "", vec![id, "gone".to_string()]);
+        assert!(matches!(
+            workflow.step_commands(&trove),
+            Err(HoardError::NotFound(_))
+        ));
+    }
+}
@@ -0,0 +1,284 @@
+use hoardlib::core::error::HoardError;
+use hoardlib::core::trove::Trove;
+use hoardlib::core::{string_to_tags, HoardCmd};
+
+/// A change of all marked commands at once, applied to the trove by `commands_gui::run`
+#[derive(Debug, PartialEq, Eq)]
+pub enum BulkAction {
+    /// Move the commands to the namespace
+    Move(String),
+    AddTags(Vec<String>),
+    RemoveTags(Vec<String>),
+    /// Make the commands favorites, or no favorites if all of them are
+    ToggleFavorite,
+    /// Hide the commands, or show them if all of them are hidden
+    ToggleHidden,
+    Delete,
+}
+
+impl BulkAction {
+    /// What the action did to `count` commands, for the footer
+    pub fn describe(&self, count: usize) -> String {
+        let commands = count_commands(count);
+        match self {
+            Self::Move(namespace) => format!("Moved {commands} to {namespace}"),
+            Self::AddTags(_) => format!("Tagged {commands}"),
+            Self::RemoveTags(_) => format!("Untagged {commands}"),
+            Self::ToggleFavorite => format!("Toggled favorite of {commands}"),
+            Self::ToggleHidden => format!("Toggled hidden of {commands}"),
+            Self::Delete => format!("Deleted {commands}"),
+        }
+    }
+}
+
+/// "1 command" or "`count` commands"
+pub fn count_commands(count: usize) -> String {
+    if count == 1 {
+        "1 command".to_string()
+    } else {
+        format!("{count} commands")
+    }
+}
+
+/// What the prompt in place of the search asks for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Prompt {
+    AddTags,
+    RemoveTags,
+    /// A new namespace to move the marked commands to
+    Namespace,
+    /// The file to export the marked commands to
+    ExportPath,
+}
+
+impl Prompt {
+    pub const fn label(self) -> &'static str {
+        match self {
+            Self::AddTags => "Add tags",
+            Self::RemoveTags => "Remove tags",
+            Self::Namespace => "New namespace",
+            Self::ExportPath => "Export to",
+        }
+    }
+}
+
+/// Applies `action` to the commands of `trove` with the ids `ids` and returns how many there are
+///
+/// Nothing is changed if the action would give two commands of a namespace the same name,
+/// or would delete steps of a workflow
+pub fn apply(trove: &mut Trove, action: &BulkAction, ids: &[String]) -> Result<usize, HoardError> {
+    let before: Vec<HoardCmd> = trove
+        .commands
+        .iter()
+        .filter(|c| ids.contains(&c.id))
+        .cloned()
+        .collect();
+    let all = |flag: fn(&HoardCmd) -> bool| before.iter().all(flag);
+    let favorite = !all(|c| c.is_favorite);
+    let hidden = !all(|c| c.is_hidden);
+    if let BulkAction::Move(namespace) = action {
+        HoardCmd::is_namespace_valid(namespace)?;
+        check_names(trove, &before, namespace)?;
+    }
+    if *action == BulkAction::Delete {
+        trove.check_removable(|c| ids.contains(&c.id))?;
+        trove.commands.retain(|c| !ids.contains(&c.id));
+        trove.namespaces = trove
+            .namespaces()
+            .into_iter()
+            .map(ToString::to_string)
+            .collect();
+        return Ok(before.len());
+    }
+    for command in &before {
+        let mut changed = command.clone();
+        match action {
+            BulkAction::Move(namespace) => changed.namespace.clone_from(namespace),
+            BulkAction::AddTags(tags) => {
+                let new = tags
+                    .iter()
+                    .filter(|tag| !changed.tags.contains(tag))
+                    .cloned();
+                changed.tags.extend(new.collect::<Vec<_>>());
+            }
+            BulkAction::RemoveTags(tags) => changed.tags.retain(|tag| !tags.contains(tag)),
+            BulkAction::ToggleFavorite => changed.is_favorite = favorite,
+            BulkAction::ToggleHidden => changed.is_hidden = hidden,
+            BulkAction::Delete => (),
+        }
+        trove.restore_command(changed);
+    }
+    Ok(before.len())
+}
+
+/// Fails if `moved` commands would have the same name as each other or as another command of `namespace`
+fn check_names(trove: &Trove, moved: &[HoardCmd], namespace: &str) -> Result<(), HoardError> {
+    for (i, command) in moved.iter().enumerate() {
+        let taken = trove
+            .commands
+            .iter()
+            .filter(|c| c.namespace == namespace && !moved.iter().any(|m| m.id == c.id))
+            .chain(&moved[..i])
+            .any(|c| c.name == command.name);
+        if taken {
+            return Err(HoardError::Validation(format!(
+                "There already is a command {namespace}/{}",
+                command.name
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// The tags in `input`, separated by commas
+pub fn parse_tags(input: &str) -> Vec<String> {
+    string_to_tags(input)
+        .into_iter()
+        .filter(|tag| !tag.is_empty())
+        .collect()
+}
+
+/// A shell script running `commands` one after another, with their names and descriptions as comments
+pub fn script(commands: &[HoardCmd]) -> String {
+    let mut script = String::from("#!/bin/sh\n");
+    for command in commands {
+        script.push_str(&format!("\n# {}/{}\n", command.namespace, command.name));
+        for line in command.description.lines().filter(|line| !line.is_empty()) {
+            script.push_str(&format!("# {line}\n"));
+        }
+        script.push_str(&command.command);
+        script.push('\n');
+    }
+    script
+}
+
+#[cfg(test)]
+mod test_bulk {
+    use super::*;
+    use hoardlib::core::journal::Journal;
+
+    fn trove() -> Trove {
+        let commands =
+            [("pods", "k8s"), ("nodes", "k8s"), ("pods", "docker")].map(|(name, namespace)| {
+                HoardCmd::default()
+                    .with_name(name)
+                    .with_namespace(namespace)
+                    .with_command(&format!("{namespace} {name}"))
+                    .with_tags_raw("ops")
+            });
+        Trove::from_commands(&commands)
+    }
+
+    fn ids(trove: &Trove, names: &[&str]) -> Vec<String> {
+        names
+            .iter()
+            .map(|name| {
+                let command = trove
+                    .commands
+                    .iter()
+                    .find(|c| format!("{}/{}", c.namespace, c.name) == *name);
+                command.unwrap().id.clone()
+            })
+            .collect()
+    }
+
+    /// Applies `action` like `run`, journaled as one change
+    fn apply_journaled(
+        trove: &mut Trove,
+        journal: &mut Journal,
+        action: &BulkAction,
+        ids: &[String],
+    ) {
+        let before = trove.clone();
+        let count = apply(trove, action, ids).unwrap();
+        assert!(journal.record(&action.describe(count), &before, trove));
+    }
+
+    #[test]
+    fn tag_and_undo_at_once() {
+        let mut trove = trove();
+        let mut journal = Journal::in_memory(10);
+        let ids = ids(&trove, &["k8s/pods", "k8s/nodes"]);
+
+        apply_journaled(
+            &mut trove,
+            &mut journal,
+            &BulkAction::AddTags(parse_tags("kube, ops,")),
+            &ids,
+        );
+        assert_eq!(vec!["ops", "kube"], trove.commands[0].tags);
+        assert_eq!(vec!["ops", "kube"], trove.commands[1].tags);
+        assert_eq!(vec!["ops"], trove.commands[2].tags);
+
+        assert_eq!(
+            Some("Tagged 2 commands".to_string()),
+            journal.undo(&mut trove).unwrap()
+        );
+        assert!(trove.commands.iter().all(|c| c.tags == vec!["ops"]));
+    }
+
+    #[test]
+    fn toggle_flags() {
+        let mut trove = trove();
+        let first = ids(&trove, &["k8s/pods"]);
+        let both = ids(&trove, &["k8s/pods", "k8s/nodes"]);
+        apply(&mut trove, &BulkAction::ToggleFavorite, &first).unwrap();
+        apply(&mut trove, &BulkAction::ToggleFavorite, &both).unwrap();
+        assert!(trove.commands[0].is_favorite && trove.commands[1].is_favorite);
+        apply(&mut trove, &BulkAction::ToggleFavorite, &both).unwrap();
+        assert!(!trove.commands[0].is_favorite && !trove.commands[1].is_favorite);
+    }
+
+    #[test]
+    fn move_without_name_collisions() {
+        let mut trove = trove();
+        let ids = ids(&trove, &["k8s/pods", "k8s/nodes"]);
+        let error = apply(&mut trove, &BulkAction::Move("docker".to_string()), &ids).unwrap_err();
+        assert!(error.to_string().contains("docker/pods"));
+        assert_eq!(vec!["docker", "k8s"], trove.namespaces());
+
+        apply(&mut trove, &BulkAction::Move("kube".to_string()), &ids).unwrap();
+        assert_eq!(vec!["docker", "kube"], trove.namespaces());
+    }
+
+    #[test]
+    fn delete_and_undo() {
+        let mut trove = trove();
+        let mut journal = Journal::in_memory(10);
+        let ids = ids(&trove, &["k8s/nodes", "docker/pods"]);
+        apply_journaled(&mut trove, &mut journal, &BulkAction::Delete, &ids);
+        assert_eq!(1, trove.commands.len());
+        assert_eq!(vec!["k8s"], trove.namespaces());
+
+        journal.undo(&mut trove).unwrap();
+        assert_eq!(3, trove.commands.len());
+        assert_eq!(vec!["docker", "k8s"], trove.namespaces());
+    }
+
+    #[test]
+    fn steps_of_workflows_are_not_deleted() {
+        let mut trove = trove();
+        let ids = ids(&trove, &["k8s/nodes", "docker/pods"]);
+        let workflow =
+            hoardlib::core::workflow::Workflow::new("check", "k8s", "", vec![ids[0].clone()]);
+        trove.add_workflow(workflow).unwrap();
+        assert!(matches!(
+            apply(&mut trove, &BulkAction::Delete, &ids),
+            Err(HoardError::Validation(_))
+        ));
+        assert_eq!(3, trove.commands.len());
+    }
+
+    #[test]
+    fn script_with_comments() {
+        let command = HoardCmd::default()
+            .with_name("pods")
+            .with_namespace("k8s")
+            .with_command("kubectl get pods")
+            .with_description("List pods\nof the cluster");
+        assert_eq!(
+            "#!/bin/sh\n\n# k8s/pods\n# List pods\n# of the cluster\nkubectl get pods\n",
+            script(&[command])
+        );
+    }
+}
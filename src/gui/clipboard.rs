@@ -0,0 +1,62 @@
+use base64::Engine;
+use std::io::{stdout, Write};
+use std::process::{Command, Stdio};
+
+/// Programs which write stdin to the system clipboard, tried in order
+const COPY_PROGRAMS: [&[&str]; 4] = [
+    &["pbcopy"],
+    &["wl-copy"],
+    &["xclip", "-selection", "clipboard"],
+    &["xsel", "--clipboard", "--input"],
+];
+
+/// Programs which print the system clipboard, tried in order
+const PASTE_PROGRAMS: [&[&str]; 4] = [
+    &["pbpaste"],
+    &["wl-paste", "--no-newline"],
+    &["xclip", "-selection", "clipboard", "-o"],
+    &["xsel", "--clipboard", "--output"],
+];
+
+/// Puts `text` into the system clipboard
+///
+/// Without a clipboard program it asks the terminal to do it with an OSC 52 escape sequence, which also works over ssh
+pub fn copy(text: &str) -> std::io::Result<()> {
+    for program in COPY_PROGRAMS {
+        let child = Command::new(program[0])
+            .args(&program[1..])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn();
+        let Ok(mut child) = child else {
+            continue;
+        };
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(text.as_bytes())?;
+        }
+        if child.wait()?.success() {
+            return Ok(());
+        }
+    }
+    let encoded = base64::engine::general_purpose::STANDARD.encode(text);
+    let mut stdout = stdout();
+    write!(stdout, "\x1b]52;c;{encoded}\x07")?;
+    stdout.flush()
+}
+
+/// The text in the system clipboard, if a clipboard program can read it
+pub fn paste() -> Option<String> {
+    PASTE_PROGRAMS.iter().find_map(|program| {
+        let output = Command::new(program[0])
+            .args(&program[1..])
+            .stdin(Stdio::null())
+            .stderr(Stdio::null())
+            .output()
+            .ok()?;
+        output
+            .status
+            .success()
+            .then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+    })
+}
@@ -0,0 +1,280 @@
+use crate::gui::merge::{with_conflict_resolve_prompt, ConflictResolve};
+use crate::gui::prompts::{prompt_input, prompt_input_validate, prompt_select_with_options};
+use hoardlib::core::parameters::{fill_next_parameter, Parameterized};
+use hoardlib::core::trove::Trove;
+use hoardlib::core::HoardCmd;
+
+/// Builders of a `HoardCmd` which ask the user for input on the terminal
+///
+/// They live next to the rest of the terminal user interface, so `hoardlib` does not depend on a terminal
+pub trait CommandInput: Sized {
+    /// Prompts the user for a command string, with optional default value and parameter tokens.
+    ///
+    /// This function prompts the user for a command string. The user can mark unknown parameters with a specified token
+    /// and name the parameter with any string, ending it with a specified ending token. An optional default value can be provided.
+    ///
+    /// # Arguments
+    ///
+    /// * `default_value` - An Option that holds a default value for the command string.
+    /// * `parameter_token` - A string slice that holds the token to mark unknown parameters.
+    /// * `parameter_ending_token` - A string slice that holds the token to end the parameter name.
+    ///
+    /// # Returns
+    ///
+    /// This function returns a new instance of the command with the user-inputted command string.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let command = HoardCmd::default();
+    /// let command_with_input = command.with_command_string_input(None, "#", "$");
+    /// // The user is prompted for a command string.
+    /// // The command string is updated with the user's input.
+    /// ```
+    fn with_command_string_input(
+        self,
+        default_value: Option<String>,
+        parameter_token: &str,
+        parameter_ending_token: &str,
+    ) -> Self;
+
+    /// Prompts the user for tags, with an optional default value, and validates the input.
+    ///
+    /// This function prompts the user for tags, which are comma-separated. The input is validated to ensure that
+    /// it does not contain any whitespaces. An optional default value can be provided.
+    ///
+    /// # Arguments
+    ///
+    /// * `default_value` - An Option that holds a default value for the tags.
+    ///
+    /// # Returns
+    ///
+    /// This function returns a new instance of the command with the user-inputted tags.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let command = HoardCmd::default();
+    /// let command_with_tags = command.with_tags_input(Some("default-tag".to_string()));
+    /// // The user is prompted for tags.
+    /// // The tags are updated with the user's input.
+    /// ```
+    fn with_tags_input(self, default_value: Option<String>) -> Self;
+
+    /// Prompts the user to select one of the namespaces in `selection` or to enter a new one
+    fn with_namespace_input(self, selection: &[&str]) -> Self;
+
+    /// Prompts the user for a name which is not taken yet in the namespace of the command
+    fn with_name_input(self, default_value: Option<String>, trove: &Trove) -> Self;
+
+    /// Resolves a name conflict when a command should be added to a trove file.
+    ///
+    /// This function takes a command with a conflicting name and a reference to a trove. It prompts the user to resolve the conflict
+    /// by either replacing the existing command, keeping the existing command, or providing a new name for the new command.
+    ///
+    /// # Arguments
+    ///
+    /// * `collision` - A command that has a name conflict with the current command.
+    /// * `trove` - A reference to a trove where the command should be added.
+    ///
+    /// # Returns
+    ///
+    /// This function returns a tuple of options. If the first option is set, the new command should be added. If the second option is set,
+    /// the existing command should be removed.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let command = HoardCmd::default().with_command("echo Hello, world!");
+    /// let colliding_command = HoardCmd::default().with_command("echo Hello, world!");
+    /// let trove = Trove::default();
+    /// let (add_command, remove_command) = command.resolve_name_conflict(colliding_command, &trove);
+    /// // The user is prompted to resolve the conflict.
+    /// // The commands to add and remove are determined based on the user's input.
+    /// ```
+    #[allow(dead_code)]
+    fn resolve_name_conflict(self, collision: Self, trove: &Trove) -> (Option<Self>, Option<Self>);
+
+    /// Prompts the user for a description of the command
+    fn with_description_input(self, default_value: String) -> Self;
+
+    /// Replaces all occurrences of a parameter, identified by a token and an ending token, in the command string with user input.
+    ///
+    /// This function takes a token and an ending token. It prompts the user for input for each occurrence of the parameter
+    /// in the command string and replaces the parameter with the user's input. Secret parameters are kept.
+    ///
+    /// # Arguments
+    ///
+    /// * `token` - A string slice that holds the token of the parameter.
+    /// * `ending_token` - A string slice that holds the ending token of the parameter.
+    ///
+    /// # Returns
+    ///
+    /// This function returns a new instance of the command with the replaced parameters.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let mut command = HoardCmd::default().with_command("echo #param1$");
+    /// command = command.with_input_parameters("#", "$");
+    /// // The user is prompted for input for each occurrence of the parameter.
+    /// // The command string is updated with the user's input.
+    /// ```
+    fn with_input_parameters(&mut self, token: &str, ending_token: &str) -> Self;
+}
+
+impl CommandInput for HoardCmd {
+    fn with_command_string_input(
+        self,
+        default_value: Option<String>,
+        parameter_token: &str,
+        parameter_ending_token: &str,
+    ) -> Self {
+        let base_prompt = format!(
+            "Command to hoard ( Mark unknown parameters with '{parameter_token}'. Name the parameter with any string and end it with '{parameter_ending_token}' )\n"
+        );
+        let command_string: String = prompt_input(&base_prompt, false, default_value);
+        Self {
+            command: command_string,
+            ..self
+        }
+    }
+
+    fn with_tags_input(self, default_value: Option<String>) -> Self {
+        let tag_validator = move |input: &String| -> Result<(), String> {
+            if input.contains(' ') {
+                Err("Tags can't contain whitespaces".to_string())
+            } else {
+                Ok(())
+            }
+        };
+        let tags: String = prompt_input_validate(
+            "Give your command some optional tags ( comma separated )",
+            true,
+            default_value,
+            Some(tag_validator),
+        );
+        self.with_tags_raw(&tags)
+    }
+
+    fn with_namespace_input(self, selection: &[&str]) -> Self {
+        // Add "New namespace" option to selction
+        let mut selection = selection.to_vec();
+        selection.push("New namespace");
+
+        let selected: usize = prompt_select_with_options("Namespace of the command", &selection);
+
+        let mut selected_namespace: String = (*selection.get(selected).unwrap()).to_string();
+        if selected_namespace == "New namespace" {
+            selected_namespace = prompt_input(
+                "Namespace of the command",
+                false,
+                Some(String::from("default")),
+            );
+        }
+
+        Self {
+            namespace: selected_namespace,
+            ..self
+        }
+    }
+
+    fn with_name_input(self, default_value: Option<String>, trove: &Trove) -> Self {
+        with_name_input_prompt(self, default_value, trove, "Name your command")
+    }
+
+    fn resolve_name_conflict(self, collision: Self, trove: &Trove) -> (Option<Self>, Option<Self>) {
+        // Behaviour if a command should be added to a trove file
+        // Returns a tuple of options
+        // If the first is set, add this as a new command
+        // If the second is set, remove this exact command
+        let name = self.name.clone();
+        let command = self.command.clone();
+        let namespace = self.namespace.clone();
+        let colliding_command = collision.command.clone();
+        // Ask user how to resolve conflict
+        let mode: ConflictResolve =
+            with_conflict_resolve_prompt(&name, &namespace, &command, &colliding_command);
+
+        match mode {
+            ConflictResolve::Replace => {
+                // Add new command, remove colliding command in the local trove
+                (Some(self), Some(collision))
+            }
+            ConflictResolve::Keep => {
+                // Do nothing
+                (None, None)
+            }
+            ConflictResolve::New => {
+                (Some(with_name_input_prompt(
+                    self,
+                    None,
+                    trove,
+                    &format!(
+                        "Enter a new name for command: '{command}'\nOld name: {name} in namespace: {namespace}\nEnter new name: "
+                    ),
+                )) , None)
+            }
+        }
+    }
+
+    fn with_description_input(self, default_value: String) -> Self {
+        let description_string: String =
+            prompt_input("Describe what the command does", false, Some(default_value));
+        Self {
+            description: description_string,
+            ..self
+        }
+    }
+
+    fn with_input_parameters(&mut self, token: &str, ending_token: &str) -> Self {
+        let mut param_count = 0;
+        // Secret parameters are left for the vault
+        while self.has_input_parameters(token, ending_token) {
+            let prompt_dialog = format!(
+                "Enter parameter({}) nr {} \n~> {}\n",
+                token,
+                (param_count + 1),
+                self.command
+            );
+            let parameter = prompt_input(&prompt_dialog, false, None);
+            self.command = fill_next_parameter(
+                &self.command,
+                token,
+                ending_token,
+                &parameter,
+                &mut Vec::new(),
+            );
+            param_count += 1;
+        }
+        self.clone()
+    }
+}
+
+fn with_name_input_prompt(
+    command: HoardCmd,
+    default_value: Option<String>,
+    trove: &Trove,
+    prompt_string: &str,
+) -> HoardCmd {
+    let namespace = command.namespace.clone();
+    let command_names = trove.commands.clone();
+    let validator = move |input: &String| -> Result<(), String> {
+        if input.contains(' ') {
+            Err("The name can't contain whitespaces".to_string())
+        } else if command_names
+            .iter()
+            .filter(|x| x.namespace == namespace)
+            .any(|x| x.name == *input)
+        {
+            Err(
+                "A command with same name exists in the this namespace. Input a different name"
+                    .to_string(),
+            )
+        } else {
+            Ok(())
+        }
+    };
+    let name = prompt_input_validate(prompt_string, false, default_value, Some(validator));
+    HoardCmd { name, ..command }
+}
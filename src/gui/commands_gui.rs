@@ -1,22 +1,36 @@
-use crate::config::HoardConfig;
-use crate::core::trove::Trove;
-use crate::core::HoardCmd;
-use crate::gui::event::{Config, Event, Events};
+use crate::gui::bulk::{self, BulkAction, Prompt};
+use crate::gui::event::{Config, Event, Events, DISABLE_BRACKETED_PASTE, ENABLE_BRACKETED_PASTE};
 use crate::gui::help::{draw as draw_help, key_handler as key_handler_help};
 use crate::gui::inline_edit::controls::key_handler as key_handler_inline_edit;
-use crate::gui::list_search::controls::key_handler as key_handler_list_search;
+use crate::gui::keymap::Keymap;
+use crate::gui::list_search::controls::{apply_filter, key_handler as key_handler_list_search};
+use crate::gui::list_search::layout::Areas;
 use crate::gui::list_search::render::draw as draw_list_search;
+use crate::gui::mouse::handler as mouse_handler;
 use crate::gui::new_command::controls::key_handler as key_handler_create_command;
 use crate::gui::new_command::render::draw as draw_new_command_input;
 use crate::gui::parameter_input::controls::key_handler as key_handler_parameter_input;
 use crate::gui::parameter_input::render::draw as draw_parameter_input;
+use crate::gui::terminal::{Height, Tui};
+use crate::gui::text_input::TextInput;
+use crate::gui::theme::Theme;
+use crate::gui::vi::Vi;
 use eyre::Result;
-use ratatui::{backend::TermionBackend, widgets::ListState, Terminal};
+use hoardlib::config::HoardConfig;
+use hoardlib::core::environment::Environment;
+use hoardlib::core::error::HoardError;
+use hoardlib::core::journal::Journal;
+use hoardlib::core::references::{self, resolve_references};
+use hoardlib::core::trove::Trove;
+use hoardlib::core::vault::{Vault, VaultKey};
+use hoardlib::core::workflow::WorkflowMode;
+use hoardlib::core::HoardCmd;
+use ratatui::widgets::ListState;
+use std::collections::{BTreeMap, HashMap};
 use std::fmt;
-use std::io::stdout;
-use std::time::Duration;
-use termion::raw::IntoRawMode;
-use termion::screen::IntoAlternateScreen;
+use std::io::{stdout, Write};
+use std::ops::Range;
+use std::time::{Duration, Instant};
 
 #[allow(clippy::struct_excessive_bools, clippy::struct_field_names)]
 pub struct State {
@@ -28,7 +42,7 @@ pub struct State {
     pub draw: DrawState,
     pub edit_selection: EditSelection,
     pub error_message: String,
-    pub input: String,
+    pub input: TextInput,
     pub namespace_tab: ListState,
     pub new_command: Option<HoardCmd>,
     pub parameter_ending_token: String,
@@ -37,35 +51,226 @@ pub struct State {
     pub selected_command: Option<HoardCmd>,
     pub should_delete: bool,
     pub should_exit: bool,
-    pub string_to_edit: String,
+    pub string_to_edit: TextInput,
+    // Ids of the marked commands, in the order they were marked
+    pub marked: Vec<String>,
+    // Position the marking of a range started at and the commands marked before it, while a range is marked
+    pub mark_range: Option<(usize, Vec<String>)>,
+    // Input for a bulk action, asked for in place of the search
+    pub prompt: Option<Prompt>,
+    pub prompt_input: TextInput,
+    // Bulk action for `run` to apply to the commands with the ids
+    pub bulk_action: Option<(BulkAction, Vec<String>)>,
+    // Set to undo or redo the last change of the trove in `run`
+    pub should_undo: bool,
+    pub should_redo: bool,
+    // Selected entry of the namespace picker of the edited command while it is open. The entry after the namespaces is a new one
+    pub namespace_picker: Option<usize>,
+    // Vi editing of the input fields, with the registers shared by all of them
+    pub vi: Vi,
+    // Keys of the actions of every screen, from the defaults and the config
+    pub keymap: Keymap,
+    // Styles of the parts of the TUI, from the theme of the config
+    pub theme: Theme,
+    // Where the panes of the list screen were drawn last, to find what the mouse clicks on
+    pub areas: Option<Areas>,
+    // When and on which command the last click in the list was, to tell a double click
+    pub last_click: Option<(Instant, usize)>,
+    // Id of the command whose description is scrolled with the mouse wheel, and by how many lines
+    pub description_scroll: Option<(String, u16)>,
+    // Vault with the values of secret parameters. Unlocked when a picked command needs one of its secrets
+    pub vault: Option<Vault>,
+    pub vault_key: Option<VaultKey>,
+    pub secrets: BTreeMap<String, String>,
+    // Variables to expand `${VAR}` in the selected command with
+    pub environment: Environment,
+    // Steps of the listed workflows by the id of the workflow
+    pub workflows: HashMap<String, Vec<HoardCmd>>,
+    // The listed commands without the workflows, which commands can embed. Kept up to date by `relate`
+    pub hoarded: Vec<HoardCmd>,
+    // Commands and workflows which use a listed command, as `namespace/name` by the id of the command
+    pub used_by: HashMap<String, Vec<String>>,
+    // Steps of the selected workflow while their parameters are provided
+    pub workflow_steps: Vec<HoardCmd>,
+    // Byte ranges of the values provided so far, in the selected command or in each step of the selected workflow
+    pub filled: Vec<Vec<Range<usize>>>,
+    // Id of a command other commands depend on, deleted when it is deleted a second time
+    pub pending_delete: Option<String>,
+    pub workflow_mode: WorkflowMode,
 }
 
 impl State {
+    /// The command string of `command` with its variables expanded
+    pub fn expand_variables(&self, command: &HoardCmd) -> String {
+        self.environment.expand(
+            &command.command,
+            &command.namespace,
+            &self.parameter_token,
+            &self.parameter_ending_token,
+        )
+    }
+
+    /// The command string of `command` with the commands it embeds resolved and its variables expanded
+    ///
+    /// Workflows can't be embedded
+    pub fn resolve(&self, command: &HoardCmd) -> Result<String, HoardError> {
+        let resolved = resolve_references(command, &self.hoarded)?;
+        Ok(self.expand_variables(&command.clone().with_command(&resolved)))
+    }
+
+    /// Commands and workflows which use the command with the id `id`, as `namespace/name`
+    pub fn dependents(&self, id: &str) -> &[String] {
+        self.used_by.get(id).map_or(&[], Vec::as_slice)
+    }
+
+    /// Finds out which of `entries`, all listed commands, embed or run which others
+    ///
+    /// Called whenever the listed commands change, so drawing a frame doesn't go through all of them
+    pub fn relate(&mut self, entries: &[HoardCmd]) {
+        self.hoarded = entries
+            .iter()
+            .filter(|entry| !self.workflows.contains_key(&entry.id))
+            .cloned()
+            .collect();
+        let mut used_by: HashMap<String, Vec<String>> = HashMap::new();
+        let mut add = |id: &str, label: String| {
+            let users = used_by.entry(id.to_string()).or_default();
+            if !users.contains(&label) {
+                users.push(label);
+            }
+        };
+        for command in &self.hoarded {
+            for referenced in references::referenced_commands(command, &self.hoarded) {
+                if referenced.id != command.id {
+                    add(
+                        &referenced.id,
+                        format!("{}/{}", command.namespace, command.name),
+                    );
+                }
+            }
+        }
+        for entry in entries {
+            for step in self.workflows.get(&entry.id).into_iter().flatten() {
+                add(
+                    &step.id,
+                    format!("workflow {}/{}", entry.namespace, entry.name),
+                );
+            }
+        }
+        self.used_by = used_by;
+    }
+
     pub fn update_string_to_edit(&mut self) -> &mut Self {
-        let selected_idx = self.command_list.selected().unwrap();
-        let cloned_selected_command = self.commands.get(selected_idx).unwrap().clone();
+        let cloned_selected_command = self.selected_command.clone().unwrap_or_else(|| {
+            let selected_idx = self.command_list.selected().unwrap();
+            self.commands.get(selected_idx).unwrap().clone()
+        });
 
         match self.edit_selection {
-            EditSelection::Name => self.string_to_edit = cloned_selected_command.name,
+            EditSelection::Name => self.string_to_edit.set_value(&cloned_selected_command.name),
 
             EditSelection::Tags => {
-                self.string_to_edit = cloned_selected_command.get_tags_as_string();
+                self.string_to_edit
+                    .set_value(&cloned_selected_command.get_tags_as_string());
             }
             EditSelection::Description => {
-                self.string_to_edit = cloned_selected_command.description;
+                self.string_to_edit
+                    .set_value(&cloned_selected_command.description);
             }
 
-            EditSelection::Command => self.string_to_edit = cloned_selected_command.command,
-            EditSelection::Namespace => (),
+            EditSelection::Command => self
+                .string_to_edit
+                .set_value(&cloned_selected_command.command),
+            EditSelection::Namespace => {
+                self.string_to_edit
+                    .set_value(&cloned_selected_command.namespace);
+            }
         };
         self
     }
 }
 
+#[cfg(test)]
+impl State {
+    /// The state of the search screen listing `commands`, with the first command and namespace tab selected
+    ///
+    /// Tests set up what else they need with struct update syntax
+    pub fn for_test(commands: Vec<HoardCmd>) -> Self {
+        let mut state = Self {
+            input: TextInput::default(),
+            commands,
+            command_list: ListState::default(),
+            namespace_tab: ListState::default(),
+            should_exit: false,
+            should_delete: false,
+            draw: DrawState::Search,
+            control: ControlState::Search,
+            vimode: ViMode::Normal,
+            new_command: None,
+            edit_selection: EditSelection::Command,
+            string_to_edit: TextInput::default(),
+            marked: Vec::new(),
+            mark_range: None,
+            prompt: None,
+            prompt_input: TextInput::default(),
+            bulk_action: None,
+            should_undo: false,
+            should_redo: false,
+            namespace_picker: None,
+            vi: Vi::default(),
+            keymap: Keymap::default(),
+            theme: Theme::default(),
+            areas: None,
+            last_click: None,
+            description_scroll: None,
+            parameter_token: "#".to_string(),
+            parameter_ending_token: "!".to_string(),
+            selected_command: None,
+            provided_parameter_count: 0,
+            error_message: String::new(),
+            buffered_tick: false,
+            vault: None,
+            vault_key: None,
+            secrets: BTreeMap::new(),
+            environment: Environment::default(),
+            workflows: HashMap::new(),
+            hoarded: Vec::new(),
+            used_by: HashMap::new(),
+            workflow_steps: Vec::new(),
+            filled: Vec::new(),
+            pending_delete: None,
+            workflow_mode: WorkflowMode::Join,
+        };
+        let commands = state.commands.clone();
+        state.relate(&commands);
+        state.command_list.select(Some(0));
+        state.namespace_tab.select(Some(0));
+        state
+    }
+}
+
+/// The commands and the workflows of `trove` to list, and the steps of the listed workflows by their id
+///
+/// Workflows are listed as a single command with their steps joined. Workflows with a removed step are left out
+pub fn list_entries(trove: &Trove) -> (Vec<HoardCmd>, HashMap<String, Vec<HoardCmd>>) {
+    let mut entries = trove.get_commands_sorted_by_usage();
+    let mut workflows = HashMap::new();
+    for workflow in &trove.workflows {
+        if let Ok(steps) = workflow.step_commands(trove) {
+            entries.push(workflow.to_command(&steps));
+            workflows.insert(workflow.id.clone(), steps);
+        }
+    }
+    (entries, workflows)
+}
+
 #[derive(Debug, Eq, PartialEq, Clone)]
 pub enum ViMode {
     Normal,
     Insert,
+    // Vi's normal mode inside the edited field, see `Vi`
+    Edit,
+    Visual,
 }
 
 #[derive(Debug, Eq, PartialEq)]
@@ -82,19 +287,7 @@ pub enum ControlState {
     Edit,
 }
 
-impl fmt::Display for ControlState {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match self {
-            Self::Search => write!(
-                f,
-                "Tab (Cycle Right), k (Up), j (Down), a (Add), <Alt+D> (Delete), ? (Help)"
-            ),
-            Self::Edit => write!(f, "k (Up), j (Down)"),
-        }
-    }
-}
-
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
 pub enum EditSelection {
     Name,
     Tags,
@@ -118,16 +311,20 @@ impl fmt::Display for EditSelection {
 impl EditSelection {
     pub const fn next(&self) -> Self {
         match self {
-            Self::Name | Self::Namespace | Self::Command => Self::Tags,
+            Self::Name => Self::Namespace,
+            Self::Namespace => Self::Tags,
             Self::Tags => Self::Description,
             Self::Description => Self::Command,
+            Self::Command => Self::Name,
         }
     }
     pub const fn prev(&self) -> Self {
         match self {
-            Self::Name | Self::Namespace | Self::Command => Self::Description,
-            Self::Tags => Self::Command,
+            Self::Name => Self::Command,
+            Self::Namespace => Self::Name,
+            Self::Tags => Self::Namespace,
             Self::Description => Self::Tags,
+            Self::Command => Self::Description,
         }
     }
     pub const fn edit_next(&self) -> Self {
@@ -141,16 +338,22 @@ impl EditSelection {
     }
 }
 
-#[allow(clippy::too_many_lines)]
-pub fn run(trove: &mut Trove, config: &HoardConfig) -> Result<Option<HoardCmd>> {
-    let events = Events::with_config(Config {
-        tick_rate: Duration::from_millis(200),
-    });
-    let trove_clone = trove.clone();
+#[allow(clippy::too_many_lines, clippy::too_many_arguments)]
+pub fn run(
+    trove: &mut Trove,
+    config: &HoardConfig,
+    environment: Environment,
+    keymap: Keymap,
+    theme: Theme,
+    height: Option<Height>,
+    mouse: bool,
+    journal: &mut Journal,
+) -> Result<Option<HoardCmd>> {
+    let (mut entries, workflows) = list_entries(trove);
 
     let mut app_state = State {
-        input: String::new(),
-        commands: trove.get_commands_sorted_by_usage(),
+        input: TextInput::default(),
+        commands: entries.clone(),
         command_list: ListState::default(),
         namespace_tab: ListState::default(),
         should_exit: false,
@@ -160,106 +363,203 @@ pub fn run(trove: &mut Trove, config: &HoardConfig) -> Result<Option<HoardCmd>>
         control: ControlState::Search,
         edit_selection: EditSelection::Command,
         new_command: None,
-        string_to_edit: String::new(),
+        string_to_edit: TextInput::default(),
+        marked: Vec::new(),
+        mark_range: None,
+        prompt: None,
+        prompt_input: TextInput::default(),
+        bulk_action: None,
+        should_undo: false,
+        should_redo: false,
+        namespace_picker: None,
+        vi: Vi::default(),
+        keymap,
+        theme,
+        areas: None,
+        last_click: None,
+        description_scroll: None,
         parameter_token: config.parameter_token.as_ref().unwrap().clone(),
         parameter_ending_token: config.parameter_ending_token.as_ref().unwrap().clone(),
         selected_command: None,
         provided_parameter_count: 0,
         error_message: String::new(),
         buffered_tick: false,
+        // A broken vault is reported when a secret is asked for instead
+        vault: config
+            .vault_path
+            .as_deref()
+            .and_then(|path| Vault::open(path).ok()),
+        vault_key: None,
+        secrets: BTreeMap::new(),
+        environment,
+        workflows,
+        hoarded: Vec::new(),
+        used_by: HashMap::new(),
+        workflow_steps: Vec::new(),
+        filled: Vec::new(),
+        pending_delete: None,
+        workflow_mode: config.workflow_mode.unwrap_or_default(),
     };
 
+    app_state.relate(&entries);
     app_state.command_list.select(Some(0));
     app_state.namespace_tab.select(Some(0));
 
-    let stdout = stdout().into_raw_mode()?;
-    let stdout = stdout.into_alternate_screen().unwrap();
-    let backend = TermionBackend::new(stdout);
-    let mut terminal = Terminal::new(backend)?;
-    terminal.clear()?;
+    let _bracketed_paste = BracketedPaste::enable()?;
+    // Opened before the events are read, finding the cursor for the inline mode reads from the terminal too
+    let mut tui = Tui::open(height, mouse)?;
+    let terminal = &mut tui.terminal;
+    let events = Events::with_config(Config {
+        tick_rate: Duration::from_millis(200),
+    });
 
     //let menu_titles = vec!["List", "Search", "Add", "Delete", "Quit"];
-    let mut namespace_tabs: Vec<&str> = trove_clone.namespaces();
-    namespace_tabs.insert(0, "All");
+    let mut namespace_tabs = namespace_tabs(trove);
     loop {
+        let tabs: Vec<&str> = namespace_tabs.iter().map(String::as_str).collect();
         // Draw GUI
         match app_state.draw {
             DrawState::Search => {
-                draw_list_search(&mut app_state, config, &namespace_tabs, &mut terminal)?;
+                draw_list_search(&mut app_state, config, &tabs, terminal)?;
             }
             DrawState::ParameterInput => {
-                draw_parameter_input(&app_state, config, &mut terminal)?;
+                draw_parameter_input(&app_state, config, terminal)?;
             }
             DrawState::Help => {
-                draw_help(config, &mut terminal)?;
+                draw_help(&app_state.theme, &app_state.keymap, terminal)?;
             }
             DrawState::Create => {
-                draw_new_command_input(
-                    &app_state,
-                    config,
-                    &mut terminal,
-                    &config.default_namespace,
-                )?;
+                draw_new_command_input(&app_state, config, terminal, &config.default_namespace)?;
             }
         }
 
         app_state.buffered_tick = true;
 
-        if let Event::Input(input) = events.next()? {
-            let command = match app_state.draw {
+        let command = match events.next()? {
+            Event::Input(input) => Some(match app_state.draw {
                 DrawState::Search => match app_state.control {
-                    ControlState::Search => key_handler_list_search(
-                        input,
-                        &mut app_state,
-                        &trove.commands,
-                        &namespace_tabs,
-                    ),
-                    ControlState::Edit => key_handler_inline_edit(
-                        input,
-                        &mut app_state,
-                        &trove.commands,
-                        &namespace_tabs,
-                    ),
+                    ControlState::Search => {
+                        key_handler_list_search(input, &mut app_state, &entries, &tabs)
+                    }
+                    ControlState::Edit => {
+                        key_handler_inline_edit(input, &mut app_state, &entries, &tabs)
+                    }
                 },
                 DrawState::ParameterInput => key_handler_parameter_input(input, &mut app_state),
                 DrawState::Help => key_handler_help(input, &mut app_state),
                 DrawState::Create => {
                     key_handler_create_command(input, &mut app_state, &config.default_namespace)
                 }
-            };
-
+            }),
+            Event::Mouse(mouse) => Some(mouse_handler(mouse, &mut app_state, &entries, &tabs)),
+            Event::Paste(text) => {
+                paste(&mut app_state, &text, &entries, &tabs);
+                None
+            }
+            Event::Tick => None,
+        };
+        if let Some(command) = command {
             if let Some(output) = command {
+                // Every change of the trove is journaled, to undo it
+                let before = trove.clone();
+                let label = format!("{}/{}", output.namespace, output.name);
                 if app_state.draw == DrawState::Create {
+                    let id = output.id.clone();
                     let _ = trove.add_command(output, true);
-                    app_state.commands = trove.commands.clone();
-                    app_state
-                        .commands
-                        .sort_by(|a, b| b.usage_count.cmp(&a.usage_count));
+                    journal.record(&format!("Created {label}"), &before, trove);
+                    reload(
+                        &mut app_state,
+                        trove,
+                        &mut entries,
+                        &mut namespace_tabs,
+                        Some(&id),
+                    );
                     app_state.draw = DrawState::Search;
                 } else if app_state.control == ControlState::Edit {
                     // Command has been edited
-                    trove.update_command_by_name(&output);
-                    app_state.commands = trove.commands.clone();
-                    app_state
-                        .commands
-                        .sort_by(|a, b| b.usage_count.cmp(&a.usage_count));
+                    trove.update_command(&output);
+                    journal.record(&format!("Edited {label}"), &before, trove);
+                    app_state.selected_command = Some(output.clone());
+                    reload(
+                        &mut app_state,
+                        trove,
+                        &mut entries,
+                        &mut namespace_tabs,
+                        Some(&output.id),
+                    );
+                    app_state.update_string_to_edit();
                     // app_state.control = ControlState::Search;
                 } else if app_state.should_delete {
-                    trove.remove_command(&output.name).ok();
-                    app_state.commands = trove.commands.clone();
-                    app_state
-                        .commands
-                        .sort_by(|a, b| b.usage_count.cmp(&a.usage_count));
+                    let removed = if app_state.workflows.contains_key(&output.id) {
+                        trove.remove_workflow(&output.id).map(|_| ())
+                    } else {
+                        trove.remove_command_by_id(&output.id)
+                    };
+                    match removed {
+                        Ok(()) => {
+                            journal.record(&format!("Deleted {label}"), &before, trove);
+                            reload(
+                                &mut app_state,
+                                trove,
+                                &mut entries,
+                                &mut namespace_tabs,
+                                None,
+                            );
+                        }
+                        Err(e) => app_state.error_message = e.to_string(),
+                    }
                     app_state.should_delete = false;
                 } else {
-                    // Command has been selected
-                    // Update the command's meta info
-                    let _ = trove.update_command_meta(&output);
+                    // Command has been selected. Its usage is counted by the store of the trove
                     terminal.show_cursor()?;
                     return Ok(Some(output));
                 }
             }
 
+            if let Some((action, ids)) = app_state.bulk_action.take() {
+                let before = trove.clone();
+                match bulk::apply(trove, &action, &ids) {
+                    Ok(count) => {
+                        let description = action.describe(count);
+                        journal.record(&description, &before, trove);
+                        app_state.error_message = description;
+                        app_state.marked.clear();
+                        reload(
+                            &mut app_state,
+                            trove,
+                            &mut entries,
+                            &mut namespace_tabs,
+                            None,
+                        );
+                    }
+                    Err(e) => app_state.error_message = e.to_string(),
+                }
+            }
+            if app_state.should_undo || app_state.should_redo {
+                let redo = app_state.should_redo;
+                app_state.should_undo = false;
+                app_state.should_redo = false;
+                let stepped = if redo {
+                    journal.redo(trove)
+                } else {
+                    journal.undo(trove)
+                };
+                app_state.error_message = match stepped {
+                    Ok(Some(description)) => {
+                        reload(
+                            &mut app_state,
+                            trove,
+                            &mut entries,
+                            &mut namespace_tabs,
+                            None,
+                        );
+                        format!("{}: {description}", if redo { "Redone" } else { "Undone" })
+                    }
+                    Ok(None) => format!("Nothing to {}", if redo { "redo" } else { "undo" }),
+                    Err(e) => e.to_string(),
+                };
+            }
+
             if app_state.should_exit {
                 terminal.show_cursor()?;
                 return Ok(None);
@@ -267,3 +567,90 @@ pub fn run(trove: &mut Trove, config: &HoardConfig) -> Result<Option<HoardCmd>>
         }
     }
 }
+
+/// The namespace tabs of `trove`, after the tab listing all namespaces
+fn namespace_tabs(trove: &Trove) -> Vec<String> {
+    std::iter::once("All")
+        .chain(trove.namespaces())
+        .map(ToString::to_string)
+        .collect()
+}
+
+/// Lists the commands of `trove` again after it changed, keeping the selected namespace tab and the search
+///
+/// Selects the command with the id `selected`, on the tab of its namespace if the selected tab doesn't list it
+fn reload(
+    app_state: &mut State,
+    trove: &Trove,
+    entries: &mut Vec<HoardCmd>,
+    namespace_tabs: &mut Vec<String>,
+    selected: Option<&str>,
+) {
+    (*entries, app_state.workflows) = list_entries(trove);
+    app_state.relate(entries);
+    let selected_tab = app_state
+        .namespace_tab
+        .selected()
+        .and_then(|index| namespace_tabs.get(index))
+        .cloned();
+    *namespace_tabs = self::namespace_tabs(trove);
+    let tabs: Vec<&str> = namespace_tabs.iter().map(String::as_str).collect();
+    let position = |namespace: &str| tabs.iter().position(|tab| *tab == namespace);
+    let tab = selected_tab.and_then(|tab| position(&tab)).unwrap_or(0);
+    app_state.namespace_tab.select(Some(tab));
+    apply_filter(app_state, &tabs, entries);
+
+    let Some(id) = selected else {
+        return;
+    };
+    if !app_state.commands.iter().any(|c| c.id == id) {
+        if let Some(tab) = entries
+            .iter()
+            .find(|c| c.id == id)
+            .and_then(|c| position(&c.namespace))
+        {
+            app_state.namespace_tab.select(Some(tab));
+            apply_filter(app_state, &tabs, entries);
+        }
+    }
+    if let Some(index) = app_state.commands.iter().position(|c| c.id == id) {
+        app_state.command_list.select(Some(index));
+    }
+}
+
+/// Puts text pasted in one go into the input field of the current screen
+fn paste(app_state: &mut State, text: &str, entries: &[HoardCmd], namespace_tabs: &[&str]) {
+    match (&app_state.draw, &app_state.control) {
+        (DrawState::Search, ControlState::Search) => {
+            app_state.vimode = ViMode::Insert;
+            app_state.vi.paste(text, &mut app_state.input);
+            apply_filter(app_state, namespace_tabs, entries);
+        }
+        (DrawState::Search, ControlState::Edit) => {
+            app_state.vimode = ViMode::Insert;
+            app_state.vi.paste(text, &mut app_state.string_to_edit);
+        }
+        (DrawState::ParameterInput | DrawState::Create, _) => app_state.input.insert(text),
+        (DrawState::Help, _) => (),
+    }
+}
+
+/// Keeps the terminal in bracketed paste mode while the TUI runs, also if it is left with an error
+struct BracketedPaste;
+
+impl BracketedPaste {
+    fn enable() -> std::io::Result<Self> {
+        let mut stdout = stdout();
+        write!(stdout, "{ENABLE_BRACKETED_PASTE}")?;
+        stdout.flush()?;
+        Ok(Self)
+    }
+}
+
+impl Drop for BracketedPaste {
+    fn drop(&mut self) {
+        let mut stdout = stdout();
+        let _ = write!(stdout, "{DISABLE_BRACKETED_PASTE}");
+        let _ = stdout.flush();
+    }
+}
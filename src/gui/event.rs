@@ -2,11 +2,23 @@ use crossbeam_channel::unbounded;
 use std::thread;
 use std::time::Duration;
 
-use termion::event::Key;
+use termion::event::{Event as TermEvent, Key, MouseEvent};
 use termion::input::TermRead;
 
+/// Sequences a terminal in bracketed paste mode puts around pasted text
+const PASTE_START: &[u8] = b"\x1b[200~";
+const PASTE_END: &[u8] = b"\x1b[201~";
+
+/// Turns on bracketed paste mode, so pasted text is told apart from typed keys
+pub const ENABLE_BRACKETED_PASTE: &str = "\x1b[?2004h";
+pub const DISABLE_BRACKETED_PASTE: &str = "\x1b[?2004l";
+
 pub enum Event<I> {
     Input(I),
+    /// Text pasted in one go. Its line breaks don't submit anything
+    Paste(String),
+    /// A click or a turn of the scroll wheel, with the column and the line counted from 1
+    Mouse(MouseEvent),
     Tick,
 }
 
@@ -30,7 +42,6 @@ impl Default for Config {
 }
 
 impl Events {
-
     #[allow(clippy::manual_flatten)]
     pub fn with_config(config: Config) -> Self {
         let (tx, rx) = unbounded();
@@ -41,8 +52,25 @@ impl Events {
             thread::spawn(move || {
                 let tty = termion::get_tty().expect("Could not find tty session");
 
-                for key in tty.keys().flatten() {
-                    if let Err(err) = tx.send(Event::Input(key)) {
+                let mut pasted: Option<String> = None;
+                for event in tty.events().flatten() {
+                    let event = match (event, pasted.as_mut()) {
+                        (TermEvent::Unsupported(sequence), None) if sequence == PASTE_START => {
+                            pasted = Some(String::new());
+                            continue;
+                        }
+                        (TermEvent::Unsupported(sequence), Some(_)) if sequence == PASTE_END => {
+                            Event::Paste(pasted.take().unwrap_or_default())
+                        }
+                        (TermEvent::Key(Key::Char(c)), Some(text)) => {
+                            text.push(c);
+                            continue;
+                        }
+                        (TermEvent::Key(key), None) => Event::Input(key),
+                        (TermEvent::Mouse(mouse), None) => Event::Mouse(mouse),
+                        _ => continue,
+                    };
+                    if let Err(err) = tx.send(event) {
                         eprintln!("{err}");
                         return;
                     }
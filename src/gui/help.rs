@@ -1,82 +1,140 @@
-use crate::config::HoardConfig;
-use crate::core::HoardCmd;
 use crate::gui::commands_gui::{DrawState, State};
-use ratatui::backend::TermionBackend;
-use ratatui::style::{Color, Style};
+use crate::gui::keymap::{Keymap, Screen};
+use crate::gui::terminal::HoardTerminal;
+use crate::gui::theme::Theme;
+use hoardlib::core::HoardCmd;
+use ratatui::layout::{Constraint, Direction, Layout};
+use ratatui::style::Modifier;
 use ratatui::text::{Line, Span};
-use ratatui::widgets::{Block, BorderType, Borders, List, ListItem};
-use ratatui::Terminal;
+use ratatui::widgets::{Block, BorderType, Borders, Paragraph, Wrap};
 use termion::event::Key;
-use termion::screen::AlternateScreen;
 
-pub const HELP_KEY: &str = "<F1>";
-const HELP_CONTENT: &[(&str, &str)] = &[
-    ("Next item in command list", "<Ctrl-N> / <Down-Arrow>"),
+/// Keys of every text field, which the keymap doesn't change
+const TEXT_FIELD_CONTENT: &[(&str, &str)] = &[
+    ("Move", "<Left> <Right> <Home> <End> <Alt-B> <Alt-F>"),
     (
-        "Previous item in command list",
-        "<Ctrl-P> / <Ctrl-Y> / <Up-Arrow>",
+        "Delete",
+        "<Backspace> <Delete> <Ctrl-W> <Alt-D> <Ctrl-U> <Ctrl-K>",
+    ),
+    (
+        "Vi normal mode",
+        "w b e f t / d c y x r p / u <Ctrl-R> . / v",
     ),
-    ("Next namespace tab", "<Ctrl-L> / <Right-Arrow>"),
-    ("Previous namespace tab", "<Ctrl-H> / <Left-Arrow>"),
-    ("Select command", "<Enter>"),
-    ("Create new command", "<Ctrl-W>"),
-    ("Delete command", "<Ctrl-X>"),
-    ("Toggle search/edit mode", "<Tab> / <Ctrl-E>"),
-    ("Toggle Command to edit in edit mode", "<Tab>"),
-    ("Exit edit mode", "<Esc>"),
-    ("Quit", "<Ctrl-D> / <Ctrl-C> / <Ctrl-G>"),
-    ("Show help", HELP_KEY),
     ("Close help", "<Any key>"),
 ];
 
 pub fn draw(
-    config: &HoardConfig,
-    terminal: &mut Terminal<
-        TermionBackend<AlternateScreen<termion::raw::RawTerminal<std::io::Stdout>>>,
-    >,
+    theme: &Theme,
+    keymap: &Keymap,
+    terminal: &mut HoardTerminal,
 ) -> Result<(), eyre::Error> {
+    let primary = theme.text;
+    let command = theme.command;
+    // One line per action with the descriptions of a section lined up
+    let section = |title: &str, entries: &[(&str, String)]| {
+        let width = entries
+            .iter()
+            .map(|(description, _)| description.len())
+            .max()
+            .unwrap_or(0);
+        let mut lines = vec![Line::from(Span::styled(
+            title.to_string(),
+            primary.add_modifier(Modifier::BOLD),
+        ))];
+        lines.extend(entries.iter().map(|(description, keys)| {
+            Line::from(vec![
+                Span::styled(format!("  {description:<width$}  "), command),
+                Span::styled(keys.clone(), primary),
+            ])
+        }));
+        lines.push(Line::from(""));
+        lines
+    };
+    let text_field: Vec<_> = TEXT_FIELD_CONTENT
+        .iter()
+        .map(|(description, keys)| (*description, (*keys).to_string()))
+        .collect();
+
+    let mut sections: Vec<_> = Screen::ALL
+        .iter()
+        .map(|screen| section(screen.title(), &keymap.help(*screen)))
+        .collect();
+    sections.push(section("Text fields", &text_field));
+
     terminal.draw(|rect| {
         let help = Block::default()
             .borders(Borders::ALL)
-            .style(Style::default().fg(Color::Rgb(
-                config.primary_color.unwrap().0,
-                config.primary_color.unwrap().1,
-                config.primary_color.unwrap().2,
-            )))
+            .style(theme.border)
             .title(" Help ")
             .border_type(BorderType::Plain);
+        let inner = help.inner(rect.size());
+        rect.render_widget(help, rect.size());
 
-        let items: Vec<_> = HELP_CONTENT
-            .iter()
-            .map(|item| {
-                ListItem::new(vec![
-                    Line::from(Span::styled(
-                        item.0,
-                        Style::default().fg(Color::Rgb(
-                            config.command_color.unwrap().0,
-                            config.command_color.unwrap().1,
-                            config.command_color.unwrap().2,
-                        )),
-                    )),
-                    Line::from(Span::styled(
-                        format!("    {}", item.1),
-                        Style::default().fg(Color::Rgb(
-                            config.primary_color.unwrap().0,
-                            config.primary_color.unwrap().1,
-                            config.primary_color.unwrap().2,
-                        )),
-                    )),
-                    Line::from(""),
-                ])
-            })
-            .collect();
-
-        let list = List::new(items).block(help);
-        rect.render_widget(list, rect.size());
+        let columns = columns(&sections, inner.width, usize::from(inner.height));
+        let areas = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints(vec![
+                Constraint::Ratio(1, columns.len() as u32);
+                columns.len()
+            ])
+            .split(inner);
+        for (column, area) in columns.into_iter().zip(areas.iter()) {
+            rect.render_widget(Paragraph::new(column).wrap(Wrap { trim: false }), *area);
+        }
     })?;
     Ok(())
 }
 
+/// Columns narrower than this wrap most lines, a small terminal cuts off the last sections instead
+const MIN_COLUMN_WIDTH: u16 = 36;
+
+/// Puts `sections` into the fewest columns of `height` lines that fit into `width`, or into two balanced columns
+fn columns<'a>(sections: &[Vec<Line<'a>>], width: u16, height: usize) -> Vec<Vec<Line<'a>>> {
+    let mut count = 2;
+    loop {
+        let column_width = usize::from(width / count).max(1);
+        // Lines wider than a column wrap onto the next ones
+        let lines = |section: &Vec<Line>| -> usize {
+            section
+                .iter()
+                .map(|line| line.width().div_ceil(column_width).max(1))
+                .sum()
+        };
+        let total: usize = sections.iter().map(lines).sum();
+        let limit = if total > 2 * height {
+            height
+        } else {
+            total.div_ceil(2)
+        };
+        let mut columns = vec![(Vec::new(), 0)];
+        for section in sections {
+            let (column, used) = columns.last().expect("there is a column");
+            // The blank line after a section may fall off the bottom
+            if !column.is_empty() && used + lines(section) > limit + 1 {
+                columns.push((Vec::new(), 0));
+            }
+            let (column, used) = columns.last_mut().expect("there is a column");
+            column.extend(section.iter().cloned());
+            *used += lines(section);
+        }
+        if columns.len() <= usize::from(count) || width / (count + 1) < MIN_COLUMN_WIDTH {
+            let mut columns: Vec<_> = columns.into_iter().map(|(column, _)| column).collect();
+            // What doesn't fit goes below the last column
+            let overflow: Vec<_> = columns
+                .drain(columns.len().min(usize::from(count))..)
+                .flatten()
+                .collect();
+            columns
+                .last_mut()
+                .expect("there is a column")
+                .extend(overflow);
+            columns.resize(usize::from(count), Vec::new());
+            return columns;
+        }
+        count += 1;
+    }
+}
+
 pub fn key_handler(_input: Key, app: &mut State) -> Option<HoardCmd> {
     app.draw = DrawState::Search;
     None
@@ -0,0 +1,62 @@
+use crate::gui::theme::Theme;
+use hoardlib::config::HoardConfig;
+use hoardlib::core::highlight::{highlight, Kind};
+use ratatui::style::{Modifier, Style};
+use ratatui::text::{Line, Span};
+use std::ops::Range;
+
+/// Style of the parts of a command of the kind `kind`, on top of the style of the command
+fn style(kind: Kind, theme: &Theme) -> Style {
+    let parameter = theme.parameter;
+    match kind {
+        Kind::Plain => Style::default(),
+        Kind::Command => theme.program,
+        Kind::Flag => theme.flag,
+        Kind::String => theme.string,
+        Kind::Variable => theme.variable,
+        Kind::Operator => theme.operator,
+        Kind::Comment => theme.comment,
+        Kind::ParameterToken => parameter.add_modifier(Modifier::DIM),
+        Kind::ParameterType => parameter.add_modifier(Modifier::ITALIC),
+        Kind::ParameterName => parameter.add_modifier(Modifier::BOLD),
+        Kind::Filled => Style::default().add_modifier(Modifier::UNDERLINED),
+    }
+}
+
+/// `command` colored by its shell syntax and its parameters, one line for each of its lines
+///
+/// `filled` are the byte ranges of the values filled into its parameters so far.
+/// The parts of `command` in the ranges `next`, like the parameter asked for next, stand out
+pub fn command_lines(
+    command: &str,
+    config: &HoardConfig,
+    theme: &Theme,
+    filled: &[Range<usize>],
+    next: &[Range<usize>],
+) -> Vec<Line<'static>> {
+    let token = config.parameter_token.as_deref().unwrap_or_default();
+    let ending_token = config.parameter_ending_token.as_deref().unwrap_or_default();
+    let mut lines = vec![Line::default()];
+    for (kind, range) in highlight(command, token, ending_token, filled) {
+        let mut style = style(kind, theme);
+        if next
+            .iter()
+            .any(|n| n.start <= range.start && range.end <= n.end)
+        {
+            style = style.add_modifier(Modifier::REVERSED);
+        }
+        for (i, text) in command[range].split('\n').enumerate() {
+            if i > 0 {
+                lines.push(Line::default());
+            }
+            if !text.is_empty() {
+                lines
+                    .last_mut()
+                    .unwrap()
+                    .spans
+                    .push(Span::styled(text.to_string(), style));
+            }
+        }
+    }
+    lines
+}
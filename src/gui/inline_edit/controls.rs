@@ -1,30 +1,73 @@
-use crate::core::{string_to_tags, HoardCmd};
 use crate::gui::commands_gui::{ControlState, EditSelection, State, ViMode};
+use crate::gui::keymap::{Action, Dispatch, Screen};
 use crate::gui::list_search::controls::{next_index, previous_index, switch_namespace};
+use hoardlib::core::error::HoardError;
+use hoardlib::core::{string_to_tags, HoardCmd};
 use termion::event::Key;
 
-pub fn key_handler(input: Key, state: &mut State, trove_commands: &[HoardCmd], namespace_tabs: &[&str] ) -> Option<HoardCmd> {
-    match (&state.vimode, input) {
-        (ViMode::Insert, Key::Esc) => {
-            state.vimode = ViMode::Normal;
+pub fn key_handler(
+    input: Key,
+    state: &mut State,
+    trove_commands: &[HoardCmd],
+    namespace_tabs: &[&str],
+) -> Option<HoardCmd> {
+    state.error_message.clear();
+    if let Some(selected) = state.namespace_picker {
+        // The first tab lists all namespaces
+        let namespaces = namespace_tabs.get(1..).unwrap_or_default();
+        return pick_namespace(input, selected, state, namespaces, trove_commands);
+    }
+    // Vi keys edit the field first, the keys vi doesn't know are looked up in the keymap of the edited field
+    if matches!(state.vimode, ViMode::Edit | ViMode::Visual) {
+        if let Some(mode) = state
+            .vi
+            .handle_key(&state.vimode, input, &mut state.string_to_edit)
+        {
+            state.vimode = mode;
+            return None;
+        }
+    }
+    let screen = if state.vimode == ViMode::Normal {
+        Screen::Edit
+    } else {
+        Screen::EditInsert
+    };
+    let action = match state.keymap.dispatch(screen, input) {
+        Dispatch::Action(action) => action,
+        Dispatch::Pending => return None,
+        Dispatch::Unbound(keys) => {
+            if state.vimode == ViMode::Insert {
+                for key in keys {
+                    state.vi.insert_key(key, &mut state.string_to_edit);
+                }
+            }
+            return None;
+        }
+    };
+    match action {
+        Action::NormalMode => {
+            if state.vimode == ViMode::Insert {
+                state.vi.leave_insert(&mut state.string_to_edit);
+                state.vimode = ViMode::Edit;
+            } else {
+                state.vimode = ViMode::Normal;
+            }
             None
         }
 
-        (ViMode::Normal, Key::Char('i')) => {
-            state.vimode = ViMode::Insert;
+        Action::InsertMode => {
+            start_insert(state, namespace_tabs);
             None
         }
 
-        // Go one namespace to the left.
-        (ViMode::Normal, Key::Char('d')) => {
+        Action::PreviousNamespace => {
             if let Some(selected) = state.namespace_tab.selected() {
                 let new_selected_tab = previous_index(selected, namespace_tabs.len());
                 switch_namespace(state, new_selected_tab, namespace_tabs, trove_commands);
             }
             None
         }
-        // Go one namespace the right
-        (ViMode::Normal, Key::Char('u')) => {
+        Action::NextNamespace => {
             if let Some(selected) = state.namespace_tab.selected() {
                 let new_selected_tab = next_index(selected, namespace_tabs.len());
                 switch_namespace(state, new_selected_tab, namespace_tabs, trove_commands);
@@ -33,53 +76,276 @@ pub fn key_handler(input: Key, state: &mut State, trove_commands: &[HoardCmd], n
         }
 
         // Only exit the edit mode
-        (ViMode::Normal, Key::Char('h')) => {
+        Action::Back => {
             state.control = ControlState::Search;
             None
         }
 
-        (ViMode::Insert, Key::Char('\n')) => {
-            state.vimode = ViMode::Normal;
-
+        Action::Save => {
             let mut edited_command = state.selected_command.clone().unwrap();
-            let new_string = state.string_to_edit.clone();
-
-            match state.edit_selection {
-                EditSelection::Description => edited_command.description = new_string,
-                EditSelection::Command => edited_command.command = new_string,
-                EditSelection::Tags => edited_command.tags = string_to_tags(&new_string),
-                EditSelection::Name | EditSelection::Namespace => (),
-            };
+            let new_string = state.string_to_edit.value().to_string();
+            // An invalid value stays in the field to be fixed
+            if let Err(e) = set_field(
+                &mut edited_command,
+                &state.edit_selection,
+                new_string,
+                trove_commands,
+            ) {
+                state.error_message = e.to_string();
+                return None;
+            }
 
+            if state.vimode == ViMode::Insert {
+                state.vi.leave_insert(&mut state.string_to_edit);
+            }
+            state.vimode = ViMode::Normal;
             Some(edited_command)
         }
-        // (ViMode::Normal, Key::Char('\n')) => {
-        //     state.vimode = ViMode::Insert;
-        //     None
-        // },
-        (ViMode::Normal, Key::Char('j')) => {
+        Action::NextField => {
             state.edit_selection = state.edit_selection.next();
             state.update_string_to_edit();
             None
         }
-        (ViMode::Normal, Key::Char('k')) => {
+        Action::PreviousField => {
             state.edit_selection = state.edit_selection.prev();
             state.update_string_to_edit();
             None
         }
-        (ViMode::Normal, Key::Ctrl('c' | 'd' | 'g') | Key::Char('q')) => {
+        Action::Quit => {
             // Definitely exit program
             state.should_exit = true;
             None
         }
-        (ViMode::Insert, Key::Backspace) => {
-            state.string_to_edit.pop();
-            None
+        // Actions of the other screens
+        _ => None,
+    }
+}
+
+/// Starts to edit the selected field. The namespace is picked from the existing ones in `namespace_tabs`
+pub fn start_insert(state: &mut State, namespace_tabs: &[&str]) {
+    if state.edit_selection == EditSelection::Namespace {
+        let namespaces = namespace_tabs.get(1..).unwrap_or_default();
+        let current = state.string_to_edit.value();
+        let selected = namespaces
+            .iter()
+            .position(|namespace| *namespace == current);
+        state.namespace_picker = Some(selected.unwrap_or(namespaces.len()));
+    } else {
+        state.vimode = ViMode::Insert;
+    }
+}
+
+/// An entry picked in the namespace picker
+pub enum Picked {
+    Namespace(String),
+    /// The entry for a namespace which doesn't exist yet
+    NewNamespace,
+}
+
+/// Handles `input` in the namespace picker with the entry `selected` selected, which lists `namespaces`
+/// and an entry for a new namespace after them
+pub fn namespace_picker_key(
+    input: Key,
+    selected: usize,
+    state: &mut State,
+    namespaces: &[&str],
+) -> Option<Picked> {
+    let Dispatch::Action(action) = state.keymap.dispatch(Screen::NamespacePicker, input) else {
+        return None;
+    };
+    let entries = namespaces.len() + 1;
+    match action {
+        Action::NextOption => state.namespace_picker = Some(next_index(selected, entries)),
+        Action::PreviousOption => state.namespace_picker = Some(previous_index(selected, entries)),
+        Action::Cancel => state.namespace_picker = None,
+        Action::Quit => state.should_exit = true,
+        Action::Submit => {
+            state.namespace_picker = None;
+            return Some(
+                namespaces
+                    .get(selected)
+                    .map_or(Picked::NewNamespace, |namespace| {
+                        Picked::Namespace((*namespace).to_string())
+                    }),
+            );
         }
-        (ViMode::Insert, Key::Char(c)) => {
-            state.string_to_edit.push(c);
+        // Actions of the other screens
+        _ => (),
+    }
+    None
+}
+
+/// Moves the edited command to the namespace picked with `input`. A new namespace is typed into the namespace field
+fn pick_namespace(
+    input: Key,
+    selected: usize,
+    state: &mut State,
+    namespaces: &[&str],
+    trove_commands: &[HoardCmd],
+) -> Option<HoardCmd> {
+    match namespace_picker_key(input, selected, state, namespaces)? {
+        Picked::NewNamespace => {
+            state.string_to_edit.set_value("");
+            state.vimode = ViMode::Insert;
             None
         }
-        _ => None,
+        Picked::Namespace(namespace) => {
+            let mut edited_command = state.selected_command.clone().unwrap();
+            if let Err(e) = set_field(
+                &mut edited_command,
+                &EditSelection::Namespace,
+                namespace,
+                trove_commands,
+            ) {
+                state.error_message = e.to_string();
+                return None;
+            }
+            Some(edited_command)
+        }
+    }
+}
+
+/// Sets the field `selection` of `command` to `value`
+///
+/// Fails if the value is invalid or another of `commands` has the same name in the same namespace afterwards
+fn set_field(
+    command: &mut HoardCmd,
+    selection: &EditSelection,
+    value: String,
+    commands: &[HoardCmd],
+) -> Result<(), HoardError> {
+    match selection {
+        EditSelection::Description => command.description = value,
+        EditSelection::Command => {
+            HoardCmd::is_command_valid(&value)?;
+            command.command = value;
+        }
+        EditSelection::Tags => command.tags = string_to_tags(&value),
+        EditSelection::Name => {
+            HoardCmd::is_name_valid(&value)?;
+            command.name = value;
+        }
+        EditSelection::Namespace => {
+            HoardCmd::is_namespace_valid(&value)?;
+            command.namespace = value;
+        }
+    };
+    match commands
+        .iter()
+        .find(|c| c.id != command.id && c.namespace == command.namespace && c.name == command.name)
+    {
+        Some(other) => Err(HoardError::Validation(format!(
+            "There already is a command {}/{}",
+            other.namespace, other.name
+        ))),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod test_controls {
+    use super::*;
+    use crate::gui::commands_gui::ViMode;
+
+    const NAMESPACES: [&str; 3] = ["All", "docker", "k8s"];
+
+    fn create_command(name: &str, namespace: &str) -> HoardCmd {
+        HoardCmd::default()
+            .with_name(name)
+            .with_command("echo")
+            .with_namespace(namespace)
+    }
+
+    fn create_state(commands: Vec<HoardCmd>, selection: EditSelection) -> State {
+        let mut state = State {
+            selected_command: commands.first().cloned(),
+            control: ControlState::Edit,
+            edit_selection: selection,
+            ..State::for_test(commands)
+        };
+        state.update_string_to_edit();
+        state
+    }
+
+    fn press(state: &mut State, keys: &str) -> Option<HoardCmd> {
+        let commands = state.commands.clone();
+        let mut edited = None;
+        for key in keys.chars() {
+            edited = key_handler(Key::Char(key), state, &commands, &NAMESPACES);
+        }
+        edited
+    }
+
+    #[test]
+    fn visit_every_field() {
+        let mut state = create_state(vec![create_command("pods", "k8s")], EditSelection::Command);
+        press(&mut state, "j");
+        assert_eq!(EditSelection::Name, state.edit_selection);
+        assert_eq!("pods", state.string_to_edit.value());
+        press(&mut state, "j");
+        assert_eq!(EditSelection::Namespace, state.edit_selection);
+        assert_eq!("k8s", state.string_to_edit.value());
+        press(&mut state, "kk");
+        assert_eq!(EditSelection::Command, state.edit_selection);
+    }
+
+    #[test]
+    fn rename_a_command() {
+        let commands = vec![
+            create_command("pods", "k8s"),
+            create_command("nodes", "k8s"),
+        ];
+        let mut state = create_state(commands, EditSelection::Name);
+
+        assert!(press(&mut state, "iA x\n").is_none());
+        assert!(state.error_message.contains("whitespaces"));
+        assert_eq!(ViMode::Insert, state.vimode);
+
+        state.string_to_edit.set_value("nodes");
+        assert!(press(&mut state, "\n").is_none());
+        assert!(state.error_message.contains("k8s/nodes"));
+
+        state.string_to_edit.set_value("po");
+        let edited = press(&mut state, "\n").unwrap();
+        assert_eq!("po", edited.name);
+        assert_eq!(state.commands[0].id, edited.id);
+        assert_eq!(ViMode::Normal, state.vimode);
+    }
+
+    #[test]
+    fn move_a_command_to_another_namespace() {
+        let commands = vec![
+            create_command("pods", "k8s"),
+            create_command("pods", "docker"),
+        ];
+        let mut state = create_state(commands, EditSelection::Namespace);
+
+        press(&mut state, "i");
+        assert_eq!(Some(1), state.namespace_picker);
+        assert!(press(&mut state, "k\n").is_none());
+        assert!(state.error_message.contains("docker/pods"));
+
+        press(&mut state, "ij");
+        assert_eq!(Some(2), state.namespace_picker);
+        assert!(press(&mut state, "\n").is_none());
+        assert_eq!(ViMode::Insert, state.vimode);
+        assert_eq!("", state.string_to_edit.value());
+
+        let edited = press(&mut state, "kube\n").unwrap();
+        assert_eq!("kube", edited.namespace);
+        assert_eq!("pods", edited.name);
+    }
+
+    #[test]
+    fn cancel_the_namespace_picker() {
+        let mut state = create_state(
+            vec![create_command("pods", "k8s")],
+            EditSelection::Namespace,
+        );
+        press(&mut state, "ij");
+        key_handler(Key::Esc, &mut state, &[], &NAMESPACES);
+        assert_eq!(None, state.namespace_picker);
+        assert_eq!(ViMode::Normal, state.vimode);
+        assert_eq!(EditSelection::Namespace, state.edit_selection);
     }
 }
@@ -0,0 +1,703 @@
+use hoardlib::config::HoardConfig;
+use hoardlib::core::error::HoardError;
+use std::collections::HashMap;
+use termion::event::Key;
+
+/// Keys set in the `keymap` of the config, by screen and action name
+pub type KeymapConfig = HashMap<String, HashMap<String, Vec<String>>>;
+
+/// The actions of a screen with their key sequences
+type Bindings = Vec<(Action, Vec<Vec<Key>>)>;
+
+/// A screen or mode of `hoard list` with keys of its own
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Screen {
+    /// The command list in normal mode
+    List,
+    /// The search field in insert mode
+    Search,
+    /// The fields of the selected command in normal mode
+    Edit,
+    /// A field of the selected command in insert mode
+    EditInsert,
+    /// The list of namespaces to move the selected command to
+    NamespacePicker,
+    /// The input of a bulk action, like the tags to add to the marked commands
+    Prompt,
+    /// The input of the parameters of the selected command
+    Parameters,
+    /// The input of a new command
+    Create,
+}
+
+impl Screen {
+    pub const ALL: [Self; 8] = [
+        Self::List,
+        Self::Search,
+        Self::Edit,
+        Self::EditInsert,
+        Self::NamespacePicker,
+        Self::Prompt,
+        Self::Parameters,
+        Self::Create,
+    ];
+
+    /// Name of the screen in the `keymap` of the config
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::List => "list",
+            Self::Search => "search",
+            Self::Edit => "edit",
+            Self::EditInsert => "edit_insert",
+            Self::NamespacePicker => "namespace_picker",
+            Self::Prompt => "prompt",
+            Self::Parameters => "parameters",
+            Self::Create => "create",
+        }
+    }
+
+    /// Title of the screen on the help screen
+    pub const fn title(self) -> &'static str {
+        match self {
+            Self::List => "Command list",
+            Self::Search => "Search field",
+            Self::Edit => "Editing a command",
+            Self::EditInsert => "Editing a field",
+            Self::NamespacePicker => "Picking a namespace",
+            Self::Prompt => "Input of a bulk action",
+            Self::Parameters => "Parameter input",
+            Self::Create => "New command",
+        }
+    }
+
+    /// The actions of the screen with their default keys, in the order of the help screen
+    const fn defaults(self) -> &'static [(Action, &'static [&'static str])] {
+        match self {
+            Self::List => &[
+                (Action::NextCommand, &["j", "<Down>", "<Ctrl-N>"]),
+                (
+                    Action::PreviousCommand,
+                    &["k", "<Up>", "<Ctrl-P>", "<Ctrl-Y>"],
+                ),
+                (Action::NextNamespace, &["<Right>", "<Ctrl-L>", "<Tab>"]),
+                (
+                    Action::PreviousNamespace,
+                    &["d", "<Left>", "<Ctrl-H>", "<BackTab>"],
+                ),
+                (Action::Select, &["<Enter>"]),
+                (Action::InsertMode, &["i"]),
+                (Action::EditCommand, &["l"]),
+                (Action::NewCommand, &["a"]),
+                (Action::YankCommand, &["y"]),
+                (Action::DeleteCommand, &["<Alt-D>"]),
+                (Action::ToggleMark, &["<Space>"]),
+                (Action::MarkRange, &["V"]),
+                (Action::ClearMarks, &["U"]),
+                (Action::MoveMarked, &["m"]),
+                (Action::AddTags, &["+"]),
+                (Action::RemoveTags, &["-"]),
+                (Action::ToggleFavorite, &["f"]),
+                (Action::ToggleHidden, &["z"]),
+                (Action::ExportMarked, &["e"]),
+                (Action::CopyScript, &["Y"]),
+                (Action::Undo, &["u", "<Ctrl-Z>"]),
+                (Action::Redo, &["<Ctrl-R>"]),
+                (Action::Help, &["?", "<F1>"]),
+                (
+                    Action::Quit,
+                    &["q", "<Esc>", "<Ctrl-C>", "<Ctrl-D>", "<Ctrl-G>"],
+                ),
+            ],
+            Self::Search => &[
+                (Action::NextCommand, &["<Down>", "<Ctrl-N>"]),
+                (Action::PreviousCommand, &["<Up>", "<Ctrl-P>"]),
+                (Action::NextNamespace, &["<Tab>"]),
+                (Action::PreviousNamespace, &["<BackTab>"]),
+                (Action::Select, &["<Enter>"]),
+                (Action::NormalMode, &["<Esc>"]),
+                (Action::Help, &["<F1>"]),
+                (Action::Quit, &["<Ctrl-C>", "<Ctrl-D>", "<Ctrl-G>"]),
+            ],
+            Self::Edit => &[
+                (Action::NextField, &["j", "<Down>", "<Tab>"]),
+                (Action::PreviousField, &["k", "<Up>", "<BackTab>"]),
+                (Action::InsertMode, &["i"]),
+                (Action::NextNamespace, &["u"]),
+                (Action::PreviousNamespace, &["d"]),
+                (Action::Back, &["h", "<Left>", "<Esc>"]),
+                (Action::Quit, &["q", "<Ctrl-C>", "<Ctrl-D>", "<Ctrl-G>"]),
+            ],
+            Self::EditInsert => &[
+                (Action::Save, &["<Enter>"]),
+                (Action::NormalMode, &["<Esc>"]),
+            ],
+            Self::NamespacePicker => &[
+                (Action::NextOption, &["j", "<Down>", "<Ctrl-N>", "<Tab>"]),
+                (
+                    Action::PreviousOption,
+                    &["k", "<Up>", "<Ctrl-P>", "<BackTab>"],
+                ),
+                (Action::Submit, &["<Enter>"]),
+                (Action::Cancel, &["h", "<Esc>"]),
+                (Action::Quit, &["q", "<Ctrl-C>", "<Ctrl-D>", "<Ctrl-G>"]),
+            ],
+            Self::Prompt => &[
+                (Action::Submit, &["<Enter>"]),
+                (Action::Cancel, &["<Esc>"]),
+                (Action::Quit, &["<Ctrl-C>", "<Ctrl-D>", "<Ctrl-G>"]),
+            ],
+            Self::Parameters => &[
+                (Action::Submit, &["<Enter>"]),
+                (Action::Quit, &["<Esc>", "<Ctrl-C>", "<Ctrl-D>", "<Ctrl-G>"]),
+            ],
+            Self::Create => &[
+                (Action::Submit, &["<Enter>"]),
+                (Action::Cancel, &["<Esc>"]),
+                (Action::Quit, &["<Ctrl-C>", "<Ctrl-D>", "<Ctrl-G>"]),
+            ],
+        }
+    }
+}
+
+/// Something a key of the keymap does
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    NextCommand,
+    PreviousCommand,
+    NextNamespace,
+    PreviousNamespace,
+    Select,
+    InsertMode,
+    NormalMode,
+    EditCommand,
+    NewCommand,
+    YankCommand,
+    DeleteCommand,
+    ToggleMark,
+    MarkRange,
+    ClearMarks,
+    MoveMarked,
+    AddTags,
+    RemoveTags,
+    ToggleFavorite,
+    ToggleHidden,
+    ExportMarked,
+    CopyScript,
+    Undo,
+    Redo,
+    Help,
+    Quit,
+    NextField,
+    PreviousField,
+    NextOption,
+    PreviousOption,
+    Save,
+    Back,
+    Submit,
+    Cancel,
+}
+
+impl Action {
+    /// Name of the action in the `keymap` of the config
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::NextCommand => "next_command",
+            Self::PreviousCommand => "previous_command",
+            Self::NextNamespace => "next_namespace",
+            Self::PreviousNamespace => "previous_namespace",
+            Self::Select => "select",
+            Self::InsertMode => "insert_mode",
+            Self::NormalMode => "normal_mode",
+            Self::EditCommand => "edit_command",
+            Self::NewCommand => "new_command",
+            Self::YankCommand => "yank_command",
+            Self::DeleteCommand => "delete_command",
+            Self::ToggleMark => "toggle_mark",
+            Self::MarkRange => "mark_range",
+            Self::ClearMarks => "clear_marks",
+            Self::MoveMarked => "move_marked",
+            Self::AddTags => "add_tags",
+            Self::RemoveTags => "remove_tags",
+            Self::ToggleFavorite => "toggle_favorite",
+            Self::ToggleHidden => "toggle_hidden",
+            Self::ExportMarked => "export_marked",
+            Self::CopyScript => "copy_script",
+            Self::Undo => "undo",
+            Self::Redo => "redo",
+            Self::Help => "help",
+            Self::Quit => "quit",
+            Self::NextField => "next_field",
+            Self::PreviousField => "previous_field",
+            Self::NextOption => "next_option",
+            Self::PreviousOption => "previous_option",
+            Self::Save => "save",
+            Self::Back => "back",
+            Self::Submit => "submit",
+            Self::Cancel => "cancel",
+        }
+    }
+
+    /// What the action does, for the help screen
+    pub const fn description(self) -> &'static str {
+        match self {
+            Self::NextCommand => "Next command",
+            Self::PreviousCommand => "Previous command",
+            Self::NextNamespace => "Next namespace tab",
+            Self::PreviousNamespace => "Previous namespace tab",
+            Self::Select => "Select the command",
+            Self::InsertMode => "Insert mode",
+            Self::NormalMode => "Leave insert mode",
+            Self::EditCommand => "Edit the command",
+            Self::NewCommand => "Create a new command",
+            Self::YankCommand => "Yank the command",
+            Self::DeleteCommand => "Delete the command",
+            Self::ToggleMark => "Mark",
+            Self::MarkRange => "Mark a range",
+            Self::ClearMarks => "Unmark all",
+            Self::MoveMarked => "Move namespace",
+            Self::AddTags => "Add tags",
+            Self::RemoveTags => "Remove tags",
+            Self::ToggleFavorite => "Toggle favorite",
+            Self::ToggleHidden => "Toggle hidden",
+            Self::ExportMarked => "Export to a file",
+            Self::CopyScript => "Copy as script",
+            Self::Undo => "Undo a change",
+            Self::Redo => "Redo a change",
+            Self::Help => "Show help",
+            Self::Quit => "Quit",
+            Self::NextField => "Next field",
+            Self::PreviousField => "Previous field",
+            Self::NextOption => "Next option",
+            Self::PreviousOption => "Previous option",
+            Self::Save => "Save the field",
+            Self::Back => "Back to the list",
+            Self::Submit => "Confirm the input",
+            Self::Cancel => "Cancel",
+        }
+    }
+
+    /// Short name of the action, for the hints below the list
+    const fn label(self) -> &'static str {
+        match self {
+            Self::NextCommand | Self::NextField | Self::NextOption => "Down",
+            Self::PreviousCommand | Self::PreviousField | Self::PreviousOption => "Up",
+            Self::NextNamespace => "Cycle Right",
+            Self::PreviousNamespace => "Cycle Left",
+            Self::Select => "Select",
+            Self::InsertMode => "Insert",
+            Self::NormalMode => "Normal",
+            Self::EditCommand => "Edit",
+            Self::NewCommand => "Add",
+            Self::YankCommand => "Yank",
+            Self::DeleteCommand => "Delete",
+            Self::ToggleMark => "Mark",
+            Self::MarkRange => "Mark range",
+            Self::ClearMarks => "Unmark",
+            Self::MoveMarked => "Move",
+            Self::AddTags => "Tag",
+            Self::RemoveTags => "Untag",
+            Self::ToggleFavorite => "Favorite",
+            Self::ToggleHidden => "Hide",
+            Self::ExportMarked => "Export",
+            Self::CopyScript => "Copy script",
+            Self::Undo => "Undo",
+            Self::Redo => "Redo",
+            Self::Help => "Help",
+            Self::Quit => "Quit",
+            Self::Save => "Save",
+            Self::Back => "Back",
+            Self::Submit => "Confirm",
+            Self::Cancel => "Cancel",
+        }
+    }
+}
+
+/// What a key pressed on a screen does
+#[derive(Debug, PartialEq, Eq)]
+pub enum Dispatch {
+    /// The key completes the keys of an action
+    Action(Action),
+    /// The key starts the keys of an action, which needs more of them
+    Pending,
+    /// No action has these keys. They are the key and the keys typed before it, e.g. to insert them as text
+    Unbound(Vec<Key>),
+}
+
+/// The keys of the actions of every screen of `hoard list`
+///
+/// Every key handler looks up the action of a key here, and the help screen lists the keys of the actions.
+/// Actions can have several keys, and a key can be a sequence like `gg` or `<Space>n`
+#[derive(Debug, Clone)]
+pub struct Keymap {
+    // Actions of every screen with their key sequences, in the order of the help screen
+    bindings: HashMap<Screen, Bindings>,
+    // Keys typed so far of a sequence of several keys
+    pending: Vec<Key>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Self::new(&KeymapConfig::new()).expect("the default keys are valid")
+    }
+}
+
+impl Keymap {
+    /// The default keymap with the keys of the `keymap` of `config`
+    pub fn from_config(config: &HoardConfig) -> Result<Self, HoardError> {
+        config
+            .keymap
+            .as_ref()
+            .map_or_else(|| Ok(Self::default()), Self::new)
+    }
+
+    /// The default keymap with the keys of `keys`
+    ///
+    /// Keys of `keys` replace the default keys of their action, and are taken from the default keys
+    /// of the other actions of the screen. Unknown screens, actions or keys and keys of `keys`
+    /// which collide with each other are an error
+    pub fn new(keys: &KeymapConfig) -> Result<Self, HoardError> {
+        if let Some(unknown) = keys
+            .keys()
+            .find(|name| !Screen::ALL.iter().any(|s| s.name() == *name))
+        {
+            let screens: Vec<_> = Screen::ALL.iter().map(|s| s.name()).collect();
+            return Err(HoardError::Config(format!(
+                "Unknown screen `{unknown}` in the keymap, use one of {}",
+                screens.join(", ")
+            )));
+        }
+        let mut bindings = HashMap::new();
+        for screen in Screen::ALL {
+            let configured = keys.get(screen.name());
+            if let Some(unknown) = configured.into_iter().flat_map(HashMap::keys).find(|name| {
+                !screen
+                    .defaults()
+                    .iter()
+                    .any(|(action, _)| action.name() == *name)
+            }) {
+                let actions: Vec<_> = screen
+                    .defaults()
+                    .iter()
+                    .map(|(action, _)| action.name())
+                    .collect();
+                return Err(HoardError::Config(format!(
+                    "Unknown action `{unknown}` in the keymap of `{}`, use one of {}",
+                    screen.name(),
+                    actions.join(", ")
+                )));
+            }
+            bindings.insert(screen, screen_bindings(screen, configured)?);
+        }
+        Ok(Self {
+            bindings,
+            pending: Vec::new(),
+        })
+    }
+
+    /// Looks up the action `key` completes on `screen`, together with the keys pressed before it
+    pub fn dispatch(&mut self, screen: Screen, key: Key) -> Dispatch {
+        self.pending.push(key);
+        let mut is_prefix = false;
+        for (action, sequences) in &self.bindings[&screen] {
+            for sequence in sequences {
+                if *sequence == self.pending {
+                    self.pending.clear();
+                    return Dispatch::Action(*action);
+                }
+                is_prefix |= sequence.starts_with(&self.pending);
+            }
+        }
+        if is_prefix {
+            return Dispatch::Pending;
+        }
+        Dispatch::Unbound(std::mem::take(&mut self.pending))
+    }
+
+    /// The keys of `action` on `screen`, e.g. `<Ctrl-N>` or `gg`
+    pub fn keys(&self, screen: Screen, action: Action) -> Vec<String> {
+        self.bindings[&screen]
+            .iter()
+            .filter(|(a, _)| *a == action)
+            .flat_map(|(_, sequences)| sequences)
+            .map(|sequence| sequence.iter().map(|key| key_name(*key)).collect())
+            .collect()
+    }
+
+    /// The actions of `screen` with their keys, for the help screen
+    pub fn help(&self, screen: Screen) -> Vec<(&'static str, String)> {
+        self.bindings[&screen]
+            .iter()
+            .map(|(action, _)| (action.description(), self.keys(screen, *action).join(" ")))
+            .filter(|(_, keys)| !keys.is_empty())
+            .collect()
+    }
+
+    /// The first key of each of `actions` on `screen`, like `k (Up), j (Down)`
+    pub fn hint(&self, screen: Screen, actions: &[Action]) -> String {
+        actions
+            .iter()
+            .filter_map(|action| {
+                let keys = self.keys(screen, *action);
+                keys.first()
+                    .map(|key| format!("{key} ({})", action.label()))
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+/// The actions of `screen` with the keys `configured` for some of them and the default keys of the others
+fn screen_bindings(
+    screen: Screen,
+    configured: Option<&HashMap<String, Vec<String>>>,
+) -> Result<Bindings, HoardError> {
+    let parse = |keys: &str| {
+        parse_keys(keys)
+            .map_err(|e| HoardError::Config(format!("{e} in the keymap of `{}`", screen.name())))
+    };
+    let mut bindings = Vec::new();
+    let mut custom: Vec<(Action, Vec<Key>)> = Vec::new();
+    for (action, defaults) in screen.defaults() {
+        let sequences = match configured.and_then(|keys| keys.get(action.name())) {
+            Some(keys) => {
+                let sequences = keys
+                    .iter()
+                    .map(|keys| parse(keys))
+                    .collect::<Result<Vec<_>, _>>()?;
+                for sequence in &sequences {
+                    if let Some((other, _)) = custom
+                        .iter()
+                        .find(|(other, keys)| other != action && collide(keys, sequence))
+                    {
+                        return Err(HoardError::Config(format!(
+                            "`{}` of `{}` collides with a key of `{}` in the keymap of `{}`",
+                            sequence
+                                .iter()
+                                .map(|key| key_name(*key))
+                                .collect::<String>(),
+                            action.name(),
+                            other.name(),
+                            screen.name()
+                        )));
+                    }
+                    custom.push((*action, sequence.clone()));
+                }
+                sequences
+            }
+            None => defaults
+                .iter()
+                .map(|keys| parse(keys))
+                .collect::<Result<Vec<_>, _>>()?,
+        };
+        bindings.push((*action, sequences));
+    }
+    // Configured keys win over the default keys of other actions
+    for (action, sequences) in &mut bindings {
+        sequences.retain(|sequence| {
+            custom
+                .iter()
+                .all(|(other, keys)| other == action || !collide(keys, sequence))
+        });
+    }
+    Ok(bindings)
+}
+
+/// Whether one of the key sequences starts with the other, so one of them can never be typed
+fn collide(a: &[Key], b: &[Key]) -> bool {
+    a.starts_with(b) || b.starts_with(a)
+}
+
+/// Parses keys written like in the help screen, e.g. `q`, `<Ctrl-N>`, `<Alt-D>`, `<F1>` or `<Space>n`
+///
+/// Every character outside of `<…>` is a key of its own, `<lt>` is `<`
+pub fn parse_keys(keys: &str) -> Result<Vec<Key>, String> {
+    let mut parsed = Vec::new();
+    let mut rest = keys;
+    while let Some(c) = rest.chars().next() {
+        if let Some((name, after)) = rest.strip_prefix('<').and_then(|r| r.split_once('>')) {
+            parsed.push(parse_key_name(name).ok_or_else(|| format!("Unknown key `<{name}>`"))?);
+            rest = after;
+        } else {
+            parsed.push(Key::Char(c));
+            rest = &rest[c.len_utf8()..];
+        }
+    }
+    if parsed.is_empty() {
+        return Err("Empty key".to_string());
+    }
+    Ok(parsed)
+}
+
+fn parse_key_name(name: &str) -> Option<Key> {
+    let lowercase = name.to_ascii_lowercase();
+    let single = |prefix_length: usize| {
+        let mut chars = name[prefix_length..].chars();
+        chars.next().filter(|_| chars.next().is_none())
+    };
+    if let Some(prefix) = ["ctrl-", "c-"].iter().find(|p| lowercase.starts_with(*p)) {
+        return single(prefix.len()).map(|c| Key::Ctrl(c.to_ascii_lowercase()));
+    }
+    if let Some(prefix) = ["alt-", "a-", "m-"]
+        .iter()
+        .find(|p| lowercase.starts_with(*p))
+    {
+        return single(prefix.len()).map(Key::Alt);
+    }
+    if let Some(number) = lowercase.strip_prefix('f').and_then(|n| n.parse().ok()) {
+        return (1..=12).contains(&number).then_some(Key::F(number));
+    }
+    Some(match lowercase.as_str() {
+        "enter" | "return" | "cr" => Key::Char('\n'),
+        "tab" => Key::Char('\t'),
+        "backtab" | "s-tab" => Key::BackTab,
+        "esc" | "escape" => Key::Esc,
+        "space" => Key::Char(' '),
+        "lt" => Key::Char('<'),
+        "backspace" | "bs" => Key::Backspace,
+        "delete" | "del" => Key::Delete,
+        "insert" => Key::Insert,
+        "home" => Key::Home,
+        "end" => Key::End,
+        "pageup" => Key::PageUp,
+        "pagedown" => Key::PageDown,
+        "up" => Key::Up,
+        "down" => Key::Down,
+        "left" => Key::Left,
+        "right" => Key::Right,
+        _ => return None,
+    })
+}
+
+/// How `key` is written in the keymap and on the help screen
+pub fn key_name(key: Key) -> String {
+    match key {
+        Key::Char('\n') => "<Enter>".to_string(),
+        Key::Char('\t') => "<Tab>".to_string(),
+        Key::Char(' ') => "<Space>".to_string(),
+        Key::Char('<') => "<lt>".to_string(),
+        Key::Char(c) => c.to_string(),
+        Key::Ctrl(c) => format!("<Ctrl-{}>", c.to_ascii_uppercase()),
+        Key::Alt(c) => format!("<Alt-{c}>"),
+        Key::F(number) => format!("<F{number}>"),
+        Key::BackTab => "<BackTab>".to_string(),
+        Key::Esc => "<Esc>".to_string(),
+        Key::Backspace => "<Backspace>".to_string(),
+        Key::Delete => "<Delete>".to_string(),
+        Key::Insert => "<Insert>".to_string(),
+        Key::Home => "<Home>".to_string(),
+        Key::End => "<End>".to_string(),
+        Key::PageUp => "<PageUp>".to_string(),
+        Key::PageDown => "<PageDown>".to_string(),
+        Key::Up => "<Up>".to_string(),
+        Key::Down => "<Down>".to_string(),
+        Key::Left => "<Left>".to_string(),
+        Key::Right => "<Right>".to_string(),
+        _ => format!("{key:?}"),
+    }
+}
+
+#[cfg(test)]
+mod test_keymap {
+    use super::*;
+
+    fn keymap(yaml: &str) -> Result<Keymap, HoardError> {
+        Keymap::new(&serde_yaml::from_str(yaml).unwrap())
+    }
+
+    #[test]
+    fn parse_and_name_keys() {
+        let keys = parse_keys("<Ctrl-n><alt-D>g<Space><lt><F12><Enter>").unwrap();
+        assert_eq!(
+            vec![
+                Key::Ctrl('n'),
+                Key::Alt('D'),
+                Key::Char('g'),
+                Key::Char(' '),
+                Key::Char('<'),
+                Key::F(12),
+                Key::Char('\n')
+            ],
+            keys
+        );
+        assert_eq!(
+            "<Ctrl-N><Alt-D>g<Space><lt><F12><Enter>",
+            keys.into_iter().map(key_name).collect::<String>()
+        );
+        assert!(parse_keys("<Hyper-X>").is_err());
+        assert!(parse_keys("").is_err());
+    }
+
+    #[test]
+    fn default_keys_do_not_collide() {
+        let keymap = Keymap::default();
+        for screen in Screen::ALL {
+            let sequences: Vec<_> = keymap.bindings[&screen]
+                .iter()
+                .flat_map(|(_, sequences)| sequences)
+                .collect();
+            for (i, a) in sequences.iter().enumerate() {
+                for b in &sequences[i + 1..] {
+                    assert!(!collide(a, b), "{a:?} and {b:?} on {screen:?}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn configured_keys_replace_the_defaults() {
+        let mut keymap = keymap("list: { quit: [Q], next_command: [gj, j] }").unwrap();
+        assert_eq!(vec!["Q"], keymap.keys(Screen::List, Action::Quit));
+        assert_eq!(
+            Dispatch::Unbound(vec![Key::Char('q')]),
+            keymap.dispatch(Screen::List, Key::Char('q'))
+        );
+        assert_eq!(
+            Dispatch::Pending,
+            keymap.dispatch(Screen::List, Key::Char('g'))
+        );
+        assert_eq!(
+            Dispatch::Action(Action::NextCommand),
+            keymap.dispatch(Screen::List, Key::Char('j'))
+        );
+        // `Esc` only quits with the default keys
+        assert_eq!(
+            Dispatch::Unbound(vec![Key::Esc]),
+            keymap.dispatch(Screen::List, Key::Esc)
+        );
+    }
+
+    #[test]
+    fn configured_keys_take_over_keys_of_other_actions() {
+        let keymap = keymap("search: { normal_mode: [jk] }\nlist: { help: [h, u] }").unwrap();
+        assert_eq!(vec!["<Ctrl-Z>"], keymap.keys(Screen::List, Action::Undo));
+        let mut keymap = keymap;
+        assert_eq!(
+            Dispatch::Pending,
+            keymap.dispatch(Screen::Search, Key::Char('j'))
+        );
+        assert_eq!(
+            Dispatch::Unbound(vec![Key::Char('j'), Key::Char('x')]),
+            keymap.dispatch(Screen::Search, Key::Char('x'))
+        );
+    }
+
+    #[test]
+    fn invalid_keymaps() {
+        assert!(keymap("lists: { quit: [q] }")
+            .unwrap_err()
+            .to_string()
+            .contains("Unknown screen `lists`"));
+        assert!(keymap("list: { save: [s] }")
+            .unwrap_err()
+            .to_string()
+            .contains("Unknown action `save`"));
+        assert!(keymap("list: { quit: [<Hyper-Q>] }")
+            .unwrap_err()
+            .to_string()
+            .contains("<Hyper-Q>"));
+        assert!(keymap("list: { quit: [g], help: [gh] }")
+            .unwrap_err()
+            .to_string()
+            .contains("collides"));
+    }
+}
@@ -1,42 +1,118 @@
-use crate::core::parameters::Parameterized;
-use crate::core::HoardCmd;
+use crate::gui::bulk::{self, BulkAction, Prompt};
 use crate::gui::commands_gui::{ControlState, DrawState, EditSelection, State, ViMode};
+use crate::gui::inline_edit::controls::{namespace_picker_key, Picked};
+use crate::gui::keymap::{Action, Dispatch, Screen};
+use crate::gui::text_input::TextInput;
+use crate::util::{executable_name, export_format_of, render_export};
+use hoardlib::core::error::HoardError;
+use hoardlib::core::parameters::Parameterized;
+use hoardlib::core::trove::Trove;
+use hoardlib::core::workflow::{join_steps, WorkflowMode};
+use hoardlib::core::HoardCmd;
+use std::path::Path;
 use termion::event::Key;
 
-#[allow(clippy::too_many_lines)]
 pub fn key_handler(
     input: Key,
     state: &mut State,
     trove_commands: &[HoardCmd],
     namespace_tabs: &[&str],
 ) -> Option<HoardCmd> {
-    match (&state.vimode, input) {
-        (ViMode::Insert, Key::Esc) => {
-            state.vimode = ViMode::Normal;
+    let pending_delete = state.pending_delete.take();
+    state.error_message.clear();
+    if let Some(prompt) = state.prompt {
+        prompt_key(input, prompt, state, trove_commands);
+        return None;
+    }
+    if let Some(selected) = state.namespace_picker {
+        let namespaces = namespace_tabs.get(1..).unwrap_or_default();
+        match namespace_picker_key(input, selected, state, namespaces) {
+            Some(Picked::Namespace(namespace)) => bulk(state, BulkAction::Move(namespace)),
+            Some(Picked::NewNamespace) => open_prompt(state, Prompt::Namespace),
+            None => (),
+        }
+        return None;
+    }
+    // Vi keys edit the search field first, the keys vi doesn't know are looked up in the keymap of the search field
+    if matches!(state.vimode, ViMode::Edit | ViMode::Visual) {
+        if let Some(mode) = state.vi.handle_key(&state.vimode, input, &mut state.input) {
+            state.vimode = mode;
+            apply_filter(state, namespace_tabs, trove_commands);
+            return None;
+        }
+    }
+    let screen = if state.vimode == ViMode::Normal {
+        Screen::List
+    } else {
+        Screen::Search
+    };
+    let action = match state.keymap.dispatch(screen, input) {
+        Dispatch::Action(action) => action,
+        Dispatch::Pending => return None,
+        Dispatch::Unbound(keys) => {
+            if state.vimode == ViMode::Insert {
+                let mut edited = false;
+                for key in keys {
+                    edited |= state.vi.insert_key(key, &mut state.input);
+                }
+                if edited {
+                    apply_filter(state, namespace_tabs, trove_commands);
+                }
+            }
+            return None;
+        }
+    };
+    apply_action(
+        action,
+        state,
+        trove_commands,
+        namespace_tabs,
+        pending_delete,
+    )
+}
+
+/// Does `action` of the command list or the search field, also for the mouse
+///
+/// `pending_delete` is the command, or the marked commands, whose deletion waits for a confirmation
+#[allow(clippy::too_many_lines)]
+pub fn apply_action(
+    action: Action,
+    state: &mut State,
+    trove_commands: &[HoardCmd],
+    namespace_tabs: &[&str],
+    pending_delete: Option<String>,
+) -> Option<HoardCmd> {
+    match action {
+        Action::NormalMode => {
+            if state.vimode == ViMode::Insert {
+                state.vi.leave_insert(&mut state.input);
+                state.vimode = ViMode::Edit;
+            } else {
+                state.vimode = ViMode::Normal;
+            }
             None
         }
-        (ViMode::Normal, Key::Char('i')) => {
+        Action::InsertMode => {
             state.vimode = ViMode::Insert;
             None
         }
-        (ViMode::Normal, Key::Char('q')) => {
+        Action::Quit => {
             state.control = ControlState::Search;
             state.should_exit = true;
             None
         }
-        (ViMode::Normal, Key::Char('?')) => {
+        Action::Help => {
             state.draw = DrawState::Help;
             None
         }
-        // Create Ctrl+W
-        (ViMode::Normal, Key::Char('a')) => {
+        Action::NewCommand => {
             state.draw = DrawState::Create;
             state.edit_selection = EditSelection::Command;
             state.new_command = Some(HoardCmd::default());
             None
         }
         // Switch to the right pane
-        (ViMode::Normal, Key::Char('l')) => {
+        Action::EditCommand => {
             let selected_command = state
                 .commands
                 .clone()
@@ -48,48 +124,97 @@ pub fn key_handler(
                 )
                 .expect("exists")
                 .clone();
+            // Workflows are changed with `hoard workflow`
+            if state.workflows.contains_key(&selected_command.id) {
+                return None;
+            }
             state.control = ControlState::Edit;
             state.selected_command = Some(selected_command);
             state.update_string_to_edit();
             None
         }
-
-        // Go one namespace to the left.
-        (ViMode::Normal, Key::Char('d')) => {
+        Action::PreviousNamespace => {
             if let Some(selected) = state.namespace_tab.selected() {
                 let new_selected_tab = previous_index(selected, namespace_tabs.len());
                 switch_namespace(state, new_selected_tab, namespace_tabs, trove_commands);
             }
             None
         }
-        // Go one namespace the right
-        (ViMode::Normal, Key::Char('u')) => {
+        Action::NextNamespace => {
             if let Some(selected) = state.namespace_tab.selected() {
                 let new_selected_tab = next_index(selected, namespace_tabs.len());
                 switch_namespace(state, new_selected_tab, namespace_tabs, trove_commands);
             }
             None
         }
-        // Switch command up
-        (ViMode::Normal, Key::Char('k')) => {
+        Action::PreviousCommand => {
             if !state.commands.is_empty() {
                 if let Some(selected) = state.command_list.selected() {
                     let new_selected = previous_index(selected, state.commands.len());
                     state.command_list.select(Some(new_selected));
                 }
             }
+            extend_mark_range(state);
             None
         }
-        (ViMode::Normal, Key::Char('j')) => {
+        Action::NextCommand => {
             if !state.commands.is_empty() {
                 if let Some(selected) = state.command_list.selected() {
                     let new_selected = next_index(selected, state.commands.len());
                     state.command_list.select(Some(new_selected));
                 }
             }
+            extend_mark_range(state);
+            None
+        }
+        // Yank the selected command into the system clipboard
+        Action::YankCommand => {
+            if let Some(command) = state
+                .command_list
+                .selected()
+                .and_then(|selected| state.commands.get(selected))
+            {
+                let command = command.command.clone();
+                state.vi.yank_to_clipboard(&command);
+            }
             None
         }
-        (ViMode::Normal, Key::Alt('D')) => {
+        // Marked commands are deleted at once, after a confirmation
+        Action::DeleteCommand if !state.marked.is_empty() => {
+            let marked = state.marked.join(",");
+            if pending_delete.as_ref() == Some(&marked) {
+                bulk(state, BulkAction::Delete);
+                return None;
+            }
+            // Commands which embed the deleted ones break, unless they are deleted as well
+            let deleted: Vec<String> = trove_commands
+                .iter()
+                .filter(|c| state.marked.contains(&c.id))
+                .map(|c| format!("{}/{}", c.namespace, c.name))
+                .collect();
+            let mut dependents: Vec<String> = state
+                .marked
+                .iter()
+                .flat_map(|id| state.dependents(id).to_vec())
+                .filter(|dependent| !deleted.contains(dependent))
+                .collect();
+            dependents.sort_unstable();
+            dependents.dedup();
+            let used_by = if dependents.is_empty() {
+                String::new()
+            } else {
+                format!(" Used by {}.", dependents.join(", "))
+            };
+            let keys = state.keymap.keys(Screen::List, Action::DeleteCommand);
+            state.error_message = format!(
+                "Delete {}?{used_by} Press {} again",
+                bulk::count_commands(state.marked.len()),
+                keys.first().map_or("the delete key", String::as_str)
+            );
+            state.pending_delete = Some(marked);
+            None
+        }
+        Action::DeleteCommand => {
             if state.commands.is_empty() {
                 return None;
             }
@@ -104,10 +229,22 @@ pub fn key_handler(
                 )
                 .expect("exists")
                 .clone();
+            // Deleting a command others embed breaks them, so it has to be confirmed
+            let dependents = state.dependents(&selected_command.id);
+            if !dependents.is_empty() && pending_delete.as_ref() != Some(&selected_command.id) {
+                let keys = state.keymap.keys(Screen::List, Action::DeleteCommand);
+                state.error_message = format!(
+                    "Used by {}. Press {} again to delete it",
+                    dependents.join(", "),
+                    keys.first().map_or("the delete key", String::as_str)
+                );
+                state.pending_delete = Some(selected_command.id);
+                return None;
+            }
             state.should_delete = true;
             Some(selected_command)
         }
-        (_, Key::Char('\n')) => {
+        Action::Select => {
             if state.commands.is_empty() {
                 state.should_exit = true;
                 return None;
@@ -123,33 +260,276 @@ pub fn key_handler(
                 )
                 .expect("exists")
                 .clone();
+            if let Some(steps) = state.workflows.get(&selected_command.id) {
+                return pick_workflow(state, &selected_command, steps.clone());
+            }
+            let expanded = match state.resolve(&selected_command) {
+                Ok(expanded) => expanded,
+                Err(e) => {
+                    state.error_message = e.to_string();
+                    return None;
+                }
+            };
+            // The expanded command is never written back to the trove, its id is kept to count its usage
+            let selected_command = HoardCmd {
+                command: expanded,
+                ..selected_command
+            };
             // Check if parameters need to be supplied
             if selected_command.get_parameter_count(&state.parameter_token) > 0 {
                 // Set next state to draw
                 state.draw = DrawState::ParameterInput;
                 // Save which command to replace parameters for
                 state.selected_command = Some(selected_command);
+                state.filled = Vec::new();
                 // Empty input for next screen
-                state.input = String::new();
+                state.input = TextInput::default();
                 // return None, otherwise drawing will quit
                 return None;
             }
             Some(selected_command)
         }
-        (ViMode::Insert, Key::Backspace) => {
-            state.input.pop();
-            apply_filter(state, namespace_tabs, trove_commands);
+        Action::ToggleMark => {
+            if let Some(id) = markable(state) {
+                match state.marked.iter().position(|marked| *marked == id) {
+                    Some(position) => {
+                        state.marked.remove(position);
+                    }
+                    None => state.marked.push(id),
+                }
+            }
             None
         }
-        (ViMode::Insert, Key::Char(c)) => {
-            state.input.push(c);
-            apply_filter(state, namespace_tabs, trove_commands);
+        Action::MarkRange => {
+            if state.mark_range.take().is_none() {
+                if let Some(selected) = state.command_list.selected() {
+                    state.mark_range = Some((selected, state.marked.clone()));
+                    extend_mark_range(state);
+                }
+            }
+            None
+        }
+        Action::ClearMarks => {
+            state.marked.clear();
+            state.mark_range = None;
             None
         }
+        Action::MoveMarked => {
+            if targets(state).is_some() {
+                state.namespace_picker = Some(0);
+            }
+            None
+        }
+        Action::AddTags => {
+            open_prompt(state, Prompt::AddTags);
+            None
+        }
+        Action::RemoveTags => {
+            open_prompt(state, Prompt::RemoveTags);
+            None
+        }
+        Action::ToggleFavorite => {
+            bulk(state, BulkAction::ToggleFavorite);
+            None
+        }
+        Action::ToggleHidden => {
+            bulk(state, BulkAction::ToggleHidden);
+            None
+        }
+        Action::ExportMarked => {
+            open_prompt(state, Prompt::ExportPath);
+            None
+        }
+        Action::CopyScript => {
+            if let Some(ids) = targets(state) {
+                let commands = commands_by_id(&ids, trove_commands);
+                state.vi.yank_to_clipboard(&bulk::script(&commands));
+                state.error_message = format!(
+                    "Copied {} as a shell script",
+                    bulk::count_commands(commands.len())
+                );
+            }
+            None
+        }
+        Action::Undo => {
+            state.should_undo = true;
+            None
+        }
+        Action::Redo => {
+            state.should_redo = true;
+            None
+        }
+        // Actions of the other screens
         _ => None,
     }
 }
 
+/// Id of the selected command to mark, unless it is a workflow
+fn markable(state: &mut State) -> Option<String> {
+    let command = state
+        .command_list
+        .selected()
+        .and_then(|selected| state.commands.get(selected))?;
+    if state.workflows.contains_key(&command.id) {
+        state.error_message = "Workflows are changed with `hoard workflow`".to_string();
+        return None;
+    }
+    Some(command.id.clone())
+}
+
+/// Ids of the commands a bulk action changes, the marked ones or else the selected one
+fn targets(state: &mut State) -> Option<Vec<String>> {
+    if state.marked.is_empty() {
+        markable(state).map(|id| vec![id])
+    } else {
+        Some(state.marked.clone())
+    }
+}
+
+/// The commands among `commands` with the ids `ids`, in their order
+fn commands_by_id(ids: &[String], commands: &[HoardCmd]) -> Vec<HoardCmd> {
+    ids.iter()
+        .filter_map(|id| commands.iter().find(|c| c.id == *id))
+        .cloned()
+        .collect()
+}
+
+/// Hands `action` to `run` to change the marked commands, or the selected one
+fn bulk(state: &mut State, action: BulkAction) {
+    if let Some(ids) = targets(state) {
+        state.bulk_action = Some((action, ids));
+        state.mark_range = None;
+    }
+}
+
+/// Asks for the input of a bulk action in place of the search
+fn open_prompt(state: &mut State, prompt: Prompt) {
+    if targets(state).is_some() {
+        state.prompt = Some(prompt);
+        state.prompt_input = TextInput::default();
+    }
+}
+
+/// Handles `input` in the prompt of a bulk action
+fn prompt_key(input: Key, prompt: Prompt, state: &mut State, trove_commands: &[HoardCmd]) {
+    let action = match state.keymap.dispatch(Screen::Prompt, input) {
+        Dispatch::Action(action) => action,
+        Dispatch::Pending => return,
+        Dispatch::Unbound(keys) => {
+            for key in keys {
+                state.prompt_input.handle_key(key);
+            }
+            return;
+        }
+    };
+    match action {
+        Action::Cancel => state.prompt = None,
+        Action::Quit => state.should_exit = true,
+        Action::Submit => {
+            state.prompt = None;
+            let value = state.prompt_input.take();
+            match prompt {
+                Prompt::AddTags => bulk(state, BulkAction::AddTags(bulk::parse_tags(&value))),
+                Prompt::RemoveTags => bulk(state, BulkAction::RemoveTags(bulk::parse_tags(&value))),
+                Prompt::Namespace => bulk(state, BulkAction::Move(value)),
+                Prompt::ExportPath => export(state, &value, trove_commands),
+            }
+        }
+        // Actions of the other screens
+        _ => (),
+    }
+}
+
+/// Exports the marked commands, or the selected one, to the file `path` in the format of its extension
+fn export(state: &mut State, path: &str, trove_commands: &[HoardCmd]) {
+    let Some(ids) = targets(state) else {
+        return;
+    };
+    let Some(format) = export_format_of(Path::new(path)) else {
+        state.error_message =
+            "Export to a .yml, .md, .json, .toml, .csv, .cheat or .sh file".to_string();
+        return;
+    };
+    let commands = commands_by_id(&ids, trove_commands);
+    let exported = render_export(
+        &Trove::from_commands(&commands),
+        format,
+        &state.parameter_token,
+        &state.parameter_ending_token,
+    )
+    .and_then(|content| Ok(std::fs::write(path, content)?));
+    state.error_message = match exported {
+        Ok(()) => format!(
+            "Exported {} to {path}",
+            bulk::count_commands(commands.len())
+        ),
+        Err(e) => format!("Could not export commands: {e}"),
+    };
+}
+
+/// Marks the commands between the start of the marked range and the cursor, besides the ones marked before it
+fn extend_mark_range(state: &mut State) {
+    let (Some((start, before)), Some(cursor)) = (&state.mark_range, state.command_list.selected())
+    else {
+        return;
+    };
+    let end = (*start)
+        .max(cursor)
+        .min(state.commands.len().saturating_sub(1));
+    let mut marked = before.clone();
+    for command in state
+        .commands
+        .get((*start).min(cursor)..=end)
+        .unwrap_or_default()
+    {
+        if !state.workflows.contains_key(&command.id) && !marked.contains(&command.id) {
+            marked.push(command.id.clone());
+        }
+    }
+    state.marked = marked;
+}
+
+/// Picks the workflow `entry` stands for, which runs `steps`
+///
+/// Depending on the workflow mode it returns the steps joined with `&&`, after asking for their parameters,
+/// or `hoard run` with the workflow, which asks for the parameters of each step itself
+fn pick_workflow(state: &mut State, entry: &HoardCmd, steps: Vec<HoardCmd>) -> Option<HoardCmd> {
+    if state.workflow_mode == WorkflowMode::Run {
+        // The id of the workflow is kept to count its usage
+        return Some(HoardCmd {
+            command: format!("{} run {}", executable_name(), entry.id),
+            ..entry.clone()
+        });
+    }
+    let steps = steps
+        .into_iter()
+        .map(|step| {
+            let expanded = state.resolve(&step)?;
+            Ok(step.with_command(&expanded))
+        })
+        .collect::<Result<Vec<_>, HoardError>>();
+    let steps = match steps {
+        Ok(steps) => steps,
+        Err(e) => {
+            state.error_message = e.to_string();
+            return None;
+        }
+    };
+    let joined = HoardCmd {
+        command: join_steps(&steps),
+        ..entry.clone()
+    };
+    if joined.get_parameter_count(&state.parameter_token) == 0 {
+        return Some(joined);
+    }
+    state.draw = DrawState::ParameterInput;
+    state.selected_command = Some(joined);
+    state.workflow_steps = steps;
+    state.filled = Vec::new();
+    state.input = TextInput::default();
+    None
+}
+
 pub const fn next_index(current_index: usize, collection_length: usize) -> usize {
     if current_index >= collection_length - 1 {
         0
@@ -190,7 +570,7 @@ pub fn switch_namespace(
 }
 
 fn apply_search(state: &mut State, all_commands: &[HoardCmd], selected_tab: &str) {
-    let query_term = &state.input[..];
+    let query_term = state.input.value();
     state.commands = all_commands
         .iter()
         .filter(|&c| {
@@ -205,10 +585,10 @@ fn apply_search(state: &mut State, all_commands: &[HoardCmd], selected_tab: &str
         .collect();
     state
         .commands
-        .sort_by(|a, b| b.usage_count.cmp(&a.usage_count));
+        .sort_by_key(|c| std::cmp::Reverse(c.usage_count));
 }
 
-fn apply_filter(state: &mut State, namespaces: &[&str], commands: &[HoardCmd]) {
+pub fn apply_filter(state: &mut State, namespaces: &[&str], commands: &[HoardCmd]) {
     let selected_tab = namespaces
         .get(
             state
@@ -225,7 +605,6 @@ mod test_controls {
     use crate::gui::commands_gui::ViMode;
 
     use super::*;
-    use ratatui::widgets::ListState;
 
     const DEFAULT_NAMESPACE: &str = "default";
 
@@ -237,31 +616,7 @@ mod test_controls {
     }
 
     fn create_state(commands: Vec<HoardCmd>) -> State {
-        let mut state = State {
-            input: String::new(),
-            commands,
-            command_list: ListState::default(),
-            namespace_tab: ListState::default(),
-            should_exit: false,
-            should_delete: false,
-            draw: DrawState::Search,
-            control: ControlState::Search,
-            vimode: ViMode::Normal,
-            new_command: None,
-            edit_selection: crate::gui::commands_gui::EditSelection::Command,
-            string_to_edit: String::new(),
-            parameter_token: "#".to_string(),
-            parameter_ending_token: "!".to_string(),
-            selected_command: None,
-            provided_parameter_count: 0,
-            error_message: String::new(),
-            buffered_tick: false,
-        };
-
-        state.command_list.select(Some(0));
-        state.namespace_tab.select(Some(0));
-
-        state
+        State::for_test(commands)
     }
 
     fn test_change_command(key: Key, initial_index: usize, expected_index: usize) {
@@ -400,6 +755,236 @@ mod test_controls {
         assert_eq!(DrawState::ParameterInput, state.draw);
     }
 
+    #[test]
+    fn pick_command_with_reference() {
+        let namespaces = vec![DEFAULT_NAMESPACE];
+        let pods = create_command("pods", "{{ctx}} get pods", DEFAULT_NAMESPACE);
+        let context = create_command("ctx", "kubectl --context #cluster!", DEFAULT_NAMESPACE);
+
+        let mut state = create_state(vec![pods, context]);
+        let commands = state.commands.clone();
+        key_handler(Key::Char('\n'), &mut state, &commands, &namespaces);
+
+        assert_eq!(DrawState::ParameterInput, state.draw);
+        let selected = state.selected_command.unwrap();
+        assert_eq!("kubectl --context #cluster! get pods", selected.command);
+        assert_eq!(commands[0].id, selected.id);
+    }
+
+    #[test]
+    fn confirm_deleting_a_command_in_use() {
+        let namespaces = vec![DEFAULT_NAMESPACE];
+        let context = create_command("ctx", "kubectl --context prod", DEFAULT_NAMESPACE);
+        let pods = create_command("pods", "{{ctx}} get pods", DEFAULT_NAMESPACE);
+
+        let mut state = create_state(vec![context, pods]);
+        let commands = state.commands.clone();
+        assert!(key_handler(Key::Alt('D'), &mut state, &commands, &namespaces).is_none());
+        assert!(state.error_message.contains("default/pods"));
+        assert!(!state.should_delete);
+
+        let deleted = key_handler(Key::Alt('D'), &mut state, &commands, &namespaces).unwrap();
+        assert_eq!("ctx", deleted.name);
+        assert!(state.should_delete);
+    }
+
+    #[test]
+    fn edit_search_with_vi_keys() {
+        let namespaces = vec![DEFAULT_NAMESPACE];
+        let cmd1 = create_command("first", "", DEFAULT_NAMESPACE);
+        let cmd2 = create_command("second", "", DEFAULT_NAMESPACE);
+        let mut state = create_state(vec![cmd1, cmd2]);
+        state.vimode = ViMode::Insert;
+        let commands = state.commands.clone();
+        for c in "sec x".chars() {
+            key_handler(Key::Char(c), &mut state, &commands, &namespaces);
+        }
+        assert!(state.commands.is_empty());
+
+        key_handler(Key::Esc, &mut state, &commands, &namespaces);
+        assert_eq!(ViMode::Edit, state.vimode);
+        for c in "daw".chars() {
+            key_handler(Key::Char(c), &mut state, &commands, &namespaces);
+        }
+        assert_eq!("sec", state.input.value());
+        assert_eq!(1, state.commands.len());
+
+        key_handler(Key::Esc, &mut state, &commands, &namespaces);
+        assert_eq!(ViMode::Normal, state.vimode);
+    }
+
+    fn press(state: &mut State, keys: &str, commands: &[HoardCmd], namespaces: &[&str]) {
+        for c in keys.chars() {
+            key_handler(Key::Char(c), state, commands, namespaces);
+        }
+    }
+
+    fn create_marking_state() -> State {
+        let commands = ["first", "second", "third", "fourth"]
+            .map(|name| create_command(name, "", DEFAULT_NAMESPACE))
+            .to_vec();
+        create_state(commands)
+    }
+
+    #[test]
+    fn mark_commands_and_ranges() {
+        let mut state = create_marking_state();
+        let commands = state.commands.clone();
+        let namespaces = [DEFAULT_NAMESPACE];
+        press(&mut state, "  ", &commands, &namespaces);
+        assert!(state.marked.is_empty());
+
+        press(&mut state, " jjV", &commands, &namespaces);
+        key_handler(Key::Down, &mut state, &commands, &namespaces);
+        let marked: Vec<_> = [0, 2, 3].map(|i| commands[i].id.clone()).to_vec();
+        assert_eq!(marked, state.marked);
+        key_handler(Key::Up, &mut state, &commands, &namespaces);
+        key_handler(Key::Up, &mut state, &commands, &namespaces);
+        assert_eq!(3, state.marked.len());
+        assert!(state.marked.contains(&commands[1].id));
+
+        press(&mut state, "VU", &commands, &namespaces);
+        assert!(state.marked.is_empty() && state.mark_range.is_none());
+    }
+
+    #[test]
+    fn tag_the_marked_commands() {
+        let mut state = create_marking_state();
+        let commands = state.commands.clone();
+        let namespaces = [DEFAULT_NAMESPACE];
+        press(&mut state, " j +", &commands, &namespaces);
+        assert_eq!(Some(Prompt::AddTags), state.prompt);
+
+        press(&mut state, "ops, kube\n", &commands, &namespaces);
+        assert!(state.prompt.is_none());
+        let (action, ids) = state.bulk_action.take().unwrap();
+        assert_eq!(
+            BulkAction::AddTags(vec!["ops".to_string(), "kube".to_string()]),
+            action
+        );
+        assert_eq!(vec![commands[0].id.clone(), commands[1].id.clone()], ids);
+
+        key_handler(Key::Ctrl('z'), &mut state, &commands, &namespaces);
+        assert!(state.should_undo);
+    }
+
+    #[test]
+    fn move_the_selected_command_to_a_new_namespace() {
+        let mut state = create_marking_state();
+        let commands = state.commands.clone();
+        let namespaces = ["All", DEFAULT_NAMESPACE];
+        press(&mut state, "mj\n", &commands, &namespaces);
+        assert!(state.namespace_picker.is_none());
+        assert_eq!(Some(Prompt::Namespace), state.prompt);
+
+        press(&mut state, "ops\n", &commands, &namespaces);
+        let (action, ids) = state.bulk_action.take().unwrap();
+        assert_eq!(BulkAction::Move("ops".to_string()), action);
+        assert_eq!(vec![commands[0].id.clone()], ids);
+    }
+
+    #[test]
+    fn confirm_deleting_the_marked_commands() {
+        let mut state = create_marking_state();
+        let commands = state.commands.clone();
+        let namespaces = [DEFAULT_NAMESPACE];
+        press(&mut state, " j ", &commands, &namespaces);
+        key_handler(Key::Alt('D'), &mut state, &commands, &namespaces);
+        assert!(state.error_message.starts_with("Delete 2 commands?"));
+        assert!(state.bulk_action.is_none());
+
+        key_handler(Key::Alt('D'), &mut state, &commands, &namespaces);
+        assert_eq!(BulkAction::Delete, state.bulk_action.unwrap().0);
+        assert!(!state.should_delete);
+    }
+
+    #[test]
+    fn name_the_commands_using_the_marked_ones() {
+        let namespaces = [DEFAULT_NAMESPACE];
+        let context = create_command("ctx", "kubectl --context prod", DEFAULT_NAMESPACE);
+        let pods = create_command("pods", "{{ctx}} get pods", DEFAULT_NAMESPACE);
+        let nodes = create_command("nodes", "{{ctx}} get nodes", DEFAULT_NAMESPACE);
+        let mut state = create_state(vec![context, pods, nodes]);
+        let commands = state.commands.clone();
+        state.marked = commands
+            .iter()
+            .filter(|c| c.name != "nodes")
+            .map(|c| c.id.clone())
+            .collect();
+
+        key_handler(Key::Alt('D'), &mut state, &commands, &namespaces);
+        assert!(state.error_message.contains("Used by default/nodes."));
+        assert!(!state.error_message.contains("default/pods"));
+    }
+
+    fn create_workflow_state(mode: WorkflowMode) -> State {
+        let steps = vec![
+            create_command("drain", "kubectl drain #node!", DEFAULT_NAMESPACE),
+            create_command("uncordon", "kubectl uncordon #node!", DEFAULT_NAMESPACE),
+        ];
+        let workflow = hoardlib::core::workflow::Workflow::new(
+            "upgrade",
+            DEFAULT_NAMESPACE,
+            "",
+            steps.iter().map(|step| step.id.clone()).collect(),
+        );
+        let mut state = create_state(vec![workflow.to_command(&steps)]);
+        state.workflows.insert(workflow.id, steps);
+        let commands = state.commands.clone();
+        state.relate(&commands);
+        state.workflow_mode = mode;
+        state
+    }
+
+    #[test]
+    fn pick_workflow_with_params() {
+        let mut state = create_workflow_state(WorkflowMode::Join);
+        let commands = state.commands.clone();
+        assert!(
+            key_handler(Key::Char('\n'), &mut state, &commands, &[DEFAULT_NAMESPACE]).is_none()
+        );
+
+        assert_eq!(DrawState::ParameterInput, state.draw);
+        assert_eq!(2, state.workflow_steps.len());
+        assert_eq!(
+            "kubectl drain #node! && kubectl uncordon #node!",
+            state.selected_command.unwrap().command
+        );
+    }
+
+    #[test]
+    fn dependents_are_related_once() {
+        let mut state = create_workflow_state(WorkflowMode::Join);
+        let drain = state.workflows.values().next().unwrap()[0].clone();
+        assert_eq!(["workflow default/upgrade"], state.dependents(&drain.id));
+
+        let context = create_command("ctx", "kubectl --context prod", DEFAULT_NAMESPACE);
+        let pods = create_command(
+            "pods",
+            "{{ctx}} get pods && {{ctx}} get nodes",
+            DEFAULT_NAMESPACE,
+        );
+        let mut commands = state.commands.clone();
+        commands.extend([context.clone(), pods]);
+        state.relate(&commands);
+        assert_eq!(["default/pods"], state.dependents(&context.id));
+        assert!(state.dependents("unknown").is_empty());
+    }
+
+    #[test]
+    fn pick_workflow_to_run() {
+        let mut state = create_workflow_state(WorkflowMode::Run);
+        let commands = state.commands.clone();
+        let command =
+            key_handler(Key::Char('\n'), &mut state, &commands, &[DEFAULT_NAMESPACE]).unwrap();
+
+        assert!(command
+            .command
+            .ends_with(&format!(" run {}", commands[0].id)));
+        // The usage of the workflow is counted by its id
+        assert_eq!(commands[0].id, command.id);
+    }
+
     #[test]
     fn quit_on_nothing_to_pick() {
         let mut state = create_state(vec![]);
@@ -0,0 +1,157 @@
+use crate::gui::commands_gui::EditSelection;
+use ratatui::layout::{Constraint, Direction, Layout, Rect};
+
+/// Terminals narrower than this show the command list above the selected command instead of beside it
+const STACKED_WIDTH: u16 = 80;
+/// Terminals lower than this leave out the margin around the screen
+const MARGIN_HEIGHT: u16 = 20;
+/// Height of the name, the tags and three lines of the description of the selected command
+const DETAILS_HEIGHT: u16 = 9;
+
+/// Areas of the panes of the list screen
+///
+/// The name, namespace, tags and description of the selected command are left out if they don't fit
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Areas {
+    pub tabs: Rect,
+    pub list: Rect,
+    pub name: Option<Rect>,
+    pub namespace: Option<Rect>,
+    pub tags: Option<Rect>,
+    pub description: Option<Rect>,
+    pub command: Rect,
+    pub input: Rect,
+    pub footer: Rect,
+}
+
+impl Areas {
+    /// The panes of a screen of `size`, with `command_height` lines for the selected command
+    pub fn new(size: Rect, command_height: u16) -> Self {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .margin(u16::from(size.height >= MARGIN_HEIGHT))
+            .constraints(
+                [
+                    Constraint::Length(3),
+                    Constraint::Min(2),
+                    Constraint::Length(3),
+                    Constraint::Length(1),
+                ]
+                .as_ref(),
+            )
+            .split(size);
+        let body = chunks[1];
+        let details_height = DETAILS_HEIGHT + command_height;
+
+        let (list, details) = if size.width >= STACKED_WIDTH {
+            let columns = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([Constraint::Percentage(30), Constraint::Percentage(70)].as_ref())
+                .split(body);
+            (columns[0], columns[1])
+        } else {
+            // The list keeps most of the lines if the details don't fit below it
+            let constraints = if u32::from(body.height) * 60 / 100 >= u32::from(details_height) {
+                [Constraint::Percentage(40), Constraint::Percentage(60)]
+            } else {
+                [Constraint::Min(2), Constraint::Length(command_height)]
+            };
+            let rows = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints(constraints.as_ref())
+                .split(body);
+            (rows[0], rows[1])
+        };
+
+        let mut areas = Self {
+            tabs: chunks[0],
+            list,
+            name: None,
+            namespace: None,
+            tags: None,
+            description: None,
+            command: Rect {
+                height: details.height.min(command_height),
+                ..details
+            },
+            input: chunks[2],
+            footer: chunks[3],
+        };
+        if details.height < details_height {
+            return areas;
+        }
+
+        let detail_chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints(
+                [
+                    Constraint::Length(3),
+                    Constraint::Length(3),
+                    Constraint::Percentage(60),
+                    Constraint::Length(command_height),
+                ]
+                .as_ref(),
+            )
+            .split(details);
+        let name_chunks = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(50), Constraint::Percentage(50)].as_ref())
+            .split(detail_chunks[0]);
+        areas.name = Some(name_chunks[0]);
+        areas.namespace = Some(name_chunks[1]);
+        areas.tags = Some(detail_chunks[1]);
+        areas.description = Some(detail_chunks[2]);
+        areas.command = detail_chunks[3];
+        areas
+    }
+
+    /// Area of the field `selection`. A field without room of its own is shown in place of the command while it is `edited`
+    pub fn field(&self, selection: &EditSelection, edited: bool) -> Option<Rect> {
+        let area = match selection {
+            EditSelection::Name => self.name,
+            EditSelection::Namespace => self.namespace,
+            EditSelection::Tags => self.tags,
+            EditSelection::Description => self.description,
+            EditSelection::Command => Some(self.command),
+        };
+        area.or_else(|| edited.then_some(self.command))
+    }
+}
+
+#[cfg(test)]
+mod test_layout {
+    use super::*;
+
+    #[test]
+    fn wide_terminals_show_the_details_beside_the_list() {
+        let areas = Areas::new(Rect::new(0, 0, 120, 40), 3);
+        assert_eq!(areas.list.y, areas.name.unwrap().y);
+        assert!(areas.list.right() <= areas.command.x);
+        assert!(areas.description.is_some());
+        assert!(areas.command.height >= 3);
+    }
+
+    #[test]
+    fn narrow_terminals_stack_the_details_below_the_list() {
+        let areas = Areas::new(Rect::new(0, 0, 60, 50), 3);
+        assert!(areas.list.bottom() <= areas.name.unwrap().y);
+        assert_eq!(areas.list.width, areas.command.width);
+        assert!(areas.description.is_some());
+    }
+
+    #[test]
+    fn low_terminals_only_show_the_command() {
+        let areas = Areas::new(Rect::new(0, 0, 60, 14), 3);
+        assert_eq!(None, areas.description);
+        assert_eq!(3, areas.command.height);
+        assert!(areas.list.height >= 2);
+        // Without the margin
+        assert_eq!(0, areas.tabs.y);
+        assert_eq!(Some(areas.command), areas.field(&EditSelection::Tags, true));
+        assert_eq!(None, areas.field(&EditSelection::Tags, false));
+
+        let areas = Areas::new(Rect::new(0, 0, 120, 14), 3);
+        assert_eq!(None, areas.name);
+        assert!(areas.list.right() <= areas.command.x);
+    }
+}
@@ -1,2 +1,3 @@
 pub mod controls;
+pub mod layout;
 pub mod render;
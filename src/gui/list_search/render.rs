@@ -1,55 +1,42 @@
-use crate::config::HoardConfig;
-use crate::core::HoardCmd;
 use crate::gui::commands_gui::{ControlState, EditSelection};
 use crate::gui::commands_gui::{State, ViMode};
-use ratatui::backend::TermionBackend;
-use ratatui::layout::{Alignment, Constraint, Direction, Layout};
-use ratatui::style::{Color, Modifier, Style};
+use crate::gui::highlight::command_lines;
+use crate::gui::keymap::{Action, Screen};
+use crate::gui::list_search::layout::Areas;
+use crate::gui::terminal::HoardTerminal;
+use crate::gui::text_input::TextInput;
+use crate::gui::theme::Theme;
+use hoardlib::config::HoardConfig;
+use hoardlib::core::HoardCmd;
+use ratatui::layout::{Alignment, Constraint, Direction, Layout, Rect};
+use ratatui::style::{Modifier, Style};
 use ratatui::text::{Line, Span};
-use ratatui::widgets::{Block, BorderType, Borders, List, ListItem, Paragraph, Tabs, Wrap};
-use ratatui::Terminal;
-use termion::screen::AlternateScreen;
-
-const IBEAM: &str = "▕";
+use ratatui::widgets::{
+    Block, BorderType, Borders, Clear, List, ListItem, ListState, Paragraph, Tabs, Wrap,
+};
 
 #[allow(clippy::too_many_lines)]
 pub fn draw(
     app_state: &mut State,
     config: &HoardConfig,
     namespace_tabs: &[&str],
-    terminal: &mut Terminal<
-        TermionBackend<AlternateScreen<termion::raw::RawTerminal<std::io::Stdout>>>,
-    >,
+    terminal: &mut HoardTerminal,
 ) -> Result<(), eyre::Error> {
     terminal.draw(|rect| {
-        let size = rect.size();
-
-        let chunks = Layout::default()
-            .direction(Direction::Vertical)
-            .margin(1)
-            .constraints(
-                [
-                    Constraint::Length(3),
-                    Constraint::Min(2),
-                    Constraint::Length(3),
-                    Constraint::Length(1),
-                ]
-                .as_ref(),
-            )
-            .split(size);
-
+        // One more line for the command with its variables expanded
+        let command_height = app_state
+            .command_list
+            .selected()
+            .and_then(|idx| app_state.commands.get(idx))
+            .and_then(|command| expanded_preview(app_state, command))
+            .map_or(3, |_| 4);
+        let areas = Areas::new(rect.size(), command_height);
+        app_state.areas = Some(areas);
+
+        let theme = app_state.theme;
         let menu = namespace_tabs
             .iter()
-            .map(|t| {
-                Line::from(vec![Span::styled(
-                    *t,
-                    Style::default().fg(Color::Rgb(
-                        config.primary_color.unwrap().0,
-                        config.primary_color.unwrap().1,
-                        config.primary_color.unwrap().2,
-                    )),
-                )])
-            })
+            .map(|t| Line::from(vec![Span::styled(*t, theme.tabs)]))
             .collect();
 
         let tabs = Tabs::new(menu)
@@ -62,53 +49,71 @@ pub fn draw(
             .block(
                 Block::default()
                     .title(" Hoard Namespace ")
-                    .borders(Borders::ALL),
-            )
-            .style(Style::default().fg(Color::Rgb(
-                config.primary_color.unwrap().0,
-                config.primary_color.unwrap().1,
-                config.primary_color.unwrap().2,
-            )))
-            .highlight_style(
-                Style::default()
-                    .fg(Color::Rgb(
-                        config.secondary_color.unwrap().0,
-                        config.secondary_color.unwrap().1,
-                        config.secondary_color.unwrap().2,
-                    ))
-                    .add_modifier(Modifier::UNDERLINED),
+                    .borders(Borders::ALL)
+                    .style(theme.border),
             )
+            .style(theme.tabs)
+            .highlight_style(theme.tab_selected)
             .divider(Span::raw("|"));
 
-        rect.render_widget(tabs, chunks[0]);
-
-        // This is it
-        let commands_chunks = Layout::default()
-            .direction(Direction::Horizontal)
-            .constraints([Constraint::Percentage(30), Constraint::Percentage(70)].as_ref())
-            .split(chunks[1]);
-
-        let command_detail_chunks = Layout::default()
-            .direction(Direction::Vertical)
-            .constraints(
-                [
-                    Constraint::Length(3),
-                    Constraint::Percentage(60),
-                    Constraint::Length(3),
-                ]
-                .as_ref(),
-            )
-            .split(commands_chunks[1]);
-
-        let (commands, command, tags_widget, description, input) =
-            render_commands(&app_state.commands.clone(), app_state, config);
-
-        rect.render_stateful_widget(commands, commands_chunks[0], &mut app_state.command_list);
+        rect.render_widget(tabs, areas.tabs);
+
+        // Fields without room of their own are edited in place of the command
+        let edited = (app_state.control == ControlState::Edit).then_some(app_state.edit_selection);
+        let field_area =
+            |selection: EditSelection| areas.field(&selection, edited == Some(selection));
+        let width = |area: Option<Rect>| area.map_or(0, |area| area.width.saturating_sub(2));
+
+        // Width of the text in the bordered detail fields
+        let field_width = areas.command.width.saturating_sub(2);
+        let (commands, command, tags_widget, description) =
+            render_commands(&app_state.commands.clone(), app_state, config, field_width);
+        let (name, namespace) = render_name_fields(
+            app_state,
+            width(field_area(EditSelection::Name)),
+            width(field_area(EditSelection::Namespace)),
+        );
+        let (input, input_cursor) =
+            render_input(app_state, config, areas.input.width.saturating_sub(2));
+
+        rect.render_stateful_widget(commands, areas.list, &mut app_state.command_list);
+
+        rect.render_widget(command, areas.command);
+        let fields = [
+            (EditSelection::Name, name),
+            (EditSelection::Namespace, namespace),
+            (EditSelection::Tags, tags_widget),
+            (EditSelection::Description, description),
+        ];
+        for (selection, widget) in fields {
+            if let Some(area) = field_area(selection) {
+                rect.render_widget(widget, area);
+            }
+        }
+        rect.render_widget(input, areas.input);
+
+        // The namespace picker opens over the description, or over the list if the description doesn't fit
+        if let Some(selected) = app_state.namespace_picker {
+            let (picker, mut picker_state) =
+                render_namespace_picker(&theme, namespace_tabs, selected);
+            let area = areas.description.unwrap_or(areas.list);
+            rect.render_widget(Clear, area);
+            rect.render_stateful_widget(picker, area, &mut picker_state);
+        }
 
-        rect.render_widget(tags_widget, command_detail_chunks[0]);
-        rect.render_widget(description, command_detail_chunks[1]);
-        rect.render_widget(command, command_detail_chunks[2]);
-        rect.render_widget(input, chunks[2]);
+        if app_state.vimode != ViMode::Normal || app_state.prompt.is_some() {
+            match app_state.control {
+                ControlState::Search => {
+                    rect.set_cursor(areas.input.x + 1 + input_cursor, areas.input.y + 1);
+                }
+                ControlState::Edit => {
+                    let field = field_area(app_state.edit_selection).unwrap_or(areas.command);
+                    let width = field.width.saturating_sub(2);
+                    let (_, cursor) = app_state.string_to_edit.view("", width, false);
+                    rect.set_cursor(field.x + 1 + cursor, field.y + 1);
+                }
+            }
+        }
 
         let (footer_left, footer_right) = get_footer_constraints(&app_state.control);
 
@@ -119,48 +124,42 @@ pub fn draw(
                 Constraint::Percentage(footer_left),
                 Constraint::Percentage(footer_right),
             ])
-            .split(chunks[3]);
+            .split(areas.footer);
 
-        let control_str = &app_state.control;
-
-        let help_hint_l = Paragraph::new(format!("{control_str}"))
-            .style(Style::default().fg(Color::Rgb(
-                config.primary_color.unwrap().0,
-                config.primary_color.unwrap().1,
-                config.primary_color.unwrap().2,
-            )))
+        // A problem with the last key press replaces the hint until the next one
+        let (hint, hint_style) = if app_state.error_message.is_empty() {
+            (key_hint(app_state), theme.footer)
+        } else {
+            (app_state.error_message.clone(), theme.error)
+        };
+        let help_hint_l = Paragraph::new(hint)
+            .style(hint_style)
             .alignment(Alignment::Left);
 
         let vi_mode = match &app_state.vimode {
+            _ if app_state.mark_range.is_some() => "Marking",
             ViMode::Normal => "Normal",
             ViMode::Insert => "Insert",
+            ViMode::Edit => "Edit",
+            ViMode::Visual => "Visual",
         };
 
-        let help_hint = Paragraph::new(format!("[{}] ", vi_mode)).alignment(Alignment::Left);
+        let help_hint = Paragraph::new(format!("[{}] ", vi_mode))
+            .style(theme.footer)
+            .alignment(Alignment::Left);
         rect.render_widget(help_hint, footer_chunk[0]);
         rect.render_widget(help_hint_l, footer_chunk[1]);
     })?;
     Ok(())
 }
 
-fn get_color(
-    app: &State,
-    config: &HoardConfig,
-    command_render: &EditSelection,
-) -> ratatui::style::Color {
-    let highlighted = Color::Rgb(
-        config.secondary_color.unwrap().0,
-        config.secondary_color.unwrap().1,
-        config.secondary_color.unwrap().2,
-    );
-    let normal = Color::Rgb(
-        config.primary_color.unwrap().0,
-        config.primary_color.unwrap().1,
-        config.primary_color.unwrap().2,
-    );
+/// Style of the border of the field `command_render`, which stands out while it is edited
+fn border_style(app: &State, command_render: &EditSelection) -> Style {
+    let highlighted = app.theme.border_focused;
+    let normal = app.theme.border;
 
     match app.control {
-        ControlState::Search  => normal,
+        ControlState::Search => normal,
         ControlState::Edit => {
             if command_render == &app.edit_selection {
                 return highlighted;
@@ -171,59 +170,138 @@ fn get_color(
     }
 }
 
-fn coerce_string_by_mode(s: String, app: &State, command_render: &EditSelection) -> String {
+/// The keys of the main actions of the focused pane
+fn key_hint(app_state: &State) -> String {
+    if app_state.namespace_picker.is_some() {
+        return app_state.keymap.hint(
+            Screen::NamespacePicker,
+            &[
+                Action::PreviousOption,
+                Action::NextOption,
+                Action::Submit,
+                Action::Cancel,
+            ],
+        );
+    }
+    if app_state.prompt.is_some() {
+        return app_state
+            .keymap
+            .hint(Screen::Prompt, &[Action::Submit, Action::Cancel]);
+    }
+    match app_state.control {
+        ControlState::Search => app_state.keymap.hint(
+            Screen::List,
+            &[
+                Action::NextNamespace,
+                Action::PreviousCommand,
+                Action::NextCommand,
+                Action::NewCommand,
+                Action::YankCommand,
+                Action::DeleteCommand,
+                Action::Help,
+            ],
+        ),
+        ControlState::Edit => app_state
+            .keymap
+            .hint(Screen::Edit, &[Action::PreviousField, Action::NextField]),
+    }
+}
+
+/// `s`, or the text being edited if `command_render` is the field being edited, scrolled to fit into `width`
+fn coerce_string_by_mode(
+    s: String,
+    app: &State,
+    command_render: &EditSelection,
+    width: u16,
+) -> Line<'static> {
     match app.control {
-        ControlState::Search => s,
+        ControlState::Search => Line::from(s),
         ControlState::Edit => {
             if command_render == &app.edit_selection {
-                return app.string_to_edit.clone();
+                return input_line(app, &app.string_to_edit, "", width).0;
             }
-            s
+            Line::from(s)
         }
     }
 }
 
+/// The text of `input` after `prefix`, scrolled to fit into `width`, and the column of the cursor in it
+///
+/// In visual mode the selection is highlighted
+fn input_line(app: &State, input: &TextInput, prefix: &str, width: u16) -> (Line<'static>, u16) {
+    if app.vimode == ViMode::Visual {
+        let ([before, selected, after], cursor) =
+            input.selection_view(prefix, width, app.vi.selection(input));
+        let line = Line::from(vec![
+            Span::raw(before),
+            Span::styled(selected, Style::default().add_modifier(Modifier::REVERSED)),
+            Span::raw(after),
+        ]);
+        return (line, cursor);
+    }
+    let (shown, cursor) = input.view(prefix, width, false);
+    (Line::from(shown), cursor)
+}
+
+/// The command with the commands it embeds resolved and its `${VAR}` variables expanded,
+/// if that changes it and the command is not being edited. Commands which can't be resolved show why
+fn expanded_preview(app: &State, command: &HoardCmd) -> Option<String> {
+    if matches!(app.control, ControlState::Edit) && app.edit_selection == EditSelection::Command {
+        return None;
+    }
+    match app.resolve(command) {
+        Ok(expanded) => (expanded != command.command).then(|| format!("= {expanded}")),
+        Err(e) => Some(format!("! {e}")),
+    }
+}
+
 #[allow(clippy::too_many_lines)]
 fn render_commands<'a>(
     commands_list: &[HoardCmd],
     app: &mut State,
     config: &HoardConfig,
-) -> (
-    List<'a>,
-    Paragraph<'a>,
-    Paragraph<'a>,
-    Paragraph<'a>,
-    Paragraph<'a>,
-) {
-    let highlighted = Color::Rgb(
-        config.secondary_color.unwrap().0,
-        config.secondary_color.unwrap().1,
-        config.secondary_color.unwrap().2,
-    );
-
-    let normal = Color::Rgb(
-        config.primary_color.unwrap().0,
-        config.primary_color.unwrap().1,
-        config.primary_color.unwrap().2,
-    );
-    let color = match app.control {
-        ControlState::Search if matches!(app.vimode, ViMode::Normal) => highlighted,
-        _ => normal,
+    field_width: u16,
+) -> (List<'a>, Paragraph<'a>, Paragraph<'a>, Paragraph<'a>) {
+    let theme = app.theme;
+    let border = match app.control {
+        ControlState::Search if matches!(app.vimode, ViMode::Normal) => theme.border_focused,
+        _ => theme.border,
     };
 
+    let title = if app.marked.is_empty() {
+        " Commands ".to_string()
+    } else {
+        format!(" Commands ({} marked) ", app.marked.len())
+    };
     let commands = Block::default()
         .borders(Borders::ALL)
-        .style(Style::default().fg(color))
-        .title(" Commands ")
+        .style(border)
+        .title(title)
         .border_type(BorderType::Plain);
 
     let items: Vec<_> = commands_list
         .iter()
         .map(|command| {
-            ListItem::new(Line::from(vec![Span::styled(
-                command.name.clone(),
-                Style::default(),
-            )]))
+            let mut spans = Vec::new();
+            // The marks only take up room while something is marked
+            if !app.marked.is_empty() {
+                let mark = if app.marked.contains(&command.id) {
+                    "● "
+                } else {
+                    "  "
+                };
+                spans.push(Span::raw(mark));
+            }
+            let style = if command.is_hidden {
+                theme.text.add_modifier(Modifier::DIM)
+            } else {
+                theme.text
+            };
+            spans.push(Span::styled(command.name.clone(), style));
+            if command.is_favorite {
+                spans.push(Span::raw(" ★"));
+            }
+            ListItem::new(Line::from(spans))
         })
         .collect();
 
@@ -246,95 +324,188 @@ fn render_commands<'a>(
         app.command_list.select(Some(new_selection));
     }
 
-    let list = List::new(items).block(commands).highlight_style(
-        Style::default()
-            .bg(Color::Rgb(30, 30, 30))
-            .fg(Color::Rgb(150, 130, 110))
-            .add_modifier(Modifier::BOLD),
-    );
+    let list = List::new(items)
+        .block(commands)
+        .highlight_style(theme.selection);
 
-    let hoarded_command_title = format!(
-        " Hoarded command --- Times selected: {} ",
-        selected_command.usage_count
-    );
-    let command = Paragraph::new(coerce_string_by_mode(
-        selected_command.command.clone(),
-        app,
-        &EditSelection::Command,
-    ))
-    .style(Style::default())
-    .alignment(Alignment::Left)
-    .wrap(Wrap { trim: true })
-    .block(
-        Block::default()
-            .borders(Borders::ALL)
-            .style(Style::default().fg(get_color(app, config, &EditSelection::Command)))
-            .title(hoarded_command_title)
-            .border_type(BorderType::Plain),
-    );
+    let dependents = app.dependents(&selected_command.id);
+    let hoarded_command_title = if dependents.is_empty() {
+        format!(
+            " Hoarded command --- Times selected: {} ",
+            selected_command.usage_count
+        )
+    } else {
+        format!(
+            " Hoarded command --- Times selected: {} --- Used by: {} ",
+            selected_command.usage_count,
+            dependents.join(", ")
+        )
+    };
+    let editing =
+        matches!(app.control, ControlState::Edit) && app.edit_selection == EditSelection::Command;
+    let mut command_lines = if editing {
+        vec![coerce_string_by_mode(
+            selected_command.command.clone(),
+            app,
+            &EditSelection::Command,
+            field_width,
+        )]
+    } else {
+        command_lines(&selected_command.command, config, &theme, &[], &[])
+    };
+    if let Some(expanded) = expanded_preview(app, &selected_command) {
+        command_lines.push(Line::from(Span::styled(
+            expanded,
+            Style::default().add_modifier(Modifier::DIM),
+        )));
+    }
+    let command = Paragraph::new(command_lines)
+        .style(Style::default())
+        .alignment(Alignment::Left)
+        .wrap(Wrap { trim: true })
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .style(border_style(app, &EditSelection::Command))
+                .title(hoarded_command_title)
+                .border_type(BorderType::Plain),
+        );
 
     let tags = Paragraph::new(coerce_string_by_mode(
         selected_command.get_tags_as_string(),
         app,
         &EditSelection::Tags,
+        field_width,
     ))
-    .style(Style::default().fg(Color::Rgb(
-        config.primary_color.unwrap().0,
-        config.primary_color.unwrap().1,
-        config.primary_color.unwrap().2,
-    )))
+    .style(theme.text)
     .alignment(Alignment::Left)
     .block(
         Block::default()
             .borders(Borders::ALL)
-            .style(Style::default().fg(get_color(app, config, &EditSelection::Tags)))
+            .style(border_style(app, &EditSelection::Tags))
             .title(" Tags ")
             .border_type(BorderType::Plain),
     );
 
+    // Lines of the description scrolled out of sight with the mouse wheel
+    let scroll = app
+        .description_scroll
+        .as_ref()
+        .filter(|(id, _)| *id == selected_command.id)
+        .map_or(0, |(_, lines)| *lines);
     let description: Paragraph<'_> = {
         Paragraph::new(coerce_string_by_mode(
             selected_command.description,
             app,
             &EditSelection::Description,
+            field_width,
         ))
-        .style(Style::default().fg(Color::Rgb(
-            config.primary_color.unwrap().0,
-            config.primary_color.unwrap().1,
-            config.primary_color.unwrap().2,
-        )))
+        .style(theme.text)
         .alignment(Alignment::Left)
         .wrap(Wrap { trim: true })
+        .scroll((scroll, 0))
         .block(
             Block::default()
                 .borders(Borders::ALL)
-                .style(Style::default().fg(get_color(app, config, &EditSelection::Description)))
+                .style(border_style(app, &EditSelection::Description))
                 .title(" Description ")
                 .border_type(BorderType::Plain),
         )
     };
 
-    let mut query_string = config.query_prefix.clone() + " ";
+    (list, command, tags, description)
+}
 
-    query_string.push_str(&app.input.clone()[..]);
+/// The name and the namespace fields of the selected command, `name_width` and `namespace_width` columns wide inside their borders
+fn render_name_fields<'a>(
+    app: &State,
+    name_width: u16,
+    namespace_width: u16,
+) -> (Paragraph<'a>, Paragraph<'a>) {
+    let selected_command = app
+        .command_list
+        .selected()
+        .and_then(|selected| app.commands.get(selected))
+        .cloned()
+        .unwrap_or_default();
+    let field = |value: String, selection: EditSelection, width: u16, title: &'a str| {
+        let line = coerce_string_by_mode(value, app, &selection, width);
+        Paragraph::new(line).style(app.theme.text).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .style(border_style(app, &selection))
+                .title(title)
+                .border_type(BorderType::Plain),
+        )
+    };
+    (
+        field(
+            selected_command.name,
+            EditSelection::Name,
+            name_width,
+            " Name ",
+        ),
+        field(
+            selected_command.namespace,
+            EditSelection::Namespace,
+            namespace_width,
+            " Namespace ",
+        ),
+    )
+}
 
-    if matches!(app.control, ControlState::Search) && matches!(app.vimode, ViMode::Insert) {
-        query_string.push_str(IBEAM);
-    }
+/// The existing namespaces among `namespace_tabs` and an entry for a new one, with the entry `selected` selected
+fn render_namespace_picker<'a>(
+    theme: &Theme,
+    namespace_tabs: &[&str],
+    selected: usize,
+) -> (List<'a>, ListState) {
+    let items: Vec<_> = namespace_tabs
+        .iter()
+        .skip(1)
+        .map(|namespace| ListItem::new((*namespace).to_string()))
+        .chain(std::iter::once(ListItem::new("New namespace")))
+        .collect();
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .style(theme.border_focused)
+                .title(" Move to namespace ")
+                .border_type(BorderType::Plain),
+        )
+        .highlight_style(theme.selection);
+    let mut state = ListState::default();
+    state.select(Some(selected));
+    (list, state)
+}
 
-    let color = match app.control {
-        ControlState::Search if matches!(app.vimode, ViMode::Insert) => highlighted,
-        _ => normal,
+/// The search field, `width` columns wide inside its border, and the column of the cursor in it
+fn render_input<'a>(app: &State, config: &HoardConfig, width: u16) -> (Paragraph<'a>, u16) {
+    let border = match app.control {
+        ControlState::Search if app.vimode != ViMode::Normal || app.prompt.is_some() => {
+            app.theme.border_focused
+        }
+        _ => app.theme.border,
+    };
+    // The input of a bulk action is asked for in place of the search
+    let (query_string, cursor) = match app.prompt {
+        Some(prompt) => input_line(
+            app,
+            &app.prompt_input,
+            &format!("{} > ", prompt.label()),
+            width,
+        ),
+        None => input_line(app, &app.input, &(config.query_prefix.clone() + " "), width),
     };
 
-    let input = Paragraph::new(query_string).block(
+    let input = Paragraph::new(query_string).style(app.theme.text).block(
         Block::default()
-            .style(Style::default().fg(color))
+            .style(border)
             .borders(Borders::ALL)
             .border_type(BorderType::Rounded), // .title(query_title),
     );
-
-    (list, command, tags, description, input)
+    (input, cursor)
 }
 
 const fn get_footer_constraints(control: &ControlState) -> (u16, u16) {
@@ -343,4 +514,3 @@ const fn get_footer_constraints(control: &ControlState) -> (u16, u16) {
         ControlState::Edit => (99, 1),
     }
 }
-
@@ -1,10 +1,22 @@
+mod bulk;
+mod clipboard;
+pub mod command_input;
 pub mod commands_gui;
 pub mod event;
 
 mod help;
+mod highlight;
 mod inline_edit;
+pub mod keymap;
 mod list_search;
+mod mouse;
+// Conflict resolution of `hoard sync`, which is not wired up to the command line in this fork
+#[allow(dead_code)]
 pub mod merge;
 mod new_command;
 mod parameter_input;
 pub mod prompts;
+pub mod terminal;
+mod text_input;
+pub mod theme;
+mod vi;
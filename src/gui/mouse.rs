@@ -0,0 +1,395 @@
+use crate::gui::commands_gui::{ControlState, DrawState, EditSelection, State, ViMode};
+use crate::gui::inline_edit::controls::start_insert;
+use crate::gui::keymap::Action;
+use crate::gui::list_search::controls::{apply_action, switch_namespace};
+use crate::gui::list_search::layout::Areas;
+use hoardlib::core::HoardCmd;
+use ratatui::layout::Rect;
+use std::time::{Duration, Instant};
+use termion::event::{MouseButton, MouseEvent};
+use unicode_width::UnicodeWidthStr;
+
+/// Two clicks on the same command within this time select it like `Enter`
+const DOUBLE_CLICK: Duration = Duration::from_millis(400);
+
+/// The fields of the selected command, which a click starts to edit
+const FIELDS: [EditSelection; 5] = [
+    EditSelection::Name,
+    EditSelection::Namespace,
+    EditSelection::Tags,
+    EditSelection::Description,
+    EditSelection::Command,
+];
+
+/// Handles a click or a turn of the scroll wheel on the list screen
+///
+/// Clicks select namespace tabs and commands, and start to edit the field of the selected command they hit.
+/// The wheel scrolls the command list and the description
+pub fn handler(
+    event: MouseEvent,
+    state: &mut State,
+    trove_commands: &[HoardCmd],
+    namespace_tabs: &[&str],
+) -> Option<HoardCmd> {
+    // A click closes the help, releasing the button after it doesn't do anything else
+    if state.draw == DrawState::Help {
+        if matches!(event, MouseEvent::Press(..)) {
+            state.draw = DrawState::Search;
+        }
+        return None;
+    }
+    // Bulk actions and the namespace picker wait for their keys
+    if state.draw != DrawState::Search || state.prompt.is_some() || state.namespace_picker.is_some()
+    {
+        return None;
+    }
+    let areas = state.areas?;
+    let (button, column, line) = match event {
+        // The terminal counts from 1
+        MouseEvent::Press(button, column, line) => {
+            (button, column.saturating_sub(1), line.saturating_sub(1))
+        }
+        MouseEvent::Release(..) | MouseEvent::Hold(..) => return None,
+    };
+    let hit = |area: Rect| contains(area, column, line);
+
+    match button {
+        MouseButton::WheelUp | MouseButton::WheelDown => {
+            let down = button == MouseButton::WheelDown;
+            if hit(areas.list) && state.control == ControlState::Search {
+                let action = if down {
+                    Action::NextCommand
+                } else {
+                    Action::PreviousCommand
+                };
+                return apply_action(action, state, trove_commands, namespace_tabs, None);
+            }
+            if areas.description.is_some_and(hit) {
+                scroll_description(state, &areas, down);
+            }
+            None
+        }
+        MouseButton::Left => {
+            if hit(areas.tabs) {
+                if let Some(tab) = tab_at(namespace_tabs, areas.tabs, column) {
+                    switch_namespace(state, tab, namespace_tabs, trove_commands);
+                }
+                return None;
+            }
+            if hit(areas.list) {
+                return click_command(state, &areas, line, trove_commands, namespace_tabs);
+            }
+            if hit(areas.input) {
+                state.control = ControlState::Search;
+                state.vimode = ViMode::Insert;
+                return None;
+            }
+            let edited = (state.control == ControlState::Edit).then_some(state.edit_selection);
+            let field = FIELDS
+                .into_iter()
+                .find(|field| areas.field(field, edited == Some(*field)).is_some_and(hit));
+            if let Some(field) = field {
+                edit_field(state, field, trove_commands, namespace_tabs);
+            }
+            None
+        }
+        _ => None,
+    }
+}
+
+/// Selects the command on `line` of the list, and picks it like `Enter` if it was clicked just before
+fn click_command(
+    state: &mut State,
+    areas: &Areas,
+    line: u16,
+    trove_commands: &[HoardCmd],
+    namespace_tabs: &[&str],
+) -> Option<HoardCmd> {
+    // Below the top border of the list
+    let row = usize::from(line.checked_sub(areas.list.y + 1)?);
+    let index = state.command_list.offset() + row;
+    if index >= state.commands.len() {
+        return None;
+    }
+    state.control = ControlState::Search;
+    if state.vimode == ViMode::Insert {
+        state.vi.leave_insert(&mut state.input);
+    }
+    state.vimode = ViMode::Normal;
+    state.command_list.select(Some(index));
+
+    let double_click = state
+        .last_click
+        .take()
+        .is_some_and(|(at, clicked)| clicked == index && at.elapsed() <= DOUBLE_CLICK);
+    if double_click {
+        return apply_action(Action::Select, state, trove_commands, namespace_tabs, None);
+    }
+    state.last_click = Some((Instant::now(), index));
+    None
+}
+
+/// Starts to edit `field` of the selected command
+fn edit_field(
+    state: &mut State,
+    field: EditSelection,
+    trove_commands: &[HoardCmd],
+    namespace_tabs: &[&str],
+) {
+    if state.control == ControlState::Search {
+        if state.vimode == ViMode::Insert {
+            state.vi.leave_insert(&mut state.input);
+        }
+        state.vimode = ViMode::Normal;
+        apply_action(
+            Action::EditCommand,
+            state,
+            trove_commands,
+            namespace_tabs,
+            None,
+        );
+        // Workflows are not edited here
+        if state.control != ControlState::Edit {
+            return;
+        }
+    } else if state.vimode == ViMode::Insert {
+        state.vi.leave_insert(&mut state.string_to_edit);
+    }
+    state.edit_selection = field;
+    state.update_string_to_edit();
+    state.description_scroll = None;
+    start_insert(state, namespace_tabs);
+}
+
+/// Scrolls the description of the selected command by a line, no further than its last line
+fn scroll_description(state: &mut State, areas: &Areas, down: bool) {
+    // The edited description shows the line with the cursor
+    if state.control == ControlState::Edit && state.edit_selection == EditSelection::Description {
+        return;
+    }
+    let (Some(area), Some(command)) = (
+        areas.description,
+        state
+            .command_list
+            .selected()
+            .and_then(|selected| state.commands.get(selected)),
+    ) else {
+        return;
+    };
+    let width = usize::from(area.width.saturating_sub(2)).max(1);
+    let lines: usize = command
+        .description
+        .lines()
+        .map(|line| line.chars().count().div_ceil(width).max(1))
+        .sum();
+    let last = u16::try_from(lines.saturating_sub(usize::from(area.height.saturating_sub(2))))
+        .unwrap_or(u16::MAX);
+    let scrolled = state
+        .description_scroll
+        .as_ref()
+        .filter(|(id, _)| *id == command.id)
+        .map_or(0, |(_, scrolled)| *scrolled);
+    let scrolled = if down {
+        scrolled.saturating_add(1).min(last)
+    } else {
+        scrolled.saturating_sub(1)
+    };
+    state.description_scroll = Some((command.id.clone(), scrolled));
+}
+
+const fn contains(area: Rect, column: u16, line: u16) -> bool {
+    area.x <= column
+        && column < area.x + area.width
+        && area.y <= line
+        && line < area.y + area.height
+}
+
+/// Index of the tab among `namespace_tabs` at `column` of the tabs in `area`, laid out like `Tabs` of ratatui
+/// with a space before and after each title and a divider between them
+fn tab_at(namespace_tabs: &[&str], area: Rect, column: u16) -> Option<usize> {
+    // Inside the border
+    let mut start = area.x + 1;
+    for (index, tab) in namespace_tabs.iter().enumerate() {
+        let width = u16::try_from(tab.width()).unwrap_or(u16::MAX);
+        let end = start.saturating_add(width.saturating_add(2));
+        if (start..end).contains(&column) {
+            return Some(index);
+        }
+        // The divider
+        start = end.saturating_add(1);
+    }
+    None
+}
+
+#[cfg(test)]
+mod test_mouse {
+    use super::*;
+
+    const NAMESPACES: [&str; 2] = ["All", "default"];
+
+    fn create_state(count: usize) -> State {
+        let commands = (0..count)
+            .map(|i| {
+                HoardCmd::default()
+                    .with_name(&format!("command{i}"))
+                    .with_namespace("default")
+                    .with_command(&format!("echo {i}"))
+                    .with_description(
+                        "first line\nsecond line\nthird line\nfourth line\nfifth line",
+                    )
+            })
+            .collect();
+        let mut state = State {
+            areas: Some(Areas::new(Rect::new(0, 0, 120, 40), 3)),
+            ..State::for_test(commands)
+        };
+        state.namespace_tab.select(Some(1));
+        state
+    }
+
+    /// Handles `event` at the 0-based `column` and `line`, the terminal counts from 1
+    fn mouse(
+        state: &mut State,
+        event: fn(MouseButton, u16, u16) -> MouseEvent,
+        button: MouseButton,
+        column: u16,
+        line: u16,
+    ) -> Option<HoardCmd> {
+        let commands = state.commands.clone();
+        handler(
+            event(button, column + 1, line + 1),
+            state,
+            &commands,
+            &NAMESPACES,
+        )
+    }
+
+    fn click(state: &mut State, column: u16, line: u16) -> Option<HoardCmd> {
+        mouse(state, MouseEvent::Press, MouseButton::Left, column, line)
+    }
+
+    /// The column and line of the `row`th entry of the command list
+    fn list_row(state: &State, row: u16) -> (u16, u16) {
+        let list = state.areas.unwrap().list;
+        (list.x + 2, list.y + 1 + row)
+    }
+
+    #[test]
+    fn double_click_selects_a_command() {
+        let mut state = create_state(3);
+        let (column, line) = list_row(&state, 1);
+        assert!(click(&mut state, column, line).is_none());
+        assert_eq!(Some(1), state.command_list.selected());
+
+        let selected = click(&mut state, column, line).unwrap();
+        assert_eq!("command1", selected.name);
+    }
+
+    #[test]
+    fn clicks_on_a_scrolled_list_select_the_shown_command() {
+        let mut state = create_state(10);
+        *state.command_list.offset_mut() = 4;
+        let (column, line) = list_row(&state, 2);
+        click(&mut state, column, line);
+        assert_eq!(Some(6), state.command_list.selected());
+        // Below the last command
+        let (column, line) = list_row(&state, 8);
+        click(&mut state, column, line);
+        assert_eq!(Some(6), state.command_list.selected());
+    }
+
+    #[test]
+    fn wheel_scrolls_the_list_and_the_description() {
+        let mut state = create_state(3);
+        let (column, line) = list_row(&state, 0);
+        mouse(
+            &mut state,
+            MouseEvent::Press,
+            MouseButton::WheelDown,
+            column,
+            line,
+        );
+        mouse(
+            &mut state,
+            MouseEvent::Press,
+            MouseButton::WheelDown,
+            column,
+            line,
+        );
+        assert_eq!(Some(2), state.command_list.selected());
+        mouse(
+            &mut state,
+            MouseEvent::Press,
+            MouseButton::WheelUp,
+            column,
+            line,
+        );
+        assert_eq!(Some(1), state.command_list.selected());
+
+        let description = state.areas.unwrap().description.unwrap();
+        let (column, line) = (description.x + 1, description.y + 1);
+        for _ in 0..10 {
+            mouse(
+                &mut state,
+                MouseEvent::Press,
+                MouseButton::WheelDown,
+                column,
+                line,
+            );
+        }
+        let (id, scrolled) = state.description_scroll.clone().unwrap();
+        assert_eq!(state.commands[1].id, id);
+        // No further than the last line
+        assert_eq!(5u16.saturating_sub(description.height - 2), scrolled);
+    }
+
+    #[test]
+    fn click_on_a_field_edits_it() {
+        let mut state = create_state(2);
+        let name = state.areas.unwrap().name.unwrap();
+        click(&mut state, name.x + 1, name.y + 1);
+        assert_eq!(ControlState::Edit, state.control);
+        assert_eq!(EditSelection::Name, state.edit_selection);
+        assert_eq!(ViMode::Insert, state.vimode);
+        assert_eq!("command0", state.string_to_edit.value());
+    }
+
+    #[test]
+    fn only_a_press_closes_the_help() {
+        let mut state = create_state(1);
+        state.draw = DrawState::Help;
+        let commands = state.commands.clone();
+        handler(
+            MouseEvent::Release(1, 1),
+            &mut state,
+            &commands,
+            &NAMESPACES,
+        );
+        handler(MouseEvent::Hold(1, 1), &mut state, &commands, &NAMESPACES);
+        assert_eq!(DrawState::Help, state.draw);
+        click(&mut state, 0, 0);
+        assert_eq!(DrawState::Search, state.draw);
+    }
+
+    #[test]
+    fn clicks_find_the_namespace_tab() {
+        let tabs = ["All", "default", "git"];
+        let area = Rect::new(0, 0, 40, 3);
+        // `│ All | default | git`
+        assert_eq!(Some(0), tab_at(&tabs, area, 2));
+        assert_eq!(Some(0), tab_at(&tabs, area, 5));
+        assert_eq!(None, tab_at(&tabs, area, 6));
+        assert_eq!(Some(1), tab_at(&tabs, area, 8));
+        assert_eq!(Some(2), tab_at(&tabs, area, 19));
+        assert_eq!(None, tab_at(&tabs, area, 30));
+    }
+
+    #[test]
+    fn areas_contain_their_borders() {
+        let area = Rect::new(2, 3, 4, 2);
+        assert!(contains(area, 2, 3));
+        assert!(contains(area, 5, 4));
+        assert!(!contains(area, 6, 4));
+        assert!(!contains(area, 2, 5));
+    }
+}
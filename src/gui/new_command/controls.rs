@@ -1,6 +1,8 @@
-use crate::core::trove::Trove;
-use crate::core::{string_to_tags, HoardCmd};
 use crate::gui::commands_gui::{DrawState, EditSelection, State};
+use crate::gui::keymap::{Action, Dispatch, Screen};
+use crate::gui::text_input::TextInput;
+use hoardlib::core::trove::Trove;
+use hoardlib::core::{string_to_tags, HoardCmd};
 use termion::event::Key;
 
 pub fn key_handler(input: Key, app: &mut State, default_namespace: &str) -> Option<HoardCmd> {
@@ -8,23 +10,34 @@ pub fn key_handler(input: Key, app: &mut State, default_namespace: &str) -> Opti
     if app.new_command.is_none() {
         app.new_command = Some(HoardCmd::default());
     }
-    match input {
-        Key::Esc => {
+    let action = match app.keymap.dispatch(Screen::Create, input) {
+        Dispatch::Action(action) => action,
+        Dispatch::Pending => return None,
+        // Handle query input
+        Dispatch::Unbound(keys) => {
+            for key in keys {
+                app.input.handle_key(key);
+            }
+            return None;
+        }
+    };
+    match action {
+        Action::Cancel => {
             app.draw = DrawState::Search;
             app.new_command = None;
             app.edit_selection = EditSelection::Command;
             None
         }
         // Quit command
-        Key::Ctrl('c' | 'd' | 'g') => {
+        Action::Quit => {
             app.should_exit = true;
             app.new_command = None;
             app.edit_selection = EditSelection::Command;
             None
         }
-        Key::Char('\n') => {
+        Action::Submit => {
             let mut command = app.new_command.clone().unwrap();
-            let parameter = app.input.clone();
+            let parameter = app.input.value().to_string();
             app.error_message = match app.edit_selection {
                 EditSelection::Command => {
                     command.command = parameter.clone();
@@ -72,7 +85,7 @@ pub fn key_handler(input: Key, app: &mut State, default_namespace: &str) -> Opti
                     }
                 },
             };
-            app.input = String::new();
+            app.input = TextInput::default();
             if !app.error_message.is_empty() {
                 return None;
             }
@@ -83,15 +96,7 @@ pub fn key_handler(input: Key, app: &mut State, default_namespace: &str) -> Opti
             app.new_command = Some(command);
             None
         }
-        // Handle query input
-        Key::Backspace => {
-            app.input.pop();
-            None
-        }
-        Key::Char(c) => {
-            app.input.push(c);
-            None
-        }
+        // Actions of the other screens
         _ => None,
     }
 }
@@ -1,60 +1,46 @@
-use crate::config::HoardConfig;
 use crate::gui::commands_gui::State;
-use ratatui::backend::TermionBackend;
+use crate::gui::terminal::HoardTerminal;
+use hoardlib::config::HoardConfig;
 use ratatui::layout::{Constraint, Direction, Layout};
-use ratatui::style::{Color, Style};
 use ratatui::widgets::{Block, Paragraph};
-use ratatui::Terminal;
-use termion::screen::AlternateScreen;
-
-const IBEAM: &str = "▕";
 
 pub fn draw(
     app_state: &State,
     config: &HoardConfig,
-    terminal: &mut Terminal<
-        TermionBackend<AlternateScreen<termion::raw::RawTerminal<std::io::Stdout>>>,
-    >,
+    terminal: &mut HoardTerminal,
     default_namespace: &str,
 ) -> Result<(), eyre::Error> {
     terminal.draw(|rect| {
         let size = rect.size();
-        // Overlay
+        // Overlay. The new command, the input and the error keep their lines in low terminals
         let overlay_chunks = Layout::default()
             .direction(Direction::Vertical)
             .margin(1)
             .constraints(
                 [
-                    Constraint::Percentage(30),
-                    Constraint::Percentage(30),
-                    Constraint::Percentage(10),
-                    Constraint::Percentage(20),
-                    Constraint::Percentage(10),
+                    Constraint::Percentage(25),
+                    Constraint::Length(7),
+                    Constraint::Length(2),
+                    Constraint::Min(2),
                 ]
                 .as_ref(),
             )
             .split(size);
 
-        let mut query_string = config.query_prefix.clone();
-        query_string.push_str(&app_state.input.clone()[..]);
-
         let title_string = format!("Provide {} for the command", app_state.edit_selection);
 
-        let command_style = Style::default().fg(Color::Rgb(
-            config.command_color.unwrap().0,
-            config.command_color.unwrap().1,
-            config.command_color.unwrap().2,
-        ));
+        let command_style = app_state.theme.command;
+        let primary_style = app_state.theme.text;
 
-        let primary_style = Style::default().fg(Color::Rgb(
-            config.primary_color.unwrap().0,
-            config.primary_color.unwrap().1,
-            config.primary_color.unwrap().2,
-        ));
-
-        let input = Paragraph::new(query_string + IBEAM)
+        let input_block = Block::default().style(command_style).title(title_string);
+        let input_area = input_block.inner(overlay_chunks[2]);
+        let (query_string, cursor) =
+            app_state
+                .input
+                .view(&config.query_prefix, input_area.width, false);
+        let input = Paragraph::new(query_string)
             .style(primary_style)
-            .block(Block::default().style(command_style).title(title_string));
+            .block(input_block);
         let new_command = app_state.new_command.clone().unwrap();
         let command_render = format!(
             "Command: {}\nNamespace: {}(\"{}\" if empty)\nName: {}\nDescription: {}\nTags: {}",
@@ -70,11 +56,12 @@ pub fn draw(
             .block(Block::default().style(command_style).title("New command:"));
 
         let error_message = Paragraph::new(app_state.error_message.clone())
-            .style(primary_style)
+            .style(app_state.theme.error)
             .block(Block::default().style(command_style).title("Error:"));
 
         rect.render_widget(new_command, overlay_chunks[1]);
         rect.render_widget(input, overlay_chunks[2]);
+        rect.set_cursor(input_area.x + cursor, input_area.y);
         if !app_state.error_message.is_empty() {
             rect.render_widget(error_message, overlay_chunks[3]);
         }
@@ -1,40 +1,262 @@
-use crate::core::parameters::Parameterized;
-use crate::core::HoardCmd;
 use crate::gui::commands_gui::State;
+use crate::gui::keymap::{Action, Dispatch, Screen};
+use crate::util::hide_from_history;
+use hoardlib::core::parameters::{
+    fill_next_parameter, replace_secrets, secret_names, Parameterized,
+};
+use hoardlib::core::workflow::{self, join_steps};
+use hoardlib::core::HoardCmd;
 use termion::event::Key;
 
+/// What the parameter input asks the user for next
+#[derive(Debug, Eq, PartialEq)]
+pub enum ParameterPrompt {
+    /// The value of the next parameter
+    Parameter,
+    /// The passphrase of the vault, which holds some of the secrets of the command
+    Passphrase,
+    /// The value of a secret which is not in the vault
+    Secret(String),
+    /// Everything is provided
+    Done,
+}
+
+impl ParameterPrompt {
+    /// Input which must not be shown on screen
+    pub const fn is_masked(&self) -> bool {
+        matches!(self, Self::Passphrase | Self::Secret(_))
+    }
+}
+
+pub fn next_prompt(app: &State) -> ParameterPrompt {
+    let Some(command) = app.selected_command.as_ref() else {
+        return ParameterPrompt::Done;
+    };
+    if command.has_input_parameters(&app.parameter_token, &app.parameter_ending_token) {
+        return ParameterPrompt::Parameter;
+    }
+    secret_names(
+        &command.command,
+        &app.parameter_token,
+        &app.parameter_ending_token,
+    )
+    .into_iter()
+    .find(|name| !app.secrets.contains_key(name))
+    .map_or(ParameterPrompt::Done, |name| {
+        let in_vault = app
+            .vault
+            .as_ref()
+            .is_some_and(|vault| vault.contains(&name));
+        if in_vault && app.vault_key.is_none() {
+            ParameterPrompt::Passphrase
+        } else {
+            ParameterPrompt::Secret(name)
+        }
+    })
+}
+
 pub fn key_handler(input: Key, app: &mut State) -> Option<HoardCmd> {
-    match input {
+    let action = match app.keymap.dispatch(Screen::Parameters, input) {
+        Dispatch::Action(action) => action,
+        Dispatch::Pending => return None,
+        // Handle query input
+        Dispatch::Unbound(keys) => {
+            for key in keys {
+                app.input.handle_key(key);
+            }
+            return None;
+        }
+    };
+    match action {
         // Quit command
-        Key::Esc | Key::Ctrl('c' | 'd' | 'g') => {
+        Action::Quit => {
             app.should_exit = true;
             None
         }
-        Key::Char('\n') => {
+        Action::Submit => {
             let command = app.selected_command.clone().unwrap();
-            let parameter = app.input.clone();
-            let replaced_command = command.replace_parameter(
+            let parameter = app.input.take();
+            app.error_message = String::new();
+            app.filled
+                .resize_with(app.workflow_steps.len().max(1), Vec::new);
+            match next_prompt(app) {
+                ParameterPrompt::Parameter if !app.workflow_steps.is_empty() => {
+                    workflow::fill_parameter(
+                        &mut app.workflow_steps,
+                        &app.parameter_token,
+                        &app.parameter_ending_token,
+                        &parameter,
+                        &mut app.filled,
+                    );
+                    app.selected_command = Some(HoardCmd {
+                        command: join_steps(&app.workflow_steps),
+                        ..command.clone()
+                    });
+                    app.provided_parameter_count += 1;
+                }
+                ParameterPrompt::Parameter => {
+                    let replaced = fill_next_parameter(
+                        &command.command,
+                        &app.parameter_token,
+                        &app.parameter_ending_token,
+                        &parameter,
+                        &mut app.filled[0],
+                    );
+                    // The id is kept to count the usage of the command
+                    app.selected_command = Some(HoardCmd {
+                        command: replaced,
+                        ..command.clone()
+                    });
+                    app.provided_parameter_count += 1;
+                }
+                ParameterPrompt::Passphrase => unlock_vault(app, &command, &parameter),
+                ParameterPrompt::Secret(name) => {
+                    app.secrets.insert(name, parameter);
+                }
+                ParameterPrompt::Done => (),
+            }
+            if next_prompt(app) != ParameterPrompt::Done {
+                return None;
+            }
+            let command = app.selected_command.clone().unwrap();
+            if app.secrets.is_empty() {
+                return Some(command);
+            }
+            let filled = replace_secrets(
+                &command.command,
                 &app.parameter_token,
                 &app.parameter_ending_token,
-                &parameter,
+                &app.secrets,
             );
-            app.input = String::new();
-            if replaced_command.get_parameter_count(&app.parameter_token) == 0 {
-                return Some(replaced_command);
-            }
-            app.selected_command = Some(replaced_command);
-            app.provided_parameter_count += 1;
-            None
+            Some(HoardCmd {
+                command: hide_from_history(&filled),
+                ..command
+            })
         }
-        // Handle query input
-        Key::Backspace => {
-            app.input.pop();
-            None
+        // Actions of the other screens
+        _ => None,
+    }
+}
+
+/// Unlocks the vault with `passphrase` and takes the secrets of `command` from it
+fn unlock_vault(app: &mut State, command: &HoardCmd, passphrase: &str) {
+    let Some(vault) = app.vault.as_mut() else {
+        return;
+    };
+    let key = match vault.unlock(passphrase) {
+        Ok(key) => key,
+        Err(e) => {
+            app.error_message = e.to_string();
+            return;
         }
-        Key::Char(c) => {
-            app.input.push(c);
-            None
+    };
+    for name in secret_names(
+        &command.command,
+        &app.parameter_token,
+        &app.parameter_ending_token,
+    ) {
+        match vault.get(&key, &name) {
+            Ok(Some(value)) => {
+                app.secrets.insert(name, value);
+            }
+            Ok(None) => (),
+            Err(e) => app.error_message = e.to_string(),
         }
-        _ => None,
+    }
+    app.vault_key = Some(key);
+}
+
+#[cfg(test)]
+mod test_controls {
+    use super::*;
+    use crate::gui::commands_gui::DrawState;
+    use crate::gui::text_input::TextInput;
+    use hoardlib::core::vault::Vault;
+
+    fn create_state(command: &str, vault: Option<Vault>) -> State {
+        State {
+            draw: DrawState::ParameterInput,
+            selected_command: Some(HoardCmd::default().with_command(command)),
+            vault,
+            ..State::for_test(Vec::new())
+        }
+    }
+
+    fn enter(state: &mut State, input: &str) -> Option<HoardCmd> {
+        state.input = TextInput::new(input);
+        key_handler(Key::Char('\n'), state)
+    }
+
+    #[test]
+    fn secrets_are_asked_for_last() {
+        let mut state = create_state("curl -u #secret:user! #url!", None);
+        let id = state.selected_command.as_ref().unwrap().id.clone();
+        assert_eq!(ParameterPrompt::Parameter, next_prompt(&state));
+        assert!(enter(&mut state, "example.com").is_none());
+
+        assert_eq!(
+            ParameterPrompt::Secret("user".to_string()),
+            next_prompt(&state)
+        );
+        let command = enter(&mut state, "me:hunter2").unwrap();
+        assert_eq!(" curl -u me:hunter2 example.com", command.command);
+        // The usage of the picked command is counted by its id
+        assert_eq!(id, command.id);
+    }
+
+    #[test]
+    fn workflow_steps_are_walked_through() {
+        let steps: Vec<HoardCmd> = ["kubectl drain #node! #", "ssh #node! reboot"]
+            .iter()
+            .map(|command| HoardCmd::default().with_command(command))
+            .collect();
+        let mut state = create_state(&join_steps(&steps), None);
+        state.workflow_steps = steps;
+
+        assert!(enter(&mut state, "worker-1").is_none());
+        assert_eq!(
+            Some(0),
+            workflow::current_step(&state.workflow_steps, "#", "!")
+        );
+        let command = enter(&mut state, "--force").unwrap();
+        assert_eq!(
+            "kubectl drain worker-1 --force && ssh worker-1 reboot",
+            command.command
+        );
+    }
+
+    #[test]
+    fn filled_values_are_told_apart() {
+        let steps: Vec<HoardCmd> = ["ssh #host! uptime", "scp #host!:#file! ."]
+            .iter()
+            .map(|command| HoardCmd::default().with_command(command))
+            .collect();
+        let mut state = create_state(&join_steps(&steps), None);
+        state.workflow_steps = steps;
+
+        enter(&mut state, "my host");
+        assert_eq!(vec![vec![4..13], vec![4..13]], state.filled);
+        enter(&mut state, "log");
+        assert_eq!(vec![vec![4..13], vec![4..13, 14..17]], state.filled);
+
+        let mut state = create_state("ssh #host! #!", None);
+        enter(&mut state, "pi");
+        assert_eq!(vec![vec![4..6]], state.filled);
+    }
+
+    #[test]
+    fn secrets_are_filled_from_the_vault() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let mut vault = Vault::open(&tmp_dir.path().join("vault.yml")).unwrap();
+        let key = vault.unlock("passphrase").unwrap();
+        vault.set(&key, "token", "s3cr3t").unwrap();
+
+        let mut state = create_state("curl -H #secret:token!", Some(vault));
+        assert_eq!(ParameterPrompt::Passphrase, next_prompt(&state));
+        assert!(enter(&mut state, "wrong").is_none());
+        assert!(!state.error_message.is_empty());
+
+        let command = enter(&mut state, "passphrase").unwrap();
+        assert_eq!(" curl -H s3cr3t", command.command);
     }
 }
@@ -1,129 +1,150 @@
-use crate::config::HoardConfig;
 use crate::gui::commands_gui::State;
-use crate::util::{split_with_delim, string_find_next, translate_number_to_nth};
-use ratatui::backend::TermionBackend;
+use crate::gui::highlight::command_lines;
+use crate::gui::parameter_input::controls::{next_prompt, ParameterPrompt};
+use crate::gui::terminal::HoardTerminal;
+use crate::util::translate_number_to_nth;
+use hoardlib::config::HoardConfig;
+use hoardlib::core::parameters::parse_parameters;
+use hoardlib::core::workflow::current_step;
 use ratatui::layout::{Alignment, Constraint, Direction, Layout};
-use ratatui::style::{Color, Style};
-use ratatui::text::{Line, Span};
 use ratatui::widgets::{Block, Paragraph, Wrap};
-use ratatui::Terminal;
-use termion::screen::AlternateScreen;
+use std::ops::Range;
 
 pub fn draw(
     app_state: &State,
     config: &HoardConfig,
-    terminal: &mut Terminal<
-        TermionBackend<AlternateScreen<termion::raw::RawTerminal<std::io::Stdout>>>,
-    >,
+    terminal: &mut HoardTerminal,
 ) -> Result<(), eyre::Error> {
     terminal.draw(|rect| {
         let size = rect.size();
-        // Overlay
+        // Overlay. The command and the input keep a line each in low terminals
         let overlay_chunks = Layout::default()
             .direction(Direction::Vertical)
             .margin(1)
             .constraints(
                 [
                     Constraint::Percentage(40),
-                    Constraint::Percentage(10),
-                    Constraint::Percentage(10),
-                    Constraint::Percentage(40),
+                    Constraint::Length((size.height / 10).max(1)),
+                    Constraint::Length(2),
+                    Constraint::Min(0),
                 ]
                 .as_ref(),
             )
             .split(size);
 
-        let mut query_string = config.query_prefix.clone();
-        query_string.push_str(&app_state.input.clone()[..]);
-
-        let title_string = format!(
-            "Provide {} parameter",
-            translate_number_to_nth(app_state.provided_parameter_count)
-        );
-
-        let command_style = Style::default().fg(Color::Rgb(
-            config.command_color.unwrap().0,
-            config.command_color.unwrap().1,
-            config.command_color.unwrap().2,
-        ));
-
-        let primary_style = Style::default().fg(Color::Rgb(
-            config.primary_color.unwrap().0,
-            config.primary_color.unwrap().1,
-            config.primary_color.unwrap().2,
-        ));
-
-        let input = Paragraph::new(query_string)
-            .style(primary_style)
-            .block(Block::default().style(command_style).title(title_string));
-
-        let command_text = app_state
-            .selected_command
-            .as_ref()
-            .unwrap()
-            .command
-            .as_str();
+        let prompt = next_prompt(app_state);
 
         let token = config.parameter_token.as_ref().unwrap().as_str();
         let ending_token = config.parameter_ending_token.as_ref().unwrap().as_str();
 
-        // Named parameter ending with a space
-        let named_token = string_find_next(command_text, token, " ");
-
-        // Named parameter ending with ending token. If ending token is not used, `full_named_token` is an empty string
-        let mut full_named_token = string_find_next(command_text, token, ending_token);
-
-        full_named_token.push_str(ending_token);
-        // Select the split based on whether the ending token is part of the command or not
-        
-        let split_token = if command_text.contains(ending_token) {
-            full_named_token
-        } else {
-            named_token
+        // The step of a workflow the next parameter belongs to
+        let step = current_step(&app_state.workflow_steps, token, ending_token)
+            .filter(|_| prompt == ParameterPrompt::Parameter);
+
+        let mut title_string = match &prompt {
+            ParameterPrompt::Passphrase => "Vault passphrase".to_string(),
+            ParameterPrompt::Secret(name) => format!("Provide secret {name}"),
+            ParameterPrompt::Parameter | ParameterPrompt::Done => format!(
+                "Provide {} parameter",
+                translate_number_to_nth(app_state.provided_parameter_count)
+            ),
         };
+        if let Some(step) = step {
+            title_string = format!(
+                "Step {}/{} {}: {title_string}",
+                step + 1,
+                app_state.workflow_steps.len(),
+                app_state.workflow_steps[step].name
+            );
+        }
 
-        let mut command_spans: Vec<Span> = Vec::new();
-        let split_commands: Vec<String> = split_with_delim(command_text, &split_token);
-
-        if token == split_token {
-            // If the next token to replace is not named
-            let command_parts = command_text.split_once(token);
-
-            let mut spans: Vec<Span> = if let Some((begin, end)) = command_parts {
-                vec![
-                    Span::styled(begin, command_style),
-                    Span::styled(token, primary_style),
-                    Span::styled(end, command_style),
-                ]
-            } else {
-                vec![Span::styled(command_text, command_style)]
-            };
+        let command_style = app_state.theme.command;
+        let primary_style = app_state.theme.text;
 
-            command_spans.append(&mut spans);
-        } else {
-            // if the next token to replaced is named, find all other occurrences and paint them too
-            let mut spans = split_commands
-                .iter()
-                .map(|e| {
-                    if *e == split_token {
-                        (e, primary_style)
-                    } else {
-                        (e, command_style)
-                    }
-                })
-                .map(|(command, style)| Span::styled(command, style))
-                .collect();
-            command_spans.append(&mut spans);
-        }
+        let input_block = Block::default().style(command_style).title(title_string);
+        let input_area = input_block.inner(overlay_chunks[2]);
+        // Secrets are never shown on screen
+        let (query_string, cursor) =
+            app_state
+                .input
+                .view(&config.query_prefix, input_area.width, prompt.is_masked());
+        let input = Paragraph::new(query_string)
+            .style(primary_style)
+            .block(input_block);
+
+        let (command_text, filled) = match step {
+            Some(step) => (
+                app_state.workflow_steps[step].command.as_str(),
+                app_state.filled.get(step),
+            ),
+            // The values can't be told apart in the joined steps of a workflow
+            None if !app_state.workflow_steps.is_empty() => (
+                app_state
+                    .selected_command
+                    .as_ref()
+                    .unwrap()
+                    .command
+                    .as_str(),
+                None,
+            ),
+            None => (
+                app_state
+                    .selected_command
+                    .as_ref()
+                    .unwrap()
+                    .command
+                    .as_str(),
+                app_state.filled.first(),
+            ),
+        };
+        let next = asked_parameters(command_text, token, ending_token, &prompt);
+        let command_lines = command_lines(
+            command_text,
+            config,
+            &app_state.theme,
+            filled.map_or(&[], Vec::as_slice),
+            &next,
+        );
 
-        let command = Paragraph::new(Line::from(command_spans))
+        let command = Paragraph::new(command_lines)
+            .style(command_style)
             .alignment(Alignment::Center)
             .wrap(Wrap { trim: true })
             .block(Block::default().style(primary_style));
 
         rect.render_widget(command, overlay_chunks[1]);
         rect.render_widget(input, overlay_chunks[2]);
+        rect.set_cursor(input_area.x + cursor, input_area.y);
+        if !app_state.error_message.is_empty() {
+            let error_message = Paragraph::new(app_state.error_message.clone())
+                .style(app_state.theme.error)
+                .alignment(Alignment::Center);
+            rect.render_widget(error_message, overlay_chunks[3]);
+        }
     })?;
 
     Ok(())
 }
+
+/// Ranges of the parameters of `command` `prompt` asks for the value of. A named parameter is asked for everywhere at once
+fn asked_parameters(
+    command: &str,
+    token: &str,
+    ending_token: &str,
+    prompt: &ParameterPrompt,
+) -> Vec<Range<usize>> {
+    let parameters = parse_parameters(command, token, ending_token);
+    let asked = match prompt {
+        ParameterPrompt::Parameter => parameters.iter().find(|p| p.secret_name().is_none()),
+        ParameterPrompt::Secret(name) => parameters.iter().find(|p| p.secret_name() == Some(name)),
+        ParameterPrompt::Passphrase | ParameterPrompt::Done => None,
+    };
+    let Some(asked) = asked else {
+        return Vec::new();
+    };
+    parameters
+        .iter()
+        .filter(|p| p.range == asked.range || (!asked.name.is_empty() && p.name == asked.name))
+        .map(|p| p.range.clone())
+        .collect()
+}
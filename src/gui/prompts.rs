@@ -1,5 +1,5 @@
 // use crate::gui::theme::HoardTheme;
-use dialoguer::{Input, MultiSelect, Select};
+use dialoguer::{Input, MultiSelect, Password, Select};
 pub enum Confirmation {
     Yes,
     No,
@@ -67,6 +67,16 @@ pub fn prompt_input(text: &str, allow_empty: bool, default_value: Option<String>
     )
 }
 
+/// Prompts for a secret without echoing it. If `confirm` is set, it has to be entered twice
+pub fn prompt_password(text: &str, confirm: bool) -> String {
+    let mut password = Password::new();
+    password.with_prompt(text);
+    if confirm {
+        password.with_confirmation("Repeat to confirm", "The inputs don't match");
+    }
+    password.interact().unwrap()
+}
+
 pub fn prompt_input_validate<F>(
     text: &str,
     allow_empty: bool,
@@ -90,8 +100,6 @@ where
     input.with_prompt(text).interact_text().unwrap()
 }
 
-
-
 fn take_elements_by_indices<T>(elements: &[T], indices: &[usize]) -> Vec<T>
 where
     T: Clone,
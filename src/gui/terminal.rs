@@ -0,0 +1,150 @@
+use ratatui::backend::{Backend, TermionBackend};
+use ratatui::layout::Rect;
+use ratatui::{Terminal, TerminalOptions, Viewport};
+use std::io::{self, stdout, Write};
+use std::str::FromStr;
+use termion::input::MouseTerminal;
+use termion::raw::IntoRawMode;
+use termion::screen::IntoAlternateScreen;
+
+/// The terminal the TUI draws on, either the alternate screen or the lines below the prompt
+pub type HoardTerminal = Terminal<TermionBackend<Box<dyn Write>>>;
+
+/// Fewest lines of the TUI below the prompt, which still show the list, the search and the hints
+const MIN_INLINE_HEIGHT: u16 = 10;
+
+/// Height of the TUI below the prompt, in lines or in percent of the terminal like `--height` of fzf
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Height {
+    Lines(u16),
+    Percent(u16),
+}
+
+impl FromStr for Height {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            format!(
+                "Invalid height `{s}`, use a number of lines like `20` or a percentage like `40%`"
+            )
+        };
+        let height = match s.trim().strip_suffix('%') {
+            Some(percent) => Self::Percent(percent.parse().map_err(|_| invalid())?),
+            None => Self::Lines(s.trim().parse().map_err(|_| invalid())?),
+        };
+        match height {
+            Self::Lines(0) | Self::Percent(0) => Err(invalid()),
+            Self::Percent(percent) if percent > 100 => Err(invalid()),
+            height => Ok(height),
+        }
+    }
+}
+
+impl Height {
+    /// Lines of the TUI in a terminal with `rows` lines, keeping the line of the prompt above it
+    pub fn lines(self, rows: u16) -> u16 {
+        let lines = match self {
+            Self::Lines(lines) => lines,
+            Self::Percent(percent) => {
+                u16::try_from(u32::from(rows) * u32::from(percent) / 100).unwrap_or(rows)
+            }
+        };
+        lines
+            .max(MIN_INLINE_HEIGHT)
+            .min(rows.saturating_sub(1))
+            .max(1)
+    }
+}
+
+/// The terminal of the TUI in raw mode, which is given back as it was found when this is dropped
+pub struct Tui {
+    pub terminal: HoardTerminal,
+    // Position of the cursor on the prompt the TUI opened below, in inline mode
+    prompt: Option<(u16, u16)>,
+}
+
+impl Tui {
+    /// Takes over the whole terminal on the alternate screen, or `height` lines below the cursor
+    ///
+    /// With `mouse` the terminal reports clicks and the scroll wheel instead of selecting text
+    pub fn open(height: Option<Height>, mouse: bool) -> io::Result<Self> {
+        let stdout = stdout().into_raw_mode()?;
+        let Some(height) = height else {
+            let stdout = stdout.into_alternate_screen()?;
+            let stdout: Box<dyn Write> = if mouse {
+                Box::new(MouseTerminal::from(stdout))
+            } else {
+                Box::new(stdout)
+            };
+            let mut terminal = Terminal::new(TermionBackend::new(stdout))?;
+            terminal.clear()?;
+            return Ok(Self {
+                terminal,
+                prompt: None,
+            });
+        };
+
+        let stdout: Box<dyn Write> = if mouse {
+            Box::new(MouseTerminal::from(stdout))
+        } else {
+            Box::new(stdout)
+        };
+        let mut backend = TermionBackend::new(stdout);
+        let size = backend.size()?;
+        // A terminal which doesn't tell where the cursor is gets the TUI at its bottom
+        let (x, y) = backend
+            .get_cursor()
+            .unwrap_or((0, size.height.saturating_sub(1)));
+        let height = height.lines(size.height);
+        // The screen scrolls up if there are not enough lines below the prompt
+        backend.append_lines(height)?;
+        let y = y.saturating_sub((y + height + 1).saturating_sub(size.height));
+        let area = Rect::new(0, y + 1, size.width, height);
+        let mut terminal = Terminal::with_options(
+            backend,
+            TerminalOptions {
+                viewport: Viewport::Fixed(area),
+            },
+        )?;
+        terminal.clear()?;
+        Ok(Self {
+            terminal,
+            prompt: Some((x, y)),
+        })
+    }
+}
+
+impl Drop for Tui {
+    fn drop(&mut self) {
+        // The lines of the TUI are cleared and the cursor goes back onto the prompt
+        if let Some((x, y)) = self.prompt {
+            let _ = self.terminal.clear();
+            let _ = self.terminal.set_cursor(x, y);
+        }
+        let _ = self.terminal.show_cursor();
+    }
+}
+
+#[cfg(test)]
+mod test_terminal {
+    use super::*;
+
+    #[test]
+    fn parse_heights() {
+        assert_eq!(Ok(Height::Lines(20)), "20".parse());
+        assert_eq!(Ok(Height::Percent(40)), "40%".parse());
+        assert!("0".parse::<Height>().is_err());
+        assert!("120%".parse::<Height>().is_err());
+        assert!("half".parse::<Height>().is_err());
+    }
+
+    #[test]
+    fn heights_fit_below_the_prompt() {
+        assert_eq!(20, Height::Percent(40).lines(50));
+        assert_eq!(15, Height::Lines(15).lines(50));
+        assert_eq!(MIN_INLINE_HEIGHT, Height::Lines(3).lines(50));
+        assert_eq!(49, Height::Percent(100).lines(50));
+        assert_eq!(7, Height::Lines(20).lines(8));
+    }
+}
@@ -0,0 +1,375 @@
+use std::cell::Cell;
+use std::ops::Range;
+use termion::event::Key;
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+/// A single line of editable text with a cursor, used by every input field of the TUI
+///
+/// The cursor moves by grapheme, so a character built from several code points is edited as one.
+/// Supports the usual readline keys:
+///
+/// | Key | Action |
+/// |-----|--------|
+/// | `Left` / `Ctrl-B`, `Right` / `Ctrl-F` | Move by one character |
+/// | `Alt-B`, `Alt-F` | Move by one word |
+/// | `Home` / `Ctrl-A`, `End` / `Ctrl-E` | Move to the start or the end |
+/// | `Backspace`, `Delete` | Delete the character before or under the cursor |
+/// | `Ctrl-W`, `Alt-D` | Delete the word before or after the cursor |
+/// | `Ctrl-U`, `Ctrl-K` | Delete everything before or after the cursor |
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct TextInput {
+    value: String,
+    // Byte offset of the cursor in `value`, always at the start of a grapheme
+    cursor: usize,
+    // Index of the first grapheme shown, kept between frames so the view only scrolls when the cursor leaves it
+    scroll: Cell<usize>,
+    // Values and cursors to go back to with undo, the latest last
+    history: Vec<(String, usize)>,
+    // Values undone, to go forward to again with redo
+    future: Vec<(String, usize)>,
+}
+
+impl TextInput {
+    /// An input holding `value`, with the cursor at its end
+    pub fn new(value: &str) -> Self {
+        Self {
+            value: value.to_string(),
+            cursor: value.len(),
+            scroll: Cell::new(0),
+            history: Vec::new(),
+            future: Vec::new(),
+        }
+    }
+
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+
+    /// Byte offset of the cursor in the value
+    pub const fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    /// Moves the cursor to the byte offset `cursor`, which has to be at the start of a grapheme
+    pub fn set_cursor(&mut self, cursor: usize) {
+        self.cursor = cursor.min(self.value.len());
+    }
+
+    /// Replaces the bytes in `range` with `text`, leaving the cursor at the start of `range`
+    pub fn replace_range(&mut self, range: Range<usize>, text: &str) {
+        self.cursor = range.start;
+        self.value.replace_range(range, text);
+    }
+
+    /// Remembers the current value, so the changes made from now on can be undone at once
+    pub fn checkpoint(&mut self) {
+        self.history.push((self.value.clone(), self.cursor));
+        self.future.clear();
+    }
+
+    /// Goes back to the value before the last change. Returns `false` if there is nothing to undo
+    pub fn undo(&mut self) -> bool {
+        // Checkpoints without a change after them are skipped
+        while let Some((value, cursor)) = self.history.pop() {
+            if value != self.value {
+                let current = std::mem::replace(&mut self.value, value);
+                self.future.push((current, self.cursor));
+                self.cursor = cursor;
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Goes forward to the value the last undo went back from. Returns `false` if there is nothing to redo
+    pub fn redo(&mut self) -> bool {
+        let Some((value, cursor)) = self.future.pop() else {
+            return false;
+        };
+        let current = std::mem::replace(&mut self.value, value);
+        self.history.push((current, self.cursor));
+        self.cursor = cursor;
+        true
+    }
+
+    /// Replaces the text, moving the cursor to its end
+    pub fn set_value(&mut self, value: &str) {
+        *self = Self::new(value);
+    }
+
+    /// Empties the input and returns what was typed
+    pub fn take(&mut self) -> String {
+        std::mem::take(self).value
+    }
+
+    /// Inserts `text` at the cursor, e.g. pasted text. Line breaks become spaces, the input holds a single line
+    pub fn insert(&mut self, text: &str) {
+        let text = text.replace("\r\n", " ").replace(['\n', '\r'], " ");
+        self.value.insert_str(self.cursor, &text);
+        self.cursor += text.len();
+    }
+
+    /// Applies an editing key. Returns `false` for keys which don't edit text, so the screen can handle them
+    pub fn handle_key(&mut self, key: Key) -> bool {
+        match key {
+            Key::Char('\n' | '\t') => return false,
+            Key::Char(c) => self.insert(c.encode_utf8(&mut [0; 4])),
+            Key::Left | Key::Ctrl('b') => self.cursor = self.previous_boundary(),
+            Key::Right | Key::Ctrl('f') => self.cursor = self.next_boundary(),
+            Key::Alt('b') => self.cursor = self.word_start(is_word_character),
+            Key::Alt('f') => self.cursor = self.word_end(is_word_character),
+            Key::Home | Key::Ctrl('a') => self.cursor = 0,
+            Key::End | Key::Ctrl('e') => self.cursor = self.value.len(),
+            Key::Backspace => self.delete_to(self.previous_boundary()),
+            Key::Delete => self.delete_to(self.next_boundary()),
+            Key::Ctrl('w') => self.delete_to(self.word_start(is_not_whitespace)),
+            Key::Alt('d') => self.delete_to(self.word_end(is_word_character)),
+            Key::Ctrl('u') => self.delete_to(0),
+            Key::Ctrl('k') => self.delete_to(self.value.len()),
+            _ => return false,
+        }
+        true
+    }
+
+    /// The text shown in a field `width` columns wide which starts with `prefix`, and the column of the cursor in it
+    ///
+    /// The text scrolls horizontally to keep the cursor visible. With `masked` every character is shown as `*`
+    pub fn view(&self, prefix: &str, width: u16, masked: bool) -> (String, u16) {
+        let width = usize::from(width).saturating_sub(prefix.width());
+        let graphemes: Vec<&str> = self
+            .value
+            .graphemes(true)
+            .map(|g| match g {
+                _ if masked => "*",
+                "\n" | "\r\n" => "↵",
+                g => g,
+            })
+            .collect();
+        let cursor = self.value[..self.cursor].graphemes(true).count();
+        let columns = |range: &[&str]| range.iter().map(|g| g.width().max(1)).sum::<usize>();
+
+        let mut scroll = self.scroll.get().min(cursor);
+        // One column is left for the cursor behind the last character
+        while scroll < cursor && columns(&graphemes[scroll..cursor]) + 1 > width {
+            scroll += 1;
+        }
+        self.scroll.set(scroll);
+
+        let mut shown = prefix.to_string();
+        let mut shown_width = 0;
+        for grapheme in &graphemes[scroll..] {
+            shown_width += grapheme.width().max(1);
+            if shown_width > width {
+                break;
+            }
+            shown.push_str(grapheme);
+        }
+        let column = prefix.width() + columns(&graphemes[scroll..cursor]);
+        (shown, u16::try_from(column).unwrap_or(u16::MAX))
+    }
+
+    /// Like [`TextInput::view`], but split into the text before, in and after the bytes of `selection`
+    pub fn selection_view(
+        &self,
+        prefix: &str,
+        width: u16,
+        selection: Range<usize>,
+    ) -> ([String; 3], u16) {
+        let (shown, column) = self.view(prefix, width, false);
+        let mut parts = [prefix.to_string(), String::new(), String::new()];
+        // The view shows one grapheme for every grapheme of the value from the first one scrolled to
+        let mut shown_graphemes = shown[prefix.len()..].graphemes(true);
+        for (offset, _) in self.value.grapheme_indices(true).skip(self.scroll.get()) {
+            let Some(grapheme) = shown_graphemes.next() else {
+                break;
+            };
+            let part = if offset < selection.start {
+                0
+            } else if offset < selection.end {
+                1
+            } else {
+                2
+            };
+            parts[part].push_str(grapheme);
+        }
+        (parts, column)
+    }
+
+    fn previous_boundary(&self) -> usize {
+        self.value[..self.cursor]
+            .graphemes(true)
+            .next_back()
+            .map_or(0, |g| self.cursor - g.len())
+    }
+
+    fn next_boundary(&self) -> usize {
+        self.value[self.cursor..]
+            .graphemes(true)
+            .next()
+            .map_or(self.cursor, |g| self.cursor + g.len())
+    }
+
+    /// Start of the word before the cursor, words are made of graphemes `is_word` accepts
+    fn word_start(&self, is_word: fn(&str) -> bool) -> usize {
+        let mut position = self.cursor;
+        let mut in_word = false;
+        for grapheme in self.value[..self.cursor].graphemes(true).rev() {
+            if is_word(grapheme) {
+                in_word = true;
+            } else if in_word {
+                break;
+            }
+            position -= grapheme.len();
+        }
+        position
+    }
+
+    /// End of the word after the cursor, see [`TextInput::word_start`]
+    fn word_end(&self, is_word: fn(&str) -> bool) -> usize {
+        let mut position = self.cursor;
+        let mut in_word = false;
+        for grapheme in self.value[self.cursor..].graphemes(true) {
+            if is_word(grapheme) {
+                in_word = true;
+            } else if in_word {
+                break;
+            }
+            position += grapheme.len();
+        }
+        position
+    }
+
+    /// Deletes the text between the cursor and `position`, leaving the cursor at the start of the gap
+    fn delete_to(&mut self, position: usize) {
+        let range = self.cursor.min(position)..self.cursor.max(position);
+        self.cursor = range.start;
+        self.value.replace_range(range, "");
+    }
+}
+
+/// Whether `grapheme` belongs to a word, words end at whitespace and punctuation
+pub fn is_word_character(grapheme: &str) -> bool {
+    grapheme.chars().any(|c| c.is_alphanumeric() || c == '_')
+}
+
+fn is_not_whitespace(grapheme: &str) -> bool {
+    !grapheme.chars().all(char::is_whitespace)
+}
+
+#[cfg(test)]
+mod test_text_input {
+    use super::*;
+
+    fn typed(keys: &[Key]) -> TextInput {
+        let mut input = TextInput::default();
+        for key in keys {
+            input.handle_key(*key);
+        }
+        input
+    }
+
+    fn chars(text: &str) -> Vec<Key> {
+        text.chars().map(Key::Char).collect()
+    }
+
+    #[test]
+    fn insert_at_cursor() {
+        let mut keys = chars("gt status");
+        keys.extend([
+            Key::Home,
+            Key::Right,
+            Key::Char('i'),
+            Key::End,
+            Key::Char('!'),
+        ]);
+        let input = typed(&keys);
+        assert_eq!("git status!", input.value());
+    }
+
+    #[test]
+    fn graphemes_are_edited_as_one() {
+        // `é` made of `e` and a combining accent, and a family emoji made of several code points
+        let mut input = TextInput::new("caf\u{65}\u{301} 👨‍👩‍👧");
+        input.handle_key(Key::Backspace);
+        assert_eq!("caf\u{65}\u{301} ", input.value());
+        input.handle_key(Key::Left);
+        input.handle_key(Key::Left);
+        input.handle_key(Key::Delete);
+        assert_eq!("caf ", input.value());
+    }
+
+    #[test]
+    fn word_motions() {
+        let mut input = TextInput::new("git commit -m message");
+        input.handle_key(Key::Ctrl('w'));
+        assert_eq!("git commit -m ", input.value());
+        input.handle_key(Key::Alt('b'));
+        input.handle_key(Key::Alt('b'));
+        input.handle_key(Key::Alt('d'));
+        assert_eq!("git  -m ", input.value());
+        input.handle_key(Key::Alt('f'));
+        input.handle_key(Key::Ctrl('k'));
+        assert_eq!("git  -m", input.value());
+        input.handle_key(Key::Ctrl('a'));
+        input.handle_key(Key::Alt('f'));
+        input.handle_key(Key::Ctrl('u'));
+        assert_eq!("  -m", input.value());
+    }
+
+    #[test]
+    fn paste_is_a_single_line() {
+        let mut input = TextInput::new("echo ");
+        input.insert("a\nb\r\nc");
+        assert_eq!("echo a b c", input.value());
+        assert!(!input.handle_key(Key::Char('\n')));
+    }
+
+    #[test]
+    fn view_scrolls_to_the_cursor() {
+        let mut input = TextInput::new("0123456789");
+        assert_eq!(("56789".to_string(), 5), input.view("", 6, false));
+        input.handle_key(Key::Home);
+        assert_eq!(("> 0123".to_string(), 2), input.view("> ", 6, false));
+        // Moving right within the view does not scroll
+        input.handle_key(Key::Right);
+        assert_eq!(("012345".to_string(), 1), input.view("", 6, false));
+        assert_eq!(("******".to_string(), 1), input.view("", 6, true));
+    }
+
+    #[test]
+    fn undo_and_redo() {
+        let mut input = TextInput::new("ls");
+        input.checkpoint();
+        input.insert(" -la");
+        input.checkpoint();
+        input.checkpoint();
+        input.insert(" /tmp");
+        assert!(input.undo());
+        assert_eq!("ls -la", input.value());
+        // The checkpoint without a change after it is skipped
+        assert!(input.undo());
+        assert_eq!("ls", input.value());
+        assert!(!input.undo());
+        assert!(input.redo());
+        assert_eq!("ls -la", input.value());
+    }
+
+    #[test]
+    fn selection_view() {
+        let mut input = TextInput::new("kubectl get pods");
+        input.set_cursor(8);
+        let ([before, selected, after], cursor) = input.selection_view("> ", 14, 8..11);
+        assert_eq!(
+            ("> kubectl ", "get", " "),
+            (before.as_str(), selected.as_str(), after.as_str())
+        );
+        assert_eq!(10, cursor);
+    }
+
+    #[test]
+    fn view_counts_wide_characters() {
+        let input = TextInput::new("日本語");
+        assert_eq!(("本語".to_string(), 4), input.view("", 5, false));
+    }
+}
@@ -0,0 +1,420 @@
+use hoardlib::config::{ColorDepth, HoardConfig};
+use hoardlib::core::error::HoardError;
+use ratatui::style::{Color, Modifier, Style};
+use std::env;
+
+/// Names of the built-in themes, as set in the `theme` of the config
+pub const THEMES: [&str; 5] = [
+    "default",
+    "gruvbox",
+    "solarized",
+    "high-contrast",
+    "no-color",
+];
+
+type Rgb = (u8, u8, u8);
+
+/// Colors of a theme, from which the styles of its parts are made
+struct Palette {
+    text: Rgb,
+    accent: Rgb,
+    command: Rgb,
+    selection_fg: Rgb,
+    selection_bg: Rgb,
+    error: Rgb,
+    // Shell syntax of commands. The default theme takes the colors of the terminal's own palette for them
+    program: Color,
+    flag: Color,
+    string: Color,
+    variable: Color,
+    operator: Color,
+    comment: Color,
+}
+
+impl Palette {
+    /// The colors of the config, with the tertiary color behind the selected entry of a list
+    fn from_config(config: &HoardConfig) -> Self {
+        let defaults = HoardConfig::default();
+        let color = |configured: Option<Rgb>, default: Option<Rgb>| {
+            configured
+                .or(default)
+                .expect("the default config has every color")
+        };
+        let accent = color(config.secondary_color, defaults.secondary_color);
+        Self {
+            text: color(config.primary_color, defaults.primary_color),
+            accent,
+            command: color(config.command_color, defaults.command_color),
+            selection_fg: accent,
+            selection_bg: color(config.tertiary_color, defaults.tertiary_color),
+            error: (251, 73, 52),
+            program: Color::Yellow,
+            flag: Color::Cyan,
+            string: Color::Green,
+            variable: Color::Magenta,
+            operator: Color::LightRed,
+            comment: Color::DarkGray,
+        }
+    }
+
+    const GRUVBOX: Self = Self {
+        text: (235, 219, 178),
+        accent: (250, 189, 47),
+        command: (184, 187, 38),
+        selection_fg: (254, 128, 25),
+        selection_bg: (80, 73, 69),
+        error: (251, 73, 52),
+        program: Color::Rgb(250, 189, 47),
+        flag: Color::Rgb(142, 192, 124),
+        string: Color::Rgb(184, 187, 38),
+        variable: Color::Rgb(211, 134, 155),
+        operator: Color::Rgb(254, 128, 25),
+        comment: Color::Rgb(146, 131, 116),
+    };
+
+    const SOLARIZED: Self = Self {
+        text: (147, 161, 161),
+        accent: (38, 139, 210),
+        command: (42, 161, 152),
+        selection_fg: (181, 137, 0),
+        selection_bg: (7, 54, 66),
+        error: (220, 50, 47),
+        program: Color::Rgb(181, 137, 0),
+        flag: Color::Rgb(42, 161, 152),
+        string: Color::Rgb(133, 153, 0),
+        variable: Color::Rgb(211, 54, 130),
+        operator: Color::Rgb(203, 75, 22),
+        comment: Color::Rgb(88, 110, 117),
+    };
+
+    const HIGH_CONTRAST: Self = Self {
+        text: (255, 255, 255),
+        accent: (255, 255, 0),
+        command: (0, 255, 255),
+        selection_fg: (0, 0, 0),
+        selection_bg: (255, 255, 0),
+        error: (255, 0, 0),
+        program: Color::Rgb(255, 255, 0),
+        flag: Color::Rgb(0, 255, 255),
+        string: Color::Rgb(0, 255, 0),
+        variable: Color::Rgb(255, 0, 255),
+        operator: Color::Rgb(255, 128, 0),
+        comment: Color::Rgb(192, 192, 192),
+    };
+}
+
+/// Styles of the parts of the TUI
+#[derive(Debug, Clone, Copy)]
+pub struct Theme {
+    /// Text of the fields and of the entries of lists
+    pub text: Style,
+    /// Borders and titles of the panes without focus
+    pub border: Style,
+    /// Borders and titles of the focused pane and of the edited field
+    pub border_focused: Style,
+    /// The selected entry of a list
+    pub selection: Style,
+    /// The namespace tabs, and the tab of the selected namespace
+    pub tabs: Style,
+    pub tab_selected: Style,
+    /// The command whose parameters or fields are asked for
+    pub command: Style,
+    /// Parameters of the selected command
+    pub parameter: Style,
+    /// Problems with the last key press or input
+    pub error: Style,
+    /// Mode and key hints below the search
+    pub footer: Style,
+    /// Shell syntax of commands: the program, flags, quoted strings, variables, operators like `|` and comments
+    pub program: Style,
+    pub flag: Style,
+    pub string: Style,
+    pub variable: Style,
+    pub operator: Style,
+    pub comment: Style,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::from_palette(
+            &Palette::from_config(&HoardConfig::default()),
+            ColorDepth::TrueColor,
+        )
+    }
+}
+
+impl Theme {
+    /// The theme set in the config, in the colors the terminal can show. `NO_COLOR` turns colors off
+    pub fn from_config(config: &HoardConfig) -> Result<Self, HoardError> {
+        let no_color = env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+        let depth = match config.color_depth.unwrap_or_default() {
+            ColorDepth::Auto => detect_depth(env::var("COLORTERM").ok(), env::var("TERM").ok()),
+            depth => depth,
+        };
+        Self::new(
+            config,
+            config.theme.as_deref().unwrap_or("default"),
+            depth,
+            no_color,
+        )
+    }
+
+    /// The theme `name`, with the colors of the config for the `default` theme, in `depth` colors
+    pub fn new(
+        config: &HoardConfig,
+        name: &str,
+        depth: ColorDepth,
+        no_color: bool,
+    ) -> Result<Self, HoardError> {
+        let palette = match name {
+            _ if no_color => return Ok(Self::no_color()),
+            "default" => Palette::from_config(config),
+            "gruvbox" => Palette::GRUVBOX,
+            "solarized" => Palette::SOLARIZED,
+            "high-contrast" => Palette::HIGH_CONTRAST,
+            "no-color" => return Ok(Self::no_color()),
+            unknown => {
+                return Err(HoardError::Config(format!(
+                    "Unknown theme `{unknown}`, use one of {}",
+                    THEMES.join(", ")
+                )))
+            }
+        };
+        Ok(Self::from_palette(&palette, depth))
+    }
+
+    fn from_palette(palette: &Palette, depth: ColorDepth) -> Self {
+        let color = |rgb: Rgb| Style::default().fg(convert(rgb, depth));
+        let syntax = |color: Color| {
+            Style::default().fg(match color {
+                Color::Rgb(r, g, b) => convert((r, g, b), depth),
+                color => color,
+            })
+        };
+        Self {
+            text: color(palette.text),
+            border: color(palette.text),
+            border_focused: color(palette.accent),
+            selection: color(palette.selection_fg)
+                .bg(convert(palette.selection_bg, depth))
+                .add_modifier(Modifier::BOLD),
+            tabs: color(palette.text),
+            tab_selected: color(palette.accent).add_modifier(Modifier::UNDERLINED),
+            command: color(palette.command),
+            parameter: color(palette.accent),
+            error: color(palette.error).add_modifier(Modifier::BOLD),
+            footer: color(palette.text),
+            program: syntax(palette.program).add_modifier(Modifier::BOLD),
+            flag: syntax(palette.flag),
+            string: syntax(palette.string),
+            variable: syntax(palette.variable),
+            operator: syntax(palette.operator),
+            comment: syntax(palette.comment).add_modifier(Modifier::ITALIC),
+        }
+    }
+
+    /// The terminal's own colors, with the focus and the selection shown by modifiers
+    fn no_color() -> Self {
+        Self {
+            text: Style::default(),
+            border: Style::default(),
+            border_focused: Style::default().add_modifier(Modifier::BOLD),
+            selection: Style::default().add_modifier(Modifier::REVERSED),
+            tabs: Style::default(),
+            tab_selected: Style::default().add_modifier(Modifier::UNDERLINED | Modifier::BOLD),
+            command: Style::default(),
+            parameter: Style::default(),
+            error: Style::default().add_modifier(Modifier::BOLD),
+            footer: Style::default(),
+            program: Style::default().add_modifier(Modifier::BOLD),
+            flag: Style::default(),
+            string: Style::default(),
+            variable: Style::default(),
+            operator: Style::default(),
+            comment: Style::default().add_modifier(Modifier::ITALIC),
+        }
+    }
+}
+
+/// Colors the terminal shows, from its `COLORTERM` and `TERM` variables
+///
+/// Truecolor unless `TERM` names a terminal with 256 colors or less, `COLORTERM` is often lost in tmux or over ssh
+fn detect_depth(colorterm: Option<String>, term: Option<String>) -> ColorDepth {
+    if colorterm.is_some_and(|c| c == "truecolor" || c == "24bit") {
+        return ColorDepth::TrueColor;
+    }
+    match term.as_deref() {
+        Some("linux" | "dumb" | "ansi" | "cons25") => ColorDepth::Ansi16,
+        Some(t) if t.ends_with("-256color") => ColorDepth::Ansi256,
+        Some(t)
+            if t.starts_with("vt")
+                || ["-color", "-8color", "-16color"]
+                    .iter()
+                    .any(|s| t.ends_with(s)) =>
+        {
+            ColorDepth::Ansi16
+        }
+        _ => ColorDepth::TrueColor,
+    }
+}
+
+/// The closest color to `rgb` with `depth` colors
+fn convert(rgb: Rgb, depth: ColorDepth) -> Color {
+    match depth {
+        ColorDepth::Auto | ColorDepth::TrueColor => Color::Rgb(rgb.0, rgb.1, rgb.2),
+        ColorDepth::Ansi256 => Color::Indexed(to_ansi256(rgb)),
+        ColorDepth::Ansi16 => to_ansi16(rgb),
+    }
+}
+
+fn distance(a: Rgb, b: Rgb) -> u32 {
+    let d = |x: u8, y: u8| (i32::from(x) - i32::from(y)).unsigned_abs().pow(2);
+    d(a.0, b.0) + d(a.1, b.1) + d(a.2, b.2)
+}
+
+/// Levels of red, green and blue of the 6x6x6 color cube of 256 color terminals
+const CUBE: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+/// Index of the closest color to `rgb` in the color cube or the gray ramp of 256 color terminals
+fn to_ansi256(rgb: Rgb) -> u8 {
+    let level = |c: u8| {
+        (0..6u8)
+            .min_by_key(|&i| CUBE[usize::from(i)].abs_diff(c))
+            .expect("the cube has levels")
+    };
+    let (r, g, b) = (level(rgb.0), level(rgb.1), level(rgb.2));
+    let cube = (
+        CUBE[usize::from(r)],
+        CUBE[usize::from(g)],
+        CUBE[usize::from(b)],
+    );
+    // The gray ramp goes from 8 to 238 in steps of 10
+    let average = (u16::from(rgb.0) + u16::from(rgb.1) + u16::from(rgb.2)) / 3;
+    let step = u8::try_from(average.saturating_sub(3) / 10)
+        .unwrap_or(23)
+        .min(23);
+    let gray = 8 + step * 10;
+    if distance(rgb, (gray, gray, gray)) < distance(rgb, cube) {
+        232 + step
+    } else {
+        16 + 36 * r + 6 * g + b
+    }
+}
+
+/// The 16 colors of ANSI terminals with their usual xterm values
+const ANSI16: [(Color, Rgb); 16] = [
+    (Color::Black, (0, 0, 0)),
+    (Color::Red, (205, 0, 0)),
+    (Color::Green, (0, 205, 0)),
+    (Color::Yellow, (205, 205, 0)),
+    (Color::Blue, (0, 0, 238)),
+    (Color::Magenta, (205, 0, 205)),
+    (Color::Cyan, (0, 205, 205)),
+    (Color::Gray, (229, 229, 229)),
+    (Color::DarkGray, (127, 127, 127)),
+    (Color::LightRed, (255, 0, 0)),
+    (Color::LightGreen, (0, 255, 0)),
+    (Color::LightYellow, (255, 255, 0)),
+    (Color::LightBlue, (92, 92, 255)),
+    (Color::LightMagenta, (255, 0, 255)),
+    (Color::LightCyan, (0, 255, 255)),
+    (Color::White, (255, 255, 255)),
+];
+
+fn to_ansi16(rgb: Rgb) -> Color {
+    ANSI16
+        .iter()
+        .min_by_key(|(_, ansi)| distance(rgb, *ansi))
+        .map(|(color, _)| *color)
+        .expect("there are 16 colors")
+}
+
+#[cfg(test)]
+mod test_theme {
+    use super::*;
+
+    #[test]
+    fn default_theme_uses_the_colors_of_the_config() {
+        let config = HoardConfig {
+            primary_color: Some((1, 2, 3)),
+            secondary_color: Some((4, 5, 6)),
+            ..HoardConfig::default()
+        };
+        let theme = Theme::new(&config, "default", ColorDepth::TrueColor, false).unwrap();
+        assert_eq!(Some(Color::Rgb(1, 2, 3)), theme.text.fg);
+        assert_eq!(Some(Color::Rgb(4, 5, 6)), theme.border_focused.fg);
+    }
+
+    #[test]
+    fn syntax_colors_follow_the_theme() {
+        let config = HoardConfig::default();
+        let theme = Theme::new(&config, "default", ColorDepth::TrueColor, false).unwrap();
+        assert_eq!(Some(Color::Yellow), theme.program.fg);
+        let theme = Theme::new(&config, "gruvbox", ColorDepth::Ansi16, false).unwrap();
+        assert_eq!(Some(Color::Yellow), theme.program.fg);
+        assert!(theme.comment.add_modifier.contains(Modifier::ITALIC));
+    }
+
+    #[test]
+    fn unknown_themes_are_an_error() {
+        let config = HoardConfig::default();
+        assert!(matches!(
+            Theme::new(&config, "neon", ColorDepth::TrueColor, false),
+            Err(HoardError::Config(_))
+        ));
+    }
+
+    #[test]
+    fn no_color_drops_every_color() {
+        let config = HoardConfig::default();
+        let theme = Theme::new(&config, "gruvbox", ColorDepth::TrueColor, true).unwrap();
+        assert_eq!(None, theme.text.fg);
+        assert_eq!(None, theme.selection.bg);
+        assert_eq!(None, theme.program.fg);
+        assert!(theme.selection.add_modifier.contains(Modifier::REVERSED));
+    }
+
+    #[test]
+    fn colors_fall_back_to_the_depth_of_the_terminal() {
+        assert_eq!(16, to_ansi256((0, 0, 0)));
+        assert_eq!(231, to_ansi256((255, 255, 255)));
+        assert_eq!(196, to_ansi256((255, 0, 0)));
+        assert_eq!(244, to_ansi256((128, 128, 128)));
+        assert_eq!(Color::LightYellow, to_ansi16((250, 240, 40)));
+        assert_eq!(Color::Black, to_ansi16((30, 30, 30)));
+        assert_eq!(
+            Color::Indexed(196),
+            convert((255, 0, 0), ColorDepth::Ansi256)
+        );
+    }
+
+    #[test]
+    fn depth_is_detected_from_the_environment() {
+        let var = |s: &str| Some(s.to_string());
+        assert_eq!(
+            ColorDepth::TrueColor,
+            detect_depth(var("truecolor"), var("xterm"))
+        );
+        assert_eq!(
+            ColorDepth::TrueColor,
+            detect_depth(var("24bit"), var("xterm-256color"))
+        );
+        // Without `COLORTERM`, like in tmux or over ssh, colors are only reduced for terminals which clearly lack them
+        assert_eq!(
+            ColorDepth::Ansi256,
+            detect_depth(None, var("tmux-256color"))
+        );
+        assert_eq!(
+            ColorDepth::Ansi256,
+            detect_depth(None, var("xterm-256color"))
+        );
+        assert_eq!(
+            ColorDepth::TrueColor,
+            detect_depth(None, var("xterm-kitty"))
+        );
+        assert_eq!(ColorDepth::TrueColor, detect_depth(None, None));
+        assert_eq!(ColorDepth::Ansi16, detect_depth(None, var("linux")));
+        assert_eq!(ColorDepth::Ansi16, detect_depth(None, var("xterm-color")));
+        assert_eq!(ColorDepth::Ansi16, detect_depth(None, var("vt100")));
+    }
+}
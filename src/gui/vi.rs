@@ -0,0 +1,916 @@
+use std::collections::HashMap;
+use std::ops::Range;
+use termion::event::Key;
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::gui::clipboard;
+use crate::gui::commands_gui::ViMode;
+use crate::gui::text_input::{is_word_character, TextInput};
+
+// Larger counts are cut down, so `999999p` doesn't fill the memory
+const MAX_COUNT: usize = 999;
+
+/// Vi editing of the TUI input fields
+///
+/// `Esc` in insert mode switches a field to [`ViMode::Edit`], vi's normal mode for the field.
+/// Another `Esc` goes back to [`ViMode::Normal`], where the keys control the screen again.
+///
+/// | Keys | Action |
+/// |------|--------|
+/// | `h` `l` `w` `b` `e` `W` `B` `E` `0` `^` `$` | Motions, with a count like `3w` |
+/// | `f` `t` `F` `T` and a character, `;` `,` | Find a character, repeat the last find |
+/// | `d` `c` `y` and a motion, `dd` `cc` `yy` | Delete, change or yank. `iw` `aw` `iW` `aW` select a word |
+/// | `x` `X` `s` `S` `D` `C` `Y` | Short forms of `dl` `dh` `cl` `cc` `d$` `c$` `yy` |
+/// | `r` and a character | Replace the character under the cursor |
+/// | `p` `P` | Put a register after or before the cursor |
+/// | `i` `a` `I` `A` | Insert before or after the cursor, at the start or the end |
+/// | `u` `Ctrl-R` | Undo, redo |
+/// | `.` | Repeat the last change |
+/// | `v` | Visual mode. Motions extend the selection, `d` `c` `y` `p` act on it and `o` goes to its other end |
+/// | `"` and a register | Register of the next delete, yank or put. `"+` and `"*` are the system clipboard |
+#[derive(Debug, Default)]
+pub struct Vi {
+    // Keys of the command typed so far, e.g. `2d` while the motion is missing
+    pending: Vec<Key>,
+    registers: HashMap<char, String>,
+    // Keys of the last change, typed again by `.`
+    last_change: Vec<Key>,
+    // Keys of a change which continues in insert mode, until insert mode is left
+    recording: Option<Vec<Key>>,
+    // Whether insert mode started with a checkpoint, so all text typed in it is undone at once
+    inserting: bool,
+    // Byte offset the visual selection started at
+    anchor: usize,
+    last_find: Option<(Find, char)>,
+    // Whether `.` is typing the last change again, which is not recorded as a change itself
+    repeating: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Find {
+    Forward,
+    Backward,
+    TillForward,
+    TillBackward,
+}
+
+impl Find {
+    const fn reversed(self) -> Self {
+        match self {
+            Self::Forward => Self::Backward,
+            Self::Backward => Self::Forward,
+            Self::TillForward => Self::TillBackward,
+            Self::TillBackward => Self::TillForward,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Motion {
+    Left,
+    Right,
+    // The flags are `true` for WORDs, which only end at whitespace
+    WordForward(bool),
+    WordBackward(bool),
+    WordEnd(bool),
+    LineStart,
+    FirstNonBlank,
+    LineEnd,
+    Find(Find, char),
+    RepeatFind { reversed: bool },
+    Word { around: bool, big: bool },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Operator {
+    Delete,
+    Change,
+    Yank,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Target {
+    Motion(Motion),
+    Line,
+    Selection,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Insertion {
+    Before,
+    After,
+    LineStart,
+    LineEnd,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Action {
+    Move(Motion),
+    Operate(Operator, Target),
+    Put { before: bool },
+    Replace(char),
+    Insert(Insertion),
+    Undo,
+    Redo,
+    Repeat,
+    Visual,
+    SwapAnchor,
+}
+
+impl Action {
+    /// Whether `.` repeats the action
+    const fn is_change(self) -> bool {
+        matches!(
+            self,
+            Self::Operate(Operator::Delete | Operator::Change, _)
+                | Self::Put { .. }
+                | Self::Replace(_)
+                | Self::Insert(_)
+        )
+    }
+}
+
+#[derive(Debug)]
+enum Parsed {
+    Incomplete,
+    Invalid,
+    Command {
+        register: Option<char>,
+        count: Option<usize>,
+        action: Action,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum WordClass {
+    Blank,
+    Word,
+    Punctuation,
+}
+
+impl Vi {
+    /// Handles `key` in the field `input`, which is in the edit or the visual mode `mode`
+    ///
+    /// Returns the mode to continue in, or `None` if `key` is not a vi command, so the screen can handle it
+    pub fn handle_key(&mut self, mode: &ViMode, key: Key, input: &mut TextInput) -> Option<ViMode> {
+        let visual = *mode == ViMode::Visual;
+        if key == Key::Esc {
+            if !self.pending.is_empty() {
+                self.pending.clear();
+                return Some(mode.clone());
+            }
+            return visual.then_some(ViMode::Edit);
+        }
+        self.pending.push(key);
+        match parse(&self.pending, visual) {
+            Parsed::Incomplete => Some(mode.clone()),
+            Parsed::Invalid => {
+                let unknown = self.pending.len() == 1;
+                self.pending.clear();
+                (!unknown).then(|| mode.clone())
+            }
+            Parsed::Command {
+                register,
+                count,
+                action,
+            } => {
+                let keys = std::mem::take(&mut self.pending);
+                let count = count.unwrap_or(1).min(MAX_COUNT);
+                let next = self.execute(action, register, count, visual, input);
+                if action.is_change() && !visual && !self.repeating {
+                    if next == ViMode::Insert {
+                        self.recording = Some(keys);
+                    } else {
+                        self.last_change = keys;
+                    }
+                }
+                if next != ViMode::Insert {
+                    clamp_cursor(input);
+                }
+                Some(next)
+            }
+        }
+    }
+
+    /// Handles `key` in insert mode. Returns `false` for keys which don't edit text, like [`TextInput::handle_key`]
+    pub fn insert_key(&mut self, key: Key, input: &mut TextInput) -> bool {
+        self.start_inserting(input);
+        if let Some(keys) = &mut self.recording {
+            keys.push(key);
+        }
+        input.handle_key(key)
+    }
+
+    /// Inserts pasted `text` in insert mode
+    pub fn paste(&mut self, text: &str, input: &mut TextInput) {
+        self.start_inserting(input);
+        if let Some(keys) = &mut self.recording {
+            let text = text.replace("\r\n", " ").replace(['\n', '\r'], " ");
+            keys.extend(text.chars().map(Key::Char));
+        }
+        input.insert(text);
+    }
+
+    /// Leaves insert mode, the cursor moves onto the last inserted character like in vi
+    pub fn leave_insert(&mut self, input: &mut TextInput) {
+        self.inserting = false;
+        if let Some(mut keys) = self.recording.take() {
+            keys.push(Key::Esc);
+            self.last_change = keys;
+        }
+        input.handle_key(Key::Left);
+    }
+
+    /// Yanks `text` into the system clipboard and the unnamed register, e.g. a command of the list
+    pub fn yank_to_clipboard(&mut self, text: &str) {
+        self.write_register(Some('+'), text);
+    }
+
+    /// Byte range of the visual selection in `input`, the character under the cursor included
+    pub fn selection(&self, input: &TextInput) -> Range<usize> {
+        let anchor = self.anchor.min(input.value().len());
+        let start = anchor.min(input.cursor());
+        let end = anchor.max(input.cursor());
+        let end = input.value()[end..]
+            .graphemes(true)
+            .next()
+            .map_or(end, |grapheme| end + grapheme.len());
+        start..end
+    }
+
+    fn start_inserting(&mut self, input: &mut TextInput) {
+        if !self.inserting {
+            input.checkpoint();
+            self.inserting = true;
+        }
+    }
+
+    fn execute(
+        &mut self,
+        action: Action,
+        register: Option<char>,
+        count: usize,
+        visual: bool,
+        input: &mut TextInput,
+    ) -> ViMode {
+        let mode = if visual { ViMode::Visual } else { ViMode::Edit };
+        match action {
+            Action::Move(motion) => {
+                if let Some((cursor, _)) = self.motion(motion, count, None, input) {
+                    input.set_cursor(cursor);
+                }
+                mode
+            }
+            Action::Operate(operator, target) => {
+                let range = match target {
+                    Target::Line => Some(0..input.value().len()),
+                    Target::Selection => Some(self.selection(input)),
+                    Target::Motion(motion) => self
+                        .motion(motion, count, Some(operator), input)
+                        .map(|(_, range)| range),
+                };
+                range.map_or(mode, |range| self.operate(operator, range, register, input))
+            }
+            Action::Put { before } => {
+                let Some(text) = self.read_register(register) else {
+                    return mode;
+                };
+                input.checkpoint();
+                if visual {
+                    let range = self.selection(input);
+                    let replaced = input.value()[range.clone()].to_string();
+                    input.replace_range(range, "");
+                    self.write_register(None, &replaced);
+                } else if !before && !input.value().is_empty() {
+                    input.handle_key(Key::Right);
+                }
+                input.insert(&text.repeat(count));
+                input.handle_key(Key::Left);
+                ViMode::Edit
+            }
+            Action::Replace(c) => {
+                let start = input.cursor();
+                let replaced: Vec<&str> =
+                    input.value()[start..].graphemes(true).take(count).collect();
+                if replaced.len() == count {
+                    let end = start + replaced.concat().len();
+                    input.checkpoint();
+                    input.replace_range(start..end, &c.to_string().repeat(count));
+                    input.set_cursor(start + (count - 1) * c.len_utf8());
+                }
+                mode
+            }
+            Action::Insert(insertion) => {
+                match insertion {
+                    Insertion::Before => (),
+                    Insertion::After => {
+                        input.handle_key(Key::Right);
+                    }
+                    Insertion::LineStart => {
+                        let blank = input.value().len() - input.value().trim_start().len();
+                        input.set_cursor(blank);
+                    }
+                    Insertion::LineEnd => input.set_cursor(input.value().len()),
+                }
+                input.checkpoint();
+                self.inserting = true;
+                ViMode::Insert
+            }
+            Action::Undo => {
+                for _ in 0..count {
+                    if !input.undo() {
+                        break;
+                    }
+                }
+                mode
+            }
+            Action::Redo => {
+                for _ in 0..count {
+                    if !input.redo() {
+                        break;
+                    }
+                }
+                mode
+            }
+            Action::Repeat => {
+                for _ in 0..count {
+                    self.repeat(input);
+                }
+                mode
+            }
+            Action::Visual if visual => ViMode::Edit,
+            Action::Visual => {
+                self.anchor = input.cursor();
+                ViMode::Visual
+            }
+            Action::SwapAnchor => {
+                let cursor = input.cursor();
+                input.set_cursor(self.anchor);
+                self.anchor = cursor;
+                mode
+            }
+        }
+    }
+
+    /// Applies `operator` to the bytes in `range` and returns the mode to continue in
+    fn operate(
+        &mut self,
+        operator: Operator,
+        range: Range<usize>,
+        register: Option<char>,
+        input: &mut TextInput,
+    ) -> ViMode {
+        let text = input.value()[range.clone()].to_string();
+        self.write_register(register, &text);
+        match operator {
+            Operator::Yank => {
+                input.set_cursor(range.start);
+                ViMode::Edit
+            }
+            Operator::Delete => {
+                input.checkpoint();
+                input.replace_range(range, "");
+                ViMode::Edit
+            }
+            Operator::Change => {
+                input.checkpoint();
+                input.replace_range(range, "");
+                self.inserting = true;
+                ViMode::Insert
+            }
+        }
+    }
+
+    /// Where `motion` moves the cursor of `input` to, and the bytes `operator` applies to with it
+    ///
+    /// Returns `None` if the motion fails, e.g. if the character to find is missing
+    fn motion(
+        &mut self,
+        motion: Motion,
+        count: usize,
+        operator: Option<Operator>,
+        input: &TextInput,
+    ) -> Option<(usize, Range<usize>)> {
+        let value = input.value();
+        let (offsets, graphemes): (Vec<usize>, Vec<&str>) = value.grapheme_indices(true).unzip();
+        let offset = |index: usize| offsets.get(index).copied().unwrap_or(value.len());
+        let length = graphemes.len();
+        let at = offsets
+            .iter()
+            .position(|o| *o >= input.cursor())
+            .unwrap_or(length);
+        let is_blank = |index: usize| {
+            index < length && word_class(graphemes[index], false) == WordClass::Blank
+        };
+
+        let (target, inclusive) = match motion {
+            Motion::Left => (at.saturating_sub(count), false),
+            Motion::Right => ((at + count).min(length), false),
+            // Like in vi `cw` on a word changes up to its end, not up to the next word
+            Motion::WordForward(big)
+                if operator == Some(Operator::Change) && at < length && !is_blank(at) =>
+            {
+                let mut end = word_end(&graphemes, at, big);
+                for _ in 1..count {
+                    end = word_end(&graphemes, end + 1, big);
+                }
+                (end, true)
+            }
+            Motion::WordForward(big) => {
+                let mut index = at;
+                for _ in 0..count {
+                    index = next_word_start(&graphemes, index, big);
+                }
+                (index, false)
+            }
+            Motion::WordBackward(big) => {
+                let mut index = at;
+                for _ in 0..count {
+                    index = previous_word_start(&graphemes, index, big);
+                }
+                (index, false)
+            }
+            Motion::WordEnd(big) => {
+                let mut index = at;
+                for _ in 0..count {
+                    index = word_end(&graphemes, index + 1, big);
+                }
+                (index, true)
+            }
+            Motion::LineStart => (0, false),
+            Motion::FirstNonBlank => ((0..length).find(|i| !is_blank(*i)).unwrap_or(length), false),
+            Motion::LineEnd => (length.saturating_sub(1), true),
+            Motion::Find(find, c) => {
+                self.last_find = Some((find, c));
+                find_character(&graphemes, at, find, c, count)?
+            }
+            Motion::RepeatFind { reversed } => {
+                let (find, c) = self.last_find?;
+                let find = if reversed { find.reversed() } else { find };
+                find_character(&graphemes, at, find, c, count)?
+            }
+            Motion::Word { around, big } => {
+                let range = word_object(&graphemes, at, around, big)?;
+                return Some((offset(range.start), offset(range.start)..offset(range.end)));
+            }
+        };
+        let start = at.min(target);
+        let end = at.max(target) + usize::from(inclusive);
+        Some((offset(target), offset(start)..offset(end.min(length))))
+    }
+
+    /// Types the keys of the last change again
+    fn repeat(&mut self, input: &mut TextInput) {
+        let keys = self.last_change.clone();
+        self.repeating = true;
+        let mut mode = ViMode::Edit;
+        for key in keys {
+            mode = match mode {
+                ViMode::Insert if key == Key::Esc => {
+                    self.leave_insert(input);
+                    ViMode::Edit
+                }
+                ViMode::Insert => {
+                    self.insert_key(key, input);
+                    ViMode::Insert
+                }
+                _ => self.handle_key(&mode, key, input).unwrap_or(mode),
+            };
+        }
+        self.repeating = false;
+    }
+
+    /// Stores `text` in `register` and in the unnamed register. Uppercase registers append to the lowercase ones
+    fn write_register(&mut self, register: Option<char>, text: &str) {
+        match register {
+            Some('_') => return,
+            Some('+' | '*') => {
+                // Without a clipboard the text is still in the unnamed register
+                let _ = clipboard::copy(text);
+            }
+            Some(name) if name.is_ascii_uppercase() => self
+                .registers
+                .entry(name.to_ascii_lowercase())
+                .or_default()
+                .push_str(text),
+            Some(name) => {
+                self.registers.insert(name, text.to_string());
+            }
+            None => (),
+        }
+        self.registers.insert('"', text.to_string());
+    }
+
+    fn read_register(&self, register: Option<char>) -> Option<String> {
+        match register.unwrap_or('"') {
+            '+' | '*' => clipboard::paste().or_else(|| self.registers.get(&'"').cloned()),
+            name => self.registers.get(&name.to_ascii_lowercase()).cloned(),
+        }
+    }
+}
+
+/// Keeps the cursor on a character, in vi's normal mode it can't be behind the last one
+fn clamp_cursor(input: &mut TextInput) {
+    if input.cursor() == input.value().len() {
+        input.handle_key(Key::Left);
+    }
+}
+
+fn parse(keys: &[Key], visual: bool) -> Parsed {
+    let mut keys = keys;
+    let mut register = None;
+    if let [Key::Char('"'), rest @ ..] = keys {
+        match rest {
+            [] => return Parsed::Incomplete,
+            [Key::Char(name), rest @ ..] => {
+                register = Some(*name);
+                keys = rest;
+            }
+            _ => return Parsed::Invalid,
+        }
+    }
+    let (mut count, keys) = parse_count(keys);
+    let [key, rest @ ..] = keys else {
+        return Parsed::Incomplete;
+    };
+    let action = match (*key, visual) {
+        (Key::Char(name @ ('d' | 'c' | 'y')), false) => {
+            let operator = match name {
+                'd' => Operator::Delete,
+                'c' => Operator::Change,
+                _ => Operator::Yank,
+            };
+            if rest == [Key::Char(name)] {
+                Action::Operate(operator, Target::Line)
+            } else {
+                let (motion_count, rest) = parse_count(rest);
+                let motion = match parse_motion(rest, true) {
+                    Ok(motion) => motion,
+                    Err(parsed) => return parsed,
+                };
+                if let Some(motion_count) = motion_count {
+                    count = Some(count.unwrap_or(1).saturating_mul(motion_count));
+                }
+                Action::Operate(operator, Target::Motion(motion))
+            }
+        }
+        (Key::Char('d' | 'x'), true) => Action::Operate(Operator::Delete, Target::Selection),
+        (Key::Char('c' | 's'), true) => Action::Operate(Operator::Change, Target::Selection),
+        (Key::Char('y'), true) => Action::Operate(Operator::Yank, Target::Selection),
+        (Key::Char('X' | 'D'), true) => Action::Operate(Operator::Delete, Target::Line),
+        (Key::Char('S' | 'C'), true) | (Key::Char('S'), false) => {
+            Action::Operate(Operator::Change, Target::Line)
+        }
+        (Key::Char('Y'), _) => Action::Operate(Operator::Yank, Target::Line),
+        (Key::Char('x'), false) => Action::Operate(Operator::Delete, Target::Motion(Motion::Right)),
+        (Key::Char('X'), false) => Action::Operate(Operator::Delete, Target::Motion(Motion::Left)),
+        (Key::Char('s'), false) => Action::Operate(Operator::Change, Target::Motion(Motion::Right)),
+        (Key::Char('D'), false) => {
+            Action::Operate(Operator::Delete, Target::Motion(Motion::LineEnd))
+        }
+        (Key::Char('C'), false) => {
+            Action::Operate(Operator::Change, Target::Motion(Motion::LineEnd))
+        }
+        (Key::Char('p'), _) => Action::Put { before: false },
+        (Key::Char('P'), _) => Action::Put { before: true },
+        (Key::Char('r'), false) => match rest {
+            [] => return Parsed::Incomplete,
+            [Key::Char(c)] => Action::Replace(*c),
+            _ => return Parsed::Invalid,
+        },
+        (Key::Char('u'), false) => Action::Undo,
+        (Key::Ctrl('r'), false) => Action::Redo,
+        (Key::Char('.'), false) => Action::Repeat,
+        (Key::Char('i'), false) => Action::Insert(Insertion::Before),
+        (Key::Char('a'), false) => Action::Insert(Insertion::After),
+        (Key::Char('I'), false) => Action::Insert(Insertion::LineStart),
+        (Key::Char('A'), false) => Action::Insert(Insertion::LineEnd),
+        (Key::Char('v'), _) => Action::Visual,
+        (Key::Char('o'), true) => Action::SwapAnchor,
+        _ => match parse_motion(keys, false) {
+            Ok(motion) => Action::Move(motion),
+            Err(parsed) => return parsed,
+        },
+    };
+    Parsed::Command {
+        register,
+        count,
+        action,
+    }
+}
+
+/// Splits a count off the start of `keys`. A count can't start with `0`, which is a motion
+fn parse_count(keys: &[Key]) -> (Option<usize>, &[Key]) {
+    let digits: String = keys
+        .iter()
+        .enumerate()
+        .map_while(|(i, key)| match key {
+            Key::Char(c @ '1'..='9') => Some(*c),
+            Key::Char('0') if i > 0 => Some('0'),
+            _ => None,
+        })
+        .collect();
+    let count = (!digits.is_empty()).then(|| digits.parse().unwrap_or(MAX_COUNT));
+    (count, &keys[digits.len()..])
+}
+
+/// Parses the motion in `keys`. Words as text objects are only motions after an `operator`
+fn parse_motion(keys: &[Key], operator: bool) -> Result<Motion, Parsed> {
+    let [key, rest @ ..] = keys else {
+        return Err(Parsed::Incomplete);
+    };
+    let motion = match key {
+        Key::Char('h') | Key::Left | Key::Backspace => Motion::Left,
+        Key::Char('l' | ' ') | Key::Right => Motion::Right,
+        Key::Char(c @ ('w' | 'W')) => Motion::WordForward(*c == 'W'),
+        Key::Char(c @ ('b' | 'B')) => Motion::WordBackward(*c == 'B'),
+        Key::Char(c @ ('e' | 'E')) => Motion::WordEnd(*c == 'E'),
+        Key::Char('0') | Key::Home => Motion::LineStart,
+        Key::Char('^') => Motion::FirstNonBlank,
+        Key::Char('$') | Key::End => Motion::LineEnd,
+        Key::Char(';') => Motion::RepeatFind { reversed: false },
+        Key::Char(',') => Motion::RepeatFind { reversed: true },
+        Key::Char(c @ ('f' | 't' | 'F' | 'T')) => {
+            let find = match c {
+                'f' => Find::Forward,
+                't' => Find::TillForward,
+                'F' => Find::Backward,
+                _ => Find::TillBackward,
+            };
+            return match rest {
+                [] => Err(Parsed::Incomplete),
+                [Key::Char(target)] => Ok(Motion::Find(find, *target)),
+                _ => Err(Parsed::Invalid),
+            };
+        }
+        Key::Char(c @ ('i' | 'a')) if operator => {
+            return match rest {
+                [] => Err(Parsed::Incomplete),
+                [Key::Char(w @ ('w' | 'W'))] => Ok(Motion::Word {
+                    around: *c == 'a',
+                    big: *w == 'W',
+                }),
+                _ => Err(Parsed::Invalid),
+            };
+        }
+        _ => return Err(Parsed::Invalid),
+    };
+    if rest.is_empty() {
+        Ok(motion)
+    } else {
+        Err(Parsed::Invalid)
+    }
+}
+
+fn word_class(grapheme: &str, big: bool) -> WordClass {
+    if grapheme.chars().all(char::is_whitespace) {
+        WordClass::Blank
+    } else if big || is_word_character(grapheme) {
+        WordClass::Word
+    } else {
+        WordClass::Punctuation
+    }
+}
+
+/// Index of the start of the word after the one at `index`
+fn next_word_start(graphemes: &[&str], mut index: usize, big: bool) -> usize {
+    let class = |i: usize| word_class(graphemes[i], big);
+    if index < graphemes.len() && class(index) != WordClass::Blank {
+        let word = class(index);
+        while index < graphemes.len() && class(index) == word {
+            index += 1;
+        }
+    }
+    while index < graphemes.len() && class(index) == WordClass::Blank {
+        index += 1;
+    }
+    index
+}
+
+/// Index of the start of the word before `index`
+fn previous_word_start(graphemes: &[&str], mut index: usize, big: bool) -> usize {
+    let class = |i: usize| word_class(graphemes[i], big);
+    while index > 0 && class(index - 1) == WordClass::Blank {
+        index -= 1;
+    }
+    if index > 0 {
+        let word = class(index - 1);
+        while index > 0 && class(index - 1) == word {
+            index -= 1;
+        }
+    }
+    index
+}
+
+/// Index of the last character of the first word which starts at or after `index`
+fn word_end(graphemes: &[&str], mut index: usize, big: bool) -> usize {
+    let class = |i: usize| word_class(graphemes[i], big);
+    while index < graphemes.len() && class(index) == WordClass::Blank {
+        index += 1;
+    }
+    if index >= graphemes.len() {
+        return graphemes.len().saturating_sub(1);
+    }
+    let word = class(index);
+    while index + 1 < graphemes.len() && class(index + 1) == word {
+        index += 1;
+    }
+    index
+}
+
+/// The index `find` moves to from `at`, and whether an operator includes it
+fn find_character(
+    graphemes: &[&str],
+    at: usize,
+    find: Find,
+    c: char,
+    count: usize,
+) -> Option<(usize, bool)> {
+    let target = c.to_string();
+    let mut index = at;
+    for _ in 0..count {
+        index = match find {
+            Find::Forward | Find::TillForward => {
+                (index + 1..graphemes.len()).find(|i| graphemes[*i] == target)?
+            }
+            Find::Backward | Find::TillBackward => {
+                (0..index).rev().find(|i| graphemes[*i] == target)?
+            }
+        };
+    }
+    Some(match find {
+        Find::Forward => (index, true),
+        Find::TillForward => (index - 1, true),
+        Find::Backward => (index, false),
+        Find::TillBackward => (index + 1, false),
+    })
+}
+
+/// Indices of the word at `at`, with the whitespace after it, or before it if there is none, if `around`
+fn word_object(graphemes: &[&str], at: usize, around: bool, big: bool) -> Option<Range<usize>> {
+    if at >= graphemes.len() {
+        return None;
+    }
+    let class = |i: usize| word_class(graphemes[i], big);
+    let word = class(at);
+    let mut start = at;
+    while start > 0 && class(start - 1) == word {
+        start -= 1;
+    }
+    let mut end = at + 1;
+    while end < graphemes.len() && class(end) == word {
+        end += 1;
+    }
+    if around {
+        let trailing = end;
+        while end < graphemes.len() && class(end) == WordClass::Blank {
+            end += 1;
+        }
+        if end == trailing {
+            while start > 0 && class(start - 1) == WordClass::Blank {
+                start -= 1;
+            }
+        }
+    }
+    Some(start..end)
+}
+
+#[cfg(test)]
+mod test_vi {
+    use super::*;
+
+    /// The field after typing `keys` in edit mode, with the cursor at `cursor`, and the mode it ends in
+    fn edit(value: &str, cursor: usize, keys: &str) -> (TextInput, ViMode) {
+        let mut vi = Vi::default();
+        let mut input = TextInput::new(value);
+        input.set_cursor(cursor);
+        let mode = type_keys(&mut vi, &mut input, ViMode::Edit, keys);
+        (input, mode)
+    }
+
+    /// Types `keys` into `input`, `<` stands for `Esc`
+    fn type_keys(vi: &mut Vi, input: &mut TextInput, mut mode: ViMode, keys: &str) -> ViMode {
+        for c in keys.chars() {
+            let key = if c == '<' { Key::Esc } else { Key::Char(c) };
+            mode = match mode {
+                ViMode::Insert if key == Key::Esc => {
+                    vi.leave_insert(input);
+                    ViMode::Edit
+                }
+                ViMode::Insert => {
+                    vi.insert_key(key, input);
+                    ViMode::Insert
+                }
+                _ => vi.handle_key(&mode, key, input).unwrap_or(ViMode::Normal),
+            };
+        }
+        mode
+    }
+
+    #[test]
+    fn motions() {
+        let command = "git commit --amend -m msg";
+        assert_eq!(4, edit(command, 0, "w").0.cursor());
+        assert_eq!(13, edit(command, 0, "3w").0.cursor());
+        assert_eq!(9, edit(command, 0, "2e").0.cursor());
+        assert_eq!(19, edit(command, 22, "B").0.cursor());
+        assert_eq!(24, edit(command, 0, "$").0.cursor());
+        assert_eq!(0, edit(command, 10, "0").0.cursor());
+        assert_eq!(17, edit(command, 0, "fd").0.cursor());
+        assert_eq!(16, edit(command, 0, "td").0.cursor());
+        assert_eq!(14, edit(command, 0, "2fm;").0.cursor());
+        assert_eq!(14, edit(command, 24, "Fm;;").0.cursor());
+        assert_eq!(22, edit(command, 24, "Fm;,").0.cursor());
+    }
+
+    #[test]
+    fn operators() {
+        let command = "git commit --amend -m msg";
+        assert_eq!("commit --amend -m msg", edit(command, 0, "dw").0.value());
+        assert_eq!(
+            "git push --amend -m msg",
+            edit(command, 4, "cwpush<").0.value()
+        );
+        assert_eq!("git -m msg", edit(command, 4, "d2W").0.value());
+        assert_eq!("git commit --amend", edit(command, 18, "D").0.value());
+        assert_eq!("git  --amend -m msg", edit(command, 6, "diw").0.value());
+        assert_eq!("git --amend -m msg", edit(command, 6, "daw").0.value());
+        assert_eq!("commit --amend -m msg", edit(command, 0, "dtc").0.value());
+        assert_eq!("", edit(command, 3, "dd").0.value());
+        assert_eq!(
+            "bit commit --amend -m msg",
+            edit(command, 0, "rb").0.value()
+        );
+        assert_eq!("it commit --amend -m msg", edit(command, 0, "x").0.value());
+        let (input, mode) = edit(command, 0, "cc");
+        assert_eq!(("", ViMode::Insert), (input.value(), mode));
+    }
+
+    #[test]
+    fn registers_and_put() {
+        let (input, _) = edit("ls -la", 0, "yiwwP");
+        assert_eq!("ls ls-la", input.value());
+        let (input, _) = edit("one two", 0, "\"adwyiw$\"ap");
+        assert_eq!("twoone ", input.value());
+        let (input, _) = edit("abc", 0, "x$p");
+        assert_eq!("bca", input.value());
+    }
+
+    #[test]
+    fn undo_redo_and_repeat() {
+        let mut vi = Vi::default();
+        let mut input = TextInput::new("a b c d");
+        input.set_cursor(0);
+        type_keys(&mut vi, &mut input, ViMode::Edit, "cwx<w.w.");
+        assert_eq!("x x x d", input.value());
+        type_keys(&mut vi, &mut input, ViMode::Edit, "uu");
+        assert_eq!("x b c d", input.value());
+        vi.handle_key(&ViMode::Edit, Key::Ctrl('r'), &mut input);
+        assert_eq!("x x c d", input.value());
+        type_keys(&mut vi, &mut input, ViMode::Edit, "0dw..");
+        assert_eq!("d", input.value());
+        // Typing in insert mode is undone at once
+        type_keys(&mut vi, &mut input, ViMode::Edit, "Aefg<u");
+        assert_eq!("d", input.value());
+    }
+
+    #[test]
+    fn visual_mode() {
+        let (input, mode) = edit("kubectl get pods", 8, "vey");
+        assert_eq!(ViMode::Edit, mode);
+        assert_eq!(8, input.cursor());
+        let (input, _) = edit("kubectl get pods", 8, "velld");
+        assert_eq!("kubectl ods", input.value());
+        let mut vi = Vi::default();
+        let mut input = TextInput::new("kubectl get pods");
+        input.set_cursor(8);
+        let mode = type_keys(&mut vi, &mut input, ViMode::Edit, "vlo");
+        assert_eq!(ViMode::Visual, mode);
+        assert_eq!(8..10, vi.selection(&input));
+        assert_eq!(8, input.cursor());
+        let mode = type_keys(&mut vi, &mut input, ViMode::Visual, "cdescribe<");
+        assert_eq!(ViMode::Edit, mode);
+        assert_eq!("kubectl describet pods", input.value());
+    }
+
+    #[test]
+    fn unknown_keys_are_left_to_the_screen() {
+        let mut vi = Vi::default();
+        let mut input = TextInput::new("ls");
+        assert_eq!(None, vi.handle_key(&ViMode::Edit, Key::Esc, &mut input));
+        assert_eq!(
+            None,
+            vi.handle_key(&ViMode::Edit, Key::Char('q'), &mut input)
+        );
+        assert_eq!(
+            Some(ViMode::Edit),
+            vi.handle_key(&ViMode::Edit, Key::Char('d'), &mut input)
+        );
+        // Esc cancels the pending operator instead
+        assert_eq!(
+            Some(ViMode::Edit),
+            vi.handle_key(&ViMode::Edit, Key::Esc, &mut input)
+        );
+        assert_eq!("ls", input.value());
+    }
+}
@@ -1,49 +1,95 @@
-use crate::cli_commands::{Cli, Commands};
+use crate::cli_commands::{
+    Cli, Commands, ExportFormat, ImportFormat, SecretAction, StorageFormat, WorkflowAction,
+};
 use clap::Parser;
-use dotenv::dotenv;
+use dotenvy::dotenv;
 use log::info;
 use std::fs;
-use std::path::{Path, PathBuf};
-use std::str::FromStr;
-
-use crate::config::HoardConfig;
-use crate::config::{load_or_build_config, save_parameter_token};
-use crate::core::trove::Trove;
-use crate::core::HoardCmd;
-use crate::filter::query_trove;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::Command;
+
+use crate::gui::command_input::CommandInput;
 use crate::gui::commands_gui;
+use crate::gui::keymap::Keymap;
 use crate::gui::prompts::{
-    prompt_multiselect_options,
-    prompt_yes_or_no, Confirmation,
+    prompt_input, prompt_multiselect_options, prompt_password, prompt_yes_or_no, Confirmation,
 };
-use base64::Engine as _;
+use crate::gui::terminal::Height;
+use crate::gui::theme::Theme;
+use crate::util::{hide_from_history, render_export};
+use hoardlib::config::HoardConfig;
+use hoardlib::config::{load_or_build_config, save_parameter_token, save_storage_backend};
+use hoardlib::core::environment::Environment;
+use hoardlib::core::error::{HoardError, TroveError};
+use hoardlib::core::export::{self, ListFormat};
+use hoardlib::core::import::{import_path, navi, pet, tldr};
+use hoardlib::core::journal::Journal;
+use hoardlib::core::parameters::{replace_secrets, secret_names, Parameterized};
+use hoardlib::core::references::resolve_references;
+use hoardlib::core::store::{open_store, StorageBackend, TroveStore};
+use hoardlib::core::trove::Trove;
+use hoardlib::core::vault::{Vault, VaultKey, PASSPHRASE_ENV};
+use hoardlib::core::workflow::{self, join_steps, Workflow};
+use hoardlib::core::HoardCmd;
+use hoardlib::filter::query_trove;
+use prettytable::{color, Attr, Cell, Row, Table};
+use std::collections::BTreeMap;
 #[derive(Default, Debug)]
 pub struct Hoard {
     config: HoardConfig,
     trove: Trove,
+    // Set if the trove file exists but could not be loaded. Nothing is saved while it is set
+    trove_error: Option<TroveError>,
+    // The trove as it has been loaded, to merge our changes if another hoard process saved the trove in the meantime
+    loaded_trove: Trove,
+    store: Option<Box<dyn TroveStore>>,
+    // The last changes of the trove, to undo and redo them
+    journal: Journal,
 }
 
 impl Hoard {
-    pub fn with_config(&mut self, hoard_home_path: Option<String>) -> &mut Self {
+    pub fn with_config(
+        &mut self,
+        hoard_home_path: Option<String>,
+    ) -> Result<&mut Self, HoardError> {
         info!("Loading config");
-        match load_or_build_config(hoard_home_path) {
-            Ok(config) => self.config = config,
-            Err(err) => {
-                eprintln!("ERROR: {err}");
-                err.chain()
-                    .skip(1)
-                    .for_each(|cause| eprintln!("because: {cause}"));
-                std::process::exit(1);
-            }
+        // `{:#}` includes the causes of the error
+        let default_namespace = || {
+            prompt_input(
+                "This is the first time running hoard.\nChoose a default namespace where you want to hoard your commands.",
+                false,
+                Some("default".to_string()),
+            )
         };
-        self
+        self.config = load_or_build_config(hoard_home_path, default_namespace)
+            .map_err(|err| HoardError::Config(format!("{err:#}")))?;
+        Ok(self)
     }
 
-    pub fn start(&mut self) -> (String, bool) {
+    /// Runs the command given on the command line
+    ///
+    /// Returns the command to put into the shell's input, if any, and if hoard is run as a shell plugin
+    pub fn start(&mut self) -> Result<(String, bool), HoardError> {
         dotenv().ok();
         let mut autocomplete_command = String::new();
         let cli = Cli::parse();
 
+        if let Some(trove_error) = self.trove_error.take() {
+            if cli.command.mutates_trove() {
+                eprintln!("Fix or restore the trove file before changing it. Backups are kept in the `backups` folder next to your config file");
+                return Err(trove_error.into());
+            }
+            // Reading an empty trove is fine, but let the user know why it is empty
+            eprintln!("{trove_error}");
+            self.trove_error = Some(trove_error);
+        }
+        if !cli.command.locks_trove() {
+            if let Some(store) = self.store.as_mut() {
+                store.unlock();
+            }
+        }
+
         match &cli.command {
             Commands::Info {} => {
                 self.show_info();
@@ -59,46 +105,94 @@ impl Hoard {
                     tags.clone(),
                     command.clone(),
                     description.clone(),
-                );
+                )?;
             }
             Commands::List {
                 filter,
                 json,
+                yaml,
+                fields,
+                format,
+                preview_format,
+                print0,
                 simple,
+                height,
             } => {
+                let list_format = if *json {
+                    Some(ListFormat::Json)
+                } else if *yaml {
+                    Some(ListFormat::Yaml)
+                } else if let Some(fields) = fields {
+                    Some(ListFormat::Template(export::template_from_fields(fields)))
+                } else if let Some(format) = format {
+                    Some(ListFormat::Template(format.clone()))
+                } else if *preview_format || *print0 {
+                    Some(ListFormat::Template(export::PICKER_TEMPLATE.to_string()))
+                } else {
+                    None
+                };
+                let list_format = list_format.map(|list_format| match list_format {
+                    ListFormat::Template(template) if *print0 => ListFormat::Records(template),
+                    list_format => list_format,
+                });
                 let commands =
-                    self.list_commands(simple.to_owned(), json.to_owned(), filter.clone());
+                    self.list_commands(simple.to_owned(), list_format, filter.clone(), *height)?;
                 if let Some(c) = commands {
                     autocomplete_command = c;
                 }
             }
             Commands::Pick { name } => {
-                self.pick_command(name);
+                self.pick_command(name)?;
+            }
+            Commands::Preview { reference } => {
+                self.preview_command(reference)?;
             }
             Commands::Remove { name } => {
-                self.remove_command(name);
+                self.remove_command(name)?;
             }
             Commands::RemoveNamespace { namespace } => {
-                self.remove_namespace(namespace);
+                self.remove_namespace(namespace)?;
             }
             Commands::SetParameterToken { name } => {
-                self.set_parameter_token(name);
+                self.set_parameter_token(name)?;
             }
-            Commands::Import { uri } => {
-                self.import_trove(uri);
+            Commands::Import { uri, from } => {
+                self.import_trove(uri, *from)?;
             }
-            Commands::Export { path } => {
-                self.export_command(path);
+            Commands::Export { path, format } => {
+                self.export_command(path, *format)?;
             }
             Commands::Edit { name } => {
-                self.edit_command(name);
+                self.edit_command(name)?;
+            }
+            Commands::ShellConfig { shell, fzf } => {
+                Self::shell_config_command(shell, *fzf)?;
+            }
+            Commands::Secret { action } => {
+                self.secret_command(action)?;
+            }
+            Commands::Workflow { action } => {
+                self.workflow_command(action)?;
+            }
+            Commands::Run { name } => {
+                self.run_command(name)?;
+            }
+            Commands::MigrateStorage { to } => {
+                let to = match to {
+                    StorageFormat::Yaml => StorageBackend::Yaml,
+                    StorageFormat::Sqlite => StorageBackend::Sqlite,
+                };
+                self.migrate_storage(to)?;
             }
-            Commands::ShellConfig { shell } => {
-                Self::shell_config_command(shell);
+            Commands::Undo {} => {
+                self.undo(false)?;
+            }
+            Commands::Redo {} => {
+                self.undo(true)?;
             }
         }
 
-        (autocomplete_command, cli.autocomplete)
+        Ok((autocomplete_command, cli.autocomplete))
     }
 
     pub fn show_info(&self) {
@@ -110,8 +204,20 @@ impl Hoard {
             );
         }
 
-        if let Some(trove_path) = self.config.trove_path.clone() {
-            println!("✨ Trove file is located at {}", trove_path.display());
+        match self.config.storage_backend.unwrap_or_default() {
+            StorageBackend::Yaml => {
+                if let Some(trove_path) = self.config.trove_path.clone() {
+                    println!("✨ Trove file is located at {}", trove_path.display());
+                }
+            }
+            StorageBackend::Sqlite => {
+                if let Some(database_path) = self.config.database_path.clone() {
+                    println!(
+                        "✨ Trove database is located at {}",
+                        database_path.display()
+                    );
+                }
+            }
         }
     }
 
@@ -121,7 +227,7 @@ impl Hoard {
         tags: Option<String>,
         command: Option<String>,
         description: Option<String>,
-    ) {
+    ) -> Result<(), HoardError> {
         let trove_namespaces = self.trove.namespaces();
         //trove_namespaces.push(&default_ns_clone);
         let new_command = HoardCmd::default()
@@ -134,84 +240,487 @@ impl Hoard {
             .with_name_input(name, &self.trove)
             .with_description_input(description.unwrap_or_default())
             .with_tags_input(tags);
-        let _ = self.trove.add_command(new_command, true);
-        self.save_trove(None);
+        let description = format!("Created {}/{}", new_command.namespace, new_command.name);
+        self.trove.add_command(new_command, true)?;
+        self.save_trove(&description)
     }
 
     fn list_commands(
         &mut self,
         is_simple: bool,
-        is_structured: bool,
+        list_format: Option<ListFormat>,
         filter: Option<String>,
-    ) -> Option<String> {
+        height: Option<Height>,
+    ) -> Result<Option<String>, HoardError> {
+        if let Some(list_format) = list_format {
+            // Return list of commands in a machine readable format, filtered by `filter`
+            let query_string: String = filter.unwrap_or_default();
+            let filtered_trove = self.query(&query_string)?;
+            let token = self.config.parameter_token.clone().unwrap();
+            let ending_token = self.config.parameter_ending_token.clone().unwrap();
+            let rendered = match list_format {
+                ListFormat::Json => export::to_list_json(&filtered_trove, &token, &ending_token),
+                ListFormat::Yaml => Ok(filtered_trove.to_yaml()),
+                ListFormat::Template(template) => {
+                    export::render_template(&filtered_trove, &template, &token, &ending_token)
+                        .map(|lines| lines.join("\n"))
+                }
+                ListFormat::Records(template) => {
+                    export::render_template(&filtered_trove, &template, &token, &ending_token).map(
+                        |records| {
+                            let mut stdout = std::io::stdout().lock();
+                            // Ignore write errors, pickers may exit before reading all records
+                            let _ = records
+                                .iter()
+                                .try_for_each(|record| write!(stdout, "{record}\0"));
+                            let _ = stdout.flush();
+                            String::new()
+                        },
+                    )
+                }
+            };
+            return rendered
+                .map(Some)
+                .map_err(|e| HoardError::Usage(e.to_string()));
+        }
         if self.trove.is_empty() {
             println!("No command hoarded.\nRun [ hoard new ] first to hoard a command.");
         } else if is_simple {
-            self.trove.print_trove();
-        } else if is_structured {
-            // Return list of commands in json format, filtered by `filter`
-            let query_string: String = filter.unwrap_or_default();
-            let filtered_trove = query_trove(&self.trove, &query_string);
-            return Some(filtered_trove.to_yaml());
+            self.print_trove();
         } else {
-            match commands_gui::run(&mut self.trove, &self.config) {
-                Ok(selected_command) => {
-                    self.save_trove(None);
-                    if let Some(c) = selected_command {
-                        // Is set if a command is selected in GUI
-                        if !c.command.is_empty() {
-                            //TODO: If run as cli program, copy command into clipboard, else will be written to READLINE_LINE
-                            return Some(c.command);
-                        }
-                    }
+            let environment = self.environment()?;
+            let keymap = Keymap::from_config(&self.config)?;
+            let theme = Theme::from_config(&self.config)?;
+            let height = match height {
+                Some(height) => Some(height),
+                None => self
+                    .config
+                    .inline_height
+                    .as_deref()
+                    .map(str::parse)
+                    .transpose()
+                    .map_err(HoardError::Config)?,
+            };
+            let selected_command = commands_gui::run(
+                &mut self.trove,
+                &self.config,
+                environment,
+                keymap,
+                theme,
+                height,
+                self.config.mouse.unwrap_or(true),
+                &mut self.journal,
+            )
+            .map_err(|e| HoardError::Other(e.to_string()))?;
+            // The TUI journals every change on its own. Without any there is nothing to save
+            if self.journal.has_changes() || self.trove.to_yaml() != self.loaded_trove.to_yaml() {
+                self.write_trove()?;
+            }
+            if let Some(c) = selected_command {
+                self.record_usage(&c);
+                // Is set if a command is selected in GUI
+                if !c.command.is_empty() {
+                    //TODO: If run as cli program, copy command into clipboard, else will be written to READLINE_LINE
+                    return Ok(Some(c.command));
                 }
-                Err(e) => {
-                    println!("{e}");
+            }
+        }
+        Ok(None)
+    }
+
+    /// The commands matching every word of `query`, searched by the store of the trove
+    fn query(&self, query: &str) -> Result<Trove, HoardError> {
+        match &self.store {
+            Some(store) if self.trove_error.is_none() => store
+                .query(query)
+                .map(|commands| Trove::from_commands(&commands))
+                .map_err(|e| HoardError::Other(format!("{e:#}"))),
+            _ => Ok(query_trove(&self.trove, query)),
+        }
+    }
+
+    /// Counts a usage of `command` in the store, without writing the rest of the trove again
+    fn record_usage(&mut self, command: &HoardCmd) {
+        if self.trove_error.is_some() {
+            return;
+        }
+        // Picking a workflow counts as a usage of each of its steps
+        let ids = self
+            .trove
+            .workflows
+            .iter()
+            .find(|w| w.id == command.id)
+            .map_or_else(|| vec![command.id.clone()], |w| w.steps.clone());
+        if let Some(store) = self.store.as_mut() {
+            for id in ids {
+                if let Err(e) = store.record_usage(&id) {
+                    eprintln!("Could not count the usage of {}: {e:#}", command.name);
                 }
             }
         }
-        None
     }
 
-    fn pick_command(&mut self, name: &str) {
-        let command_result = self.trove.pick_command(&self.config, name);
-        match command_result {
-            Ok(c) => {
-                println!("{}", c.command);
+    fn pick_command(&mut self, name: &str) -> Result<(), HoardError> {
+        let command = self.pick_with_parameters(name)?;
+        println!("{command}");
+        Ok(())
+    }
+
+    fn print_trove(&self) {
+        // Create the table
+        let mut table = Table::new();
+        // Add header
+        table.add_row(row!["Name", "namespace", "command", "description", "tags"]);
+        // Iterate through trove and populate table
+        self.trove.commands.iter().for_each(|c| {
+            table.add_row(Row::new(vec![
+                // Name
+                Cell::new(&c.name[..])
+                    .with_style(Attr::Bold)
+                    .with_style(Attr::ForegroundColor(color::GREEN)),
+                // namespace
+                Cell::new(&c.namespace[..]),
+                // command
+                Cell::new(&c.command[..]),
+                // description
+                Cell::new(&c.description[..]),
+                // tags
+                Cell::new(&c.get_tags_as_string()),
+            ]));
+        });
+        // Print the table to stdout
+        table.printstd();
+    }
+
+    /// Finds a command or workflow by its id, `namespace/name` or name and asks the user for its parameters
+    ///
+    /// The steps of a workflow are joined with `&&`. Parameters shared by several steps are asked for once.
+    /// Secret parameters are filled last, so their values are never shown. A command with secrets is hidden from the shell history
+    fn pick_with_parameters(&self, reference: &str) -> Result<String, HoardError> {
+        let token = self.config.parameter_token.clone().unwrap();
+        let ending_token = self.config.parameter_ending_token.clone().unwrap();
+        let steps = self.expanded_steps(reference)?;
+        let command = HoardCmd::default()
+            .with_command(&join_steps(&steps))
+            .with_input_parameters(&token, &ending_token)
+            .command;
+        let names = secret_names(&command, &token, &ending_token);
+        if names.is_empty() {
+            return Ok(command);
+        }
+        let secrets = self.secret_values(&names)?;
+        Ok(hide_from_history(&replace_secrets(
+            &command,
+            &token,
+            &ending_token,
+            &secrets,
+        )))
+    }
+
+    /// The steps of the workflow `reference` points to, or the command it points to as the only step
+    ///
+    /// Embedded commands are resolved and variables are expanded with the `.env` file of the namespace of every step.
+    /// Unresolved variables become parameters
+    fn expanded_steps(&self, reference: &str) -> Result<Vec<HoardCmd>, HoardError> {
+        let token = self.config.parameter_token.clone().unwrap();
+        let ending_token = self.config.parameter_ending_token.clone().unwrap();
+        let steps = match self.trove.pick_command(reference) {
+            Ok(command) => vec![command],
+            Err(HoardError::NotFound(_)) => self
+                .trove
+                .get_workflow(reference)?
+                .step_commands(&self.trove)?
+                .into_iter()
+                .map(|step| {
+                    let command = resolve_references(&step, &self.trove.commands)?;
+                    Ok(step.with_command(&command))
+                })
+                .collect::<Result<_, HoardError>>()?,
+            Err(e) => return Err(e),
+        };
+        let environment = self.environment()?;
+        Ok(steps
+            .into_iter()
+            .map(|step| {
+                let command =
+                    environment.expand(&step.command, &step.namespace, &token, &ending_token);
+                step.with_command(&command)
+            })
+            .collect())
+    }
+
+    /// Runs the steps of the workflow `reference` points to one by one, or the command it points to
+    ///
+    /// The parameters of every step are asked for right before it is run, the ones shared with earlier steps are filled already.
+    /// Stops at the first step which fails
+    fn run_command(&self, reference: &str) -> Result<(), HoardError> {
+        let token = self.config.parameter_token.clone().unwrap();
+        let ending_token = self.config.parameter_ending_token.clone().unwrap();
+        let mut steps = self.expanded_steps(reference)?;
+        let mut names: Vec<String> = Vec::new();
+        for step in &steps {
+            for name in secret_names(&step.command, &token, &ending_token) {
+                if !names.contains(&name) {
+                    names.push(name);
+                }
+            }
+        }
+        let secrets = if names.is_empty() {
+            BTreeMap::new()
+        } else {
+            self.secret_values(&names)?
+        };
+        let shell = std::env::var("SHELL").unwrap_or_else(|_| "sh".to_string());
+        let count = steps.len();
+        for current in 0..count {
+            let name = steps[current].name.clone();
+            eprintln!("[{}/{count}] {name}", current + 1);
+            while steps[current].has_input_parameters(&token, &ending_token) {
+                let prompt_dialog = format!("Enter parameter\n~> {}\n", steps[current].command);
+                let parameter = prompt_input(&prompt_dialog, false, None);
+                workflow::replace_parameter(
+                    &mut steps[current..],
+                    &token,
+                    &ending_token,
+                    &parameter,
+                );
+            }
+            // Secrets are never shown
+            eprintln!("~> {}", steps[current].command);
+            let command = replace_secrets(&steps[current].command, &token, &ending_token, &secrets);
+            let status = Command::new(&shell)
+                .arg("-c")
+                .arg(&command)
+                .status()
+                .map_err(|e| HoardError::Other(format!("Could not run {shell}: {e}")))?;
+            if !status.success() {
+                return Err(HoardError::StepFailed(format!(
+                    "Step {}/{count} {name} failed: {status}",
+                    current + 1
+                )));
             }
-            Err(e) => eprintln!("{e}"),
         }
+        Ok(())
     }
 
-    fn remove_command(&mut self, command_name: &str) {
-        let command_result = self.trove.remove_command(command_name);
-        match command_result {
-            Ok(()) => {
-                println!("Removed [{command_name}]");
+    fn workflow_command(&mut self, action: &WorkflowAction) -> Result<(), HoardError> {
+        match action {
+            WorkflowAction::New {
+                name,
+                namespace,
+                description,
+                steps,
+            } => {
+                let commands = steps
+                    .iter()
+                    .map(|step| self.trove.get_command(step))
+                    .collect::<Result<Vec<_>, _>>()?;
+                let namespace = namespace
+                    .clone()
+                    .unwrap_or_else(|| commands[0].namespace.clone());
+                let workflow = Workflow::new(
+                    name,
+                    &namespace,
+                    description.as_deref().unwrap_or_default(),
+                    commands.iter().map(|c| c.id.clone()).collect(),
+                );
+                self.trove.add_workflow(workflow)?;
+                println!(
+                    "Saved workflow [{namespace}/{name}] with {} steps",
+                    steps.len()
+                );
+                self.save_trove(&format!("Created workflow {namespace}/{name}"))
+            }
+            WorkflowAction::List => {
+                for workflow in &self.trove.workflows {
+                    println!(
+                        "{}/{} ( {} )",
+                        workflow.namespace, workflow.name, workflow.id
+                    );
+                    if !workflow.description.is_empty() {
+                        println!("  {}", workflow.description);
+                    }
+                    for (i, id) in workflow.steps.iter().enumerate() {
+                        match self.trove.commands.iter().find(|c| &c.id == id) {
+                            Some(c) => {
+                                println!("  {}. {}/{}: {}", i + 1, c.namespace, c.name, c.command)
+                            }
+                            None => println!("  {}. Missing command {id}", i + 1),
+                        }
+                    }
+                }
+                Ok(())
+            }
+            WorkflowAction::Rm { name } => {
+                let workflow = self.trove.remove_workflow(name)?;
+                println!(
+                    "Removed workflow [{}/{}]",
+                    workflow.namespace, workflow.name
+                );
+                self.save_trove(&format!(
+                    "Removed workflow {}/{}",
+                    workflow.namespace, workflow.name
+                ))
             }
-            Err(e) => eprintln!("{e}"),
         }
-        self.save_trove(None);
     }
 
-    fn remove_namespace(&mut self, namespace: &str) {
-        let command_result = self.trove.remove_namespace_commands(namespace);
-        match command_result {
-            Ok(()) => {
-                println!("Removed all commands of namespace [{namespace}]");
+    /// The values of the secrets `names`, from the vault or else asked for
+    fn secret_values(&self, names: &[String]) -> Result<BTreeMap<String, String>, HoardError> {
+        let mut vault = self.open_vault()?;
+        let mut secrets = BTreeMap::new();
+        if names.iter().any(|name| vault.contains(name)) {
+            let key = Self::unlock_vault(&mut vault)?;
+            for name in names {
+                if let Some(value) = vault
+                    .get(&key, name)
+                    .map_err(|e| HoardError::Secret(e.to_string()))?
+                {
+                    secrets.insert(name.clone(), value);
+                }
             }
-            Err(e) => eprintln!("{e}"),
         }
-        self.save_trove(None);
+        for name in names {
+            secrets
+                .entry(name.clone())
+                .or_insert_with_key(|name| prompt_password(&format!("Enter secret {name}"), false));
+        }
+        Ok(secrets)
+    }
+
+    /// Variables to expand in commands, from the process environment and the `.env` files of the namespaces
+    fn environment(&self) -> Result<Environment, HoardError> {
+        Environment::load(&self.config.env_files.clone().unwrap_or_default())
+            .map_err(|e| HoardError::Config(e.to_string()))
+    }
+
+    fn open_vault(&self) -> Result<Vault, HoardError> {
+        let vault_path = self
+            .config
+            .vault_path
+            .clone()
+            .ok_or_else(|| HoardError::Config("No vault_path configured".to_string()))?;
+        Vault::open(&vault_path).map_err(|e| HoardError::Secret(e.to_string()))
+    }
+
+    /// Asks for the passphrase of `vault`, unless it is set in the environment
+    fn unlock_vault(vault: &mut Vault) -> Result<VaultKey, HoardError> {
+        let passphrase = std::env::var(PASSPHRASE_ENV).unwrap_or_else(|_| {
+            if vault.is_new() {
+                prompt_password("Choose a passphrase for your new vault", true)
+            } else {
+                prompt_password("Vault passphrase", false)
+            }
+        });
+        vault
+            .unlock(&passphrase)
+            .map_err(|e| HoardError::Secret(e.to_string()))
+    }
+
+    fn secret_command(&self, action: &SecretAction) -> Result<(), HoardError> {
+        let mut vault = self.open_vault()?;
+        let into_secret_error = |e: anyhow::Error| HoardError::Secret(e.to_string());
+        match action {
+            SecretAction::Set { name } => {
+                let key = Self::unlock_vault(&mut vault)?;
+                let value = prompt_password(&format!("Value of secret {name}"), true);
+                vault.set(&key, name, &value).map_err(into_secret_error)?;
+                vault.save().map_err(into_secret_error)?;
+                println!("Saved secret [{name}]");
+            }
+            SecretAction::Get { name } => {
+                if !vault.contains(name) {
+                    return Err(HoardError::NotFound(format!("secret {name}")));
+                }
+                let key = Self::unlock_vault(&mut vault)?;
+                if let Some(value) = vault.get(&key, name).map_err(into_secret_error)? {
+                    println!("{value}");
+                }
+            }
+            SecretAction::List => {
+                for name in vault.names() {
+                    println!("{name}");
+                }
+            }
+            SecretAction::Rm { name } => {
+                if !vault.remove(name) {
+                    return Err(HoardError::NotFound(format!("secret {name}")));
+                }
+                vault.save().map_err(into_secret_error)?;
+                println!("Removed secret [{name}]");
+            }
+        }
+        Ok(())
+    }
+
+    fn preview_command(&self, reference: &str) -> Result<(), HoardError> {
+        let command = self.trove.get_command(reference)?;
+        print!(
+            "{}",
+            export::render_preview(
+                command,
+                &self.config.parameter_token.clone().unwrap(),
+                &self.config.parameter_ending_token.clone().unwrap(),
+            )
+        );
+        Ok(())
     }
 
-    fn import_trove(&mut self, path: &str) {
-        let imported_trove = Trove::load_trove_file(&Some(PathBuf::from(path)));
+    fn remove_command(&mut self, reference: &str) -> Result<(), HoardError> {
+        let command = self.trove.get_command(reference)?;
+        let (id, label) = (
+            command.id.clone(),
+            format!("{}/{}", command.namespace, command.name),
+        );
+        self.trove.check_removable(|c| c.id == id)?;
+        let dependents = self.trove.dependents(&id);
+        if !dependents.is_empty()
+            && matches!(
+                prompt_yes_or_no(&format!(
+                    "[{label}] is used by {}. Remove it anyway?",
+                    dependents.join(", ")
+                )),
+                Confirmation::No
+            )
+        {
+            println!("Keeping [{label}]");
+            return Ok(());
+        }
+        self.trove.remove_command_by_id(&id)?;
+        println!("Removed [{label}]");
+        self.save_trove(&format!("Removed {label}"))
+    }
+
+    fn remove_namespace(&mut self, namespace: &str) -> Result<(), HoardError> {
+        self.trove.remove_namespace_commands(namespace)?;
+        println!("Removed all commands of namespace [{namespace}]");
+        self.save_trove(&format!("Removed namespace {namespace}"))
+    }
+
+    fn import_trove(&mut self, path: &str, format: ImportFormat) -> Result<(), HoardError> {
+        let description = format!("Imported {path}");
+        let path = PathBuf::from(path);
+        let token = self.config.parameter_token.clone().unwrap();
+        let ending_token = self.config.parameter_ending_token.clone().unwrap();
+        let imported_trove = match format {
+            ImportFormat::Hoard => Ok(Trove::load_trove_file(&Some(path))?),
+            ImportFormat::Navi => import_path(&path, navi::EXTENSION, |content, namespace| {
+                Ok(navi::parse(content, namespace, &token, &ending_token))
+            }),
+            ImportFormat::Pet => import_path(&path, pet::EXTENSION, |content, namespace| {
+                pet::parse(content, namespace, &token, &ending_token)
+            }),
+            ImportFormat::Tldr => import_path(&path, tldr::EXTENSION, |content, namespace| {
+                Ok(tldr::parse(content, namespace, &token, &ending_token))
+            }),
+        }
+        .map_err(|e| HoardError::Other(format!("Could not import commands: {e:#}")))?;
+        println!("Imported {} commands", imported_trove.commands.len());
         self.trove.merge_trove(&imported_trove);
-        self.save_trove(None);
+        self.save_trove(&description)
     }
 
-    fn export_command(&self, path: &str) {
+    fn export_command(&self, path: &str, format: ExportFormat) -> Result<(), HoardError> {
         let target_path = PathBuf::from(path);
         if target_path.file_name().is_some() {
             let namespaces = self.trove.namespaces();
@@ -225,7 +734,7 @@ impl Hoard {
 
             if selected_namespaces.is_empty() {
                 println!("Nothing selected");
-                return;
+                return Ok(());
             }
 
             let commands = self
@@ -244,7 +753,7 @@ impl Hoard {
 
             if selected_commands.is_empty() {
                 println!("Nothing selected");
-                return;
+                return Ok(());
             }
 
             let mut trove_for_export = Trove::default();
@@ -252,94 +761,216 @@ impl Hoard {
                 let _ = trove_for_export.add_command(command.clone(), true);
             }
 
-            trove_for_export.save_trove_file(&target_path);
+            let token = self.config.parameter_token.clone().unwrap();
+            let ending_token = self.config.parameter_ending_token.clone().unwrap();
+            render_export(&trove_for_export, format, &token, &ending_token)
+                .and_then(|content| Ok(fs::write(&target_path, content)?))
+                .map_err(|e| HoardError::Other(format!("Could not export commands: {e}")))?;
+            println!("Exported to {}", target_path.display());
+            Ok(())
         } else {
-            println!("No valid path with filename provided.");
+            Err(HoardError::Usage(
+                "No valid path with filename provided.".to_string(),
+            ))
         }
     }
 
-    pub fn set_parameter_token(&self, parameter_token: &str) {
+    pub fn set_parameter_token(&self, parameter_token: &str) -> Result<(), HoardError> {
         if let Some(config_path) = self.config.config_home_path.clone() {
-            if !save_parameter_token(&self.config, &config_path, parameter_token) {
-                std::process::exit(1);
-            }
+            save_parameter_token(&self.config, &config_path, parameter_token)
+                .map_err(|err| HoardError::Config(format!("{err:#}")))?;
         }
+        Ok(())
     }
 
-    fn edit_command(&mut self, command_name: &str) {
+    fn edit_command(&mut self, command_name: &str) -> Result<(), HoardError> {
         println!("Editing {command_name}");
-        let command_to_edit = self.trove.pick_command(&self.config, command_name);
+        // Edit the command with its parameters, never with values filled in
+        let c = self.trove.get_command(command_name)?.clone();
 
         let trove_namespaces = self.trove.namespaces();
-        match command_to_edit {
-            Ok(c) => {
-                println!("{}", c.command);
-                let new_command = HoardCmd::default()
-                    .with_command_string_input(
-                        Some(c.command.clone()),
-                        &self.config.parameter_token.clone().unwrap(),
-                        &self.config.parameter_ending_token.clone().unwrap(),
-                    )
-                    .with_name_input(Some(c.name.clone()), &self.trove)
-                    .with_description_input(c.description.clone())
-                    .with_tags_input(Some(c.get_tags_as_string()))
-                    .with_namespace_input(&trove_namespaces);
-                self.trove.remove_command(command_name).ok();
-                let _ = self.trove.add_command(new_command, true);
-                self.save_trove(None);
-            }
-            Err(_e) => eprintln!("Could not find command {command_name} to edit"),
-        }
+        println!("{}", c.command);
+        let new_command = HoardCmd::default()
+            .with_command_string_input(
+                Some(c.command.clone()),
+                &self.config.parameter_token.clone().unwrap(),
+                &self.config.parameter_ending_token.clone().unwrap(),
+            )
+            .with_name_input(Some(c.name.clone()), &self.trove)
+            .with_description_input(c.description.clone())
+            .with_tags_input(Some(c.get_tags_as_string()))
+            .with_namespace_input(&trove_namespaces);
+        // Keep the id, workflows refer to their steps by it
+        let new_command = HoardCmd {
+            id: c.id.clone(),
+            ..new_command
+        };
+        self.trove.commands.retain(|command| command.id != c.id);
+        self.trove.add_command(new_command, true)?;
+        self.save_trove(&format!("Edited {command_name}"))
     }
 
-    fn shell_config_command(shell: &str) {
-        let src = match shell {
-            "bash" => include_str!("shell/hoard.bash"),
-            "fish" => include_str!("shell/hoard.fish"),
-            "zsh" => include_str!("shell/hoard.zsh"),
-            s => {
-                println!("Unknown shell '{s}'!\nMust be either bash, fish or zsh!");
-                return;
+    fn shell_config_command(shell: &str, fzf: bool) -> Result<(), HoardError> {
+        let src = match (shell, fzf) {
+            ("bash", false) => include_str!("shell/hoard.bash"),
+            ("fish", false) => include_str!("shell/hoard.fish"),
+            ("zsh", false) => include_str!("shell/hoard.zsh"),
+            ("bash", true) => include_str!("shell/hoard_fzf.bash"),
+            ("fish", true) => include_str!("shell/hoard_fzf.fish"),
+            ("zsh", true) => include_str!("shell/hoard_fzf.zsh"),
+            (s, _) => {
+                return Err(HoardError::Usage(format!(
+                    "Unknown shell '{s}'!\nMust be either bash, fish or zsh!"
+                )));
             }
         };
         print!("{src}");
+        Ok(())
     }
 
     pub fn load_trove(&mut self) -> &mut Self {
-        self.trove = Trove::load_trove_file(&self.config.trove_path);
+        let backend = self.config.storage_backend.unwrap_or_default();
+        let path = match backend {
+            StorageBackend::Yaml => self.config.trove_path.clone(),
+            StorageBackend::Sqlite => self.config.database_path.clone(),
+        }
+        .unwrap_or_default();
+        match open_store(&self.config, backend) {
+            Ok(mut store) => {
+                // Kept until the trove is saved, or until it turns out the command doesn't change it
+                let loaded = store
+                    .lock()
+                    .map_err(|e| TroveError::Io {
+                        path: path.clone(),
+                        source: std::io::Error::other(format!("{e:#}")),
+                    })
+                    .and_then(|()| store.load());
+                match loaded {
+                    Ok(trove) => {
+                        self.loaded_trove = trove.clone();
+                        self.trove = trove;
+                    }
+                    Err(e) => self.trove_error = Some(e),
+                }
+                self.store = Some(store);
+            }
+            Err(e) => {
+                self.trove_error = Some(TroveError::Invalid {
+                    path,
+                    message: format!("{e:#}"),
+                });
+            }
+        }
+        let journal_size = self.config.journal_size.unwrap_or_default();
+        self.journal = match &self.config.journal_path {
+            // A broken journal is kept as it is, changes can still be undone while hoard runs
+            Some(path) => Journal::open(path, journal_size).unwrap_or_else(|e| {
+                eprintln!("{e:#}");
+                Journal::in_memory(journal_size)
+            }),
+            None => Journal::in_memory(journal_size),
+        };
         self
     }
 
-    pub fn save_trove(&self, path: Option<&Path>) {
-        let path_to_save = path.unwrap_or_else(|| self.config.trove_path.as_ref().unwrap());
-        self.trove.save_trove_file(path_to_save);
+    /// Save the changes made to the trove since it has been loaded, journaled as one change called `description`
+    pub fn save_trove(&mut self, description: &str) -> Result<(), HoardError> {
+        self.journal
+            .record(description, &self.loaded_trove, &self.trove);
+        self.write_trove()
     }
 
-    fn save_backup_trove(&self, path: Option<&Path>) {
-        let backup_trove_path_str = format!(
-            "{}.bk",
-            self.config.trove_path.as_ref().unwrap().to_str().unwrap()
-        );
-        let backup_trove_path = PathBuf::from_str(&backup_trove_path_str).ok().unwrap();
-        let path_to_save = path.unwrap_or(&backup_trove_path);
-        self.trove.save_trove_file(path_to_save);
-    }
-
-    fn revert_trove(&self) {
-        let trove_path = self.config.trove_path.as_ref().unwrap();
-        let backup_trove_path_str = format!("{}.bk", trove_path.to_str().unwrap());
-        let backup_trove_path = PathBuf::from_str(&backup_trove_path_str).ok().unwrap();
-        if backup_trove_path.exists() {
-            if matches!(prompt_yes_or_no("Found a backup from just before the last time you ran `hoard sync`. Are you sure you want to revert to this state?"), Confirmation::Yes) {
-                let e = fs::remove_file(trove_path);
-                // make clippy happy
-                drop(e);
-                fs::rename(backup_trove_path_str, trove_path).unwrap();
-                println!("Done!");
-            } else {
-                println!("Keeping current trove file...");
+    /// Save the changes made to the trove since it has been loaded, and the journal
+    ///
+    /// If another hoard process saved the trove since it has been loaded, the changes made in this process are merged into it
+    fn write_trove(&mut self) -> Result<(), HoardError> {
+        if self.trove_error.is_some() {
+            return Err(HoardError::TroveWrite(
+                "The trove could not be loaded, refusing to overwrite it".to_string(),
+            ));
+        }
+        let store = self.store.as_mut().ok_or_else(|| {
+            HoardError::TroveWrite("No storage for the trove available".to_string())
+        })?;
+        store.lock().map_err(into_trove_write_error)?;
+        let saved = store.save(&self.loaded_trove, &self.trove);
+        // The journal is read and written under the lock of the trove as well, no entry of another process gets lost
+        let journaled = if saved.is_ok() {
+            self.journal.save()
+        } else {
+            Ok(())
+        };
+        store.unlock();
+        let saved = saved.map_err(into_trove_write_error)?;
+        self.loaded_trove = saved.clone();
+        self.trove = saved;
+        journaled.map_err(|e| HoardError::TroveWrite(format!("Could not write the journal: {e:#}")))
+    }
+
+    /// Undoes the last change of the trove, or redoes the last undone one if `redo`
+    fn undo(&mut self, redo: bool) -> Result<(), HoardError> {
+        let stepped = if redo {
+            self.journal.redo(&mut self.trove)
+        } else {
+            self.journal.undo(&mut self.trove)
+        };
+        match stepped {
+            Ok(Some(description)) => {
+                println!("{}: {description}", if redo { "Redone" } else { "Undone" });
+                self.write_trove()
+            }
+            Ok(None) => {
+                println!("Nothing to {}", if redo { "redo" } else { "undo" });
+                Ok(())
             }
+            // The entry which couldn't be undone stays in the journal, nothing has been changed
+            Err(e) => Err(e),
         }
     }
+
+    fn migrate_storage(&mut self, to: StorageBackend) -> Result<(), HoardError> {
+        let from = self.config.storage_backend.unwrap_or_default();
+        if from == to {
+            return Err(HoardError::Usage(format!(
+                "The trove is already stored in the {to} storage"
+            )));
+        }
+        let mut target = open_store(&self.config, to).map_err(into_trove_write_error)?;
+        let existing = target.load()?;
+        if !existing.is_empty()
+            && matches!(
+                prompt_yes_or_no(&format!(
+                    "The {to} storage already contains {} commands. Replace them with the {} commands of the {from} storage?",
+                    existing.commands.len(),
+                    self.trove.commands.len()
+                )),
+                Confirmation::No
+            )
+        {
+            println!("Keeping the {from} storage");
+            return Ok(());
+        }
+        // Saving with the existing commands as base replaces them
+        target
+            .save(&existing, &self.trove)
+            .map_err(into_trove_write_error)?;
+        if let Some(config_path) = self.config.config_home_path.clone() {
+            save_storage_backend(&self.config, &config_path, to)
+                .map_err(|err| HoardError::Config(format!("{err:#}")))?;
+        }
+        self.config.storage_backend = Some(to);
+        println!(
+            "Moved {} commands from the {from} storage to the {to} storage. The {from} storage has been kept as it was",
+            self.trove.commands.len()
+        );
+        Ok(())
+    }
 }
 
+/// Keeps errors of a trove someone else broke in the meantime apart from errors writing it
+fn into_trove_write_error(e: anyhow::Error) -> HoardError {
+    match e.downcast::<TroveError>() {
+        Ok(trove_error) => HoardError::Trove(trove_error),
+        Err(e) => HoardError::TroveWrite(format!("{e:#}")),
+    }
+}
@@ -0,0 +1,15 @@
+//! The trove model of hoard, without any terminal user interface
+//!
+//! Use it to build your own tools on top of your hoarded commands
+//!
+//! ```no_run
+//! use hoardlib::core::trove::Trove;
+//!
+//! let trove = Trove::load_trove_file(&Some("trove.yml".into())).unwrap();
+//! for command in &trove.commands {
+//!     println!("{}/{}: {}", command.namespace, command.name, command.command);
+//! }
+//! ```
+pub mod config;
+pub mod core;
+pub mod filter;
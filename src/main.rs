@@ -2,29 +2,38 @@ extern crate serde_yaml;
 
 #[macro_use]
 extern crate prettytable;
-extern crate dotenv;
+extern crate dotenvy;
 extern crate rand;
 
 extern crate array_tool;
 
 mod cli_commands;
-mod config;
-mod core;
-mod filter;
 mod gui;
 mod hoard;
 mod util;
 use hoard::Hoard;
+use std::io::Write;
 
 #[tokio::main]
 async fn main() {
-    let (command, is_autocomplete) = Hoard::default()
+    let result = Hoard::default()
         .with_config(None)
-        .load_trove()
-        .start();
+        .and_then(|hoard| hoard.load_trove().start());
+    let (command, is_autocomplete) = match result {
+        Ok(output) => output,
+        Err(e) => {
+            eprintln!("{e}");
+            std::process::exit(e.exit_code());
+        }
+    };
+    if command.is_empty() {
+        return;
+    }
+    // Ignore write errors, the reading end of a pipe ( e.g. fzf or head ) may already be closed.
+    // Leading whitespace is kept, it hides commands with secrets from the shell history
     if is_autocomplete {
-        eprintln!("{}", command.trim());
+        let _ = writeln!(std::io::stderr(), "{}", command.trim_end());
     } else {
-        println!("{}", command.trim());
+        let _ = writeln!(std::io::stdout(), "{}", command.trim_end());
     }
 }
@@ -0,0 +1,14 @@
+__hoard_fzf ()
+{
+    local id
+    id="$(hoard list --print0 | fzf --read0 --height 40% --reverse --delimiter '\t' --with-nth 2.. --preview 'hoard preview {1}' | head -n 1 | cut -f 1)"
+    if [[ -n $id ]]; then
+        # Missing parameters are prompted for on stderr, the resolved command is printed to stdout
+        READLINE_LINE="$(hoard pick --name "$id")"
+        READLINE_POINT=${#READLINE_LINE}
+    fi
+}
+
+if [[ -z $HOARD_NOBIND ]]; then
+	bind -x '"\C-h": __hoard_fzf'
+fi
@@ -0,0 +1,13 @@
+# Hoard bindings using fzf as picker
+function __hoard_fzf
+    set id (hoard list --print0 | fzf --read0 --height 40% --reverse --delimiter '\t' --with-nth 2.. --preview 'hoard preview {1}' | head -n 1 | cut -f 1)
+    if test -n "$id"
+        # Missing parameters are prompted for on stderr, the resolved command is printed to stdout
+        commandline -j (hoard pick --name $id)
+    end
+    commandline -f repaint
+end
+
+if ! set -q HOARD_NOBIND
+    bind \ch __hoard_fzf
+end
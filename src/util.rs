@@ -1,3 +1,8 @@
+use crate::cli_commands::ExportFormat;
+use hoardlib::core::export;
+use hoardlib::core::trove::Trove;
+use std::path::Path;
+
 pub fn translate_number_to_nth(count: u16) -> String {
     match count {
         0 => "first".to_string(),
@@ -12,104 +17,52 @@ pub fn translate_number_to_nth(count: u16) -> String {
     }
 }
 
-
-pub fn string_find_next(s: &str, from: &str, to: &str) -> String {
-    // Find substring of a string `s` from first occurrence of `from` to first occurrence of `to` after `from` was encountered
-    // For example:
-    // `s` = "Hello #my test" | `from` = "#" | `to` = " "
-    // Returns "#my"
-    if s.contains(from) {
-        let split = s.split(from).nth(1).unwrap().split(to).next().unwrap();
-        return format!("{from}{split}");
-    }
-    String::new()
+/// Prefix `command` with a space, so shells configured to ignore such commands don't save it in their history
+///
+/// `HISTCONTROL=ignorespace` in bash, `setopt HIST_IGNORE_SPACE` in zsh and fish by default
+pub fn hide_from_history(command: &str) -> String {
+    format!(" {command}")
 }
 
-pub fn split_with_delim(s: &str, delim: &str) -> Vec<String> {
-    //Credits to chatGPT for simplifying this wtf
-    let mut result = Vec::new();
-    let mut start = 0;
-
-    for (i, _) in s.match_indices(delim) {
-        if i > start {
-            result.push(s[start..i].to_string());
-        }
-        result.push(delim.to_string());
-        start = i + delim.len();
+/// `trove` in the export format `format`
+pub fn render_export(
+    trove: &Trove,
+    format: ExportFormat,
+    token: &str,
+    ending_token: &str,
+) -> anyhow::Result<String> {
+    match format {
+        ExportFormat::Yaml => Ok(trove.to_yaml()),
+        ExportFormat::Md => Ok(export::to_markdown(trove)),
+        ExportFormat::Json => export::to_json(trove),
+        ExportFormat::Toml => export::to_toml(trove),
+        ExportFormat::Csv => Ok(export::to_csv(trove)),
+        ExportFormat::Navi => Ok(export::to_navi(trove, token, ending_token)),
+        ExportFormat::ShellAliases => Ok(export::to_shell_functions(trove, token, ending_token)),
     }
-
-    if start < s.len() {
-        result.push(s[start..].to_string());
-    }
-
-    while result.first().map_or(false, std::string::String::is_empty) {
-        result.remove(0);
-    }
-
-    while result.last().map_or(false, std::string::String::is_empty) {
-        result.pop();
-    }
-
-    result
 }
 
-#[cfg(test)]
-mod test_commands {
-    use super::*;
-
-    #[test]
-    fn extract_named_parameter() {
-        let s = String::from("Hello #test command");
-        let expected = "#test";
-        assert_eq!(expected, string_find_next(&s, "#", " "));
-    }
-
-    #[test]
-    fn extract_named_parameter_first() {
-        let s = String::from("#test command");
-        let expected = "#test";
-        assert_eq!(expected, string_find_next(&s, "#", " "));
-    }
-
-    #[test]
-    fn extract_named_parameter_last() {
-        let s = String::from("command #test");
-        let expected = "#test";
-        assert_eq!(expected, string_find_next(&s, "#", " "));
-    }
-
-    #[test]
-    fn extract_named_parameter_noname() {
-        let s = String::from("test # bar");
-        let expected = "#";
-        assert_eq!(expected, string_find_next(&s, "#", " "));
-    }
-
-    #[test]
-    fn extract_named_parameter_none() {
-        let s = String::from("command test");
-        let expected = "";
-        assert_eq!(expected, string_find_next(&s, "#", " "));
-    }
-
-    #[test]
-    fn test_split_with_delim() {
-        let s = String::from("command #param test #param lol");
-        let expected = vec!["command ", "#param", " test ", "#param", " lol"];
-        assert_eq!(expected, split_with_delim(&s, "#param"));
-    }
-
-    #[test]
-    fn test_split_with_delim_at_end() {
-        let s = String::from("command #param test #param lol #param");
-        let expected = vec!["command ", "#param", " test ", "#param", " lol ", "#param"];
-        assert_eq!(expected, split_with_delim(&s, "#param"));
+/// The export format of a file named like `path`, by its extension
+pub fn export_format_of(path: &Path) -> Option<ExportFormat> {
+    match path.extension()?.to_str()? {
+        "yml" | "yaml" => Some(ExportFormat::Yaml),
+        "md" => Some(ExportFormat::Md),
+        "json" => Some(ExportFormat::Json),
+        "toml" => Some(ExportFormat::Toml),
+        "csv" => Some(ExportFormat::Csv),
+        "cheat" => Some(ExportFormat::Navi),
+        "sh" => Some(ExportFormat::ShellAliases),
+        _ => None,
     }
+}
 
-    #[test]
-    fn test_split_with_delim_at_start() {
-        let s = String::from("#param test #param lol");
-        let expected = vec!["#param", " test ", "#param", " lol"];
-        assert_eq!(expected, split_with_delim(&s, "#param"));
-    }
+/// Name of the running hoard executable, to put `hoard` commands into the shell with
+pub fn executable_name() -> String {
+    std::env::current_exe()
+        .ok()
+        .and_then(|path| {
+            path.file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+        })
+        .unwrap_or_else(|| env!("CARGO_BIN_NAME").to_string())
 }